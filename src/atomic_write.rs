@@ -0,0 +1,338 @@
+//! 原子多浏览器写入 - 备份 → 暂存 → 校验 → 替换，任一步失败全部回滚
+//!
+//! `hub` 和 `migrate-safari` 以前逐个浏览器写入，第三个失败时前两个已经被修改。
+//! 这里先为每个目标做备份（`backup_bookmarks`），再把书签写到暂存副本上
+//! （Firefox 系通过 `db_safety::safe_write_transaction`），全部读回校验通过后
+//! 才逐个替换真实文件；替换途中失败则把已经替换的浏览器从备份恢复。
+//!
+//! 只有书签是原子写入的：历史、阅读列表和 Cookie 仍在之后逐个浏览器写入，失败只记警告。
+
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, error, info, warn};
+
+use crate::browsers::{Bookmark, BrowserAdapter};
+use crate::db_safety;
+
+/// SQLite 数据库旁的 WAL/SHM 文件后缀
+const SIDECARS: [&str; 2] = ["-wal", "-shm"];
+
+/// 一个写入目标：浏览器 + 要写入的书签树（清空时为空）
+pub struct WriteTarget<'a> {
+    pub adapter: &'a dyn BrowserAdapter,
+    pub bookmarks: &'a [Bookmark],
+}
+
+/// 已暂存、等待替换的目标
+struct Staged<'a> {
+    target: &'a WriteTarget<'a>,
+    name: &'static str,
+    /// 浏览器真实的书签文件
+    path: PathBuf,
+    /// 写好的暂存副本
+    staged: PathBuf,
+    /// 原文件备份；原文件不存在时为 None（回滚时删除新文件）
+    backup: Option<PathBuf>,
+    /// (后缀, 备份副本)：原文件旁的 WAL/SHM
+    sidecars: Vec<(&'static str, PathBuf)>,
+}
+
+/// 默认暂存目录：~/.browser-sync/staging
+pub fn default_staging_root() -> Result<PathBuf> {
+    let home = std::env::var("HOME")?;
+    Ok(PathBuf::from(format!("{}/.browser-sync/staging", home)))
+}
+
+/// 原子地把书签写入所有目标
+///
+/// 成功时所有目标都已替换；失败时没有浏览器被修改（回滚本身失败会记录错误并保留备份）。
+pub fn write_bookmarks_atomic(targets: &[WriteTarget], staging_root: &Path) -> Result<()> {
+    let dir = staging_root.join(format!(
+        "{}-{}",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S"),
+        &uuid::Uuid::new_v4().simple().to_string()[..8]
+    ));
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create staging directory {}", dir.display()))?;
+
+    let result = stage_and_swap(targets, &dir);
+    if let Err(e) = fs::remove_dir_all(&dir) {
+        debug!(
+            "Failed to remove staging directory {}: {}",
+            dir.display(),
+            e
+        );
+    }
+    result
+}
+
+fn stage_and_swap(targets: &[WriteTarget], dir: &Path) -> Result<()> {
+    // 1. 备份 + 写暂存副本（不触碰真实文件）
+    let mut staged = Vec::new();
+    for (i, target) in targets.iter().enumerate() {
+        let name = target.adapter.browser_type().name();
+        let entry = stage(target, &dir.join(i.to_string()))
+            .with_context(|| format!("Failed to stage bookmarks for {}", name))?;
+        debug!("Staged {} at {}", name, entry.staged.display());
+        staged.push(entry);
+    }
+
+    // 2. 全部读回校验
+    for entry in &staged {
+        validate(entry)
+            .with_context(|| format!("Staged bookmarks for {} failed validation", entry.name))?;
+    }
+    info!("  ✅ Staged and validated {} browsers", staged.len());
+
+    // 3. 逐个替换，失败则回滚已经替换的
+    for i in 0..staged.len() {
+        if let Err(e) = swap(&staged[i]) {
+            error!(
+                "  ❌ {} : failed to replace bookmarks: {}",
+                staged[i].name, e
+            );
+            rollback(&staged[..=i]);
+            return Err(e.context(format!(
+                "Failed to write {}; all browsers were rolled back",
+                staged[i].name
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn stage<'a>(target: &'a WriteTarget<'a>, dir: &Path) -> Result<Staged<'a>> {
+    let adapter = target.adapter;
+    let name = adapter.browser_type().name();
+    let path = adapter.detect_bookmark_path()?;
+    let sqlite = is_sqlite(&path);
+    if sqlite && path.is_file() && db_safety::is_database_locked(&path) {
+        anyhow::bail!(
+            "{} is locked by the running browser. Please close it and try again.",
+            name
+        );
+    }
+    fs::create_dir_all(dir)?;
+
+    let backup = if path.is_file() {
+        Some(adapter.backup_bookmarks()?)
+    } else {
+        None
+    };
+    let mut sidecars = Vec::new();
+    for suffix in SIDECARS {
+        let original = with_suffix(&path, suffix);
+        if original.is_file() {
+            let copy = dir.join(format!("original{}", suffix));
+            fs::copy(&original, &copy)?;
+            sidecars.push((suffix, copy));
+        }
+    }
+
+    let file_name = path.file_name().context("Invalid bookmark path")?;
+    let staged = dir.join(file_name);
+    if path.is_file() {
+        fs::copy(&path, &staged)?;
+        for (suffix, copy) in &sidecars {
+            fs::copy(copy, with_suffix(&staged, suffix))?;
+        }
+        if sqlite {
+            // 把 WAL 合并进暂存副本，之后它不再依赖旁边的文件
+            let conn = rusqlite::Connection::open(&staged)?;
+            conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        }
+    }
+    adapter.write_bookmarks_to(&staged, target.bookmarks)?;
+
+    Ok(Staged {
+        target,
+        name,
+        path,
+        staged,
+        backup,
+        sidecars,
+    })
+}
+
+fn validate(entry: &Staged) -> Result<()> {
+    let adapter = entry.target.adapter;
+    let read_back = adapter.read_bookmarks_from(&entry.staged)?;
+    if !adapter.validate_bookmarks(&read_back)? {
+        anyhow::bail!("bookmark structure is invalid");
+    }
+    let expected = count_urls(entry.target.bookmarks);
+    if expected > 0 && count_urls(&read_back) == 0 {
+        anyhow::bail!("staged copy is empty, expected {} bookmarks", expected);
+    }
+    if is_sqlite(&entry.staged) {
+        db_safety::check_compatibility(&entry.staged)?;
+    }
+    Ok(())
+}
+
+fn swap(entry: &Staged) -> Result<()> {
+    if fs::rename(&entry.staged, &entry.path).is_err() {
+        // 跨文件系统时 rename 会失败，退回复制
+        fs::copy(&entry.staged, &entry.path)
+            .with_context(|| format!("Failed to replace {}", entry.path.display()))?;
+    }
+    // 暂存副本已包含 WAL 内容，旧的 WAL/SHM 不能留给新数据库
+    for suffix in SIDECARS {
+        let stale = with_suffix(&entry.path, suffix);
+        if stale.exists() {
+            fs::remove_file(&stale)?;
+        }
+    }
+    Ok(())
+}
+
+fn rollback(touched: &[Staged]) {
+    for entry in touched.iter().rev() {
+        let restored = match entry.backup {
+            Some(ref backup) => fs::copy(backup, &entry.path).map(|_| ()),
+            None if entry.path.is_file() => fs::remove_file(&entry.path),
+            None => Ok(()),
+        };
+        let sidecars = entry.sidecars.iter().try_for_each(|(suffix, copy)| {
+            fs::copy(copy, with_suffix(&entry.path, suffix)).map(|_| ())
+        });
+        match restored.and(sidecars) {
+            Ok(()) => warn!("  ↩️  {} : rolled back", entry.name),
+            Err(e) => error!(
+                "  ❌ {} : rollback failed: {} (backup: {:?})",
+                entry.name, e, entry.backup
+            ),
+        }
+    }
+}
+
+fn is_sqlite(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "sqlite")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn count_urls(bookmarks: &[Bookmark]) -> usize {
+    bookmarks
+        .iter()
+        .map(|b| if b.folder { count_urls(&b.children) } else { 1 })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::browsers::BrowserType;
+    use crate::test_support::{self, FileAdapter};
+
+    fn make_bookmark(title: &str, url: &str) -> Bookmark {
+        Bookmark {
+            date_added: Some(1000000),
+            ..test_support::make_bookmark(title, url)
+        }
+    }
+
+    fn titles(adapter: &FileAdapter) -> Vec<String> {
+        adapter
+            .read_bookmarks()
+            .unwrap()
+            .into_iter()
+            .map(|b| b.title)
+            .collect()
+    }
+
+    #[test]
+    fn test_all_targets_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = vec![make_bookmark("Old", "https://old.example")];
+        let brave = FileAdapter::new(BrowserType::Brave, dir.path().join("Brave"));
+        let chrome = FileAdapter::new(BrowserType::Chrome, dir.path().join("Chrome"));
+        brave.write_bookmarks(&old).unwrap();
+        chrome.write_bookmarks(&old).unwrap();
+
+        let merged = vec![make_bookmark("New", "https://new.example")];
+        let staging = dir.path().join("staging");
+        write_bookmarks_atomic(
+            &[
+                WriteTarget {
+                    adapter: &brave,
+                    bookmarks: &merged,
+                },
+                WriteTarget {
+                    adapter: &chrome,
+                    bookmarks: &[],
+                },
+            ],
+            &staging,
+        )
+        .unwrap();
+
+        assert_eq!(titles(&brave), vec!["New"]);
+        assert!(titles(&chrome).is_empty());
+        assert!(dir.path().join("Brave.backup").exists());
+        assert_eq!(fs::read_dir(&staging).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_validation_failure_touches_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = vec![make_bookmark("Old", "https://old.example")];
+        let brave = FileAdapter::new(BrowserType::Brave, dir.path().join("Brave"));
+        let mut chrome = FileAdapter::new(BrowserType::Chrome, dir.path().join("Chrome"));
+        brave.write_bookmarks(&old).unwrap();
+        chrome.write_bookmarks(&old).unwrap();
+        chrome.fail_read = true;
+
+        let merged = vec![make_bookmark("New", "https://new.example")];
+        let err = write_bookmarks_atomic(
+            &[
+                WriteTarget {
+                    adapter: &brave,
+                    bookmarks: &merged,
+                },
+                WriteTarget {
+                    adapter: &chrome,
+                    bookmarks: &merged,
+                },
+            ],
+            &dir.path().join("staging"),
+        )
+        .unwrap_err();
+
+        assert!(format!("{:#}", err).contains("Chrome"));
+        assert_eq!(titles(&brave), vec!["Old"]);
+        chrome.fail_read = false;
+        assert_eq!(titles(&chrome), vec!["Old"]);
+    }
+
+    #[test]
+    fn test_swap_failure_rolls_back_written_browsers() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = vec![make_bookmark("Old", "https://old.example")];
+        let brave = FileAdapter::new(BrowserType::Brave, dir.path().join("Brave"));
+        brave.write_bookmarks(&old).unwrap();
+        // Chrome has no bookmark file yet; it must be removed again on rollback
+        let chrome = FileAdapter::new(BrowserType::Chrome, dir.path().join("Chrome"));
+        // Safari's "file" is a non-empty directory, so replacing it fails
+        let safari_path = dir.path().join("Safari");
+        fs::create_dir_all(safari_path.join("inner")).unwrap();
+        let safari = FileAdapter::new(BrowserType::Safari, safari_path);
+
+        let merged = vec![make_bookmark("New", "https://new.example")];
+        let targets = [&brave, &chrome, &safari].map(|adapter| WriteTarget {
+            adapter,
+            bookmarks: &merged,
+        });
+        let err = write_bookmarks_atomic(&targets, &dir.path().join("staging")).unwrap_err();
+
+        assert!(format!("{:#}", err).contains("rolled back"));
+        assert_eq!(titles(&brave), vec!["Old"]);
+        assert!(!dir.path().join("Chrome").exists());
+    }
+}
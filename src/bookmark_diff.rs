@@ -0,0 +1,664 @@
+//! 书签差异 - 比较任意两个书签来源
+//!
+//! 来源可以是：
+//! - 浏览器（`chrome`、`brave-nightly`，读取默认配置文件）
+//! - 浏览器的某个配置文件（`brave:Profile 1`、`waterfox:abcd.default-release`）
+//! - 书签文件：导出的 HTML / JSON、Chromium `Bookmarks`、Firefox `places.sqlite`、Safari `Bookmarks.plist`
+//!
+//! `bsync diff A B` 报告从 A 到 B 的变化：B 新增、B 缺少、改名、移动的书签和文件夹。
+//! 书签按去重用的规范化 URL 对应（与三方同步的 diff_trees 相同），文件夹移动/改名也沿用其检测。
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::browsers::{
+    get_all_adapters, Bookmark, BrowserAdapter, ChromeAdapter, SafariAdapter, WaterfoxAdapter,
+};
+use crate::sync::{read_bookmarks_file, ExportFormat, SyncEngine};
+use crate::three_way::{self, bookmark_key, BookmarkChange};
+
+/// 差异输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiffFormat {
+    /// 分组列出的可读报告
+    #[default]
+    Human,
+    /// 机器可读 JSON
+    Json,
+    /// 类似 `diff -u` 的树形输出（+ 新增 / - 缺少 / ~ 改名 / > 移入）
+    UnifiedTree,
+}
+
+impl DiffFormat {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "human" | "text" => Ok(DiffFormat::Human),
+            "json" => Ok(DiffFormat::Json),
+            "unified-tree" | "tree" | "unified" => Ok(DiffFormat::UnifiedTree),
+            other => anyhow::bail!(
+                "Unknown diff format: {} (expected human, json or unified-tree)",
+                other
+            ),
+        }
+    }
+}
+
+/// 书签来源
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffSource {
+    Browser {
+        name: String,
+        profile: Option<String>,
+    },
+    File(PathBuf),
+}
+
+impl DiffSource {
+    /// 解析来源：路径（含 `/` 或已存在的文件）按文件处理，否则为 `browser[:profile]`
+    pub fn parse(spec: &str) -> Self {
+        let path = Path::new(spec);
+        if spec.contains('/') || spec.contains('\\') || path.exists() {
+            return DiffSource::File(path.to_path_buf());
+        }
+        match spec.split_once(':') {
+            Some((name, profile)) => DiffSource::Browser {
+                name: name.trim().to_string(),
+                profile: Some(profile.trim().to_string()),
+            },
+            None => DiffSource::Browser {
+                name: spec.trim().to_string(),
+                profile: None,
+            },
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            DiffSource::Browser {
+                name,
+                profile: Some(profile),
+            } => format!("{}:{}", name, profile),
+            DiffSource::Browser { name, .. } => name.clone(),
+            DiffSource::File(path) => path.display().to_string(),
+        }
+    }
+
+    pub fn load(&self) -> Result<Vec<Bookmark>> {
+        match self {
+            DiffSource::File(path) => read_file(path),
+            DiffSource::Browser { name, profile } => {
+                let wanted = name.to_lowercase().replace('-', " ");
+                let adapter = get_all_adapters()
+                    .into_iter()
+                    .find(|a| a.browser_type().name().to_lowercase() == wanted)
+                    .with_context(|| format!("Unknown or unavailable browser: {}", name))?;
+                match profile {
+                    None => adapter.read_bookmarks(),
+                    Some(profile) => {
+                        // 同一浏览器的配置文件是默认配置文件目录的兄弟目录
+                        let default_path = adapter.detect_bookmark_path()?;
+                        let (Some(file_name), Some(root)) = (
+                            default_path.file_name(),
+                            default_path.parent().and_then(|p| p.parent()),
+                        ) else {
+                            anyhow::bail!("Cannot locate profiles of {}", name);
+                        };
+                        let path = root.join(profile).join(file_name);
+                        if !path.exists() {
+                            anyhow::bail!("Profile not found: {}", path.display());
+                        }
+                        adapter.read_bookmarks_from(&path)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 按文件名 / 扩展名选择读取方式
+fn read_file(path: &Path) -> Result<Vec<Bookmark>> {
+    if !path.exists() {
+        anyhow::bail!("File not found: {}", path.display());
+    }
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if name.ends_with(".sqlite") {
+        WaterfoxAdapter.read_bookmarks_from(path)
+    } else if name.ends_with(".plist") {
+        SafariAdapter.read_bookmarks_from(path)
+    } else if name == "bookmarks" || name == "bookmarks.bak" {
+        ChromeAdapter.read_bookmarks_from(path)
+    } else {
+        let path = path.to_string_lossy();
+        read_bookmarks_file(&path, ExportFormat::from_path(&path))
+    }
+}
+
+/// 一个来源的概况
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SourceSummary {
+    pub source: String,
+    pub bookmarks: usize,
+    /// 规范化后不同的 URL 数（重复书签只按第一次出现比较）
+    pub unique_urls: usize,
+    pub folders: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffBookmark {
+    pub path: Vec<String>,
+    pub title: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffRetitle {
+    pub path: Vec<String>,
+    pub url: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffMove {
+    pub title: String,
+    pub url: String,
+    pub from: Vec<String>,
+    pub to: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffFolderMove {
+    pub from: Vec<String>,
+    pub to: Vec<String>,
+}
+
+/// 从 left 到 right 的差异
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiffReport {
+    pub left: SourceSummary,
+    pub right: SourceSummary,
+    /// right 有、left 没有
+    pub added: Vec<DiffBookmark>,
+    /// left 有、right 没有
+    pub removed: Vec<DiffBookmark>,
+    pub retitled: Vec<DiffRetitle>,
+    pub moved: Vec<DiffMove>,
+    pub folders_added: Vec<Vec<String>>,
+    pub folders_removed: Vec<Vec<String>>,
+    pub folders_moved: Vec<DiffFolderMove>,
+}
+
+impl DiffReport {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.retitled.is_empty()
+            && self.moved.is_empty()
+            && self.folders_added.is_empty()
+            && self.folders_removed.is_empty()
+            && self.folders_moved.is_empty()
+    }
+}
+
+/// 比较两棵书签树
+pub fn diff_bookmarks(
+    left_label: &str,
+    left: &[Bookmark],
+    right_label: &str,
+    right: &[Bookmark],
+) -> DiffReport {
+    let left_index = first_by_key(left);
+    let right_index = first_by_key(right);
+    let mut report = DiffReport {
+        left: summarize(left_label, left),
+        right: summarize(right_label, right),
+        ..Default::default()
+    };
+
+    for change in three_way::diff_trees(left, right) {
+        match change {
+            BookmarkChange::Added { path, bookmark, .. } => {
+                report.added.push(DiffBookmark {
+                    path,
+                    title: bookmark.title,
+                    url: bookmark.url.unwrap_or_default(),
+                });
+            }
+            BookmarkChange::Deleted { key, path } => {
+                if let Some(entry) = left_index.get(&key) {
+                    report.removed.push(DiffBookmark {
+                        path,
+                        title: entry.bookmark.title.clone(),
+                        url: entry.bookmark.url.clone().unwrap_or_default(),
+                    });
+                }
+            }
+            BookmarkChange::Edited {
+                key,
+                old_title,
+                new_title,
+                ..
+            } => {
+                if let Some(entry) = right_index.get(&key) {
+                    report.retitled.push(DiffRetitle {
+                        path: entry.path.clone(),
+                        url: entry.bookmark.url.clone().unwrap_or_default(),
+                        from: old_title,
+                        to: new_title,
+                    });
+                }
+            }
+            BookmarkChange::Moved { key, from, to } => {
+                if let Some(entry) = right_index.get(&key) {
+                    report.moved.push(DiffMove {
+                        title: entry.bookmark.title.clone(),
+                        url: entry.bookmark.url.clone().unwrap_or_default(),
+                        from,
+                        to,
+                    });
+                }
+            }
+            BookmarkChange::FolderAdded { path } => report.folders_added.push(path),
+            BookmarkChange::FolderDeleted { path } => report.folders_removed.push(path),
+            BookmarkChange::FolderMoved { from, to } => {
+                report.folders_moved.push(DiffFolderMove { from, to })
+            }
+        }
+    }
+
+    report
+        .added
+        .sort_by(|a, b| (&a.path, &a.url).cmp(&(&b.path, &b.url)));
+    report
+        .removed
+        .sort_by(|a, b| (&a.path, &a.url).cmp(&(&b.path, &b.url)));
+    report
+        .retitled
+        .sort_by(|a, b| (&a.path, &a.url).cmp(&(&b.path, &b.url)));
+    report
+        .moved
+        .sort_by(|a, b| (&a.to, &a.url).cmp(&(&b.to, &b.url)));
+    report
+}
+
+/// 书签键 → 第一次出现的位置和书签（与 diff_trees 的索引规则一致）
+struct Located<'a> {
+    path: Vec<String>,
+    bookmark: &'a Bookmark,
+}
+
+fn first_by_key(tree: &[Bookmark]) -> HashMap<String, Located<'_>> {
+    fn walk<'a>(
+        nodes: &'a [Bookmark],
+        path: &mut Vec<String>,
+        index: &mut HashMap<String, Located<'a>>,
+    ) {
+        for node in nodes {
+            if node.folder {
+                path.push(node.title.clone());
+                walk(&node.children, path, index);
+                path.pop();
+            } else {
+                index.entry(bookmark_key(node)).or_insert_with(|| Located {
+                    path: path.clone(),
+                    bookmark: node,
+                });
+            }
+        }
+    }
+    let mut index = HashMap::new();
+    walk(tree, &mut Vec::new(), &mut index);
+    index
+}
+
+fn summarize(label: &str, tree: &[Bookmark]) -> SourceSummary {
+    // 去重时的 URL 分组，重复书签在这里体现为 unique_urls < bookmarks
+    let mut url_map = HashMap::new();
+    SyncEngine::collect_all_bookmarks(tree, &mut url_map, &[], &[]);
+    fn count_folders(nodes: &[Bookmark]) -> usize {
+        nodes
+            .iter()
+            .filter(|n| n.folder)
+            .map(|n| 1 + count_folders(&n.children))
+            .sum()
+    }
+    SourceSummary {
+        source: label.to_string(),
+        bookmarks: url_map.values().map(|locations| locations.len()).sum(),
+        unique_urls: url_map.len(),
+        folders: count_folders(tree),
+    }
+}
+
+fn show_path(path: &[String]) -> String {
+    format!("/{}", path.join("/"))
+}
+
+/// 可读报告
+pub fn render_human(report: &DiffReport) -> String {
+    let mut out = String::new();
+    for (marker, summary) in [("---", &report.left), ("+++", &report.right)] {
+        let _ = writeln!(
+            out,
+            "{} {} ({} bookmarks, {} unique URLs, {} folders)",
+            marker, summary.source, summary.bookmarks, summary.unique_urls, summary.folders
+        );
+    }
+    if report.is_empty() {
+        out.push_str("\nNo differences\n");
+        return out;
+    }
+
+    let mut section = |title: &str, lines: Vec<String>| {
+        if !lines.is_empty() {
+            let _ = writeln!(out, "\n{} ({}):", title, lines.len());
+            for line in lines {
+                let _ = writeln!(out, "  {}", line);
+            }
+        }
+    };
+    section(
+        &format!("Only in {}", report.right.source),
+        report
+            .added
+            .iter()
+            .map(|b| format!("+ {} <{}> in {}", b.title, b.url, show_path(&b.path)))
+            .collect(),
+    );
+    section(
+        &format!("Only in {}", report.left.source),
+        report
+            .removed
+            .iter()
+            .map(|b| format!("- {} <{}> in {}", b.title, b.url, show_path(&b.path)))
+            .collect(),
+    );
+    section(
+        "Retitled",
+        report
+            .retitled
+            .iter()
+            .map(|r| format!("~ \"{}\" → \"{}\" <{}>", r.from, r.to, r.url))
+            .collect(),
+    );
+    section(
+        "Moved",
+        report
+            .moved
+            .iter()
+            .map(|m| {
+                format!(
+                    "> {} <{}>: {} → {}",
+                    m.title,
+                    m.url,
+                    show_path(&m.from),
+                    show_path(&m.to)
+                )
+            })
+            .collect(),
+    );
+    section(
+        "Folders",
+        report
+            .folders_added
+            .iter()
+            .map(|p| format!("+ {}", show_path(p)))
+            .chain(
+                report
+                    .folders_removed
+                    .iter()
+                    .map(|p| format!("- {}", show_path(p))),
+            )
+            .chain(
+                report
+                    .folders_moved
+                    .iter()
+                    .map(|m| format!("> {} → {}", show_path(&m.from), show_path(&m.to))),
+            )
+            .collect(),
+    );
+    out
+}
+
+/// 类似 `diff -u` 的树：以 right 的树为骨架，把 left 独有的内容按原位置插回
+pub fn render_unified_tree(report: &DiffReport, right: &[Bookmark]) -> String {
+    let moves: Vec<(Vec<String>, Vec<String>)> = report
+        .folders_moved
+        .iter()
+        .map(|m| (m.from.clone(), m.to.clone()))
+        .collect();
+    let to_right =
+        |path: &[String]| three_way::rewrite_path(path, moves.iter().map(|(f, t)| (f, t)));
+
+    let ctx = TreeContext {
+        added: report
+            .added
+            .iter()
+            .map(|b| (b.path.clone(), b.url.clone()))
+            .collect(),
+        retitled: report
+            .retitled
+            .iter()
+            .map(|r| ((r.path.clone(), r.url.clone()), r.from.clone()))
+            .collect(),
+        moved: report
+            .moved
+            .iter()
+            .map(|m| ((m.to.clone(), m.url.clone()), m.from.clone()))
+            .collect(),
+        folders_added: report.folders_added.iter().cloned().collect(),
+        folders_moved: moves.iter().map(|(f, t)| (t.clone(), f.clone())).collect(),
+        removed: report.removed.iter().fold(BTreeMap::new(), |mut map, b| {
+            map.entry(to_right(&b.path))
+                .or_insert_with(Vec::new)
+                .push(b.clone());
+            map
+        }),
+        folders_removed: report.folders_removed.iter().map(|p| to_right(p)).collect(),
+    };
+
+    let mut out = format!("--- {}\n+++ {}\n", report.left.source, report.right.source);
+    ctx.render(right, &mut Vec::new(), &mut out);
+    out
+}
+
+struct TreeContext {
+    added: BTreeSet<(Vec<String>, String)>,
+    retitled: BTreeMap<(Vec<String>, String), String>,
+    moved: BTreeMap<(Vec<String>, String), Vec<String>>,
+    folders_added: BTreeSet<Vec<String>>,
+    /// 新路径 → 旧路径
+    folders_moved: BTreeMap<Vec<String>, Vec<String>>,
+    /// 所在文件夹（right 坐标）→ left 独有的书签
+    removed: BTreeMap<Vec<String>, Vec<DiffBookmark>>,
+    folders_removed: BTreeSet<Vec<String>>,
+}
+
+impl TreeContext {
+    fn render(&self, nodes: &[Bookmark], path: &mut Vec<String>, out: &mut String) {
+        let indent = "  ".repeat(path.len());
+        for node in nodes {
+            if node.folder {
+                path.push(node.title.clone());
+                let line = if self.folders_added.contains(path.as_slice()) {
+                    format!("+ {}📁 {}", indent, node.title)
+                } else if let Some(from) = self.folders_moved.get(path.as_slice()) {
+                    format!("> {}📁 {} (was {})", indent, node.title, show_path(from))
+                } else {
+                    format!("  {}📁 {}", indent, node.title)
+                };
+                let _ = writeln!(out, "{}", line);
+                self.render(&node.children, path, out);
+                path.pop();
+                continue;
+            }
+            let url = node.url.clone().unwrap_or_default();
+            let key = (path.clone(), url.clone());
+            let line = if self.added.contains(&key) {
+                format!("+ {}{} <{}>", indent, node.title, url)
+            } else if let Some(from) = self.retitled.get(&key) {
+                format!("~ {}{} <{}> (was \"{}\")", indent, node.title, url, from)
+            } else if let Some(from) = self.moved.get(&key) {
+                format!(
+                    "> {}{} <{}> (from {})",
+                    indent,
+                    node.title,
+                    url,
+                    show_path(from)
+                )
+            } else {
+                format!("  {}{} <{}>", indent, node.title, url)
+            };
+            let _ = writeln!(out, "{}", line);
+        }
+        self.render_removed(path, out);
+    }
+
+    /// 输出 path 下 left 独有的子文件夹和书签
+    fn render_removed(&self, path: &mut Vec<String>, out: &mut String) {
+        let indent = "  ".repeat(path.len());
+        let children: Vec<Vec<String>> = self
+            .folders_removed
+            .iter()
+            .filter(|p| p.len() == path.len() + 1 && p.starts_with(path))
+            .cloned()
+            .collect();
+        for folder in children {
+            let _ = writeln!(out, "- {}📁 {}", indent, folder[folder.len() - 1]);
+            let mut folder = folder;
+            self.render_removed(&mut folder, out);
+        }
+        for bookmark in self.removed.get(path.as_slice()).into_iter().flatten() {
+            let _ = writeln!(out, "- {}{} <{}>", indent, bookmark.title, bookmark.url);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn make_bookmark(title: &str, url: &str) -> Bookmark {
+        Bookmark {
+            id: String::new(),
+            ..test_support::make_bookmark(title, url)
+        }
+    }
+
+    fn make_folder(title: &str, children: Vec<Bookmark>) -> Bookmark {
+        Bookmark {
+            id: String::new(),
+            ..test_support::make_folder(title, children)
+        }
+    }
+
+    fn left() -> Vec<Bookmark> {
+        vec![
+            make_folder(
+                "Dev",
+                vec![
+                    make_bookmark("Rust", "https://rust-lang.org/"),
+                    make_bookmark("Go", "https://go.dev"),
+                ],
+            ),
+            make_folder("Old", vec![make_bookmark("Gone", "https://gone.example")]),
+            make_bookmark("News", "https://news.example"),
+            make_bookmark("News again", "https://NEWS.example#top"),
+        ]
+    }
+
+    fn right() -> Vec<Bookmark> {
+        vec![
+            make_folder(
+                "Dev",
+                vec![make_bookmark("Rust Language", "https://rust-lang.org")],
+            ),
+            make_folder("Archive", vec![make_bookmark("Go", "https://go.dev")]),
+            make_bookmark("News", "https://news.example"),
+            make_bookmark("Zig", "https://ziglang.org"),
+        ]
+    }
+
+    #[test]
+    fn test_diff_reports_each_kind_with_normalized_urls() {
+        let report = diff_bookmarks("A", &left(), "B", &right());
+
+        assert_eq!(report.left.bookmarks, 5);
+        // "https://NEWS.example#top" normalizes to the same URL as News
+        assert_eq!(report.left.unique_urls, 4);
+
+        let added: Vec<&str> = report.added.iter().map(|b| b.url.as_str()).collect();
+        assert_eq!(added, vec!["https://ziglang.org"]);
+        let removed: Vec<&str> = report.removed.iter().map(|b| b.url.as_str()).collect();
+        assert_eq!(removed, vec!["https://gone.example"]);
+        assert_eq!(report.retitled.len(), 1);
+        assert_eq!(report.retitled[0].from, "Rust");
+        assert_eq!(report.retitled[0].to, "Rust Language");
+        assert_eq!(report.moved.len(), 1);
+        assert_eq!(report.moved[0].to, vec!["Archive".to_string()]);
+        assert_eq!(report.folders_added, vec![vec!["Archive".to_string()]]);
+        assert_eq!(report.folders_removed, vec![vec!["Old".to_string()]]);
+    }
+
+    #[test]
+    fn test_identical_sources_have_no_differences() {
+        let report = diff_bookmarks("A", &left(), "B", &left());
+        assert!(report.is_empty());
+        assert!(render_human(&report).contains("No differences"));
+    }
+
+    #[test]
+    fn test_unified_tree_marks_lines() {
+        let report = diff_bookmarks("A", &left(), "B", &right());
+        let tree = render_unified_tree(&report, &right());
+
+        assert!(tree.starts_with("--- A\n+++ B\n"));
+        assert!(tree.contains("~   Rust Language <https://rust-lang.org> (was \"Rust\")"));
+        assert!(tree.contains("+ 📁 Archive"));
+        assert!(tree.contains(">   Go <https://go.dev> (from /Dev)"));
+        assert!(tree.contains("- 📁 Old\n-   Gone <https://gone.example>"));
+        assert!(tree.contains("+ Zig <https://ziglang.org>"));
+    }
+
+    #[test]
+    fn test_source_parsing() {
+        assert_eq!(
+            DiffSource::parse("brave:Profile 1"),
+            DiffSource::Browser {
+                name: "brave".to_string(),
+                profile: Some("Profile 1".to_string()),
+            }
+        );
+        assert_eq!(
+            DiffSource::parse("chrome"),
+            DiffSource::Browser {
+                name: "chrome".to_string(),
+                profile: None,
+            }
+        );
+        assert_eq!(
+            DiffSource::parse("./exports/week1.html"),
+            DiffSource::File(PathBuf::from("./exports/week1.html"))
+        );
+        assert!(DiffFormat::parse("unified-tree").is_ok());
+        assert!(DiffFormat::parse("yaml").is_err());
+    }
+
+    #[test]
+    fn test_load_json_file_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("export.json");
+        std::fs::write(&file, serde_json::to_string(&left()).unwrap()).unwrap();
+
+        let loaded = DiffSource::parse(&file.to_string_lossy()).load().unwrap();
+        let report = diff_bookmarks("file", &loaded, "tree", &left());
+        assert!(report.is_empty());
+    }
+}
@@ -0,0 +1,471 @@
+//! 书签排序 - 导出和写回浏览器时的排序模式
+//!
+//! 支持：
+//! - 标题（近似本地化排序：忽略 emoji/标点/空白，拉丁字母忽略大小写和重音，
+//!   数字按数值比较，中日韩文字按 Unicode 部首笔画顺序、平假名/片假名合并）
+//! - 添加时间
+//! - 域名（可注册域名）
+//! - 文件夹优先（可与以上任一模式组合）
+//!
+//! 可递归排序全部层级，也可只排序指定深度（0 = 顶层）
+
+use anyhow::{Context, Result};
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+use crate::browsers::{
+    Bookmark, BrowserAdapter, BrowserType, Cookie, HistoryItem, ReadingListItem,
+};
+use crate::sync::registrable_domain;
+
+/// 排序键
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortMode {
+    /// 标题（本地化排序）
+    Title,
+    /// 添加时间（无时间的排在最后）
+    DateAdded,
+    /// 可注册域名，同域名按标题
+    Domain,
+}
+
+/// 排序配置
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SortConfig {
+    /// 主排序键（None 时只做文件夹优先，保留原有相对顺序）
+    pub mode: Option<SortMode>,
+    /// 文件夹排在书签前面
+    pub folders_first: bool,
+    /// 只排序这些深度（None = 全部层级递归）
+    pub depths: Option<Vec<usize>>,
+}
+
+impl SortConfig {
+    /// 解析 CLI 参数
+    /// - spec: 逗号分隔，如 "title"、"folders-first,date"
+    /// - depths: 逗号分隔的深度或范围，如 "0"、"0,2"、"1-3"
+    pub fn parse(spec: &str, depths: Option<&str>) -> Result<Self> {
+        let mut config = SortConfig::default();
+        for part in spec.split(',').map(|s| s.trim().to_lowercase()) {
+            let mode = match part.as_str() {
+                "" => continue,
+                "folders-first" | "folders_first" | "folders" => {
+                    config.folders_first = true;
+                    continue;
+                }
+                "title" | "name" | "alpha" | "alphabetical" => SortMode::Title,
+                "date" | "date-added" | "added" => SortMode::DateAdded,
+                "domain" => SortMode::Domain,
+                other => anyhow::bail!(
+                    "Unknown sort mode: {} (expected title, date, domain, folders-first)",
+                    other
+                ),
+            };
+            if config.mode.is_some() {
+                anyhow::bail!("Only one of title, date, domain can be used per sort");
+            }
+            config.mode = Some(mode);
+        }
+
+        if let Some(depths) = depths {
+            config.depths = Some(parse_depths(depths)?);
+        }
+        Ok(config)
+    }
+
+    /// 用于日志的简短描述，如 "folders-first, title (depth 0,1)"
+    pub fn description(&self) -> String {
+        let mut parts = Vec::new();
+        if self.folders_first {
+            parts.push("folders-first");
+        }
+        match self.mode {
+            Some(SortMode::Title) => parts.push("title"),
+            Some(SortMode::DateAdded) => parts.push("date added"),
+            Some(SortMode::Domain) => parts.push("domain"),
+            None => {}
+        }
+        let mut desc = parts.join(", ");
+        if let Some(ref depths) = self.depths {
+            let list: Vec<String> = depths.iter().map(|d| d.to_string()).collect();
+            desc.push_str(&format!(" (depth {})", list.join(",")));
+        }
+        desc
+    }
+
+    /// 是否会改变顺序
+    pub fn is_noop(&self) -> bool {
+        self.mode.is_none() && !self.folders_first
+    }
+
+    fn applies_at(&self, depth: usize) -> bool {
+        self.depths.as_ref().is_none_or(|d| d.contains(&depth))
+    }
+
+    /// 比较两个同级项
+    pub fn compare(&self, a: &Bookmark, b: &Bookmark) -> Ordering {
+        if self.folders_first {
+            match (a.folder, b.folder) {
+                (true, false) => return Ordering::Less,
+                (false, true) => return Ordering::Greater,
+                _ => {}
+            }
+        }
+
+        match self.mode {
+            None => Ordering::Equal,
+            Some(SortMode::Title) => compare_titles(&a.title, &b.title),
+            Some(SortMode::DateAdded) => {
+                cmp_none_last(a.date_added, b.date_added).then_with(|| compare_titles(&a.title, &b.title))
+            }
+            Some(SortMode::Domain) => {
+                let da = a.url.as_deref().and_then(registrable_domain);
+                let db = b.url.as_deref().and_then(registrable_domain);
+                cmp_none_last(da, db).then_with(|| compare_titles(&a.title, &b.title))
+            }
+        }
+    }
+}
+
+/// 解析深度列表："0,2" / "1-3"
+fn parse_depths(value: &str) -> Result<Vec<usize>> {
+    let mut depths = Vec::new();
+    for part in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        if let Some((start, end)) = part.split_once('-') {
+            let start: usize = start.trim().parse().with_context(|| format!("Invalid depth range: {}", part))?;
+            let end: usize = end.trim().parse().with_context(|| format!("Invalid depth range: {}", part))?;
+            depths.extend(start..=end);
+        } else {
+            depths.push(part.parse().with_context(|| format!("Invalid depth: {}", part))?);
+        }
+    }
+    depths.sort_unstable();
+    depths.dedup();
+    Ok(depths)
+}
+
+fn cmp_none_last<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// 按配置排序书签树（稳定排序，相等项保持原顺序）
+pub fn sort_bookmarks(bookmarks: &mut [Bookmark], config: &SortConfig) {
+    fn sort_recursive(bookmarks: &mut [Bookmark], config: &SortConfig, depth: usize) {
+        if config.applies_at(depth) {
+            bookmarks.sort_by(|a, b| config.compare(a, b));
+        }
+        for bookmark in bookmarks.iter_mut().filter(|b| b.folder) {
+            sort_recursive(&mut bookmark.children, config, depth + 1);
+        }
+    }
+
+    if !config.is_noop() {
+        sort_recursive(bookmarks, config, 0);
+    }
+}
+
+/// 字符所属文字类别（决定跨文字的排序先后，参考 CLDR root 顺序）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Script {
+    Digit,
+    Latin,
+    Greek,
+    Cyrillic,
+    OtherLetter,
+    Hangul,
+    Kana,
+    Han,
+}
+
+fn script_of(c: char) -> Option<Script> {
+    let cp = c as u32;
+    let script = match cp {
+        0x0030..=0x0039 => Script::Digit,
+        0x0041..=0x005A | 0x0061..=0x007A | 0x00C0..=0x024F | 0x1E00..=0x1EFF => Script::Latin,
+        0x0370..=0x03FF => Script::Greek,
+        0x0400..=0x04FF => Script::Cyrillic,
+        0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Script::Hangul,
+        0x3040..=0x30FF => Script::Kana,
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x2FA1F => Script::Han,
+        _ if c.is_alphabetic() => Script::OtherLetter,
+        _ if c.is_numeric() => Script::Digit,
+        // emoji、标点、空白：主排序时忽略
+        _ => return None,
+    };
+    Some(script)
+}
+
+/// 拉丁字母折叠大小写和常见重音：É → e, ß → s
+fn fold_latin(c: char) -> char {
+    let lower = c.to_lowercase().next().unwrap_or(c);
+    match lower {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+        'ď' | 'đ' => 'd',
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => 'e',
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => 'g',
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => 'i',
+        'ñ' | 'ń' | 'ņ' | 'ň' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => 'o',
+        'ś' | 'ŝ' | 'ş' | 'š' | 'ß' => 's',
+        'ţ' | 'ť' => 't',
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        other => other,
+    }
+}
+
+/// 标题的主排序键：(文字类别, 权重) 序列
+/// 数字串按数值比较（"2" < "10"），片假名折叠为平假名
+pub fn collation_key(title: &str) -> Vec<(u8, u64)> {
+    let mut key = Vec::new();
+    let mut chars = title.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let Some(script) = script_of(c) else { continue };
+        let weight = match script {
+            Script::Digit => {
+                let mut value = c.to_digit(10).unwrap_or(0) as u64;
+                while let Some(d) = chars.peek().and_then(|n| n.to_digit(10)) {
+                    value = value.saturating_mul(10).saturating_add(d as u64);
+                    chars.next();
+                }
+                value
+            }
+            Script::Latin => fold_latin(c) as u64,
+            Script::Kana if (0x30A1..=0x30F6).contains(&(c as u32)) => (c as u64) - 0x60,
+            _ => c.to_lowercase().next().unwrap_or(c) as u64,
+        };
+        key.push((script as u8, weight));
+    }
+    key
+}
+
+/// 比较两个标题：先按主排序键，再按小写、原文打破平局
+pub fn compare_titles(a: &str, b: &str) -> Ordering {
+    collation_key(a)
+        .cmp(&collation_key(b))
+        .then_with(|| a.to_lowercase().cmp(&b.to_lowercase()))
+        .then_with(|| a.cmp(b))
+}
+
+/// 写回浏览器前先排序的适配器包装
+/// 其余方法全部委托给内部适配器
+pub struct SortingAdapter {
+    inner: Box<dyn BrowserAdapter + Send + Sync>,
+    sort: SortConfig,
+}
+
+impl SortingAdapter {
+    pub fn new(inner: Box<dyn BrowserAdapter + Send + Sync>, sort: SortConfig) -> Self {
+        Self { inner, sort }
+    }
+}
+
+impl BrowserAdapter for SortingAdapter {
+    fn browser_type(&self) -> BrowserType {
+        self.inner.browser_type()
+    }
+
+    fn detect_bookmark_path(&self) -> Result<PathBuf> {
+        self.inner.detect_bookmark_path()
+    }
+
+    fn read_bookmarks(&self) -> Result<Vec<Bookmark>> {
+        self.inner.read_bookmarks()
+    }
+
+    fn write_bookmarks(&self, bookmarks: &[Bookmark]) -> Result<()> {
+        let mut sorted = bookmarks.to_vec();
+        sort_bookmarks(&mut sorted, &self.sort);
+        self.inner.write_bookmarks(&sorted)
+    }
+
+    fn backup_bookmarks(&self) -> Result<PathBuf> {
+        self.inner.backup_bookmarks()
+    }
+
+    fn validate_bookmarks(&self, bookmarks: &[Bookmark]) -> Result<bool> {
+        self.inner.validate_bookmarks(bookmarks)
+    }
+
+    fn write_bookmarks_to(&self, path: &Path, bookmarks: &[Bookmark]) -> Result<()> {
+        let mut sorted = bookmarks.to_vec();
+        sort_bookmarks(&mut sorted, &self.sort);
+        self.inner.write_bookmarks_to(path, &sorted)
+    }
+
+    fn read_bookmarks_from(&self, path: &Path) -> Result<Vec<Bookmark>> {
+        self.inner.read_bookmarks_from(path)
+    }
+
+    fn supports_reading_list(&self) -> bool {
+        self.inner.supports_reading_list()
+    }
+
+    fn read_reading_list(&self) -> Result<Vec<ReadingListItem>> {
+        self.inner.read_reading_list()
+    }
+
+    fn write_reading_list(&self, items: &[ReadingListItem]) -> Result<()> {
+        self.inner.write_reading_list(items)
+    }
+
+    fn supports_history(&self) -> bool {
+        self.inner.supports_history()
+    }
+
+    fn read_history(&self, days: Option<i32>) -> Result<Vec<HistoryItem>> {
+        self.inner.read_history(days)
+    }
+
+    fn write_history(&self, items: &[HistoryItem]) -> Result<()> {
+        self.inner.write_history(items)
+    }
+
+    fn supports_cookies(&self) -> bool {
+        self.inner.supports_cookies()
+    }
+
+    fn read_cookies(&self) -> Result<Vec<Cookie>> {
+        self.inner.read_cookies()
+    }
+
+    fn write_cookies(&self, cookies: &[Cookie]) -> Result<()> {
+        self.inner.write_cookies(cookies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, make_folder};
+
+    fn make_bookmark(title: &str, url: &str, added: Option<i64>) -> Bookmark {
+        Bookmark {
+            date_added: added,
+            ..test_support::make_bookmark(title, url)
+        }
+    }
+
+    fn titles(bookmarks: &[Bookmark]) -> Vec<&str> {
+        bookmarks.iter().map(|b| b.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_compare_titles_case_accents_numbers() {
+        let mut names = vec!["item 10", "Éclair", "apple", "item 2", "Banana", "eclair"];
+        names.sort_by(|a, b| compare_titles(a, b));
+        assert_eq!(names, vec!["apple", "Banana", "eclair", "Éclair", "item 2", "item 10"]);
+    }
+
+    #[test]
+    fn test_compare_titles_ignores_leading_emoji() {
+        let mut names = vec!["📁Zeta", "Alpha", "👀 Beta"];
+        names.sort_by(|a, b| compare_titles(a, b));
+        assert_eq!(names, vec!["Alpha", "👀 Beta", "📁Zeta"]);
+    }
+
+    #[test]
+    fn test_compare_titles_cjk_after_latin_kana_folded() {
+        let mut names = vec!["中文", "한국어", "カタカナ", "English", "かたかな"];
+        names.sort_by(|a, b| compare_titles(a, b));
+        assert_eq!(names, vec!["English", "한국어", "かたかな", "カタカナ", "中文"]);
+    }
+
+    #[test]
+    fn test_parse_sort_config() {
+        let config = SortConfig::parse("folders-first,title", Some("0,2-3")).unwrap();
+        assert_eq!(config.mode, Some(SortMode::Title));
+        assert!(config.folders_first);
+        assert_eq!(config.depths, Some(vec![0, 2, 3]));
+        assert!(SortConfig::parse("title,date", None).is_err());
+        assert!(SortConfig::parse("random", None).is_err());
+        assert!(SortConfig::parse("folders-first", None).unwrap().mode.is_none());
+    }
+
+    #[test]
+    fn test_sort_by_date_and_domain() {
+        let mut bookmarks = vec![
+            make_bookmark("none", "https://z.com", None),
+            make_bookmark("new", "https://b.github.com", Some(30)),
+            make_bookmark("old", "https://a.org", Some(10)),
+        ];
+        sort_bookmarks(&mut bookmarks, &SortConfig { mode: Some(SortMode::DateAdded), ..Default::default() });
+        assert_eq!(titles(&bookmarks), vec!["old", "new", "none"]);
+
+        bookmarks.push(make_bookmark("js", "javascript:void(0)", Some(1)));
+        sort_bookmarks(&mut bookmarks, &SortConfig { mode: Some(SortMode::Domain), ..Default::default() });
+        assert_eq!(titles(&bookmarks), vec!["old", "new", "none", "js"]);
+    }
+
+    #[test]
+    fn test_folders_first_keeps_relative_order() {
+        let mut bookmarks = vec![
+            make_bookmark("b", "https://b.com", None),
+            make_folder("Z", vec![]),
+            make_bookmark("a", "https://a.com", None),
+            make_folder("A", vec![]),
+        ];
+        sort_bookmarks(&mut bookmarks, &SortConfig { folders_first: true, ..Default::default() });
+        assert_eq!(titles(&bookmarks), vec!["Z", "A", "b", "a"]);
+    }
+
+    struct RecordingAdapter {
+        written: std::sync::Arc<std::sync::Mutex<Vec<Bookmark>>>,
+    }
+
+    impl BrowserAdapter for RecordingAdapter {
+        fn browser_type(&self) -> BrowserType {
+            BrowserType::Chrome
+        }
+        fn detect_bookmark_path(&self) -> Result<PathBuf> {
+            Ok(PathBuf::new())
+        }
+        fn read_bookmarks(&self) -> Result<Vec<Bookmark>> {
+            Ok(vec![])
+        }
+        fn write_bookmarks(&self, bookmarks: &[Bookmark]) -> Result<()> {
+            *self.written.lock().unwrap() = bookmarks.to_vec();
+            Ok(())
+        }
+        fn backup_bookmarks(&self) -> Result<PathBuf> {
+            Ok(PathBuf::new())
+        }
+        fn validate_bookmarks(&self, _bookmarks: &[Bookmark]) -> Result<bool> {
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn test_sorting_adapter_sorts_on_write() {
+        let written = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let adapter = SortingAdapter::new(
+            Box::new(RecordingAdapter { written: written.clone() }),
+            SortConfig::parse("folders-first,title", None).unwrap(),
+        );
+        adapter
+            .write_bookmarks(&[make_bookmark("b", "https://b.com", None), make_folder("z", vec![])])
+            .unwrap();
+        assert_eq!(titles(&written.lock().unwrap()), vec!["z", "b"]);
+    }
+
+    #[test]
+    fn test_sort_selected_depths_only() {
+        let mut bookmarks = vec![
+            make_folder("B", vec![make_bookmark("y", "https://y.com", None), make_bookmark("x", "https://x.com", None)]),
+            make_folder("A", vec![make_bookmark("d", "https://d.com", None), make_bookmark("c", "https://c.com", None)]),
+        ];
+        let config = SortConfig { mode: Some(SortMode::Title), depths: Some(vec![1]), ..Default::default() };
+        sort_bookmarks(&mut bookmarks, &config);
+        assert_eq!(titles(&bookmarks), vec!["B", "A"], "depth 0 untouched");
+        assert_eq!(titles(&bookmarks[0].children), vec!["x", "y"]);
+        assert_eq!(titles(&bookmarks[1].children), vec!["c", "d"]);
+    }
+}
@@ -1,7 +1,7 @@
 use anyhow::Result;
 
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tracing::{debug, info, warn};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -118,6 +118,31 @@ pub trait BrowserAdapter: Send + Sync {
     fn backup_bookmarks(&self) -> Result<PathBuf>;
     fn validate_bookmarks(&self, bookmarks: &[Bookmark]) -> Result<bool>;
 
+    /// 本机装有这个浏览器：能找到书签文件且文件存在
+    fn is_installed(&self) -> bool {
+        self.detect_bookmark_path()
+            .map(|path| path.exists())
+            .unwrap_or(false)
+    }
+
+    /// 把书签写入指定文件（原子写入的暂存副本），格式与浏览器自己的书签文件相同
+    fn write_bookmarks_to(&self, path: &Path, _bookmarks: &[Bookmark]) -> Result<()> {
+        anyhow::bail!(
+            "{} does not support staged writes ({})",
+            self.browser_type().name(),
+            path.display()
+        )
+    }
+
+    /// 从指定文件读取书签（用于校验暂存副本）
+    fn read_bookmarks_from(&self, path: &Path) -> Result<Vec<Bookmark>> {
+        anyhow::bail!(
+            "{} does not support staged reads ({})",
+            self.browser_type().name(),
+            path.display()
+        )
+    }
+
     // Reading list support (reserved for future)
     #[allow(dead_code)]
     fn supports_reading_list(&self) -> bool {
@@ -266,6 +291,14 @@ impl BrowserAdapter for WaterfoxAdapter {
         Ok(true)
     }
 
+    fn write_bookmarks_to(&self, path: &Path, bookmarks: &[Bookmark]) -> Result<()> {
+        write_firefox_bookmarks(path, bookmarks)
+    }
+
+    fn read_bookmarks_from(&self, path: &Path) -> Result<Vec<Bookmark>> {
+        read_firefox_bookmarks(path)
+    }
+
     fn supports_history(&self) -> bool {
         true
     }
@@ -490,6 +523,38 @@ impl BrowserAdapter for SafariAdapter {
         Ok(true)
     }
 
+    fn write_bookmarks_to(&self, path: &Path, bookmarks: &[Bookmark]) -> Result<()> {
+        #[cfg(target_os = "macos")]
+        {
+            let plist_value = bookmarks_to_safari_plist(bookmarks)?;
+            let mut data = Vec::new();
+            plist::to_writer_xml(&mut data, &plist_value)?;
+            std::fs::write(path, data)?;
+            Ok(())
+        }
+
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = (path, bookmarks);
+            anyhow::bail!("Safari is only available on macOS")
+        }
+    }
+
+    fn read_bookmarks_from(&self, path: &Path) -> Result<Vec<Bookmark>> {
+        #[cfg(target_os = "macos")]
+        {
+            let data = std::fs::read(path)?;
+            let plist_value: plist::Value = plist::from_bytes(&data)?;
+            parse_safari_plist(&plist_value)
+        }
+
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = path;
+            anyhow::bail!("Safari is only available on macOS")
+        }
+    }
+
     fn supports_reading_list(&self) -> bool {
         true
     }
@@ -709,6 +774,14 @@ impl BrowserAdapter for BraveAdapter {
         Ok(true)
     }
 
+    fn write_bookmarks_to(&self, path: &Path, bookmarks: &[Bookmark]) -> Result<()> {
+        write_chromium_bookmarks_file(path, bookmarks)
+    }
+
+    fn read_bookmarks_from(&self, path: &Path) -> Result<Vec<Bookmark>> {
+        read_chromium_bookmarks_file(path)
+    }
+
     fn supports_history(&self) -> bool {
         true
     }
@@ -880,6 +953,14 @@ impl BrowserAdapter for BraveNightlyAdapter {
         Ok(true)
     }
 
+    fn write_bookmarks_to(&self, path: &Path, bookmarks: &[Bookmark]) -> Result<()> {
+        write_chromium_bookmarks_file(path, bookmarks)
+    }
+
+    fn read_bookmarks_from(&self, path: &Path) -> Result<Vec<Bookmark>> {
+        read_chromium_bookmarks_file(path)
+    }
+
     fn supports_history(&self) -> bool {
         true
     }
@@ -1060,6 +1141,14 @@ impl BrowserAdapter for ChromeAdapter {
         Ok(true)
     }
 
+    fn write_bookmarks_to(&self, path: &Path, bookmarks: &[Bookmark]) -> Result<()> {
+        write_chromium_bookmarks_file(path, bookmarks)
+    }
+
+    fn read_bookmarks_from(&self, path: &Path) -> Result<Vec<Bookmark>> {
+        read_chromium_bookmarks_file(path)
+    }
+
     fn supports_history(&self) -> bool {
         true
     }
@@ -1264,6 +1353,14 @@ impl BrowserAdapter for FirefoxNightlyAdapter {
     fn validate_bookmarks(&self, _bookmarks: &[Bookmark]) -> Result<bool> {
         Ok(true)
     }
+
+    fn write_bookmarks_to(&self, path: &Path, bookmarks: &[Bookmark]) -> Result<()> {
+        write_firefox_bookmarks(path, bookmarks)
+    }
+
+    fn read_bookmarks_from(&self, path: &Path) -> Result<Vec<Bookmark>> {
+        read_firefox_bookmarks(path)
+    }
 }
 
 // Helper functions for Safari plist parsing
@@ -1468,6 +1565,18 @@ fn bookmarks_to_safari_plist(bookmarks: &[Bookmark]) -> Result<plist::Value> {
 }
 
 // Helper functions for Chromium JSON parsing
+fn write_chromium_bookmarks_file(path: &Path, bookmarks: &[Bookmark]) -> Result<()> {
+    let json = bookmarks_to_chromium_json(bookmarks)?;
+    std::fs::write(path, serde_json::to_string_pretty(&json)?)?;
+    Ok(())
+}
+
+fn read_chromium_bookmarks_file(path: &Path) -> Result<Vec<Bookmark>> {
+    let data = std::fs::read_to_string(path)?;
+    let json: serde_json::Value = serde_json::from_str(&data)?;
+    parse_chromium_bookmarks(&json)
+}
+
 fn parse_chromium_bookmarks(json: &serde_json::Value) -> Result<Vec<Bookmark>> {
     let mut bookmarks = Vec::new();
 
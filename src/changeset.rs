@@ -0,0 +1,1002 @@
+//! 计划/应用工作流 - 把修改浏览器的命令结果序列化为可审阅的 JSON 变更集
+//!
+//! `organize --plan`、`check --delete --plan`（以及引擎的同步预演）不写浏览器，
+//! 而是把每个浏览器"当前树 → 目标树"的差异写成变更集：新增、删除、移动、改名、
+//! URL 改写，每条都带浏览器、配置文件和 GUID / 路径。
+//! `bsync apply` 重新读取浏览器，先校验前置条件（树指纹 + 每条变更的对象仍在原位），
+//! 全部通过后才原子写入；任何不匹配都不会修改浏览器。
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::hash::Hash;
+use std::path::Path;
+
+use crate::browsers::{Bookmark, BrowserAdapter};
+use crate::three_way::{self, bookmark_key, BookmarkChange};
+
+/// 变更集格式版本
+const CHANGESET_VERSION: u32 = 1;
+
+/// 一次计划的全部变更
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Changeset {
+    pub version: u32,
+    pub created_at: String,
+    /// 生成变更集的命令
+    pub command: String,
+    /// 每个目标浏览器在计划时的状态（前置条件）
+    pub targets: Vec<PlanTarget>,
+    /// 按应用顺序排列的变更
+    pub changes: Vec<PlannedChange>,
+}
+
+/// 目标浏览器在计划时的状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanTarget {
+    pub browser: String,
+    pub profile: String,
+    /// 书签文件路径
+    pub path: String,
+    /// 书签树指纹（标题、URL 和层级，不含 ID 和时间）
+    pub fingerprint: String,
+    pub bookmarks: usize,
+}
+
+/// 一条变更，绑定到浏览器和配置文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedChange {
+    pub browser: String,
+    pub profile: String,
+    #[serde(flatten)]
+    pub op: ChangeOp,
+}
+
+/// 变更操作；路径是文件夹标题路径，guid 是浏览器里的书签 ID（可能为空）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ChangeOp {
+    Remove {
+        guid: String,
+        path: Vec<String>,
+        title: String,
+        url: String,
+    },
+    MoveFolder {
+        guid: String,
+        from: Vec<String>,
+        to: Vec<String>,
+    },
+    AddFolder {
+        path: Vec<String>,
+    },
+    RewriteUrl {
+        guid: String,
+        path: Vec<String>,
+        from: String,
+        to: String,
+    },
+    Move {
+        guid: String,
+        url: String,
+        from: Vec<String>,
+        to: Vec<String>,
+    },
+    Rename {
+        guid: String,
+        url: String,
+        path: Vec<String>,
+        from: String,
+        to: String,
+    },
+    Add {
+        path: Vec<String>,
+        title: String,
+        url: String,
+    },
+    RemoveFolder {
+        guid: String,
+        path: Vec<String>,
+    },
+}
+
+impl ChangeOp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChangeOp::Remove { .. } => "remove",
+            ChangeOp::MoveFolder { .. } => "move_folder",
+            ChangeOp::AddFolder { .. } => "add_folder",
+            ChangeOp::RewriteUrl { .. } => "rewrite_url",
+            ChangeOp::Move { .. } => "move",
+            ChangeOp::Rename { .. } => "rename",
+            ChangeOp::Add { .. } => "add",
+            ChangeOp::RemoveFolder { .. } => "remove_folder",
+        }
+    }
+
+    /// 应用顺序：删除 → 移动文件夹 → 新建文件夹 → 改写 URL → 改名 → 移动 → 新增 → 删除空文件夹
+    fn order(&self) -> u8 {
+        match self {
+            ChangeOp::Remove { .. } => 0,
+            ChangeOp::MoveFolder { .. } => 1,
+            ChangeOp::AddFolder { .. } => 2,
+            ChangeOp::RewriteUrl { .. } => 3,
+            ChangeOp::Rename { .. } => 4,
+            ChangeOp::Move { .. } => 5,
+            ChangeOp::Add { .. } => 6,
+            ChangeOp::RemoveFolder { .. } => 7,
+        }
+    }
+}
+
+impl Changeset {
+    pub fn new(command: &str) -> Self {
+        Self {
+            version: CHANGESET_VERSION,
+            created_at: chrono::Local::now().to_rfc3339(),
+            command: command.to_string(),
+            targets: Vec::new(),
+            changes: Vec::new(),
+        }
+    }
+
+    /// 记录一个浏览器从 before 到 after 的变更，返回变更条数
+    pub fn record(
+        &mut self,
+        adapter: &dyn BrowserAdapter,
+        before: &[Bookmark],
+        after: &[Bookmark],
+    ) -> Result<usize> {
+        let browser = adapter.browser_type().name().to_string();
+        let path = adapter.detect_bookmark_path()?;
+        let profile = profile_name(&path);
+        let ops = plan_ops(before, after);
+
+        self.targets.retain(|t| t.browser != browser);
+        self.changes.retain(|c| c.browser != browser);
+        self.targets.push(PlanTarget {
+            browser: browser.clone(),
+            profile: profile.clone(),
+            path: path.display().to_string(),
+            fingerprint: fingerprint(before),
+            bookmarks: count_urls(before),
+        });
+        let count = ops.len();
+        self.changes.extend(ops.into_iter().map(|op| PlannedChange {
+            browser: browser.clone(),
+            profile: profile.clone(),
+            op,
+        }));
+        Ok(count)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read changeset: {}", path.display()))?;
+        let changeset: Changeset =
+            serde_json::from_str(&content).context("Failed to parse changeset")?;
+        if changeset.version > CHANGESET_VERSION {
+            anyhow::bail!(
+                "Changeset version {} is newer than supported ({})",
+                changeset.version,
+                CHANGESET_VERSION
+            );
+        }
+        Ok(changeset)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write changeset: {}", path.display()))
+    }
+
+    /// 每个浏览器各类操作的数量
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!(
+            "Changeset from `{}` ({}): {} changes",
+            self.command,
+            self.created_at,
+            self.changes.len()
+        )];
+        for target in &self.targets {
+            let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+            for change in self.changes.iter().filter(|c| c.browser == target.browser) {
+                *counts.entry(change.op.name()).or_default() += 1;
+            }
+            let detail: Vec<String> = counts
+                .iter()
+                .map(|(op, n)| format!("{} {}", n, op))
+                .collect();
+            lines.push(format!(
+                "  {} [{}]: {}",
+                target.browser,
+                target.profile,
+                if detail.is_empty() {
+                    "no changes".to_string()
+                } else {
+                    detail.join(", ")
+                }
+            ));
+        }
+        lines.join("\n")
+    }
+
+    /// 校验前置条件，返回所有不匹配项（为空表示可以应用）
+    pub fn check(
+        &self,
+        target: &PlanTarget,
+        current: &[Bookmark],
+        allow_drift: bool,
+    ) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !allow_drift && fingerprint(current) != target.fingerprint {
+            problems.push(format!(
+                "{}: bookmarks changed since the plan was made (use --allow-drift to apply anyway)",
+                target.browser
+            ));
+        }
+        // 同一计划里先改写了 URL 的书签，在当前树中仍是旧 URL
+        let rewrites: HashMap<(&str, &str), &str> = self
+            .changes
+            .iter()
+            .filter(|c| c.browser == target.browser)
+            .filter_map(|c| match &c.op {
+                ChangeOp::RewriteUrl { guid, from, to, .. } => {
+                    Some(((guid.as_str(), to.as_str()), from.as_str()))
+                }
+                _ => None,
+            })
+            .collect();
+        for change in self.changes.iter().filter(|c| c.browser == target.browser) {
+            let op = match &change.op {
+                ChangeOp::Move {
+                    guid,
+                    url,
+                    from,
+                    to,
+                } => ChangeOp::Move {
+                    guid: guid.clone(),
+                    url: rewrites
+                        .get(&(guid.as_str(), url.as_str()))
+                        .map_or_else(|| url.clone(), |u| u.to_string()),
+                    from: from.clone(),
+                    to: to.clone(),
+                },
+                ChangeOp::Rename {
+                    guid,
+                    url,
+                    path,
+                    from,
+                    to,
+                } => ChangeOp::Rename {
+                    guid: guid.clone(),
+                    url: rewrites
+                        .get(&(guid.as_str(), url.as_str()))
+                        .map_or_else(|| url.clone(), |u| u.to_string()),
+                    path: path.clone(),
+                    from: from.clone(),
+                    to: to.clone(),
+                },
+                op => op.clone(),
+            };
+            if let Some(problem) = check_op(&op, current) {
+                problems.push(format!("{}: {}", target.browser, problem));
+            }
+        }
+        problems
+    }
+
+    /// 把一个浏览器的变更应用到它的当前书签树上，返回实际应用的条数
+    pub fn apply_to(&self, browser: &str, tree: &mut Vec<Bookmark>) -> usize {
+        let mut applied = 0;
+        let mut folder_moves: Vec<(Vec<String>, Vec<String>)> = Vec::new();
+        for change in self.changes.iter().filter(|c| c.browser == browser) {
+            let moved = |path: &[String], moves: &[(Vec<String>, Vec<String>)]| {
+                three_way::rewrite_path(path, moves.iter().map(|(f, t)| (f, t)))
+            };
+            let done = match &change.op {
+                ChangeOp::Remove {
+                    guid, path, url, ..
+                } => take_bookmark(tree, guid, url, path).is_some(),
+                ChangeOp::MoveFolder { from, to, .. } => {
+                    let source = moved(from, &folder_moves);
+                    let done = three_way::move_folder(tree, &source, to);
+                    folder_moves.push((from.clone(), to.clone()));
+                    done
+                }
+                ChangeOp::AddFolder { path } => three_way::ensure_folder(tree, path),
+                ChangeOp::RewriteUrl {
+                    guid,
+                    path,
+                    from,
+                    to,
+                } => match find_bookmark_mut(tree, guid, from, &moved(path, &folder_moves)) {
+                    Some(bookmark) => {
+                        bookmark.url = Some(to.clone());
+                        true
+                    }
+                    None => false,
+                },
+                ChangeOp::Move {
+                    guid,
+                    url,
+                    from,
+                    to,
+                } => match take_bookmark(tree, guid, url, &moved(from, &folder_moves)) {
+                    Some(bookmark) => {
+                        three_way::insert_at_path(tree, to, bookmark);
+                        true
+                    }
+                    None => false,
+                },
+                ChangeOp::Rename {
+                    guid,
+                    url,
+                    path,
+                    to,
+                    ..
+                } => match find_bookmark_mut(tree, guid, url, &moved(path, &folder_moves)) {
+                    Some(bookmark) => {
+                        bookmark.title = to.clone();
+                        true
+                    }
+                    None => false,
+                },
+                ChangeOp::Add { path, title, url } => {
+                    three_way::insert_at_path(
+                        tree,
+                        path,
+                        Bookmark {
+                            id: format!("plan-{}", uuid::Uuid::new_v4()),
+                            title: title.clone(),
+                            url: Some(url.clone()),
+                            folder: false,
+                            children: Vec::new(),
+                            date_added: Some(chrono::Utc::now().timestamp_millis()),
+                            date_modified: None,
+                        },
+                    );
+                    true
+                }
+                ChangeOp::RemoveFolder { path, .. } => {
+                    three_way::remove_empty_folder(tree, &moved(path, &folder_moves))
+                }
+            };
+            if done {
+                applied += 1;
+            }
+        }
+        applied
+    }
+}
+
+/// 从 before 到 after 计算变更操作（已按应用顺序排序）
+///
+/// 书签按出现位置配对，同一 URL 在多个文件夹里的每一份各自对应一条操作：
+/// 先配 GUID 和键都相同的，再配同一文件夹里同键的，然后配同 GUID 的（URL 改写），
+/// 最后配同键的（移动）；剩下的就是删除和新增。
+pub fn plan_ops(before: &[Bookmark], after: &[Bookmark]) -> Vec<ChangeOp> {
+    let folders = index_folders(before);
+    let mut ops = Vec::new();
+    let mut folder_moves: Vec<(Vec<String>, Vec<String>)> = Vec::new();
+
+    for change in three_way::diff_trees(before, after) {
+        match change {
+            BookmarkChange::FolderAdded { path } => ops.push(ChangeOp::AddFolder { path }),
+            BookmarkChange::FolderMoved { from, to } => {
+                folder_moves.push((from.clone(), to.clone()));
+                ops.push(ChangeOp::MoveFolder {
+                    guid: folders.get(&from).cloned().unwrap_or_default(),
+                    from,
+                    to,
+                });
+            }
+            BookmarkChange::FolderDeleted { path } => ops.push(ChangeOp::RemoveFolder {
+                guid: folders.get(&path).cloned().unwrap_or_default(),
+                path,
+            }),
+            _ => {}
+        }
+    }
+    let moved =
+        |path: &[String]| three_way::rewrite_path(path, folder_moves.iter().map(|(f, t)| (f, t)));
+    let guid = |o: &Occurrence| (!o.bookmark.id.is_empty()).then(|| o.bookmark.id.clone());
+
+    let mut old = occurrences(before);
+    let mut new = occurrences(after);
+    let mut pairs = Vec::new();
+    pair_off(
+        &mut old,
+        &mut new,
+        &mut pairs,
+        |o| guid(o).map(|g| (g, o.key.clone())),
+        |o| guid(o).map(|g| (g, o.key.clone())),
+    );
+    pair_off(
+        &mut old,
+        &mut new,
+        &mut pairs,
+        |o| Some((o.key.clone(), moved(&o.path))),
+        |o| Some((o.key.clone(), o.path.clone())),
+    );
+    pair_off(&mut old, &mut new, &mut pairs, guid, guid);
+    pair_off(
+        &mut old,
+        &mut new,
+        &mut pairs,
+        |o| Some(o.key.clone()),
+        |o| Some(o.key.clone()),
+    );
+
+    for (old, new) in pairs {
+        let guid = old.bookmark.id.clone();
+        let old_url = old.bookmark.url.clone().unwrap_or_default();
+        let new_url = new.bookmark.url.clone().unwrap_or_default();
+        // URL 改写之后，移动和改名都按新 URL 找书签
+        let url = if old.key != new.key {
+            ops.push(ChangeOp::RewriteUrl {
+                guid: guid.clone(),
+                path: old.path.clone(),
+                from: old_url,
+                to: new_url.clone(),
+            });
+            new_url
+        } else {
+            old_url
+        };
+        // 随文件夹一起移动的书签不单独记录
+        if moved(&old.path) != new.path {
+            ops.push(ChangeOp::Move {
+                guid: guid.clone(),
+                url: url.clone(),
+                from: old.path.clone(),
+                to: new.path,
+            });
+        }
+        if old.bookmark.title != new.bookmark.title {
+            ops.push(ChangeOp::Rename {
+                guid,
+                url,
+                path: old.path,
+                from: old.bookmark.title.clone(),
+                to: new.bookmark.title.clone(),
+            });
+        }
+    }
+    for old in old {
+        ops.push(ChangeOp::Remove {
+            guid: old.bookmark.id.clone(),
+            path: old.path,
+            title: old.bookmark.title.clone(),
+            url: old.bookmark.url.clone().unwrap_or_default(),
+        });
+    }
+    for new in new {
+        ops.push(ChangeOp::Add {
+            path: new.path,
+            title: new.bookmark.title.clone(),
+            url: new.bookmark.url.clone().unwrap_or_default(),
+        });
+    }
+
+    ops.sort_by(|a, b| {
+        a.order()
+            .cmp(&b.order())
+            .then_with(|| op_sort_key(a).cmp(&op_sort_key(b)))
+    });
+    ops
+}
+
+/// 书签在树中的一次出现
+struct Occurrence<'a> {
+    path: Vec<String>,
+    bookmark: &'a Bookmark,
+    key: String,
+}
+
+/// 按树中顺序列出所有书签的出现位置
+fn occurrences(tree: &[Bookmark]) -> Vec<Occurrence<'_>> {
+    fn walk<'a>(nodes: &'a [Bookmark], path: &mut Vec<String>, out: &mut Vec<Occurrence<'a>>) {
+        for node in nodes {
+            if node.folder {
+                path.push(node.title.clone());
+                walk(&node.children, path, out);
+                path.pop();
+            } else {
+                out.push(Occurrence {
+                    path: path.clone(),
+                    bookmark: node,
+                    key: bookmark_key(node),
+                });
+            }
+        }
+    }
+    let mut out = Vec::new();
+    walk(tree, &mut Vec::new(), &mut out);
+    out
+}
+
+/// 按配对键把 old 和 new 里还没配上的出现一一配对（同键的多份按树中顺序），
+/// 配上的从两边移到 pairs
+fn pair_off<'a, K: Eq + Hash>(
+    old: &mut Vec<Occurrence<'a>>,
+    new: &mut Vec<Occurrence<'a>>,
+    pairs: &mut Vec<(Occurrence<'a>, Occurrence<'a>)>,
+    old_key: impl Fn(&Occurrence<'a>) -> Option<K>,
+    new_key: impl Fn(&Occurrence<'a>) -> Option<K>,
+) {
+    let mut waiting: HashMap<K, VecDeque<usize>> = HashMap::new();
+    for (i, occurrence) in new.iter().enumerate() {
+        if let Some(key) = new_key(occurrence) {
+            waiting.entry(key).or_default().push_back(i);
+        }
+    }
+    let matches: Vec<Option<usize>> = old
+        .iter()
+        .map(|o| old_key(o).and_then(|key| waiting.get_mut(&key)?.pop_front()))
+        .collect();
+
+    let mut slots: Vec<Option<Occurrence<'a>>> = new.drain(..).map(Some).collect();
+    let mut unmatched = Vec::new();
+    for (occurrence, matched) in old.drain(..).zip(matches) {
+        match matched.and_then(|j| slots[j].take()) {
+            Some(other) => pairs.push((occurrence, other)),
+            None => unmatched.push(occurrence),
+        }
+    }
+    *old = unmatched;
+    *new = slots.into_iter().flatten().collect();
+}
+
+/// 同类操作内的稳定顺序：父文件夹先于子文件夹，其余按路径和 URL
+fn op_sort_key(op: &ChangeOp) -> (Vec<String>, String) {
+    match op {
+        ChangeOp::Remove { path, url, .. } => (path.clone(), url.clone()),
+        ChangeOp::MoveFolder { from, .. } => (from.clone(), String::new()),
+        ChangeOp::AddFolder { path } => (path.clone(), String::new()),
+        ChangeOp::RewriteUrl { path, from, .. } => (path.clone(), from.clone()),
+        ChangeOp::Move { to, url, .. } => (to.clone(), url.clone()),
+        ChangeOp::Rename { path, url, .. } => (path.clone(), url.clone()),
+        ChangeOp::Add { path, url, .. } => (path.clone(), url.clone()),
+        // 子文件夹先删，父文件夹才可能为空
+        ChangeOp::RemoveFolder { path, .. } => (
+            vec![format!("{:08}", usize::MAX - path.len())],
+            path.join("/"),
+        ),
+    }
+}
+
+/// 对当前书签树检查单条变更的前置条件
+fn check_op(op: &ChangeOp, current: &[Bookmark]) -> Option<String> {
+    let missing =
+        |url: &str, path: &[String]| format!("{} is no longer in /{}", url, path.join("/"));
+    match op {
+        ChangeOp::Remove {
+            guid, path, url, ..
+        }
+        | ChangeOp::Move {
+            guid,
+            url,
+            from: path,
+            ..
+        } => find_bookmark(current, guid, url, path)
+            .is_none()
+            .then(|| missing(url, path)),
+        ChangeOp::RewriteUrl {
+            guid, path, from, ..
+        } => find_bookmark(current, guid, from, path)
+            .is_none()
+            .then(|| missing(from, path)),
+        ChangeOp::Rename {
+            guid,
+            url,
+            path,
+            from,
+            ..
+        } => match find_bookmark(current, guid, url, path) {
+            None => Some(missing(url, path)),
+            Some(bookmark) if bookmark.title != *from => Some(format!(
+                "{} was renamed to \"{}\" (plan expects \"{}\")",
+                url, bookmark.title, from
+            )),
+            Some(_) => None,
+        },
+        ChangeOp::Add { path, url, .. } => find_bookmark(current, "", url, path)
+            .is_some()
+            .then(|| format!("{} is already in /{}", url, path.join("/"))),
+        ChangeOp::MoveFolder { from: path, .. } | ChangeOp::RemoveFolder { path, .. } => {
+            folder_children(current, path)
+                .is_none()
+                .then(|| format!("folder /{} no longer exists", path.join("/")))
+        }
+        ChangeOp::AddFolder { .. } => None,
+    }
+}
+
+fn same_bookmark(bookmark: &Bookmark, guid: &str, key: &str) -> bool {
+    !bookmark.folder && bookmark_key(bookmark) == key && (guid.is_empty() || bookmark.id == guid)
+}
+
+fn url_key(url: &str) -> String {
+    bookmark_key(&Bookmark {
+        id: String::new(),
+        title: String::new(),
+        url: Some(url.to_string()),
+        folder: false,
+        children: Vec::new(),
+        date_added: None,
+        date_modified: None,
+    })
+}
+
+fn folder_children<'a>(tree: &'a [Bookmark], path: &[String]) -> Option<&'a [Bookmark]> {
+    match path.split_first() {
+        None => Some(tree),
+        Some((first, rest)) => {
+            let folder = tree.iter().find(|b| b.folder && &b.title == first)?;
+            folder_children(&folder.children, rest)
+        }
+    }
+}
+
+/// 在指定文件夹中找书签：GUID 匹配优先，其次同 URL（GUID 在浏览器重写后可能变化）
+fn find_bookmark<'a>(
+    tree: &'a [Bookmark],
+    guid: &str,
+    url: &str,
+    path: &[String],
+) -> Option<&'a Bookmark> {
+    let key = url_key(url);
+    let children = folder_children(tree, path)?;
+    children
+        .iter()
+        .find(|b| same_bookmark(b, guid, &key))
+        .or_else(|| children.iter().find(|b| same_bookmark(b, "", &key)))
+}
+
+fn find_bookmark_mut<'a>(
+    tree: &'a mut Vec<Bookmark>,
+    guid: &str,
+    url: &str,
+    path: &[String],
+) -> Option<&'a mut Bookmark> {
+    let key = url_key(url);
+    let children = three_way::folder_children_mut(tree, path)?;
+    let pos = children
+        .iter()
+        .position(|b| same_bookmark(b, guid, &key))
+        .or_else(|| children.iter().position(|b| same_bookmark(b, "", &key)))?;
+    children.get_mut(pos)
+}
+
+fn take_bookmark(
+    tree: &mut Vec<Bookmark>,
+    guid: &str,
+    url: &str,
+    path: &[String],
+) -> Option<Bookmark> {
+    let key = url_key(url);
+    let children = three_way::folder_children_mut(tree, path)?;
+    let pos = children
+        .iter()
+        .position(|b| same_bookmark(b, guid, &key))
+        .or_else(|| children.iter().position(|b| same_bookmark(b, "", &key)))?;
+    Some(children.remove(pos))
+}
+
+/// 文件夹路径 → 文件夹 ID
+fn index_folders(tree: &[Bookmark]) -> HashMap<Vec<String>, String> {
+    fn walk(nodes: &[Bookmark], path: &mut Vec<String>, index: &mut HashMap<Vec<String>, String>) {
+        for node in nodes.iter().filter(|n| n.folder) {
+            path.push(node.title.clone());
+            index.entry(path.clone()).or_insert_with(|| node.id.clone());
+            walk(&node.children, path, index);
+            path.pop();
+        }
+    }
+    let mut index = HashMap::new();
+    walk(tree, &mut Vec::new(), &mut index);
+    index
+}
+
+/// 书签树指纹：只看层级、标题和 URL（浏览器重写时会变的 ID 和时间不参与）
+pub fn fingerprint(tree: &[Bookmark]) -> String {
+    fn walk(nodes: &[Bookmark], depth: usize, hasher: &mut Sha256) {
+        for node in nodes {
+            hasher.update(format!("{}|{}|", depth, node.folder).as_bytes());
+            hasher.update(node.title.as_bytes());
+            hasher.update(b"|");
+            hasher.update(node.url.as_deref().unwrap_or("").as_bytes());
+            hasher.update(b"\n");
+            walk(&node.children, depth + 1, hasher);
+        }
+    }
+    let mut hasher = Sha256::new();
+    walk(tree, 0, &mut hasher);
+    format!("{:x}", hasher.finalize())
+}
+
+/// 配置文件名：书签文件所在目录名（如 "Default"、"abcd.default-release"）
+fn profile_name(path: &Path) -> String {
+    path.parent()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn count_urls(tree: &[Bookmark]) -> usize {
+    tree.iter()
+        .map(|b| if b.folder { count_urls(&b.children) } else { 1 })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn make_bookmark(id: &str, title: &str, url: &str) -> Bookmark {
+        Bookmark {
+            id: id.to_string(),
+            date_added: Some(1000000),
+            ..test_support::make_bookmark(title, url)
+        }
+    }
+
+    fn make_folder(id: &str, title: &str, children: Vec<Bookmark>) -> Bookmark {
+        Bookmark {
+            id: id.to_string(),
+            date_added: Some(1000000),
+            ..test_support::make_folder(title, children)
+        }
+    }
+
+    fn path(parts: &[&str]) -> Vec<String> {
+        parts.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn before() -> Vec<Bookmark> {
+        vec![
+            make_folder(
+                "1",
+                "Dev",
+                vec![
+                    make_bookmark("2", "Rust", "https://rust-lang.org"),
+                    make_bookmark("3", "Go", "https://go.dev"),
+                    make_bookmark("4", "Old docs", "http://docs.example.com/v1"),
+                ],
+            ),
+            make_bookmark("5", "Dead", "https://dead.example"),
+        ]
+    }
+
+    fn after() -> Vec<Bookmark> {
+        vec![
+            make_folder(
+                "1",
+                "Dev",
+                vec![
+                    make_bookmark("2", "Rust Lang", "https://rust-lang.org"),
+                    make_bookmark("4", "Old docs", "https://docs.example.com/v2"),
+                ],
+            ),
+            make_folder(
+                "9",
+                "Archive",
+                vec![make_bookmark("3", "Go", "https://go.dev")],
+            ),
+            make_bookmark("10", "Zig", "https://ziglang.org"),
+        ]
+    }
+
+    fn changeset(before: &[Bookmark], after: &[Bookmark]) -> Changeset {
+        let mut changeset = Changeset::new("test");
+        changeset.targets.push(PlanTarget {
+            browser: "Brave".to_string(),
+            profile: "Default".to_string(),
+            path: "/tmp/Bookmarks".to_string(),
+            fingerprint: fingerprint(before),
+            bookmarks: count_urls(before),
+        });
+        changeset.changes = plan_ops(before, after)
+            .into_iter()
+            .map(|op| PlannedChange {
+                browser: "Brave".to_string(),
+                profile: "Default".to_string(),
+                op,
+            })
+            .collect();
+        changeset
+    }
+
+    #[test]
+    fn test_plan_ops_covers_every_kind() {
+        let ops = plan_ops(&before(), &after());
+        let names: Vec<&str> = ops.iter().map(|op| op.name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "remove",
+                "add_folder",
+                "rewrite_url",
+                "rename",
+                "move",
+                "add"
+            ]
+        );
+        assert_eq!(
+            ops[0],
+            ChangeOp::Remove {
+                guid: "5".into(),
+                path: vec![],
+                title: "Dead".into(),
+                url: "https://dead.example".into(),
+            }
+        );
+        assert_eq!(
+            ops[2],
+            ChangeOp::RewriteUrl {
+                guid: "4".into(),
+                path: path(&["Dev"]),
+                from: "http://docs.example.com/v1".into(),
+                to: "https://docs.example.com/v2".into(),
+            }
+        );
+        assert_eq!(
+            ops[4],
+            ChangeOp::Move {
+                guid: "3".into(),
+                url: "https://go.dev".into(),
+                from: path(&["Dev"]),
+                to: path(&["Archive"]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_apply_reproduces_planned_tree() {
+        let changeset = changeset(&before(), &after());
+        let target = &changeset.targets[0];
+        assert!(changeset.check(target, &before(), false).is_empty());
+
+        let mut tree = before();
+        let applied = changeset.apply_to("Brave", &mut tree);
+        assert_eq!(applied, changeset.changes.len());
+        assert_eq!(fingerprint(&tree), fingerprint(&after()));
+    }
+
+    #[test]
+    fn test_plan_removes_every_copy_of_a_url() {
+        let before = vec![
+            make_folder(
+                "1",
+                "Dev",
+                vec![make_bookmark("2", "Rust", "https://rust-lang.org")],
+            ),
+            make_folder(
+                "3",
+                "Reading",
+                vec![
+                    make_bookmark("4", "Rust", "https://rust-lang.org/"),
+                    make_bookmark("5", "Go", "https://go.dev"),
+                ],
+            ),
+        ];
+        let after = vec![
+            make_folder("1", "Dev", vec![]),
+            make_folder(
+                "3",
+                "Reading",
+                vec![make_bookmark("5", "Go", "https://go.dev")],
+            ),
+        ];
+        let changeset = changeset(&before, &after);
+        let guids: Vec<&str> = changeset
+            .changes
+            .iter()
+            .filter_map(|c| match &c.op {
+                ChangeOp::Remove { guid, .. } => Some(guid.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(guids, vec!["2", "4"]);
+        assert!(changeset
+            .check(&changeset.targets[0], &before, false)
+            .is_empty());
+
+        let mut tree = before.clone();
+        assert_eq!(changeset.apply_to("Brave", &mut tree), 2);
+        assert_eq!(fingerprint(&tree), fingerprint(&after));
+    }
+
+    #[test]
+    fn test_plan_moves_one_copy_of_a_url() {
+        let before = vec![
+            make_folder(
+                "1",
+                "Dev",
+                vec![make_bookmark("2", "Rust", "https://rust-lang.org")],
+            ),
+            make_folder(
+                "3",
+                "Reading",
+                vec![make_bookmark("4", "Rust", "https://rust-lang.org")],
+            ),
+        ];
+        let after = vec![
+            make_folder(
+                "1",
+                "Dev",
+                vec![make_bookmark("2", "Rust", "https://rust-lang.org")],
+            ),
+            make_folder("3", "Reading", vec![]),
+            make_folder(
+                "6",
+                "Archive",
+                vec![make_bookmark("4", "Rust", "https://rust-lang.org")],
+            ),
+        ];
+        let ops = plan_ops(&before, &after);
+        assert_eq!(
+            ops,
+            vec![
+                ChangeOp::AddFolder {
+                    path: path(&["Archive"]),
+                },
+                ChangeOp::Move {
+                    guid: "4".into(),
+                    url: "https://rust-lang.org".into(),
+                    from: path(&["Reading"]),
+                    to: path(&["Archive"]),
+                },
+            ]
+        );
+
+        let changeset = changeset(&before, &after);
+        let mut tree = before.clone();
+        assert_eq!(changeset.apply_to("Brave", &mut tree), 2);
+        assert_eq!(fingerprint(&tree), fingerprint(&after));
+    }
+
+    #[test]
+    fn test_check_reports_drift_and_missing_items() {
+        let changeset = changeset(&before(), &after());
+        let target = &changeset.targets[0];
+
+        // Go was deleted and Rust renamed after the plan was made
+        let mut drifted = before();
+        drifted[0].children.remove(1);
+        drifted[0].children[0].title = "Rust!".to_string();
+
+        let problems = changeset.check(target, &drifted, false);
+        assert!(problems[0].contains("changed since the plan"));
+        assert!(problems.iter().any(|p| p.contains("https://go.dev")));
+        assert!(problems.iter().any(|p| p.contains("renamed to \"Rust!\"")));
+
+        // --allow-drift only skips the fingerprint check
+        let problems = changeset.check(target, &drifted, true);
+        assert_eq!(problems.len(), 2);
+    }
+
+    #[test]
+    fn test_changeset_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("plan.json");
+        let changeset = changeset(&before(), &after());
+        changeset.save(&file).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&file).unwrap()).unwrap();
+        assert_eq!(json["changes"][0]["op"], "remove");
+        assert_eq!(json["changes"][0]["browser"], "Brave");
+        assert_eq!(json["changes"][0]["profile"], "Default");
+
+        let loaded = Changeset::load(&file).unwrap();
+        assert_eq!(loaded.changes.len(), changeset.changes.len());
+        assert!(loaded.summary().contains("1 rewrite_url"));
+    }
+}
@@ -0,0 +1,459 @@
+//! 链接检查缓存 - 把 `UrlCheckResult` 存在本地 SQLite（`~/.browser-sync/check-cache.db`）
+//!
+//! 以规范化 URL 为键，记录状态、代理/直连状态码、延迟、最终 URL 和检查时间（完整结果另存为 JSON）。
+//! 缓存按状态设置有效期：有效结果保留较久，无效结果隔几天复查，不确定结果很快重试；
+//! 跳过的 URL 不需要网络请求，不缓存。
+//!
+//! 选取待检查 URL 时，从未检查过的排最前，其次是检查时间最早的，`--limit` 截取的就是这一顺序。
+//! 规范化后相同的几种写法只检查一次，合并结果时每种写法各得一份。
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::url_canon::canonicalize;
+use crate::url_checker::{UrlCheckResult, ValidationStatus};
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// 各状态结果的有效期（秒）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CacheTtl {
+    pub valid: i64,
+    pub invalid: i64,
+    pub uncertain: i64,
+}
+
+impl Default for CacheTtl {
+    fn default() -> Self {
+        Self {
+            valid: 30 * DAY,
+            invalid: 7 * DAY,
+            uncertain: DAY,
+        }
+    }
+}
+
+impl CacheTtl {
+    /// 解析 CLI 参数，如 "valid=60d,uncertain=6h"（单位 d/h/m，未提到的状态用默认值）
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut ttl = Self::default();
+        for part in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (status, duration) = part
+                .split_once('=')
+                .with_context(|| format!("Cache TTL must be status=duration, got \"{}\"", part))?;
+            let seconds = parse_duration(duration.trim())?;
+            match status.trim().to_lowercase().as_str() {
+                "valid" => ttl.valid = seconds,
+                "invalid" => ttl.invalid = seconds,
+                "uncertain" => ttl.uncertain = seconds,
+                other => bail!(
+                    "Unknown status in cache TTL: {} (expected valid, invalid, uncertain)",
+                    other
+                ),
+            }
+        }
+        Ok(ttl)
+    }
+
+    fn for_status(&self, status: ValidationStatus) -> i64 {
+        match status {
+            ValidationStatus::Valid => self.valid,
+            ValidationStatus::Invalid => self.invalid,
+            ValidationStatus::Uncertain => self.uncertain,
+            ValidationStatus::Skipped => 0,
+        }
+    }
+}
+
+fn parse_duration(value: &str) -> Result<i64> {
+    let (number, multiplier) = [("d", DAY), ("h", 60 * 60), ("m", 60)]
+        .into_iter()
+        .find_map(|(unit, multiplier)| Some((value.strip_suffix(unit)?, multiplier)))
+        .with_context(|| format!("Cache TTL needs a unit d, h or m: \"{}\"", value))?;
+    let number: i64 = number
+        .parse()
+        .with_context(|| format!("Invalid cache TTL: \"{}\"", value))?;
+    if number < 0 {
+        bail!("Cache TTL cannot be negative: \"{}\"", value);
+    }
+    number
+        .checked_mul(multiplier)
+        .with_context(|| format!("Cache TTL too large: \"{}\"", value))
+}
+
+/// 选取结果：可直接使用的缓存结果 + 本次要检查的 URL
+#[derive(Debug, Default)]
+pub struct CheckPlan {
+    pub cached: Vec<UrlCheckResult>,
+    pub to_check: Vec<String>,
+    /// 因 `--limit` 留到下次检查的 URL 数
+    pub deferred: usize,
+    /// 规范化后相同的其他写法：首次出现的写法 → 其余写法
+    pub aliases: HashMap<String, Vec<String>>,
+}
+
+impl CheckPlan {
+    /// 合并本次检查的结果和缓存结果，并给同一 URL 的其他写法各复制一份
+    pub fn merge(self, checked: Vec<UrlCheckResult>) -> Vec<UrlCheckResult> {
+        let mut results = checked;
+        results.extend(self.cached);
+        let copies: Vec<UrlCheckResult> = results
+            .iter()
+            .flat_map(|result| {
+                self.aliases
+                    .get(&result.url)
+                    .into_iter()
+                    .flatten()
+                    .map(move |alias| UrlCheckResult {
+                        url: alias.clone(),
+                        ..result.clone()
+                    })
+            })
+            .collect();
+        results.extend(copies);
+        results
+    }
+}
+
+pub struct CheckCache {
+    conn: Connection,
+}
+
+impl CheckCache {
+    pub fn default_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME")?;
+        Ok(PathBuf::from(format!(
+            "{}/.browser-sync/check-cache.db",
+            home
+        )))
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open check cache {}", path.display()))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS checks (
+                url TEXT PRIMARY KEY,
+                status TEXT NOT NULL,
+                proxy_code INTEGER,
+                direct_code INTEGER,
+                latency_ms INTEGER,
+                final_url TEXT,
+                checked_at INTEGER NOT NULL,
+                result TEXT NOT NULL
+            );",
+        )?;
+        Ok(Self { conn })
+    }
+
+    /// 缓存的结果和检查时间
+    pub fn get(&self, url: &str) -> Result<Option<(UrlCheckResult, i64)>> {
+        let row: Option<(String, i64)> = self
+            .conn
+            .query_row(
+                "SELECT result, checked_at FROM checks WHERE url = ?1",
+                params![canonicalize(url)],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((json, checked_at)) = row else {
+            return Ok(None);
+        };
+        let mut result: UrlCheckResult = serde_json::from_str(&json)?;
+        // 同一规范化 URL 可能来自不同的原始写法
+        result.url = url.to_string();
+        Ok(Some((result, checked_at)))
+    }
+
+    /// 保存检查结果（跳过的不保存）
+    pub fn store(&mut self, results: &[UrlCheckResult], now: i64) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut stored = 0;
+        for result in results {
+            if result.status == ValidationStatus::Skipped {
+                continue;
+            }
+            let direct = result.direct_result.as_ref();
+            let latency = [result.direct_result.as_ref(), result.proxy_result.as_ref()]
+                .into_iter()
+                .flatten()
+                .map(|r| r.latency_ms)
+                .min();
+            let final_url = [result.direct_result.as_ref(), result.proxy_result.as_ref()]
+                .into_iter()
+                .flatten()
+                .find_map(|r| r.final_url.clone());
+            tx.execute(
+                "INSERT OR REPLACE INTO checks
+                    (url, status, proxy_code, direct_code, latency_ms, final_url, checked_at, result)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    canonicalize(&result.url),
+                    format!("{:?}", result.status),
+                    result.proxy_result.as_ref().and_then(|r| r.status_code),
+                    direct.and_then(|r| r.status_code),
+                    latency.map(|l| l as i64),
+                    final_url,
+                    now,
+                    serde_json::to_string(result)?,
+                ],
+            )?;
+            stored += 1;
+        }
+        tx.commit()?;
+        Ok(stored)
+    }
+
+    /// 按有效期分出缓存命中和待检查 URL；`refresh` 忽略缓存结果，`limit` 为 0 时不限制
+    pub fn plan(
+        &self,
+        urls: Vec<String>,
+        ttl: &CacheTtl,
+        now: i64,
+        refresh: bool,
+        limit: usize,
+    ) -> Result<CheckPlan> {
+        let mut plan = CheckPlan::default();
+        let mut stale: Vec<(Option<i64>, String)> = Vec::new();
+        // 规范化 URL → 首次出现的写法
+        let mut seen: HashMap<String, String> = HashMap::new();
+        for url in urls {
+            let key = canonicalize(&url);
+            if let Some(first) = seen.get(&key) {
+                let aliases = plan.aliases.entry(first.clone()).or_default();
+                if *first != url && !aliases.contains(&url) {
+                    aliases.push(url);
+                }
+                continue;
+            }
+            seen.insert(key, url.clone());
+            match self.get(&url)? {
+                Some((result, checked_at))
+                    if !refresh && now - checked_at < ttl.for_status(result.status) =>
+                {
+                    plan.cached.push(result)
+                }
+                Some((_, checked_at)) => stale.push((Some(checked_at), url)),
+                None => stale.push((None, url)),
+            }
+        }
+        // 从未检查的在前（None < Some），其次最早检查的
+        stale.sort();
+        if limit > 0 && stale.len() > limit {
+            plan.deferred = stale.len() - limit;
+            stale.truncate(limit);
+        }
+        plan.to_check = stale.into_iter().map(|(_, url)| url).collect();
+        plan.aliases.retain(|_, aliases| !aliases.is_empty());
+        Ok(plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::url_checker::HttpResult;
+    use tempfile::TempDir;
+
+    fn result(url: &str, code: u16) -> UrlCheckResult {
+        UrlCheckResult::from_results(url.to_string(), None, Some(HttpResult::success(code, 42)))
+    }
+
+    #[test]
+    fn test_parse_ttl() {
+        let ttl = CacheTtl::parse("valid=60d, uncertain=6h").unwrap();
+        assert_eq!(ttl.valid, 60 * DAY);
+        assert_eq!(ttl.invalid, CacheTtl::default().invalid);
+        assert_eq!(ttl.uncertain, 6 * 60 * 60);
+        assert!(CacheTtl::parse("valid=10").is_err());
+        assert!(CacheTtl::parse("skipped=1d").is_err());
+        // 多字节单位、负数和溢出都是错误，不会 panic
+        assert!(CacheTtl::parse("valid=7天").is_err());
+        assert!(CacheTtl::parse("valid=-1d").is_err());
+        assert!(CacheTtl::parse("valid=9223372036854775807d").is_err());
+        assert_eq!(CacheTtl::parse("valid=0m").unwrap().valid, 0);
+    }
+
+    #[test]
+    fn test_cache_roundtrip_ttl_and_order() {
+        let dir = TempDir::new().unwrap();
+        let mut cache = CheckCache::open(&dir.path().join("cache.db")).unwrap();
+        let now = 1_700_000_000;
+        cache
+            .store(&[result("https://valid.example/", 200)], now - 10 * DAY)
+            .unwrap();
+        cache
+            .store(&[result("https://gone.example/", 404)], now - 8 * DAY)
+            .unwrap();
+        cache
+            .store(&[result("https://old.example/", 200)], now - 40 * DAY)
+            .unwrap();
+
+        // 规范化后命中，结果带回调用方的 URL
+        let (cached, checked_at) = cache.get("https://www.valid.example").unwrap().unwrap();
+        assert_eq!(cached.url, "https://www.valid.example");
+        assert_eq!(cached.status, ValidationStatus::Valid);
+        assert_eq!(checked_at, now - 10 * DAY);
+
+        let urls: Vec<String> = [
+            "https://old.example/",
+            "https://valid.example/",
+            "https://new.example/",
+            "https://gone.example/",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let ttl = CacheTtl::default();
+
+        let plan = cache.plan(urls.clone(), &ttl, now, false, 0).unwrap();
+        assert_eq!(plan.cached.len(), 1);
+        assert_eq!(plan.cached[0].url, "https://valid.example/");
+        // 从未检查 → 最早检查（40 天前）→ 8 天前
+        assert_eq!(
+            plan.to_check,
+            vec![
+                "https://new.example/",
+                "https://old.example/",
+                "https://gone.example/"
+            ]
+        );
+
+        let plan = cache.plan(urls.clone(), &ttl, now, false, 2).unwrap();
+        assert_eq!(plan.to_check.len(), 2);
+        assert_eq!(plan.deferred, 1);
+
+        let plan = cache.plan(urls, &ttl, now, true, 0).unwrap();
+        assert!(plan.cached.is_empty());
+        assert_eq!(plan.to_check.len(), 4);
+        assert_eq!(plan.to_check[2], "https://valid.example/");
+    }
+
+    fn urls(urls: &[&str]) -> Vec<String> {
+        urls.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_plan_checks_each_canonical_url_once() {
+        let dir = TempDir::new().unwrap();
+        let cache = CheckCache::open(&dir.path().join("cache.db")).unwrap();
+        let plan = cache
+            .plan(
+                urls(&[
+                    "https://www.example.com/page",
+                    "https://example.com/page/",
+                    "https://www.example.com/page",
+                    "https://other.example/",
+                ]),
+                &CacheTtl::default(),
+                1_700_000_000,
+                false,
+                0,
+            )
+            .unwrap();
+        assert_eq!(
+            plan.to_check,
+            vec!["https://other.example/", "https://www.example.com/page"]
+        );
+
+        // 每种写法都拿到结果，完全相同的写法只算一次
+        let results = plan.merge(vec![
+            result("https://www.example.com/page", 200),
+            result("https://other.example/", 200),
+        ]);
+        let mut got: Vec<(&str, ValidationStatus)> =
+            results.iter().map(|r| (r.url.as_str(), r.status)).collect();
+        got.sort_by_key(|(url, _)| *url);
+        assert_eq!(
+            got,
+            vec![
+                ("https://example.com/page/", ValidationStatus::Valid),
+                ("https://other.example/", ValidationStatus::Valid),
+                ("https://www.example.com/page", ValidationStatus::Valid),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_ttl_expiry_per_status() {
+        let dir = TempDir::new().unwrap();
+        let mut cache = CheckCache::open(&dir.path().join("cache.db")).unwrap();
+        let now = 1_700_000_000;
+        let ttl = CacheTtl::parse("valid=2d,uncertain=1d").unwrap();
+        cache
+            .store(&[result("https://fresh.example/", 200)], now - 2 * DAY + 1)
+            .unwrap();
+        cache
+            .store(&[result("https://expired.example/", 200)], now - 2 * DAY)
+            .unwrap();
+        let flaky = result("https://flaky.example/", 404);
+        assert_eq!(flaky.status, ValidationStatus::Uncertain);
+        cache.store(&[flaky], now - 2 * DAY + 1).unwrap();
+
+        let plan = cache
+            .plan(
+                urls(&[
+                    "https://fresh.example/",
+                    "https://expired.example/",
+                    "https://flaky.example/",
+                ]),
+                &ttl,
+                now,
+                false,
+                0,
+            )
+            .unwrap();
+        assert_eq!(plan.cached.len(), 1);
+        assert_eq!(plan.cached[0].url, "https://fresh.example/");
+        assert_eq!(
+            plan.to_check,
+            vec!["https://expired.example/", "https://flaky.example/"]
+        );
+    }
+
+    #[test]
+    fn test_merge_combines_cached_and_checked() {
+        let dir = TempDir::new().unwrap();
+        let mut cache = CheckCache::open(&dir.path().join("cache.db")).unwrap();
+        let now = 1_700_000_000;
+        cache
+            .store(&[result("https://cached.example/", 200)], now - DAY)
+            .unwrap();
+
+        let plan = cache
+            .plan(
+                urls(&[
+                    "https://cached.example/",
+                    "https://www.cached.example",
+                    "https://new.example/",
+                ]),
+                &CacheTtl::default(),
+                now,
+                false,
+                0,
+            )
+            .unwrap();
+        assert_eq!(plan.to_check, vec!["https://new.example/"]);
+
+        let results = plan.merge(vec![result("https://new.example/", 404)]);
+        let mut got: Vec<&str> = results.iter().map(|r| r.url.as_str()).collect();
+        got.sort();
+        assert_eq!(
+            got,
+            vec![
+                "https://cached.example/",
+                "https://new.example/",
+                "https://www.cached.example"
+            ]
+        );
+        assert!(results
+            .iter()
+            .filter(|r| r.url.contains("cached"))
+            .all(|r| r.status == ValidationStatus::Valid));
+    }
+}
@@ -10,6 +10,7 @@
 use std::collections::HashMap;
 
 use crate::browsers::Bookmark;
+use crate::url_canon::canonicalize;
 
 /// 异常检测结果
 #[derive(Debug, Default)]
@@ -124,7 +125,7 @@ fn collect_bookmark_stats(
         if bookmark.folder {
             collect_bookmark_stats(&bookmark.children, url_counts, report);
         } else if let Some(ref url) = bookmark.url {
-            let normalized = normalize_url(url);
+            let normalized = canonicalize(url);
             *url_counts.entry(normalized).or_insert(0) += 1;
 
             // NSFW统计
@@ -169,17 +170,6 @@ pub fn is_nsfw_url(url: &str, title: &str) -> bool {
     false
 }
 
-fn normalize_url(url: &str) -> String {
-    let mut normalized = url.trim().to_lowercase();
-    if normalized.ends_with('/') {
-        normalized.pop();
-    }
-    if let Some(pos) = normalized.find('#') {
-        normalized.truncate(pos);
-    }
-    normalized
-}
-
 // ============================================================
 // 以下功能已被移除（误删风险太高）：
 // - remove_bulk_imported_bookmarks
@@ -223,10 +213,4 @@ mod tests {
         assert!(is_nsfw_url("https://example.com/page", "Hentai Collection"));
         assert!(!is_nsfw_url("https://github.com/user/repo", "Repository"));
     }
-
-    #[test]
-    fn test_normalize_url() {
-        assert_eq!(normalize_url("https://example.com/"), "https://example.com");
-        assert_eq!(normalize_url("HTTPS://EXAMPLE.COM"), "https://example.com");
-    }
 }
@@ -0,0 +1,748 @@
+//! 冲突解决策略与冲突报告
+//!
+//! 同一条目（URL / cookie 键）在多个浏览器中内容不同时按策略选择：
+//! - newest：修改时间最新的版本
+//! - prefer:<browser>：优先指定浏览器的版本
+//! - keep-both：保留所有版本，其他版本标题加后缀（仅书签和阅读列表）
+//! - ask：交互式询问（非终端时退回 newest）
+//!
+//! 每次运行写出 JSON 报告和文本摘要到 ~/.browser-sync/conflicts/
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// 保留的历史报告数量
+const MAX_REPORTS: usize = 20;
+
+/// keep-both 副本标题后缀的前缀："标题 (conflict: Brave)"
+const COPY_MARKER: &str = " (conflict: ";
+
+/// 可配置策略的数据类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DataKind {
+    Bookmarks,
+    History,
+    ReadingList,
+    Cookies,
+}
+
+impl DataKind {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "bookmarks" | "bookmark" => Ok(DataKind::Bookmarks),
+            "history" => Ok(DataKind::History),
+            "reading-list" | "reading_list" | "readinglist" => Ok(DataKind::ReadingList),
+            "cookies" | "cookie" => Ok(DataKind::Cookies),
+            other => anyhow::bail!(
+                "Unknown data type '{}' (expected bookmarks, history, reading-list or cookies)",
+                other
+            ),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataKind::Bookmarks => "bookmarks",
+            DataKind::History => "history",
+            DataKind::ReadingList => "reading-list",
+            DataKind::Cookies => "cookies",
+        }
+    }
+
+    /// keep-both 只对可以重复存在的条目有意义
+    fn supports_keep_both(&self) -> bool {
+        matches!(self, DataKind::Bookmarks | DataKind::ReadingList)
+    }
+}
+
+/// 冲突解决策略
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ConflictPolicy {
+    #[default]
+    Newest,
+    Prefer(String),
+    KeepBoth,
+    Ask,
+}
+
+impl ConflictPolicy {
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let lower = s.to_lowercase();
+        if let Some(browser) = lower
+            .strip_prefix("prefer:")
+            .or_else(|| lower.strip_prefix("prefer-"))
+        {
+            if browser.trim().is_empty() {
+                anyhow::bail!("Policy 'prefer:' needs a browser name");
+            }
+            return Ok(ConflictPolicy::Prefer(browser.trim().to_string()));
+        }
+        match lower.as_str() {
+            "newest" | "newest-modified" | "newest-wins" => Ok(ConflictPolicy::Newest),
+            "keep-both" | "keep_both" | "both" => Ok(ConflictPolicy::KeepBoth),
+            "ask" | "interactive" => Ok(ConflictPolicy::Ask),
+            _ => anyhow::bail!(
+                "Unknown conflict policy '{}' (expected newest, prefer:<browser>, keep-both or ask)",
+                s
+            ),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            ConflictPolicy::Newest => "newest".to_string(),
+            ConflictPolicy::Prefer(browser) => format!("prefer:{}", browser),
+            ConflictPolicy::KeepBoth => "keep-both".to_string(),
+            ConflictPolicy::Ask => "ask".to_string(),
+        }
+    }
+}
+
+impl Serialize for ConflictPolicy {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for ConflictPolicy {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        ConflictPolicy::parse(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// 每种数据类型的策略
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ConflictPolicies {
+    #[serde(default)]
+    pub bookmarks: ConflictPolicy,
+    #[serde(default)]
+    pub history: ConflictPolicy,
+    #[serde(default)]
+    pub reading_list: ConflictPolicy,
+    #[serde(default)]
+    pub cookies: ConflictPolicy,
+}
+
+impl ConflictPolicies {
+    pub fn default_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME")?;
+        Ok(PathBuf::from(format!(
+            "{}/.browser-sync/conflict_policy.json",
+            home
+        )))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content).context("Failed to parse conflict policies")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, kind: DataKind) -> &ConflictPolicy {
+        match kind {
+            DataKind::Bookmarks => &self.bookmarks,
+            DataKind::History => &self.history,
+            DataKind::ReadingList => &self.reading_list,
+            DataKind::Cookies => &self.cookies,
+        }
+    }
+
+    pub fn set(&mut self, kind: DataKind, policy: ConflictPolicy) {
+        match kind {
+            DataKind::Bookmarks => self.bookmarks = policy,
+            DataKind::History => self.history = policy,
+            DataKind::ReadingList => self.reading_list = policy,
+            DataKind::Cookies => self.cookies = policy,
+        }
+    }
+
+    /// 应用策略说明："newest" 对所有类型生效，"bookmarks=keep-both,history=prefer:safari" 按类型设置
+    pub fn apply_spec(&mut self, spec: &str) -> Result<()> {
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match part.split_once('=') {
+                Some((kind, policy)) => {
+                    self.set(DataKind::parse(kind)?, ConflictPolicy::parse(policy)?)
+                }
+                None => {
+                    let policy = ConflictPolicy::parse(part)?;
+                    for kind in [
+                        DataKind::Bookmarks,
+                        DataKind::History,
+                        DataKind::ReadingList,
+                        DataKind::Cookies,
+                    ] {
+                        self.set(kind, policy.clone());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn description(&self) -> String {
+        format!(
+            "bookmarks={}, history={}, reading-list={}, cookies={}",
+            self.bookmarks.name(),
+            self.history.name(),
+            self.reading_list.name(),
+            self.cookies.name()
+        )
+    }
+}
+
+/// 冲突中的一个版本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictVersion {
+    pub browser: String,
+    pub title: String,
+    /// 附加信息（文件夹路径、cookie 值指纹等）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// 修改时间（毫秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<i64>,
+}
+
+/// 冲突的处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", tag = "kind", content = "index")]
+pub enum Choice {
+    /// 采用 versions[i]
+    Version(usize),
+    /// 保留所有版本
+    KeepBoth,
+}
+
+/// 一条冲突记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conflict {
+    pub data: DataKind,
+    pub key: String,
+    pub versions: Vec<ConflictVersion>,
+    pub policy: String,
+    pub choice: Choice,
+    pub reason: String,
+}
+
+impl Conflict {
+    fn choice_label(&self) -> String {
+        match self.choice {
+            Choice::Version(i) => self
+                .versions
+                .get(i)
+                .map(|v| format!("{} ({})", v.title, v.browser))
+                .unwrap_or_else(|| "?".to_string()),
+            Choice::KeepBoth => "kept all versions".to_string(),
+        }
+    }
+}
+
+/// 一次运行的冲突报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictReport {
+    pub generated_at: String,
+    pub operation: String,
+    pub dry_run: bool,
+    pub policies: ConflictPolicies,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl ConflictReport {
+    /// 人类可读摘要
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!(
+            "Conflict report: {} ({}{})\n",
+            self.operation,
+            self.generated_at,
+            if self.dry_run { ", dry run" } else { "" }
+        ));
+        out.push_str(&format!("Policies: {}\n", self.policies.description()));
+        out.push_str(&format!("Conflicts: {}\n", self.conflicts.len()));
+        for conflict in &self.conflicts {
+            out.push_str(&format!(
+                "\n[{}] {}  ({})\n",
+                conflict.data.name(),
+                conflict.key,
+                conflict.policy
+            ));
+            for (i, version) in conflict.versions.iter().enumerate() {
+                let chosen =
+                    conflict.choice == Choice::Version(i) || conflict.choice == Choice::KeepBoth;
+                out.push_str(&format!(
+                    "  {} {:<16} {}",
+                    if chosen { "*" } else { " " },
+                    version.browser,
+                    version.title
+                ));
+                if let Some(ref detail) = version.detail {
+                    out.push_str(&format!("  [{}]", detail));
+                }
+                if let Some(dt) = version
+                    .modified
+                    .and_then(chrono::DateTime::from_timestamp_millis)
+                {
+                    out.push_str(&format!("  {}", dt.format("%Y-%m-%d %H:%M")));
+                }
+                out.push('\n');
+            }
+            out.push_str(&format!(
+                "  → {}: {}\n",
+                conflict.choice_label(),
+                conflict.reason
+            ));
+        }
+        out
+    }
+}
+
+/// 交互询问函数（测试中可替换）
+pub type AskFn = fn(&Conflict) -> Option<Choice>;
+
+/// 冲突解决器：按策略选择并记录每一个冲突
+#[derive(Debug)]
+pub struct ConflictResolver {
+    pub policies: ConflictPolicies,
+    conflicts: Vec<Conflict>,
+    ask: AskFn,
+}
+
+impl Default for ConflictResolver {
+    fn default() -> Self {
+        Self::new(ConflictPolicies::default())
+    }
+}
+
+impl ConflictResolver {
+    pub fn new(policies: ConflictPolicies) -> Self {
+        Self {
+            policies,
+            conflicts: Vec::new(),
+            ask: ask_on_terminal,
+        }
+    }
+
+    /// 读取 ~/.browser-sync/conflict_policy.json；失败时使用默认策略
+    pub fn load_default() -> Self {
+        let policies = ConflictPolicies::default_path()
+            .and_then(|p| ConflictPolicies::load(&p))
+            .unwrap_or_else(|e| {
+                warn!("⚠️  Using default conflict policy: {}", e);
+                ConflictPolicies::default()
+            });
+        Self::new(policies)
+    }
+
+    #[cfg(test)]
+    pub fn with_ask(mut self, ask: AskFn) -> Self {
+        self.ask = ask;
+        self
+    }
+
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    /// 按策略解决冲突并记录
+    pub fn resolve(&mut self, data: DataKind, key: &str, versions: Vec<ConflictVersion>) -> Choice {
+        let policy = self.policies.get(data).clone();
+        let mut conflict = Conflict {
+            data,
+            key: key.to_string(),
+            versions,
+            policy: policy.name(),
+            choice: Choice::Version(0),
+            reason: String::new(),
+        };
+
+        let (choice, reason) = match policy {
+            ConflictPolicy::Newest => newest(&conflict.versions),
+            ConflictPolicy::Prefer(ref browser) => match conflict
+                .versions
+                .iter()
+                .position(|v| browser_matches(&v.browser, browser))
+            {
+                Some(i) => (Choice::Version(i), format!("preferred browser {}", browser)),
+                None => {
+                    let (choice, reason) = newest(&conflict.versions);
+                    (choice, format!("{} not involved, {}", browser, reason))
+                }
+            },
+            ConflictPolicy::KeepBoth if data.supports_keep_both() => {
+                (Choice::KeepBoth, "kept all versions".to_string())
+            }
+            ConflictPolicy::KeepBoth => {
+                let (choice, reason) = newest(&conflict.versions);
+                (
+                    choice,
+                    format!("keep-both not possible for {}, {}", data.name(), reason),
+                )
+            }
+            ConflictPolicy::Ask => match (self.ask)(&conflict) {
+                Some(Choice::KeepBoth) if !data.supports_keep_both() => {
+                    let (choice, reason) = newest(&conflict.versions);
+                    (
+                        choice,
+                        format!("keep-both not possible for {}, {}", data.name(), reason),
+                    )
+                }
+                Some(choice) => (choice, "chosen interactively".to_string()),
+                None => {
+                    let (choice, reason) = newest(&conflict.versions);
+                    (choice, format!("no answer, {}", reason))
+                }
+            },
+        };
+
+        debug!(
+            "Conflict [{}] {} → {:?} ({})",
+            data.name(),
+            key,
+            choice,
+            reason
+        );
+        conflict.choice = choice;
+        conflict.reason = reason;
+        self.conflicts.push(conflict);
+        choice
+    }
+
+    /// 记录按固定规则解决的冲突（如删除 vs 修改时保留修改）
+    pub fn record(
+        &mut self,
+        data: DataKind,
+        key: &str,
+        versions: Vec<ConflictVersion>,
+        choice: Choice,
+        rule: &str,
+        reason: &str,
+    ) {
+        self.conflicts.push(Conflict {
+            data,
+            key: key.to_string(),
+            versions,
+            policy: rule.to_string(),
+            choice,
+            reason: reason.to_string(),
+        });
+    }
+
+    /// 生成并写出本次运行的报告，然后清空记录；返回冲突数
+    pub fn finish_report(&mut self, operation: &str, dry_run: bool) -> usize {
+        let report = ConflictReport {
+            generated_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            operation: operation.to_string(),
+            dry_run,
+            policies: self.policies.clone(),
+            conflicts: std::mem::take(&mut self.conflicts),
+        };
+        let count = report.conflicts.len();
+
+        match reports_dir().and_then(|dir| write_report(&dir, &report)) {
+            Ok(path) => {
+                if count > 0 {
+                    info!(
+                        "⚖️  {} conflicts resolved, report: {}",
+                        count,
+                        path.display()
+                    );
+                } else {
+                    debug!("No conflicts, report: {}", path.display());
+                }
+            }
+            Err(e) => warn!("⚠️  Failed to write conflict report: {}", e),
+        }
+        count
+    }
+}
+
+/// keep-both 副本的标题
+pub fn conflict_copy_title(title: &str, browser: &str) -> String {
+    format!("{}{}{})", title, COPY_MARKER, browser)
+}
+
+/// 若标题是 keep-both 副本，返回来源浏览器
+pub fn conflict_copy_browser(title: &str) -> Option<&str> {
+    let start = title.rfind(COPY_MARKER)?;
+    title[start + COPY_MARKER.len()..].strip_suffix(')')
+}
+
+fn browser_matches(browser: &str, wanted: &str) -> bool {
+    let browser = browser.to_lowercase();
+    let wanted = wanted.to_lowercase().replace('-', " ");
+    browser == wanted || browser.contains(&wanted)
+}
+
+/// 选择修改时间最新的版本；相同时取第一个
+fn newest(versions: &[ConflictVersion]) -> (Choice, String) {
+    let mut best = 0;
+    for (i, version) in versions.iter().enumerate() {
+        if version.modified.unwrap_or(i64::MIN) > versions[best].modified.unwrap_or(i64::MIN) {
+            best = i;
+        }
+    }
+    let reason = if versions.iter().all(|v| v.modified.is_none()) {
+        "no modification times, first browser wins".to_string()
+    } else {
+        "newest modification".to_string()
+    };
+    (Choice::Version(best), reason)
+}
+
+/// 在终端中询问用户；非交互环境返回 None
+fn ask_on_terminal(conflict: &Conflict) -> Option<Choice> {
+    if !std::io::stdin().is_terminal() {
+        return None;
+    }
+    let mut stderr = std::io::stderr();
+    let _ = writeln!(
+        stderr,
+        "\n⚖️  Conflict [{}] {}",
+        conflict.data.name(),
+        conflict.key
+    );
+    for (i, version) in conflict.versions.iter().enumerate() {
+        let _ = writeln!(
+            stderr,
+            "  {}) {:<16} {}",
+            i + 1,
+            version.browser,
+            version.title
+        );
+    }
+    let _ = write!(
+        stderr,
+        "Choose 1-{}, b = keep both, Enter = newest: ",
+        conflict.versions.len()
+    );
+    let _ = stderr.flush();
+
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line).ok()?;
+    parse_answer(line.trim(), conflict.versions.len())
+}
+
+fn parse_answer(answer: &str, count: usize) -> Option<Choice> {
+    if answer.eq_ignore_ascii_case("b") {
+        return Some(Choice::KeepBoth);
+    }
+    match answer.parse::<usize>() {
+        Ok(n) if (1..=count).contains(&n) => Some(Choice::Version(n - 1)),
+        _ => None,
+    }
+}
+
+fn reports_dir() -> Result<PathBuf> {
+    let home = std::env::var("HOME")?;
+    Ok(PathBuf::from(format!("{}/.browser-sync/conflicts", home)))
+}
+
+/// 写出 JSON 和文本摘要，并更新 latest.*；返回 JSON 路径
+fn write_report(dir: &Path, report: &ConflictReport) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S%3f").to_string();
+    let json_path = dir.join(format!("conflicts-{}.json", stamp));
+    let json = serde_json::to_string_pretty(report)?;
+    let summary = report.summary();
+
+    std::fs::write(&json_path, &json)?;
+    std::fs::write(json_path.with_extension("txt"), &summary)?;
+    std::fs::write(dir.join("latest.json"), &json)?;
+    std::fs::write(dir.join("latest.txt"), &summary)?;
+
+    prune_reports(dir)?;
+    Ok(json_path)
+}
+
+fn prune_reports(dir: &Path) -> Result<()> {
+    let mut reports: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("conflicts-") && n.ends_with(".json"))
+        })
+        .collect();
+    reports.sort();
+    if reports.len() > MAX_REPORTS {
+        for old in &reports[..reports.len() - MAX_REPORTS] {
+            let _ = std::fs::remove_file(old);
+            let _ = std::fs::remove_file(old.with_extension("txt"));
+        }
+    }
+    Ok(())
+}
+
+/// 最近一次报告的文本摘要
+pub fn latest_summary() -> Result<Option<String>> {
+    read_latest("latest.txt")
+}
+
+/// 最近一次报告的 JSON
+pub fn latest_report_json() -> Result<Option<String>> {
+    read_latest("latest.json")
+}
+
+fn read_latest(name: &str) -> Result<Option<String>> {
+    let path = reports_dir()?.join(name);
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(std::fs::read_to_string(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(browser: &str, title: &str, modified: Option<i64>) -> ConflictVersion {
+        ConflictVersion {
+            browser: browser.to_string(),
+            title: title.to_string(),
+            detail: None,
+            modified,
+        }
+    }
+
+    fn versions() -> Vec<ConflictVersion> {
+        vec![
+            version("Brave Nightly", "Rust", Some(100)),
+            version("Safari", "Rust Lang", Some(300)),
+            version("Waterfox", "The Rust Language", Some(200)),
+        ]
+    }
+
+    #[test]
+    fn test_policy_spec_parsing() {
+        let mut policies = ConflictPolicies::default();
+        policies.apply_spec("keep-both").unwrap();
+        assert_eq!(policies.history, ConflictPolicy::KeepBoth);
+
+        policies
+            .apply_spec("history=prefer:safari, cookies=newest-modified,reading-list=ask")
+            .unwrap();
+        assert_eq!(policies.bookmarks, ConflictPolicy::KeepBoth);
+        assert_eq!(policies.history, ConflictPolicy::Prefer("safari".into()));
+        assert_eq!(policies.cookies, ConflictPolicy::Newest);
+        assert_eq!(policies.reading_list, ConflictPolicy::Ask);
+
+        assert!(policies.apply_spec("passwords=newest").is_err());
+        assert!(policies.apply_spec("bookmarks=random").is_err());
+
+        let json = serde_json::to_string(&policies).unwrap();
+        let back: ConflictPolicies = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, policies);
+    }
+
+    #[test]
+    fn test_resolve_by_policy() {
+        let mut resolver = ConflictResolver::default();
+        assert_eq!(
+            resolver.resolve(DataKind::Bookmarks, "k", versions()),
+            Choice::Version(1)
+        );
+
+        resolver
+            .policies
+            .apply_spec("prefer:brave-nightly")
+            .unwrap();
+        assert_eq!(
+            resolver.resolve(DataKind::History, "k", versions()),
+            Choice::Version(0)
+        );
+        // Preferred browser not involved: newest wins
+        resolver.policies.apply_spec("prefer:chrome").unwrap();
+        assert_eq!(
+            resolver.resolve(DataKind::History, "k", versions()),
+            Choice::Version(1)
+        );
+
+        resolver.policies.apply_spec("keep-both").unwrap();
+        assert_eq!(
+            resolver.resolve(DataKind::Bookmarks, "k", versions()),
+            Choice::KeepBoth
+        );
+        assert_eq!(
+            resolver.resolve(DataKind::Cookies, "k", versions()),
+            Choice::Version(1)
+        );
+
+        assert_eq!(resolver.conflicts().len(), 5);
+        assert!(resolver.conflicts()[4]
+            .reason
+            .contains("keep-both not possible"));
+    }
+
+    #[test]
+    fn test_ask_policy_uses_answer_or_falls_back() {
+        let mut resolver = ConflictResolver::default().with_ask(|_| Some(Choice::Version(2)));
+        resolver.policies.apply_spec("ask").unwrap();
+        assert_eq!(
+            resolver.resolve(DataKind::Bookmarks, "k", versions()),
+            Choice::Version(2)
+        );
+
+        let mut resolver = ConflictResolver::default().with_ask(|_| None);
+        resolver.policies.apply_spec("ask").unwrap();
+        assert_eq!(
+            resolver.resolve(DataKind::Bookmarks, "k", versions()),
+            Choice::Version(1)
+        );
+
+        assert_eq!(parse_answer("b", 2), Some(Choice::KeepBoth));
+        assert_eq!(parse_answer("2", 2), Some(Choice::Version(1)));
+        assert_eq!(parse_answer("3", 2), None);
+        assert_eq!(parse_answer("", 2), None);
+    }
+
+    #[test]
+    fn test_conflict_copy_title_roundtrip() {
+        let title = conflict_copy_title("Rust Lang", "Safari");
+        assert_eq!(title, "Rust Lang (conflict: Safari)");
+        assert_eq!(conflict_copy_browser(&title), Some("Safari"));
+        assert_eq!(conflict_copy_browser("Rust (book)"), None);
+    }
+
+    #[test]
+    fn test_report_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut resolver = ConflictResolver::default();
+        resolver.resolve(DataKind::ReadingList, "https://a.com", versions());
+        let report = ConflictReport {
+            generated_at: "2024-01-01 00:00:00".into(),
+            operation: "sync".into(),
+            dry_run: false,
+            policies: resolver.policies.clone(),
+            conflicts: resolver.conflicts().to_vec(),
+        };
+
+        let path = write_report(dir.path(), &report).unwrap();
+        let parsed: ConflictReport =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(parsed.conflicts.len(), 1);
+        assert_eq!(parsed.conflicts[0].choice, Choice::Version(1));
+
+        let summary = std::fs::read_to_string(dir.path().join("latest.txt")).unwrap();
+        assert!(summary.contains("[reading-list] https://a.com  (newest)"));
+        assert!(summary.contains("* Safari"));
+        assert!(summary.contains("→ Rust Lang (Safari): newest modification"));
+    }
+}
@@ -0,0 +1,416 @@
+//! 守护进程控制接口 - Unix 套接字上的 JSON 行协议
+//!
+//! 套接字是 `~/.browser-sync/daemon.sock`，所在目录仅当前用户可访问，守护进程也只接受
+//! 同一用户的连接。客户端每行发送一个请求，守护进程每行回复一个响应：
+//!
+//! ```text
+//! → {"cmd":"status"}
+//! ← {"ok":true,"status":{"paused":false,"jobs":[...]}}
+//! → {"cmd":"trigger","job":"nightly-export"}
+//! ← {"ok":true,"message":"Job nightly-export started"}
+//! ```
+//!
+//! 命令：`status`、`trigger`、`pause`、`resume`、`reload-config`。`bsync ctl` 是对应的客户端。
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, warn};
+
+use crate::scheduler::{JobResult, RunRecord, RunStatus, Task};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "kebab-case")]
+pub enum Request {
+    Status,
+    Trigger { job: String },
+    Pause,
+    Resume,
+    ReloadConfig,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<DaemonStatus>,
+}
+
+impl Response {
+    pub fn message(message: impl Into<String>) -> Self {
+        Self {
+            ok: true,
+            message: Some(message.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn error(error: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(error.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn status(status: DaemonStatus) -> Self {
+        Self {
+            ok: true,
+            status: Some(status),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub paused: bool,
+    pub started_at: DateTime<Local>,
+    pub config_path: String,
+    pub jobs: Vec<JobStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobStatus {
+    pub name: String,
+    pub task: Task,
+    /// cron 表达式；文件监视任务为 `on change`
+    pub schedule: String,
+    pub next_run: Option<DateTime<Local>>,
+    pub running: bool,
+    pub last_run: Option<RunRecord>,
+    /// 守护进程启动以来失败的次数
+    pub failures: usize,
+}
+
+/// 收到的请求和回复通道
+pub type Incoming = (Request, oneshot::Sender<Response>);
+
+pub fn socket_path() -> Result<PathBuf> {
+    let home = std::env::var("HOME")?;
+    Ok(PathBuf::from(format!("{}/.browser-sync/daemon.sock", home)))
+}
+
+/// 监听套接字，把请求转给守护进程主循环
+///
+/// 套接字建在 0700 的目录里，绑定后、设置权限前其他用户也无法连接；
+/// 每个连接还会核对对方的 UID 是否是目录所有者
+pub fn listen(path: &Path) -> Result<mpsc::Receiver<Incoming>> {
+    let dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let owner = private_dir(dir)?;
+    listen_as(path, owner)
+}
+
+/// 创建套接字目录（已存在时收紧为 0700），返回目录所有者的 UID
+fn private_dir(dir: &Path) -> Result<u32> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+    let meta = std::fs::metadata(dir)?;
+    if meta.permissions().mode() & 0o077 != 0 {
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+            .with_context(|| format!("Failed to restrict permissions on {}", dir.display()))?;
+    }
+    Ok(meta.uid())
+}
+
+/// 只接受 UID 为 `owner` 的连接
+fn listen_as(path: &Path, owner: u32) -> Result<mpsc::Receiver<Incoming>> {
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            bail!("Another daemon is already listening on {}", path.display());
+        }
+        // 上次没有正常退出留下的套接字
+        std::fs::remove_file(path)
+            .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+    }
+    let listener = UnixListener::bind(path)
+        .with_context(|| format!("Failed to bind control socket {}", path.display()))?;
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    let (sender, receiver) = mpsc::channel(16);
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(serve(stream, sender.clone(), owner));
+                }
+                Err(e) => {
+                    warn!("⚠️  Control socket accept failed: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+    Ok(receiver)
+}
+
+async fn serve(stream: UnixStream, sender: mpsc::Sender<Incoming>, owner: u32) {
+    let peer = stream.peer_cred().map(|cred| cred.uid());
+    let (reader, mut writer) = stream.into_split();
+    match peer {
+        Ok(uid) if uid == owner => {}
+        Ok(uid) => {
+            warn!("⚠️  Rejected control connection from uid {}", uid);
+            reply(&mut writer, &Response::error("Permission denied")).await;
+            return;
+        }
+        Err(e) => {
+            warn!("⚠️  Failed to identify control client: {}", e);
+            return;
+        }
+    }
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                debug!("Control request: {:?}", request);
+                let (reply, response) = oneshot::channel();
+                if sender.send((request, reply)).await.is_err() {
+                    Response::error("Daemon is shutting down")
+                } else {
+                    response
+                        .await
+                        .unwrap_or_else(|_| Response::error("Daemon is shutting down"))
+                }
+            }
+            Err(e) => Response::error(format!("Invalid request: {}", e)),
+        };
+        if !reply(&mut writer, &response).await {
+            break;
+        }
+    }
+}
+
+/// 写一行响应，失败时返回 false
+async fn reply(writer: &mut OwnedWriteHalf, response: &Response) -> bool {
+    let Ok(mut json) = serde_json::to_string(response) else {
+        return false;
+    };
+    json.push('\n');
+    writer.write_all(json.as_bytes()).await.is_ok()
+}
+
+/// 客户端：发送一个请求并等待回复
+pub async fn send(path: &Path, request: &Request) -> Result<Response> {
+    let stream = UnixStream::connect(path).await.with_context(|| {
+        format!(
+            "No daemon listening on {} (is `bsync daemon` running?)",
+            path.display()
+        )
+    })?;
+    let (reader, mut writer) = stream.into_split();
+    let mut json = serde_json::to_string(request)?;
+    json.push('\n');
+    writer.write_all(json.as_bytes()).await?;
+
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .context("Daemon closed the connection without replying")?;
+    serde_json::from_str(&line).context("Invalid response from daemon")
+}
+
+fn result_summary(result: &JobResult) -> String {
+    match result {
+        JobResult::Sync(stats) => format!(
+            "{} synced, {} duplicates removed, {} conflicts, {} errors",
+            stats.bookmarks_synced,
+            stats.duplicates_removed,
+            stats.conflicts_resolved,
+            stats.errors
+        ),
+        JobResult::Check(report) => format!(
+            "{} checked, {} valid, {} invalid, {} uncertain",
+            report.total_checked, report.valid_count, report.invalid_count, report.uncertain_count
+        ),
+        JobResult::Dedupe(stats) => format!(
+            "{} → {} bookmarks, {} duplicates removed",
+            stats.total_before, stats.total_after, stats.duplicates_removed
+        ),
+    }
+}
+
+pub fn print_status(status: &DaemonStatus) {
+    println!("\n🛰️  Daemon Status:");
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!(
+        "  State:   {}",
+        if status.paused { "paused" } else { "running" }
+    );
+    println!(
+        "  Since:   {}",
+        status.started_at.format("%Y-%m-%d %H:%M:%S")
+    );
+    println!("  Config:  {}", status.config_path);
+    for job in &status.jobs {
+        println!(
+            "\n  {} [{}] {}{}",
+            job.name,
+            job.schedule,
+            job.task.command(),
+            if job.running { " (running)" } else { "" }
+        );
+        if let Some(next) = job.next_run {
+            println!("     Next run:  {}", next.format("%Y-%m-%d %H:%M:%S"));
+        }
+        match job.last_run {
+            Some(ref run) => {
+                let status = match run.status {
+                    RunStatus::Succeeded => "✅ succeeded",
+                    RunStatus::Failed => "❌ failed",
+                    RunStatus::SkippedOverlap => "⏭️  skipped (overlap)",
+                    RunStatus::SkippedMissed => "⏭️  skipped (missed)",
+                    RunStatus::SkippedPaused => "⏭️  skipped (paused)",
+                };
+                println!(
+                    "     Last run:  {} {} ({} ms)",
+                    run.started_at.format("%Y-%m-%d %H:%M:%S"),
+                    status,
+                    run.duration_ms
+                );
+                if let Some(ref result) = run.result {
+                    println!("     Result:    {}", result_summary(result));
+                }
+                if let Some(ref error) = run.error {
+                    let last_line = error.lines().last().unwrap_or_default();
+                    println!("     Error:     {}", last_line);
+                }
+            }
+            None => println!("     Last run:  never"),
+        }
+        if job.failures > 0 {
+            println!("     Failures:  {}", job.failures);
+        }
+    }
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_wire_format() {
+        assert_eq!(
+            serde_json::to_string(&Request::ReloadConfig).unwrap(),
+            r#"{"cmd":"reload-config"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Request>(r#"{"cmd":"trigger","job":"nightly"}"#).unwrap(),
+            Request::Trigger {
+                job: "nightly".to_string()
+            }
+        );
+        assert!(serde_json::from_str::<Request>(r#"{"cmd":"shutdown"}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_socket_round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("daemon.sock");
+        let mut requests = listen(&path).unwrap();
+        tokio::spawn(async move {
+            while let Some((request, reply)) = requests.recv().await {
+                let response = match request {
+                    Request::Pause => Response::message("Paused"),
+                    other => Response::error(format!("unexpected {:?}", other)),
+                };
+                let _ = reply.send(response);
+            }
+        });
+
+        let response = send(&path, &Request::Pause).await.unwrap();
+        assert!(response.ok);
+        assert_eq!(response.message.as_deref(), Some("Paused"));
+
+        // 已有守护进程在监听时不能再启动一个
+        assert!(listen(&path).is_err());
+    }
+
+    fn echo_daemon(mut requests: mpsc::Receiver<Incoming>) {
+        tokio::spawn(async move {
+            while let Some((request, reply)) = requests.recv().await {
+                let _ = reply.send(Response::message(format!("{:?}", request)));
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn test_socket_directory_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::TempDir::new().unwrap();
+        let run = dir.path().join("run");
+        std::fs::create_dir(&run).unwrap();
+        std::fs::set_permissions(&run, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let path = run.join("daemon.sock");
+        echo_daemon(listen(&path).unwrap());
+        let mode = |p: &Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&run), 0o700);
+        assert_eq!(mode(&path), 0o600);
+        assert!(send(&path, &Request::Status).await.unwrap().ok);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_other_users() {
+        use std::os::unix::fs::MetadataExt;
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("daemon.sock");
+        let uid = std::fs::metadata(dir.path()).unwrap().uid();
+        let mut requests = listen_as(&path, uid.wrapping_add(1)).unwrap();
+
+        let response = send(&path, &Request::Pause).await.unwrap();
+        assert!(!response.ok);
+        assert_eq!(response.error.as_deref(), Some("Permission denied"));
+        // 请求没有到达守护进程
+        assert!(requests.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_malformed_request_keeps_connection() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("daemon.sock");
+        echo_daemon(listen(&path).unwrap());
+
+        let stream = UnixStream::connect(&path).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        writer
+            .write_all(b"not json\n{\"cmd\":\"shutdown\"}\n{\"cmd\":\"resume\"}\n")
+            .await
+            .unwrap();
+
+        for _ in 0..2 {
+            let line = lines.next_line().await.unwrap().unwrap();
+            let response: Response = serde_json::from_str(&line).unwrap();
+            assert!(!response.ok);
+            assert!(response.error.unwrap().starts_with("Invalid request"));
+        }
+        let line = lines.next_line().await.unwrap().unwrap();
+        let response: Response = serde_json::from_str(&line).unwrap();
+        assert_eq!(response.message.as_deref(), Some("Resume"));
+    }
+}
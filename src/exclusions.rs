@@ -0,0 +1,425 @@
+//! 同步排除规则 - 只属于某个浏览器的私有书签
+//!
+//! 配置文件 `~/.browser-sync/exclusions.json`，按浏览器名（`*` 表示所有浏览器）列出：
+//! - `folders`：文件夹路径，如 `"Bookmarks Bar/Work VPN"`，每段支持 `*` 通配符
+//! - `urls`：URL 通配符，如 `"*://*.corp.example/*"`
+//! - `domains`：域名，规则同导出过滤（`"*.bank.com"` 含子域名）
+//!
+//! 引擎用 `ExcludingAdapter` 包装有规则的浏览器：
+//! 读取时私有书签不进入合并树；写回时丢弃合并树里与规则匹配的内容，再把浏览器当前的私有书签原样放回，
+//! 因此 hub、sync、Safari 迁移（包括清空来源浏览器）都不会覆盖或删除它们。
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::browsers::{
+    Bookmark, BrowserAdapter, BrowserType, Cookie, HistoryItem, ReadingListItem,
+};
+use crate::export_filter::{domain_matches, wildcard_match};
+use crate::sync::url_host;
+use crate::three_way;
+
+/// 所有浏览器通用的规则键
+const ALL_BROWSERS: &str = "*";
+
+/// 一个浏览器的排除规则
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExclusionSet {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub folders: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub urls: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub domains: Vec<String>,
+}
+
+/// 浏览器名 → 排除规则
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ExclusionRules(pub BTreeMap<String, ExclusionSet>);
+
+impl ExclusionRules {
+    pub fn default_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME")?;
+        Ok(PathBuf::from(format!(
+            "{}/.browser-sync/exclusions.json",
+            home
+        )))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content).context("Failed to parse exclusion rules")
+    }
+
+    pub fn load_default() -> Self {
+        Self::default_path()
+            .and_then(|p| Self::load(&p))
+            .unwrap_or_else(|e| {
+                warn!("⚠️  Ignoring exclusion rules: {}", e);
+                Self::default()
+            })
+    }
+
+    /// 某个浏览器生效的规则（自身规则 + `*` 规则）
+    pub fn for_browser(&self, browser: BrowserType) -> ExclusionSet {
+        let name = normalize_browser(browser.name());
+        let mut set = ExclusionSet::default();
+        for (key, rules) in &self.0 {
+            if key.trim() == ALL_BROWSERS || normalize_browser(key) == name {
+                set.folders.extend(rules.folders.iter().cloned());
+                set.urls.extend(rules.urls.iter().cloned());
+                set.domains.extend(rules.domains.iter().cloned());
+            }
+        }
+        set
+    }
+}
+
+fn normalize_browser(name: &str) -> String {
+    name.trim().to_lowercase().replace(['-', '_'], " ")
+}
+
+/// 从树中移出的私有内容：所在文件夹路径 + 节点
+pub type ExcludedItems = Vec<(Vec<String>, Bookmark)>;
+
+impl ExclusionSet {
+    pub fn is_empty(&self) -> bool {
+        self.folders.is_empty() && self.urls.is_empty() && self.domains.is_empty()
+    }
+
+    /// 文件夹路径是否命中（逐段比较，忽略大小写，每段支持 `*`）
+    pub fn excludes_folder(&self, path: &[String]) -> bool {
+        self.folders.iter().any(|pattern| {
+            let segments: Vec<String> = pattern
+                .split('/')
+                .map(|s| s.trim().to_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect();
+            segments.len() == path.len()
+                && segments
+                    .iter()
+                    .zip(path)
+                    .all(|(pattern, title)| wildcard_match(pattern, &title.to_lowercase()))
+        })
+    }
+
+    pub fn excludes_url(&self, url: &str) -> bool {
+        let url = url.trim().to_lowercase();
+        if self
+            .urls
+            .iter()
+            .any(|pattern| wildcard_match(&pattern.trim().to_lowercase(), &url))
+        {
+            return true;
+        }
+        match url_host(&url) {
+            Some(host) => self.domains.iter().any(|d| domain_matches(&host, d)),
+            None => false,
+        }
+    }
+
+    /// 把命中的文件夹和书签从树中移出
+    pub fn split(&self, tree: &mut Vec<Bookmark>) -> ExcludedItems {
+        fn walk(
+            rules: &ExclusionSet,
+            nodes: &mut Vec<Bookmark>,
+            path: &mut Vec<String>,
+            excluded: &mut ExcludedItems,
+        ) {
+            let mut kept = Vec::with_capacity(nodes.len());
+            for mut node in std::mem::take(nodes) {
+                let hit = if node.folder {
+                    path.push(node.title.clone());
+                    let hit = rules.excludes_folder(path);
+                    if !hit {
+                        walk(rules, &mut node.children, path, excluded);
+                    }
+                    path.pop();
+                    hit
+                } else {
+                    node.url
+                        .as_deref()
+                        .is_some_and(|url| rules.excludes_url(url))
+                };
+                if hit {
+                    excluded.push((path.clone(), node));
+                } else {
+                    kept.push(node);
+                }
+            }
+            *nodes = kept;
+        }
+        let mut excluded = Vec::new();
+        if !self.is_empty() {
+            walk(self, tree, &mut Vec::new(), &mut excluded);
+        }
+        excluded
+    }
+
+    /// 把私有内容放回原来的文件夹（文件夹不存在时重新创建）
+    pub fn restore(tree: &mut Vec<Bookmark>, excluded: ExcludedItems) {
+        for (path, node) in excluded {
+            three_way::insert_at_path(tree, &path, node);
+        }
+    }
+}
+
+/// 带排除规则的浏览器适配器
+pub struct ExcludingAdapter {
+    inner: Box<dyn BrowserAdapter + Send + Sync>,
+    rules: ExclusionSet,
+}
+
+impl ExcludingAdapter {
+    pub fn new(inner: Box<dyn BrowserAdapter + Send + Sync>, rules: ExclusionSet) -> Self {
+        Self { inner, rules }
+    }
+
+    fn read_filtered(&self, mut bookmarks: Vec<Bookmark>) -> Vec<Bookmark> {
+        let excluded = self.rules.split(&mut bookmarks);
+        if !excluded.is_empty() {
+            debug!(
+                "🔒 {}: {} excluded items kept out of sync",
+                self.inner.browser_type().name(),
+                excluded.len()
+            );
+        }
+        bookmarks
+    }
+
+    /// 要写入的树：丢弃与规则匹配的内容，放回浏览器当前的私有内容
+    fn with_private(&self, bookmarks: &[Bookmark]) -> Result<Vec<Bookmark>> {
+        let mut tree = bookmarks.to_vec();
+        self.rules.split(&mut tree);
+
+        let private = if self.inner.detect_bookmark_path()?.exists() {
+            let mut current = self.inner.read_bookmarks().with_context(|| {
+                format!(
+                    "Cannot read {} to preserve its excluded bookmarks",
+                    self.inner.browser_type().name()
+                )
+            })?;
+            self.rules.split(&mut current)
+        } else {
+            Vec::new()
+        };
+        ExclusionSet::restore(&mut tree, private);
+        Ok(tree)
+    }
+}
+
+impl BrowserAdapter for ExcludingAdapter {
+    fn browser_type(&self) -> BrowserType {
+        self.inner.browser_type()
+    }
+
+    fn detect_bookmark_path(&self) -> Result<PathBuf> {
+        self.inner.detect_bookmark_path()
+    }
+
+    fn read_bookmarks(&self) -> Result<Vec<Bookmark>> {
+        Ok(self.read_filtered(self.inner.read_bookmarks()?))
+    }
+
+    fn write_bookmarks(&self, bookmarks: &[Bookmark]) -> Result<()> {
+        self.inner.write_bookmarks(&self.with_private(bookmarks)?)
+    }
+
+    fn backup_bookmarks(&self) -> Result<PathBuf> {
+        self.inner.backup_bookmarks()
+    }
+
+    fn validate_bookmarks(&self, bookmarks: &[Bookmark]) -> Result<bool> {
+        self.inner.validate_bookmarks(bookmarks)
+    }
+
+    fn write_bookmarks_to(&self, path: &Path, bookmarks: &[Bookmark]) -> Result<()> {
+        self.inner
+            .write_bookmarks_to(path, &self.with_private(bookmarks)?)
+    }
+
+    fn read_bookmarks_from(&self, path: &Path) -> Result<Vec<Bookmark>> {
+        Ok(self.read_filtered(self.inner.read_bookmarks_from(path)?))
+    }
+
+    fn supports_reading_list(&self) -> bool {
+        self.inner.supports_reading_list()
+    }
+
+    fn read_reading_list(&self) -> Result<Vec<ReadingListItem>> {
+        self.inner.read_reading_list()
+    }
+
+    fn write_reading_list(&self, items: &[ReadingListItem]) -> Result<()> {
+        self.inner.write_reading_list(items)
+    }
+
+    fn supports_history(&self) -> bool {
+        self.inner.supports_history()
+    }
+
+    fn read_history(&self, days: Option<i32>) -> Result<Vec<HistoryItem>> {
+        self.inner.read_history(days)
+    }
+
+    fn write_history(&self, items: &[HistoryItem]) -> Result<()> {
+        self.inner.write_history(items)
+    }
+
+    fn supports_cookies(&self) -> bool {
+        self.inner.supports_cookies()
+    }
+
+    fn read_cookies(&self) -> Result<Vec<Cookie>> {
+        self.inner.read_cookies()
+    }
+
+    fn write_cookies(&self, cookies: &[Cookie]) -> Result<()> {
+        self.inner.write_cookies(cookies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{make_bookmark, make_folder};
+    use std::sync::{Arc, Mutex};
+
+    fn titles(nodes: &[Bookmark]) -> Vec<&str> {
+        nodes.iter().map(|b| b.title.as_str()).collect()
+    }
+
+    fn rules() -> ExclusionSet {
+        ExclusionSet {
+            folders: vec!["*/Work VPN".to_string()],
+            urls: vec!["*://*.corp.example/*".to_string()],
+            domains: vec!["*.mybank.com".to_string()],
+        }
+    }
+
+    fn browser_tree() -> Vec<Bookmark> {
+        vec![make_folder(
+            "Bookmarks Bar",
+            vec![
+                make_folder(
+                    "Work VPN",
+                    vec![make_bookmark("Gateway", "https://vpn.example")],
+                ),
+                make_bookmark("Bank", "https://online.mybank.com/login"),
+                make_bookmark("Wiki", "https://wiki.corp.example/home"),
+                make_bookmark("Rust", "https://rust-lang.org"),
+            ],
+        )]
+    }
+
+    struct MemoryAdapter {
+        stored: Arc<Mutex<Vec<Bookmark>>>,
+    }
+
+    impl BrowserAdapter for MemoryAdapter {
+        fn browser_type(&self) -> BrowserType {
+            BrowserType::Brave
+        }
+        fn detect_bookmark_path(&self) -> Result<PathBuf> {
+            Ok(std::env::temp_dir())
+        }
+        fn read_bookmarks(&self) -> Result<Vec<Bookmark>> {
+            Ok(self.stored.lock().unwrap().clone())
+        }
+        fn write_bookmarks(&self, bookmarks: &[Bookmark]) -> Result<()> {
+            *self.stored.lock().unwrap() = bookmarks.to_vec();
+            Ok(())
+        }
+        fn backup_bookmarks(&self) -> Result<PathBuf> {
+            Ok(PathBuf::new())
+        }
+        fn validate_bookmarks(&self, _bookmarks: &[Bookmark]) -> Result<bool> {
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn test_rules_for_browser_include_wildcard_entry() {
+        let config: ExclusionRules = serde_json::from_str(
+            r#"{
+                "brave-nightly": { "folders": ["Bookmarks Bar/Banking"] },
+                "*": { "domains": ["mybank.com"] },
+                "chrome": { "urls": ["*intranet*"] }
+            }"#,
+        )
+        .unwrap();
+
+        let set = config.for_browser(BrowserType::BraveNightly);
+        assert_eq!(set.folders, vec!["Bookmarks Bar/Banking"]);
+        assert_eq!(set.domains, vec!["mybank.com"]);
+        assert!(set.urls.is_empty());
+        assert!(config.for_browser(BrowserType::Safari).folders.is_empty());
+    }
+
+    #[test]
+    fn test_split_and_restore() {
+        let mut tree = browser_tree();
+        let excluded = rules().split(&mut tree);
+
+        assert_eq!(titles(&tree[0].children), vec!["Rust"]);
+        assert_eq!(excluded.len(), 3);
+        assert!(excluded
+            .iter()
+            .all(|(path, _)| path == &vec!["Bookmarks Bar".to_string()]));
+
+        ExclusionSet::restore(&mut tree, excluded);
+        assert_eq!(
+            titles(&tree[0].children),
+            vec!["Rust", "Work VPN", "Bank", "Wiki"]
+        );
+    }
+
+    #[test]
+    fn test_adapter_hides_private_items_and_keeps_them_on_write() {
+        let stored = Arc::new(Mutex::new(browser_tree()));
+        let adapter = ExcludingAdapter::new(
+            Box::new(MemoryAdapter {
+                stored: stored.clone(),
+            }),
+            rules(),
+        );
+
+        let read = adapter.read_bookmarks().unwrap();
+        assert_eq!(titles(&read[0].children), vec!["Rust"]);
+
+        // Merged tree from another browser carries its own "Work VPN" folder; it must not
+        // replace ours, and clearing the browser must not delete the private items
+        let incoming = vec![make_folder(
+            "Bookmarks Bar",
+            vec![
+                make_bookmark("Go", "https://go.dev"),
+                make_folder(
+                    "Work VPN",
+                    vec![make_bookmark("Other", "https://other.example")],
+                ),
+            ],
+        )];
+        adapter.write_bookmarks(&incoming).unwrap();
+        let written = stored.lock().unwrap().clone();
+        assert_eq!(
+            titles(&written[0].children),
+            vec!["Go", "Work VPN", "Bank", "Wiki"]
+        );
+        assert_eq!(titles(&written[0].children[1].children), vec!["Gateway"]);
+
+        adapter.write_bookmarks(&[]).unwrap();
+        let written = stored.lock().unwrap().clone();
+        assert_eq!(
+            titles(&written[0].children),
+            vec!["Work VPN", "Bank", "Wiki"]
+        );
+    }
+}
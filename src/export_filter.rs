@@ -0,0 +1,401 @@
+//! 导出过滤器 - 可组合的书签筛选条件
+//!
+//! 支持：
+//! - 添加时间 / 修改时间范围
+//! - 域名白名单 / 黑名单（支持通配符 *.example.com、*git*）
+//! - URL 协议过滤（如排除 javascript: 书签小工具和 file:// 本地链接）
+//! - 标题正则
+//!
+//! 过滤器在去重和扁平化之前执行，只移除书签，文件夹结构保留（空文件夹由 clean 步骤清理）
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use tracing::info;
+
+use crate::browsers::Bookmark;
+use crate::sync::url_host;
+
+/// 导出过滤条件（所有条件同时满足才保留）
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    /// 添加时间下限（毫秒，含）
+    pub added_after: Option<i64>,
+    /// 添加时间上限（毫秒，不含）
+    pub added_before: Option<i64>,
+    /// 修改时间下限（毫秒，含）
+    pub modified_after: Option<i64>,
+    /// 修改时间上限（毫秒，不含）
+    pub modified_before: Option<i64>,
+    /// 域名白名单（非空时只保留匹配的域名）
+    pub include_domains: Vec<String>,
+    /// 域名黑名单
+    pub exclude_domains: Vec<String>,
+    /// 协议白名单（非空时只保留这些协议，如 http、https）
+    pub include_schemes: Vec<String>,
+    /// 协议黑名单（如 javascript、file）
+    pub exclude_schemes: Vec<String>,
+    /// 标题正则（只保留匹配的标题）
+    pub title_regex: Option<Regex>,
+}
+
+/// 每个过滤条件移除的书签数
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FilterStats {
+    pub total_before: usize,
+    pub removed_by_added_date: usize,
+    pub removed_by_modified_date: usize,
+    pub removed_by_domain_include: usize,
+    pub removed_by_domain_exclude: usize,
+    pub removed_by_scheme: usize,
+    pub removed_by_title: usize,
+    pub total_after: usize,
+}
+
+/// 书签被移除的原因（按过滤器执行顺序取第一个不满足的条件）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterReason {
+    AddedDate,
+    ModifiedDate,
+    DomainInclude,
+    DomainExclude,
+    Scheme,
+    Title,
+}
+
+impl ExportFilter {
+    /// 是否没有任何过滤条件
+    pub fn is_empty(&self) -> bool {
+        self.added_after.is_none()
+            && self.added_before.is_none()
+            && self.modified_after.is_none()
+            && self.modified_before.is_none()
+            && self.include_domains.is_empty()
+            && self.exclude_domains.is_empty()
+            && self.include_schemes.is_empty()
+            && self.exclude_schemes.is_empty()
+            && self.title_regex.is_none()
+    }
+
+    /// 检查单个书签；返回 None 表示保留，否则返回移除原因
+    /// 设置了时间范围时，没有对应时间戳的书签视为不满足
+    pub fn check(&self, bookmark: &Bookmark) -> Option<FilterReason> {
+        if !in_range(bookmark.date_added, self.added_after, self.added_before) {
+            return Some(FilterReason::AddedDate);
+        }
+        if !in_range(bookmark.date_modified, self.modified_after, self.modified_before) {
+            return Some(FilterReason::ModifiedDate);
+        }
+
+        let url = bookmark.url.as_deref().unwrap_or("");
+        let host = url_host(url).unwrap_or_default();
+
+        if !self.include_domains.is_empty()
+            && !self.include_domains.iter().any(|p| domain_matches(&host, p))
+        {
+            return Some(FilterReason::DomainInclude);
+        }
+        if self.exclude_domains.iter().any(|p| domain_matches(&host, p)) {
+            return Some(FilterReason::DomainExclude);
+        }
+
+        let scheme = url_scheme(url);
+        if !self.include_schemes.is_empty()
+            && !self.include_schemes.iter().any(|s| normalize_scheme(s) == scheme)
+        {
+            return Some(FilterReason::Scheme);
+        }
+        if self.exclude_schemes.iter().any(|s| normalize_scheme(s) == scheme) {
+            return Some(FilterReason::Scheme);
+        }
+
+        if let Some(ref re) = self.title_regex {
+            if !re.is_match(&bookmark.title) {
+                return Some(FilterReason::Title);
+            }
+        }
+
+        None
+    }
+
+    /// 递归过滤书签树，返回每个条件的移除统计
+    pub fn apply(&self, bookmarks: &mut Vec<Bookmark>) -> FilterStats {
+        fn apply_recursive(filter: &ExportFilter, bookmarks: &mut Vec<Bookmark>, stats: &mut FilterStats) {
+            bookmarks.retain_mut(|bookmark| {
+                if bookmark.folder {
+                    apply_recursive(filter, &mut bookmark.children, stats);
+                    return true;
+                }
+
+                stats.total_before += 1;
+                match filter.check(bookmark) {
+                    None => {
+                        stats.total_after += 1;
+                        true
+                    }
+                    Some(reason) => {
+                        match reason {
+                            FilterReason::AddedDate => stats.removed_by_added_date += 1,
+                            FilterReason::ModifiedDate => stats.removed_by_modified_date += 1,
+                            FilterReason::DomainInclude => stats.removed_by_domain_include += 1,
+                            FilterReason::DomainExclude => stats.removed_by_domain_exclude += 1,
+                            FilterReason::Scheme => stats.removed_by_scheme += 1,
+                            FilterReason::Title => stats.removed_by_title += 1,
+                        }
+                        false
+                    }
+                }
+            });
+        }
+
+        let mut stats = FilterStats::default();
+        apply_recursive(self, bookmarks, &mut stats);
+        stats
+    }
+}
+
+impl FilterStats {
+    pub fn total_removed(&self) -> usize {
+        self.total_before.saturating_sub(self.total_after)
+    }
+
+    /// 打印每个过滤条件的移除数量
+    pub fn print_summary(&self) {
+        info!(
+            "  ✅ Filters kept {} of {} bookmarks ({} removed)",
+            self.total_after,
+            self.total_before,
+            self.total_removed()
+        );
+        let lines = [
+            ("added date", self.removed_by_added_date),
+            ("modified date", self.removed_by_modified_date),
+            ("domain allow list", self.removed_by_domain_include),
+            ("domain deny list", self.removed_by_domain_exclude),
+            ("scheme", self.removed_by_scheme),
+            ("title regex", self.removed_by_title),
+        ];
+        for (name, count) in lines {
+            if count > 0 {
+                info!("     - {}: {} removed", name, count);
+            }
+        }
+    }
+}
+
+fn in_range(value: Option<i64>, after: Option<i64>, before: Option<i64>) -> bool {
+    if after.is_none() && before.is_none() {
+        return true;
+    }
+    match value {
+        Some(v) => after.is_none_or(|a| v >= a) && before.is_none_or(|b| v < b),
+        None => false,
+    }
+}
+
+/// URL 协议（小写，不含冒号）；无协议时返回空串
+fn url_scheme(url: &str) -> String {
+    match url.find(':') {
+        Some(pos)
+            if pos > 0
+                && url[..pos]
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.') =>
+        {
+            url[..pos].to_lowercase()
+        }
+        _ => String::new(),
+    }
+}
+
+/// 用户输入的协议 → 标准形式："file://"、"JavaScript:" → "file"、"javascript"
+fn normalize_scheme(scheme: &str) -> String {
+    scheme.trim().trim_end_matches('/').trim_end_matches(':').to_lowercase()
+}
+
+/// 域名匹配（忽略 www. 前缀）
+/// - "github.com" 只匹配 github.com
+/// - "*.github.com" 匹配 github.com 及所有子域名
+/// - 其他含 * 的模式按通配符匹配，如 "*git*"
+pub fn domain_matches(host: &str, pattern: &str) -> bool {
+    let host = host.trim_start_matches("www.");
+    let pattern = pattern.trim().to_lowercase();
+    let pattern = pattern.trim_start_matches("www.");
+    if host.is_empty() || pattern.is_empty() {
+        return false;
+    }
+
+    if let Some(suffix) = pattern.strip_prefix("*.") {
+        if !suffix.contains('*') {
+            return host == suffix || host.ends_with(&format!(".{}", suffix));
+        }
+    }
+    if pattern.contains('*') {
+        return wildcard_match(pattern, host);
+    }
+    host == pattern
+}
+
+/// 简单通配符匹配（* 匹配任意字符序列）
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let mut rest = text;
+
+    for (i, part) in parts.iter().enumerate() {
+        if part.is_empty() {
+            continue;
+        }
+        if i == 0 {
+            match rest.strip_prefix(part) {
+                Some(r) => rest = r,
+                None => return false,
+            }
+        } else if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        } else {
+            match rest.find(part) {
+                Some(pos) => rest = &rest[pos + part.len()..],
+                None => return false,
+            }
+        }
+    }
+
+    // 模式以 * 结尾或全部匹配完
+    pattern.ends_with('*') || rest.is_empty()
+}
+
+/// 解析日期参数：支持 "2025-01-01"（UTC 零点）和 RFC 3339 时间，返回毫秒时间戳
+pub fn parse_date_bound(value: &str) -> Result<i64> {
+    let value = value.trim();
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.timestamp_millis());
+    }
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .with_context(|| format!("Invalid date: {} (expected YYYY-MM-DD or RFC 3339)", value))?;
+    Ok(date
+        .and_hms_opt(0, 0, 0)
+        .map(|dt| dt.and_utc().timestamp_millis())
+        .unwrap_or_default())
+}
+
+/// 解析逗号分隔的列表参数
+pub fn parse_list(value: Option<&str>) -> Vec<String> {
+    value
+        .map(|v| {
+            v.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, make_folder};
+
+    fn make_bookmark(title: &str, url: &str, added: Option<i64>) -> Bookmark {
+        Bookmark {
+            date_added: added,
+            ..test_support::make_bookmark(title, url)
+        }
+    }
+
+    #[test]
+    fn test_domain_matches() {
+        assert!(domain_matches("github.com", "github.com"));
+        assert!(domain_matches("www.github.com", "github.com"));
+        assert!(!domain_matches("gist.github.com", "github.com"));
+        assert!(domain_matches("gist.github.com", "*.github.com"));
+        assert!(domain_matches("github.com", "*.github.com"));
+        assert!(!domain_matches("notgithub.com", "*.github.com"));
+        assert!(domain_matches("gitlab.example.org", "*git*"));
+        assert!(domain_matches("docs.rs", "docs.*"));
+        assert!(!domain_matches("", "*"));
+    }
+
+    #[test]
+    fn test_url_scheme() {
+        assert_eq!(url_scheme("javascript:alert(1)"), "javascript");
+        assert_eq!(url_scheme("FILE:///tmp/a.html"), "file");
+        assert_eq!(url_scheme("https://a.com"), "https");
+        assert_eq!(url_scheme("no scheme"), "");
+        assert_eq!(normalize_scheme("file://"), "file");
+        assert_eq!(normalize_scheme("JavaScript:"), "javascript");
+    }
+
+    #[test]
+    fn test_parse_date_bound() {
+        assert_eq!(parse_date_bound("2025-01-01").unwrap(), 1735689600000);
+        assert_eq!(parse_date_bound("2025-01-01T08:00:00+08:00").unwrap(), 1735689600000);
+        assert!(parse_date_bound("01/01/2025").is_err());
+    }
+
+    #[test]
+    fn test_date_range_missing_date_removed() {
+        let filter = ExportFilter {
+            added_after: Some(1000),
+            ..Default::default()
+        };
+        assert_eq!(filter.check(&make_bookmark("a", "https://a.com", Some(1000))), None);
+        assert_eq!(
+            filter.check(&make_bookmark("b", "https://a.com", Some(999))),
+            Some(FilterReason::AddedDate)
+        );
+        assert_eq!(
+            filter.check(&make_bookmark("c", "https://a.com", None)),
+            Some(FilterReason::AddedDate)
+        );
+    }
+
+    #[test]
+    fn test_apply_reports_each_filter() {
+        let mut bookmarks = vec![make_folder(
+            "Dev",
+            vec![
+                make_bookmark("gh", "https://github.com/a", Some(2000)),
+                make_bookmark("gl", "https://gitlab.com/b", Some(2000)),
+                make_bookmark("old", "https://github.com/old", Some(10)),
+                make_bookmark("other", "https://example.com", Some(2000)),
+                make_bookmark("bookmarklet", "javascript:void(0)", Some(2000)),
+                make_bookmark("Draft notes", "https://github.com/c", Some(2000)),
+            ],
+        )];
+        let filter = ExportFilter {
+            added_after: Some(1000),
+            include_domains: vec!["github.com".into(), "gitlab.com".into()],
+            exclude_domains: vec!["gitlab.com".into()],
+            title_regex: Some(Regex::new("^[a-z]").unwrap()),
+            ..Default::default()
+        };
+
+        let stats = filter.apply(&mut bookmarks);
+        assert_eq!(stats.total_before, 6);
+        assert_eq!(stats.total_after, 1);
+        assert_eq!(stats.removed_by_added_date, 1);
+        // bookmarklet has no host, so it fails the allow list first
+        assert_eq!(stats.removed_by_domain_include, 2);
+        assert_eq!(stats.removed_by_domain_exclude, 1);
+        assert_eq!(stats.removed_by_title, 1);
+        assert_eq!(bookmarks[0].children.len(), 1);
+        assert_eq!(bookmarks[0].children[0].title, "gh");
+    }
+
+    #[test]
+    fn test_exclude_schemes() {
+        let mut bookmarks = vec![
+            make_bookmark("web", "https://a.com", None),
+            make_bookmark("js", "javascript:alert(1)", None),
+            make_bookmark("local", "file:///tmp/a.html", None),
+        ];
+        let filter = ExportFilter {
+            exclude_schemes: parse_list(Some("javascript, file://")),
+            ..Default::default()
+        };
+        let stats = filter.apply(&mut bookmarks);
+        assert_eq!(stats.removed_by_scheme, 2);
+        assert_eq!(bookmarks.len(), 1);
+        assert!(!filter.is_empty());
+        assert!(ExportFilter::default().is_empty());
+    }
+}
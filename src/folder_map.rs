@@ -0,0 +1,496 @@
+//! 文件夹映射 - 不同浏览器里名称/位置不同但内容应当一致的文件夹
+//!
+//! 配置文件 `~/.browser-sync/folder_map.json`，每条映射是"来源浏览器:路径 → 目标浏览器:路径"：
+//!
+//! ```json
+//! [
+//!   { "from": "safari:Favorites", "to": "chrome:Bookmarks Bar" },
+//!   { "from": "waterfox:Bookmarks Toolbar", "to": "chrome:Bookmarks Bar" },
+//!   { "from": "brave:Work/Links", "to": "waterfox:Arbeit/Links", "one_way": true }
+//! ]
+//! ```
+//!
+//! 合并、三方同步、场景同步都在来源路径上进行。引擎用 `MappingAdapter` 包装目标浏览器：
+//! 读取时把目标文件夹移到来源路径，写回时再移回目标路径；其他浏览器都使用来源路径。
+//! 同一来源路径可以映射到多个目标浏览器；同一目标浏览器里的同一路径只能对应一个来源路径，
+//! 冲突的映射只保留第一条。
+//! 单向映射（`one_way`）只把来源内容带到目标，写回来源浏览器时保留它自己的来源文件夹，不接收目标的内容。
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::browsers::{
+    Bookmark, BrowserAdapter, BrowserType, Cookie, HistoryItem, ReadingListItem,
+};
+use crate::three_way;
+
+/// 一条映射
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FolderMapping {
+    /// `browser:path`
+    pub from: String,
+    /// `browser:path`
+    pub to: String,
+    /// 只从来源流向目标
+    #[serde(default)]
+    pub one_way: bool,
+}
+
+/// 解析后的一端：浏览器名 + 路径
+#[derive(Debug, Clone, PartialEq)]
+pub struct MappedFolder {
+    pub browser: String,
+    pub path: Vec<String>,
+}
+
+impl MappedFolder {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (browser, path) = spec
+            .split_once(':')
+            .with_context(|| format!("Folder mapping must be browser:path, got \"{}\"", spec))?;
+        let path: Vec<String> = path
+            .split('/')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        if path.is_empty() {
+            anyhow::bail!("Folder mapping has an empty path: \"{}\"", spec);
+        }
+        Ok(Self {
+            browser: normalize_browser(browser),
+            path,
+        })
+    }
+
+    fn matches(&self, browser: BrowserType) -> bool {
+        self.browser == normalize_browser(browser.name())
+    }
+}
+
+fn normalize_browser(name: &str) -> String {
+    name.trim().to_lowercase().replace(['-', '_'], " ")
+}
+
+/// 映射表
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FolderMap(pub Vec<FolderMapping>);
+
+/// 对某个浏览器生效的映射（已解析）
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrowserMapping {
+    /// 这个浏览器里的路径
+    pub local: Vec<String>,
+    /// 合并树里的路径（映射的来源路径）
+    pub shared: Vec<String>,
+    /// 映射另一端的浏览器
+    pub peer: String,
+    /// 单向映射的来源端：写回时保留浏览器自己的文件夹
+    pub keep_own: bool,
+}
+
+impl FolderMap {
+    pub fn default_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME")?;
+        Ok(PathBuf::from(format!(
+            "{}/.browser-sync/folder_map.json",
+            home
+        )))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let map: FolderMap =
+            serde_json::from_str(&content).context("Failed to parse folder mappings")?;
+        for mapping in &map.0 {
+            MappedFolder::parse(&mapping.from)?;
+            MappedFolder::parse(&mapping.to)?;
+        }
+        Ok(map)
+    }
+
+    pub fn load_default() -> Self {
+        Self::default_path()
+            .and_then(|p| Self::load(&p))
+            .unwrap_or_else(|e| {
+                warn!("⚠️  Ignoring folder mappings: {}", e);
+                Self::default()
+            })
+    }
+
+    /// 对该浏览器生效的映射，深的路径在前（先移子文件夹，父文件夹移动不会影响它）
+    ///
+    /// 浏览器是目标时把目标路径改名为来源路径；是单向映射的来源时保留自己的文件夹。
+    pub fn for_browser(&self, browser: BrowserType) -> Vec<BrowserMapping> {
+        let mut mappings: Vec<BrowserMapping> = Vec::new();
+        for m in &self.0 {
+            let (Ok(from), Ok(to)) = (MappedFolder::parse(&m.from), MappedFolder::parse(&m.to))
+            else {
+                continue;
+            };
+            if to.matches(browser) && from.path != to.path {
+                let conflict = mappings
+                    .iter()
+                    .filter(|other| !other.keep_own)
+                    .find(|other| (other.local == to.path) != (other.shared == from.path));
+                if let Some(other) = conflict {
+                    warn!(
+                        "⚠️  {}: ignoring mapping {} → {}, it conflicts with /{} ⇄ /{}",
+                        browser.name(),
+                        m.from,
+                        m.to,
+                        other.local.join("/"),
+                        other.shared.join("/")
+                    );
+                    continue;
+                }
+                if mappings.iter().any(|o| o.local == to.path && !o.keep_own) {
+                    continue;
+                }
+                mappings.push(BrowserMapping {
+                    local: to.path,
+                    shared: from.path,
+                    peer: from.browser,
+                    keep_own: false,
+                });
+            } else if from.matches(browser) && m.one_way {
+                mappings.push(BrowserMapping {
+                    local: from.path.clone(),
+                    shared: from.path,
+                    peer: to.browser,
+                    keep_own: true,
+                });
+            }
+        }
+        mappings.sort_by_key(|m| std::cmp::Reverse(m.local.len()));
+        mappings
+    }
+}
+
+/// 浏览器自己的路径 → 合并树里的路径
+pub fn to_mapped(tree: &mut Vec<Bookmark>, mappings: &[BrowserMapping]) {
+    for mapping in mappings.iter().filter(|m| !m.keep_own) {
+        move_folder(tree, &mapping.local, &mapping.shared);
+    }
+}
+
+/// 合并树里的路径 → 浏览器自己的路径；单向映射的来源保留 current（浏览器当前的树）里的文件夹
+pub fn to_local(tree: &mut Vec<Bookmark>, mappings: &[BrowserMapping], current: &[Bookmark]) {
+    for mapping in mappings.iter().rev() {
+        if mapping.keep_own {
+            take_folder(tree, &mapping.shared);
+            if let Some(own) = find_folder(current, &mapping.local) {
+                if let Some((_, parent)) = mapping.local.split_last() {
+                    three_way::insert_at_path(tree, parent, own.clone());
+                }
+            }
+        } else {
+            move_folder(tree, &mapping.shared, &mapping.local);
+        }
+    }
+}
+
+/// 移动文件夹，并删除因此变空的上级文件夹（否则另一端会多出空的 `Work`/`Arbeit`）
+fn move_folder(tree: &mut Vec<Bookmark>, from: &[String], to: &[String]) {
+    if !three_way::move_folder(tree, from, to) {
+        return;
+    }
+    let mut parent = &from[..from.len() - 1];
+    while !parent.is_empty()
+        && find_folder(tree, parent).is_some_and(|f| f.children.is_empty())
+        && !to.starts_with(parent)
+    {
+        take_folder(tree, parent);
+        parent = &parent[..parent.len() - 1];
+    }
+}
+
+fn find_folder<'a>(tree: &'a [Bookmark], path: &[String]) -> Option<&'a Bookmark> {
+    let (first, rest) = path.split_first()?;
+    let folder = tree.iter().find(|b| b.folder && &b.title == first)?;
+    if rest.is_empty() {
+        Some(folder)
+    } else {
+        find_folder(&folder.children, rest)
+    }
+}
+
+fn take_folder(tree: &mut Vec<Bookmark>, path: &[String]) -> Option<Bookmark> {
+    let (name, parent) = path.split_last()?;
+    let siblings = three_way::folder_children_mut(tree, parent)?;
+    let pos = siblings.iter().position(|b| b.folder && &b.title == name)?;
+    Some(siblings.remove(pos))
+}
+
+/// 带文件夹映射的浏览器适配器
+pub struct MappingAdapter {
+    inner: Box<dyn BrowserAdapter + Send + Sync>,
+    mappings: Vec<BrowserMapping>,
+}
+
+impl MappingAdapter {
+    pub fn new(
+        inner: Box<dyn BrowserAdapter + Send + Sync>,
+        mappings: Vec<BrowserMapping>,
+    ) -> Self {
+        Self { inner, mappings }
+    }
+
+    fn mapped(&self, mut bookmarks: Vec<Bookmark>) -> Vec<Bookmark> {
+        to_mapped(&mut bookmarks, &self.mappings);
+        bookmarks
+    }
+
+    fn local(&self, bookmarks: &[Bookmark]) -> Result<Vec<Bookmark>> {
+        let mut tree = bookmarks.to_vec();
+        if tree.is_empty() {
+            // 清空浏览器（hub 清空非中心浏览器、迁移）时不放回单向映射的来源文件夹
+            return Ok(tree);
+        }
+        let current = if self.mappings.iter().any(|m| m.keep_own)
+            && self.inner.detect_bookmark_path()?.exists()
+        {
+            self.inner.read_bookmarks()?
+        } else {
+            Vec::new()
+        };
+        to_local(&mut tree, &self.mappings, &current);
+        Ok(tree)
+    }
+}
+
+impl BrowserAdapter for MappingAdapter {
+    fn browser_type(&self) -> BrowserType {
+        self.inner.browser_type()
+    }
+
+    fn detect_bookmark_path(&self) -> Result<PathBuf> {
+        self.inner.detect_bookmark_path()
+    }
+
+    fn read_bookmarks(&self) -> Result<Vec<Bookmark>> {
+        Ok(self.mapped(self.inner.read_bookmarks()?))
+    }
+
+    fn write_bookmarks(&self, bookmarks: &[Bookmark]) -> Result<()> {
+        self.inner.write_bookmarks(&self.local(bookmarks)?)
+    }
+
+    fn backup_bookmarks(&self) -> Result<PathBuf> {
+        self.inner.backup_bookmarks()
+    }
+
+    fn validate_bookmarks(&self, bookmarks: &[Bookmark]) -> Result<bool> {
+        self.inner.validate_bookmarks(bookmarks)
+    }
+
+    fn write_bookmarks_to(&self, path: &Path, bookmarks: &[Bookmark]) -> Result<()> {
+        self.inner.write_bookmarks_to(path, &self.local(bookmarks)?)
+    }
+
+    fn read_bookmarks_from(&self, path: &Path) -> Result<Vec<Bookmark>> {
+        Ok(self.mapped(self.inner.read_bookmarks_from(path)?))
+    }
+
+    fn supports_reading_list(&self) -> bool {
+        self.inner.supports_reading_list()
+    }
+
+    fn read_reading_list(&self) -> Result<Vec<ReadingListItem>> {
+        self.inner.read_reading_list()
+    }
+
+    fn write_reading_list(&self, items: &[ReadingListItem]) -> Result<()> {
+        self.inner.write_reading_list(items)
+    }
+
+    fn supports_history(&self) -> bool {
+        self.inner.supports_history()
+    }
+
+    fn read_history(&self, days: Option<i32>) -> Result<Vec<HistoryItem>> {
+        self.inner.read_history(days)
+    }
+
+    fn write_history(&self, items: &[HistoryItem]) -> Result<()> {
+        self.inner.write_history(items)
+    }
+
+    fn supports_cookies(&self) -> bool {
+        self.inner.supports_cookies()
+    }
+
+    fn read_cookies(&self) -> Result<Vec<Cookie>> {
+        self.inner.read_cookies()
+    }
+
+    fn write_cookies(&self, cookies: &[Cookie]) -> Result<()> {
+        self.inner.write_cookies(cookies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{make_bookmark, make_folder};
+
+    fn titles(nodes: &[Bookmark]) -> Vec<&str> {
+        nodes.iter().map(|b| b.title.as_str()).collect()
+    }
+
+    fn config() -> FolderMap {
+        serde_json::from_str(
+            r#"[
+                { "from": "safari:Favorites", "to": "chrome:Bookmarks Bar" },
+                { "from": "brave:Work/Links", "to": "waterfox:Arbeit/Links" },
+                { "from": "brave:Work", "to": "waterfox:Arbeit" },
+                { "from": "brave:Inbox", "to": "chrome:Bookmarks Bar/Inbox", "one_way": true }
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_mappings_for_browser() {
+        let map = config();
+        // Sources are not renamed; the target sees the source path in the merged tree
+        assert!(map.for_browser(BrowserType::Safari).is_empty());
+        let chrome = map.for_browser(BrowserType::Chrome);
+        assert_eq!(chrome.len(), 2);
+        assert_eq!(chrome[1].local, vec!["Bookmarks Bar".to_string()]);
+        assert_eq!(chrome[1].shared, vec!["Favorites".to_string()]);
+        assert_eq!(chrome[1].peer, "safari");
+
+        // Deeper paths first
+        let waterfox = map.for_browser(BrowserType::Waterfox);
+        assert_eq!(
+            waterfox[0].local,
+            vec!["Arbeit".to_string(), "Links".to_string()]
+        );
+        // A one-way source only keeps its own folder
+        let brave = map.for_browser(BrowserType::Brave);
+        assert_eq!(brave.len(), 1);
+        assert!(brave[0].keep_own);
+
+        assert!(MappedFolder::parse("safari").is_err());
+        assert!(MappedFolder::parse("safari:/").is_err());
+    }
+
+    #[test]
+    fn test_round_trip_through_mapped_paths() {
+        let mappings = config().for_browser(BrowserType::Waterfox);
+        let local = vec![make_folder(
+            "Arbeit",
+            vec![
+                make_folder("Links", vec![make_bookmark("Docs", "https://docs.example")]),
+                make_bookmark("Jira", "https://jira.example"),
+            ],
+        )];
+
+        let mut tree = local.clone();
+        to_mapped(&mut tree, &mappings);
+        assert_eq!(titles(&tree), vec!["Work"]);
+        assert_eq!(titles(&tree[0].children), vec!["Links", "Jira"]);
+
+        to_local(&mut tree, &mappings, &local);
+        assert_eq!(titles(&tree), vec!["Arbeit"]);
+        let links = tree[0]
+            .children
+            .iter()
+            .find(|b| b.title == "Links")
+            .unwrap();
+        assert_eq!(titles(&links.children), vec!["Docs"]);
+    }
+
+    #[test]
+    fn test_mapping_only_renames_the_target_browser() {
+        let map: FolderMap = serde_json::from_str(
+            r#"[
+                { "from": "brave:Work/Links", "to": "waterfox:Arbeit/Links" },
+                { "from": "brave:Work/Links", "to": "safari:Travail/Liens" },
+                { "from": "chrome:Work/Links", "to": "waterfox:Job/Links" }
+            ]"#,
+        )
+        .unwrap();
+        let brave = map.for_browser(BrowserType::Brave);
+        let waterfox = map.for_browser(BrowserType::Waterfox);
+        let chrome = map.for_browser(BrowserType::Chrome);
+        assert!(brave.is_empty() && chrome.is_empty());
+        // Same merged path twice for Waterfox: the first mapping wins
+        assert_eq!(waterfox.len(), 1);
+        assert_eq!(map.for_browser(BrowserType::Safari).len(), 1);
+
+        let mut merged = vec![make_folder(
+            "Work",
+            vec![make_folder(
+                "Links",
+                vec![make_bookmark("Docs", "https://docs.example")],
+            )],
+        )];
+        // Waterfox's own folder joins the merged one
+        let mut from_waterfox = vec![make_folder(
+            "Arbeit",
+            vec![make_folder(
+                "Links",
+                vec![make_bookmark("Wiki", "https://wiki.example")],
+            )],
+        )];
+        to_mapped(&mut from_waterfox, &waterfox);
+        assert_eq!(titles(&from_waterfox), vec!["Work"]);
+        merged[0].children[0]
+            .children
+            .extend(from_waterfox[0].children[0].children.clone());
+
+        let mut for_waterfox = merged.clone();
+        to_local(&mut for_waterfox, &waterfox, &[]);
+        assert_eq!(titles(&for_waterfox), vec!["Arbeit"]);
+        assert_eq!(
+            titles(&for_waterfox[0].children[0].children),
+            vec!["Docs", "Wiki"]
+        );
+
+        // Brave and Chrome are not targets and keep Work/Links
+        for mappings in [&brave, &chrome] {
+            let mut tree = merged.clone();
+            to_local(&mut tree, mappings, &[]);
+            assert_eq!(titles(&tree), vec!["Work"]);
+            assert_eq!(titles(&tree[0].children), vec!["Links"]);
+        }
+    }
+
+    #[test]
+    fn test_one_way_mapping_keeps_source_folder() {
+        let mappings = config().for_browser(BrowserType::Brave);
+        let local = vec![make_folder(
+            "Inbox",
+            vec![make_bookmark("Mine", "https://mine.example")],
+        )];
+
+        let mut tree = local.clone();
+        to_mapped(&mut tree, &mappings);
+        assert_eq!(titles(&tree), vec!["Inbox"]);
+
+        // Merged result has more in the folder; none of it comes back to Brave
+        tree[0]
+            .children
+            .push(make_bookmark("Theirs", "https://theirs.example"));
+        to_local(&mut tree, &mappings, &local);
+        let inbox = tree.iter().find(|b| b.title == "Inbox").unwrap();
+        assert_eq!(titles(&inbox.children), vec!["Mine"]);
+
+        // Chrome, the target, gets it under its own path
+        let mut chrome = vec![make_folder(
+            "Inbox",
+            vec![make_bookmark("Mine", "https://mine.example")],
+        )];
+        to_local(&mut chrome, &config().for_browser(BrowserType::Chrome), &[]);
+        assert_eq!(titles(&chrome), vec!["Bookmarks Bar"]);
+        assert_eq!(titles(&chrome[0].children), vec!["Inbox"]);
+    }
+}
@@ -0,0 +1,305 @@
+//! 相似文件夹合并 - 同一层级下名称几乎相同的文件夹合并为一个
+//!
+//! 从多个浏览器导入后常见 `Dev`、`dev`、`💻 Dev`、`Development`、`开发` 并列。
+//! 比较前先规范化名称：忽略大小写、emoji、空白和标点（保留字母和数字，含中日韩文字）。
+//! `+`、`#` 这类有含义的符号保留，`C++`、`C#`、`C` 是不同的文件夹。
+//! 跨语言的别名由同义词表决定，配置文件 `~/.browser-sync/folder_synonyms.json`，
+//! 每组第一个名称是这一组的代表：
+//!
+//! ```json
+//! [
+//!   ["Dev", "Development", "开发", "编程"],
+//!   ["Docs", "Documentation", "文档"]
+//! ]
+//! ```
+//!
+//! 文件不存在时使用内置的常用分组；文件存在时完全替换内置分组。
+//! 合并保留最先出现的文件夹（名称、位置不变），其余文件夹的内容依次追加进去，然后递归合并子文件夹。
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::browsers::Bookmark;
+
+/// 内置同义词分组
+const BUILTIN_SYNONYMS: &[&[&str]] = &[
+    &["Dev", "Development", "Developer", "开发", "编程"],
+    &["Docs", "Documentation", "文档"],
+    &["Tools", "Tool", "Utilities", "工具"],
+    &["News", "新闻", "资讯"],
+    &["Work", "工作"],
+    &["Learning", "Study", "学习"],
+    &["Reading", "Read Later", "阅读", "稍后阅读"],
+    &["Videos", "Video", "视频"],
+    &["Music", "音乐"],
+    &["Shopping", "购物"],
+    &["Design", "设计"],
+];
+
+/// 名称里有含义的符号（`C++`、`C#`、`F#`），规范化时保留
+const SIGNIFICANT_SYMBOLS: &[char] = &['+', '#'];
+
+/// 规范化文件夹名称：只保留字母、数字和有含义的符号并转小写
+/// 没有字母和数字的名称（emoji/标点）保留原样（去空白、小写），避免它们互相合并
+pub fn normalize_folder_name(title: &str) -> String {
+    let key: String = title
+        .chars()
+        .filter(|c| c.is_alphanumeric() || SIGNIFICANT_SYMBOLS.contains(c))
+        .flat_map(char::to_lowercase)
+        .collect();
+    if !key.chars().any(char::is_alphanumeric) {
+        title.trim().to_lowercase()
+    } else {
+        key
+    }
+}
+
+/// 用户可编辑的同义词表
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FolderSynonyms(pub Vec<Vec<String>>);
+
+impl Default for FolderSynonyms {
+    fn default() -> Self {
+        Self(
+            BUILTIN_SYNONYMS
+                .iter()
+                .map(|group| group.iter().map(|s| s.to_string()).collect())
+                .collect(),
+        )
+    }
+}
+
+impl FolderSynonyms {
+    pub fn default_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME")?;
+        Ok(PathBuf::from(format!(
+            "{}/.browser-sync/folder_synonyms.json",
+            home
+        )))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content).context("Failed to parse folder synonyms")
+    }
+
+    pub fn load_default() -> Self {
+        Self::default_path()
+            .and_then(|p| Self::load(&p))
+            .unwrap_or_else(|e| {
+                warn!("⚠️  Using built-in folder synonyms: {}", e);
+                Self::default()
+            })
+    }
+
+    /// 规范化别名 → 规范化代表名；同一别名出现在多组时以前面的组为准
+    fn lookup(&self) -> HashMap<String, String> {
+        let mut map = HashMap::new();
+        for group in self.0.iter().filter(|g| !g.is_empty()) {
+            let canonical = normalize_folder_name(&group[0]);
+            for alias in group {
+                map.entry(normalize_folder_name(alias))
+                    .or_insert_with(|| canonical.clone());
+            }
+        }
+        map
+    }
+}
+
+/// 合并键：规范化名称，再经同义词表映射到代表名
+fn merge_key(title: &str, lookup: &HashMap<String, String>) -> String {
+    let key = normalize_folder_name(title);
+    lookup.get(&key).cloned().unwrap_or(key)
+}
+
+/// 一次合并：同一父文件夹下若干文件夹并入保留的那个
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FolderMerge {
+    /// 父文件夹路径（顶层为空）
+    pub parent: String,
+    pub kept: String,
+    pub merged: Vec<String>,
+    /// 移入保留文件夹的条目数（书签和子文件夹）
+    pub items_moved: usize,
+}
+
+/// 递归合并同一层级下名称相似的文件夹，返回合并记录（父层级在前）
+pub fn merge_similar_folders(
+    bookmarks: &mut Vec<Bookmark>,
+    synonyms: &FolderSynonyms,
+) -> Vec<FolderMerge> {
+    let lookup = synonyms.lookup();
+    let mut merges = Vec::new();
+    merge_level(bookmarks, &lookup, &mut Vec::new(), &mut merges);
+    merges
+}
+
+fn merge_level(
+    nodes: &mut Vec<Bookmark>,
+    lookup: &HashMap<String, String>,
+    path: &mut Vec<String>,
+    merges: &mut Vec<FolderMerge>,
+) {
+    // (保留下标, 并入下标)
+    let mut first_by_key: HashMap<String, usize> = HashMap::new();
+    let mut absorbed: Vec<(usize, usize)> = Vec::new();
+    for (index, node) in nodes.iter().enumerate().filter(|(_, n)| n.folder) {
+        let key = merge_key(&node.title, lookup);
+        match first_by_key.get(&key) {
+            Some(&kept) => absorbed.push((kept, index)),
+            None => {
+                first_by_key.insert(key, index);
+            }
+        }
+    }
+
+    let mut records: BTreeMap<usize, FolderMerge> = BTreeMap::new();
+    for &(kept, from) in &absorbed {
+        let children = std::mem::take(&mut nodes[from].children);
+        let record = records.entry(kept).or_insert_with(|| FolderMerge {
+            parent: path.join("/"),
+            kept: nodes[kept].title.clone(),
+            merged: Vec::new(),
+            items_moved: 0,
+        });
+        record.merged.push(nodes[from].title.clone());
+        record.items_moved += children.len();
+        nodes[kept].children.extend(children);
+    }
+    let removed: HashSet<usize> = absorbed.iter().map(|&(_, from)| from).collect();
+    let mut index = 0;
+    nodes.retain(|_| {
+        index += 1;
+        !removed.contains(&(index - 1))
+    });
+    merges.extend(records.into_values());
+
+    // 合并后的文件夹里可能又出现相似的子文件夹
+    for node in nodes.iter_mut().filter(|n| n.folder) {
+        path.push(node.title.clone());
+        merge_level(&mut node.children, lookup, path, merges);
+        path.pop();
+    }
+}
+
+/// 打印合并预览/结果
+pub fn print_merges(merges: &[FolderMerge], limit: Option<usize>) {
+    if merges.is_empty() {
+        println!("📁 No similar folders to merge");
+        return;
+    }
+    let folders: usize = merges.iter().map(|m| m.merged.len()).sum();
+    println!(
+        "📁 Similar folders: {} groups, {} folders merged away",
+        merges.len(),
+        folders
+    );
+    let shown = limit.unwrap_or(merges.len()).min(merges.len());
+    for merge in &merges[..shown] {
+        let merged: Vec<String> = merge.merged.iter().map(|m| format!("\"{}\"", m)).collect();
+        println!(
+            "  /{}: \"{}\" ← {} ({} items)",
+            merge.parent,
+            merge.kept,
+            merged.join(", "),
+            merge.items_moved
+        );
+    }
+    if shown < merges.len() {
+        println!("  ... and {} more", merges.len() - shown);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{make_bookmark, make_folder};
+
+    fn folder(title: &str, children: Vec<Bookmark>) -> Bookmark {
+        Bookmark {
+            id: title.to_string(),
+            ..make_folder(title, children)
+        }
+    }
+
+    fn bookmark(title: &str) -> Bookmark {
+        Bookmark {
+            id: title.to_string(),
+            ..make_bookmark(title, &format!("https://{}.example/", title))
+        }
+    }
+
+    fn titles(nodes: &[Bookmark]) -> Vec<&str> {
+        nodes.iter().map(|b| b.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_normalize_folder_name() {
+        assert_eq!(normalize_folder_name("💻 Dev"), "dev");
+        assert_eq!(normalize_folder_name(" D-e_v. "), "dev");
+        assert_eq!(normalize_folder_name("开发 / 工具"), "开发工具");
+        assert_eq!(normalize_folder_name("👀"), "👀");
+        assert_eq!(normalize_folder_name("#"), "#");
+        assert_eq!(normalize_folder_name("💻 C++"), "c++");
+        assert_ne!(normalize_folder_name("C++"), normalize_folder_name("C#"));
+        assert_ne!(normalize_folder_name("C#"), normalize_folder_name("C"));
+        assert_ne!(normalize_folder_name("👀"), normalize_folder_name("📚"));
+    }
+
+    #[test]
+    fn test_merge_similar_folders_recursively() {
+        let mut tree = vec![
+            folder("Dev", vec![folder("Rust", vec![bookmark("a")])]),
+            bookmark("top"),
+            folder("💻 dev", vec![folder("rust", vec![bookmark("b")])]),
+            folder("Development", vec![bookmark("c")]),
+            folder("开发", vec![]),
+            folder("News", vec![bookmark("d")]),
+        ];
+        let merges = merge_similar_folders(&mut tree, &FolderSynonyms::default());
+
+        assert_eq!(titles(&tree), vec!["Dev", "top", "News"]);
+        assert_eq!(
+            merges[0],
+            FolderMerge {
+                parent: String::new(),
+                kept: "Dev".into(),
+                merged: vec!["💻 dev".into(), "Development".into(), "开发".into()],
+                items_moved: 2,
+            }
+        );
+        // 并入后的 Rust / rust 也合并
+        assert_eq!(merges[1].parent, "Dev");
+        assert_eq!(merges[1].merged, vec!["rust"]);
+        let dev = &tree[0].children;
+        assert_eq!(dev.len(), 2);
+        assert_eq!(dev[0].children.len(), 2);
+        assert_eq!(dev[1].title, "c");
+
+        // 不同语言的文件夹不合并，名称只差 emoji 的仍然合并
+        let mut tree = vec![
+            folder("C++", vec![bookmark("cpp")]),
+            folder("C#", vec![bookmark("cs")]),
+            folder("C", vec![bookmark("c")]),
+            folder("🔧 c++", vec![bookmark("cmake")]),
+        ];
+        let merges = merge_similar_folders(&mut tree, &FolderSynonyms::default());
+        assert_eq!(titles(&tree), vec!["C++", "C#", "C"]);
+        assert_eq!(merges.len(), 1);
+        assert_eq!(merges[0].merged, vec!["🔧 c++"]);
+
+        // 自定义同义词表替换内置分组
+        let mut tree = vec![folder("Dev", vec![]), folder("开发", vec![])];
+        let custom = FolderSynonyms(vec![vec!["Docs".into(), "文档".into()]]);
+        assert!(merge_similar_folders(&mut tree, &custom).is_empty());
+        assert_eq!(tree.len(), 2);
+    }
+}
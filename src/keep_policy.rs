@@ -0,0 +1,349 @@
+//! 去重保留策略 - 同一 URL 的多份书签里保留哪一份
+//!
+//! 策略是一串按顺序比较的规则：前一条分不出高下才看下一条，
+//! 全部打平时保留最先出现的那份。每一份被移除的书签都记录它输在哪条规则上。
+//!
+//! 规则（`--keep-policy` 逗号分隔）：
+//! - `oldest` / `newest`：添加时间（没有时间的总是输）
+//! - `deepest` / `shallowest`：文件夹层级
+//! - `browser:chrome|firefox`：按浏览器顺序（来源浏览器或顶层浏览器文件夹）
+//! - `folder:Work*|Projects`：优先位于这些文件夹中的副本，靠前的模式优先
+//! - `not-from[:A|B]`：尽量不保留这些文件夹中的副本（默认 Unsorted、临时）
+//!
+//! 文件夹模式大小写不敏感，`*` 通配，匹配路径中任一文件夹名或路径的开头/结尾一段（`A/B`）。
+//! 默认策略 `deepest,newest` 与之前的去重行为一致。
+
+use anyhow::{bail, Result};
+use std::cmp::Ordering;
+
+use crate::export_filter::wildcard_match;
+
+/// `not-from` 未指定文件夹时避开的文件夹
+const DEFAULT_AVOID_FOLDERS: &[&str] = &["Unsorted*", "*临时*"];
+
+/// 单条保留规则
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeepRule {
+    Oldest,
+    Newest,
+    Deepest,
+    Shallowest,
+    /// 浏览器名称（包含匹配），靠前的优先
+    Browsers(Vec<String>),
+    /// 文件夹模式，靠前的优先
+    Folders(Vec<String>),
+    /// 避开这些文件夹
+    NotFrom(Vec<String>),
+}
+
+impl KeepRule {
+    /// 规则名称（也用作移除原因）
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeepRule::Oldest => "oldest",
+            KeepRule::Newest => "newest",
+            KeepRule::Deepest => "deepest",
+            KeepRule::Shallowest => "shallowest",
+            KeepRule::Browsers(_) => "browser",
+            KeepRule::Folders(_) => "folder",
+            KeepRule::NotFrom(_) => "not-from",
+        }
+    }
+
+    /// 得分越高越应该保留
+    fn score(&self, candidate: &KeepCandidate) -> i64 {
+        match self {
+            KeepRule::Oldest => candidate.date_added.map(|d| -d).unwrap_or(i64::MIN),
+            KeepRule::Newest => candidate.date_added.unwrap_or(i64::MIN),
+            KeepRule::Deepest => candidate.depth as i64,
+            KeepRule::Shallowest => -(candidate.depth as i64),
+            KeepRule::Browsers(names) => {
+                let browser = candidate.browser.map(str::to_lowercase);
+                let rank = browser.and_then(|b| names.iter().position(|n| b.contains(n.as_str())));
+                -(rank.unwrap_or(names.len()) as i64)
+            }
+            KeepRule::Folders(patterns) => {
+                let rank = patterns
+                    .iter()
+                    .position(|p| folders_match(p, candidate.folders));
+                -(rank.unwrap_or(patterns.len()) as i64)
+            }
+            KeepRule::NotFrom(patterns) => {
+                if patterns.iter().any(|p| folders_match(p, candidate.folders)) {
+                    0
+                } else {
+                    1
+                }
+            }
+        }
+    }
+}
+
+/// 文件夹模式是否匹配路径中任一文件夹名，或从根开始/到末尾结束的一段路径
+fn folders_match(pattern: &str, folders: &[String]) -> bool {
+    let matches = |text: &str| {
+        let text = text.to_lowercase();
+        if pattern.contains('*') {
+            wildcard_match(pattern, &text)
+        } else {
+            text == pattern
+        }
+    };
+    folders.iter().any(|f| matches(f))
+        || (1..folders.len()).any(|i| matches(&folders[..=i].join("/")))
+        || (1..folders.len()).any(|i| matches(&folders[i - 1..].join("/")))
+}
+
+/// 参与比较的一份书签
+#[derive(Debug, Clone)]
+pub struct KeepCandidate<'a> {
+    pub depth: usize,
+    pub date_added: Option<i64>,
+    /// 从根到书签所在文件夹的文件夹名
+    pub folders: &'a [String],
+    pub browser: Option<&'a str>,
+}
+
+/// 保留策略
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeepPolicy {
+    pub rules: Vec<KeepRule>,
+}
+
+impl Default for KeepPolicy {
+    fn default() -> Self {
+        Self {
+            rules: vec![KeepRule::Deepest, KeepRule::Newest],
+        }
+    }
+}
+
+impl KeepPolicy {
+    /// 解析 CLI 参数，如 "not-from,browser:chrome|firefox,deepest"
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut rules = Vec::new();
+        for part in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (name, args) = match part.split_once(':') {
+                Some((name, args)) => (name.trim().to_lowercase(), Some(args)),
+                None => (part.to_lowercase(), None),
+            };
+            let list = |args: Option<&str>| -> Vec<String> {
+                args.unwrap_or("")
+                    .split('|')
+                    .map(|s| s.trim().to_lowercase())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            };
+            let rule = match name.as_str() {
+                "oldest" => KeepRule::Oldest,
+                "newest" => KeepRule::Newest,
+                "deepest" => KeepRule::Deepest,
+                "shallowest" => KeepRule::Shallowest,
+                "browser" | "browsers" => KeepRule::Browsers(list(args)),
+                "folder" | "folders" => KeepRule::Folders(list(args)),
+                "not-from" | "avoid" => {
+                    let folders = list(args);
+                    if folders.is_empty() {
+                        KeepRule::NotFrom(
+                            DEFAULT_AVOID_FOLDERS
+                                .iter()
+                                .map(|f| f.to_lowercase())
+                                .collect(),
+                        )
+                    } else {
+                        KeepRule::NotFrom(folders)
+                    }
+                }
+                other => bail!(
+                    "Unknown keep policy: {} (expected oldest, newest, deepest, shallowest, browser:, folder:, not-from)",
+                    other
+                ),
+            };
+            if matches!(&rule, KeepRule::Browsers(v) | KeepRule::Folders(v) if v.is_empty()) {
+                bail!("Keep policy '{}' needs a list, e.g. {}:a|b", name, name);
+            }
+            rules.push(rule);
+        }
+        if rules.is_empty() {
+            bail!("Keep policy is empty");
+        }
+        Ok(Self { rules })
+    }
+
+    /// 用于日志的简短描述
+    pub fn description(&self) -> String {
+        self.rules
+            .iter()
+            .map(|rule| match rule {
+                KeepRule::Browsers(v) | KeepRule::Folders(v) | KeepRule::NotFrom(v) => {
+                    format!("{}:{}", rule.name(), v.join("|"))
+                }
+                _ => rule.name().to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn compare(&self, a: &KeepCandidate, b: &KeepCandidate) -> (Ordering, Option<&'static str>) {
+        for rule in &self.rules {
+            match rule.score(a).cmp(&rule.score(b)) {
+                Ordering::Equal => continue,
+                other => return (other, Some(rule.name())),
+            }
+        }
+        (Ordering::Equal, None)
+    }
+
+    /// 选出保留的一份，返回 (保留下标, [(移除下标, 原因)])
+    pub fn select(&self, candidates: &[KeepCandidate]) -> (usize, Vec<(usize, &'static str)>) {
+        let mut winner = 0;
+        for (i, candidate) in candidates.iter().enumerate().skip(1) {
+            // 严格更好才替换，打平时保留先出现的
+            if self.compare(candidate, &candidates[winner]).0 == Ordering::Greater {
+                winner = i;
+            }
+        }
+        let losers = (0..candidates.len())
+            .filter(|&i| i != winner)
+            .map(|i| {
+                let reason = self
+                    .compare(&candidates[winner], &candidates[i])
+                    .1
+                    .unwrap_or("first-seen");
+                (i, reason)
+            })
+            .collect();
+        (winner, losers)
+    }
+}
+
+/// 一份被移除的重复书签及原因
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct DedupeLoss {
+    pub url: String,
+    pub title: String,
+    pub folder: String,
+    /// 保留的那份所在文件夹
+    pub kept_folder: String,
+    /// 输掉的规则名称，全部打平时为 "first-seen"
+    pub reason: String,
+}
+
+/// 按原因汇总，如 [("deepest", 12), ("not-from", 3)]
+pub fn count_reasons(losses: &[DedupeLoss]) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for loss in losses {
+        match counts.iter_mut().find(|(reason, _)| *reason == loss.reason) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((loss.reason.clone(), 1)),
+        }
+    }
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+/// 打印移除原因汇总，并逐条列出前 `limit` 份（None = 全部）
+pub fn print_losses(losses: &[DedupeLoss], limit: Option<usize>) {
+    if losses.is_empty() {
+        return;
+    }
+    let summary = count_reasons(losses)
+        .iter()
+        .map(|(reason, count)| format!("{} {}", count, reason))
+        .collect::<Vec<_>>()
+        .join(", ");
+    println!("  Removed copies by reason: {}", summary);
+    let shown = limit.unwrap_or(losses.len()).min(losses.len());
+    for loss in &losses[..shown] {
+        println!(
+            "    - [{}] {} ({}) in /{} — kept /{}",
+            loss.reason, loss.title, loss.url, loss.folder, loss.kept_folder
+        );
+    }
+    if shown > 0 && shown < losses.len() {
+        println!("    ... and {} more", losses.len() - shown);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate<'a>(depth: usize, date: Option<i64>, folders: &'a [String]) -> KeepCandidate<'a> {
+        KeepCandidate {
+            depth,
+            date_added: date,
+            folders,
+            browser: folders.first().map(String::as_str),
+        }
+    }
+
+    fn path(parts: &[&str]) -> Vec<String> {
+        parts.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_and_description() {
+        let policy = KeepPolicy::parse("not-from, browser:Chrome|firefox ,oldest").unwrap();
+        assert_eq!(
+            policy.rules,
+            vec![
+                KeepRule::NotFrom(vec!["unsorted*".into(), "*临时*".into()]),
+                KeepRule::Browsers(vec!["chrome".into(), "firefox".into()]),
+                KeepRule::Oldest,
+            ]
+        );
+        assert_eq!(
+            policy.description(),
+            "not-from:unsorted*|*临时*,browser:chrome|firefox,oldest"
+        );
+        assert!(KeepPolicy::parse("biggest").is_err());
+        assert!(KeepPolicy::parse("folder:").is_err());
+        assert!(KeepPolicy::parse(" , ").is_err());
+        assert_eq!(
+            KeepPolicy::parse("deepest,newest").unwrap(),
+            KeepPolicy::default()
+        );
+    }
+
+    #[test]
+    fn test_select_with_reasons() {
+        let chrome = path(&["Chrome", "Work"]);
+        let firefox = path(&["Firefox", "👀临时"]);
+        let brave = path(&["Brave", "Work", "Docs"]);
+        let copies = [
+            candidate(2, Some(300), &chrome),
+            candidate(2, Some(100), &firefox),
+            candidate(3, Some(200), &brave),
+        ];
+
+        let (winner, losers) = KeepPolicy::default().select(&copies);
+        assert_eq!(winner, 2);
+        assert_eq!(losers, vec![(0, "deepest"), (1, "deepest")]);
+
+        let (winner, losers) = KeepPolicy::parse("oldest").unwrap().select(&copies);
+        assert_eq!(winner, 1);
+        assert_eq!(losers, vec![(0, "oldest"), (2, "oldest")]);
+
+        let (winner, losers) = KeepPolicy::parse("not-from,oldest")
+            .unwrap()
+            .select(&copies);
+        assert_eq!(winner, 2);
+        assert_eq!(losers, vec![(0, "oldest"), (1, "not-from")]);
+
+        let (winner, _) = KeepPolicy::parse("browser:firefox|chrome")
+            .unwrap()
+            .select(&copies);
+        assert_eq!(winner, 1);
+
+        // 整个路径也能匹配；没有命中规则的打平，保留先出现的
+        let (winner, losers) = KeepPolicy::parse("folder:work/docs|chrome/*")
+            .unwrap()
+            .select(&copies);
+        assert_eq!(winner, 2);
+        assert_eq!(losers, vec![(0, "folder"), (1, "folder")]);
+        let (winner, losers) = KeepPolicy::parse("folder:nowhere").unwrap().select(&copies);
+        assert_eq!(winner, 0);
+        assert_eq!(losers, vec![(1, "first-seen"), (2, "first-seen")]);
+    }
+}
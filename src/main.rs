@@ -2,23 +2,45 @@ use anyhow::Result;
 use clap::{Parser, Subcommand};
 use tracing::{error, info, warn};
 
+mod atomic_write;
+mod bookmark_diff;
+mod bookmark_sort;
 mod browser_utils;
 mod browsers;
+mod changeset;
+mod check_cache;
 mod chromium_sync;
 mod cleanup;
 mod cloud_reset;
+mod conflicts;
+mod control;
 mod crypto;
 mod data_types;
 mod db_safety;
 mod enhanced_rules;
+mod exclusions;
+mod export_filter;
 mod firefox_sync;
 mod firefox_sync_api;
+mod folder_map;
+mod folder_merge;
 mod hackbrowserdata;
+mod keep_policy;
+mod near_dupes;
+mod pipeline;
+mod redirects;
+mod scenario;
 mod scheduler;
 mod sync;
 mod sync_flags;
+#[cfg(test)]
+mod test_support;
+mod three_way;
+mod tombstones;
+mod url_canon;
 mod url_checker;
 mod validator;
+mod watcher;
 
 use sync::SyncEngine;
 use sync_flags::SyncFlags;
@@ -38,6 +60,8 @@ struct Cli {
     command: Commands,
 }
 
+// Parsed once at startup; boxing the larger variants isn't worth it
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// List detected browsers and bookmark counts
@@ -133,6 +157,74 @@ enum Commands {
         /// Skips bookmarks that already exist in the target file
         #[arg(short = 'u', long)]
         update: Option<String>,
+
+        /// Deterministic output: stable ids and no generated timestamps,
+        /// so two exports of the same data are byte-identical (git-friendly)
+        #[arg(long)]
+        deterministic: bool,
+
+        /// Output format: html, json (default: from output extension, else html)
+        #[arg(long)]
+        format: Option<String>,
+
+        /// Split into one file per group: folder, browser, domain
+        /// Output path becomes a directory with an index file
+        #[arg(long)]
+        split: Option<String>,
+
+        /// Only bookmarks added on/after this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long)]
+        added_after: Option<String>,
+
+        /// Only bookmarks added before this date
+        #[arg(long)]
+        added_before: Option<String>,
+
+        /// Only bookmarks modified on/after this date
+        #[arg(long)]
+        modified_after: Option<String>,
+
+        /// Only bookmarks modified before this date
+        #[arg(long)]
+        modified_before: Option<String>,
+
+        /// Only these domains (comma-separated, wildcards: *.github.com, *git*)
+        #[arg(long)]
+        include_domain: Option<String>,
+
+        /// Skip these domains (comma-separated, wildcards allowed)
+        #[arg(long)]
+        exclude_domain: Option<String>,
+
+        /// Only these URL schemes (comma-separated, e.g. "http,https")
+        #[arg(long)]
+        include_scheme: Option<String>,
+
+        /// Skip these URL schemes (comma-separated, e.g. "javascript,file")
+        #[arg(long)]
+        exclude_scheme: Option<String>,
+
+        /// Only bookmarks whose title matches this regex
+        #[arg(long)]
+        title_regex: Option<String>,
+
+        /// Sort: title, date, domain, optionally with folders-first (e.g. "folders-first,title")
+        #[arg(long)]
+        sort: Option<String>,
+
+        /// Only sort these depths (0 = top level), e.g. "0" or "1-2" (default: all)
+        #[arg(long, requires = "sort")]
+        sort_depth: Option<String>,
+
+        /// HTML dialect of the importing browser: generic, safari, firefox, chromium
+        /// A specific dialect sets the root layout itself, so wrapping is off unless --wrap is given
+        #[arg(long, default_value = "generic")]
+        target_dialect: String,
+
+        /// Which duplicate copy survives -d: comma-separated rules tried in order
+        /// (oldest, newest, deepest, shallowest, browser:a|b, folder:a|b, not-from[:a|b])
+        #[arg(long, default_value = "deepest,newest")]
+        keep_policy: String,
     },
 
     /// Analyze bookmarks (duplicates, empty folders, NSFW)
@@ -141,6 +233,51 @@ enum Commands {
         /// Target browsers
         #[arg(short, long)]
         browsers: Option<String>,
+
+        /// Keep policy for the dedupe preview (see `export --keep-policy`)
+        #[arg(long, default_value = "deepest,newest")]
+        keep_policy: String,
+    },
+
+    /// Review or merge near-duplicate bookmarks (AMP pages, mobile hosts, index pages, same title)
+    MergeNear {
+        /// Bookmark file (HTML or JSON), e.g. an export
+        input: String,
+
+        /// Write the merged bookmarks here; without it the clusters are only listed
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Representative per cluster, e.g. "3=2" keeps member 2 of cluster 3
+        #[arg(long, value_delimiter = ',')]
+        keep: Vec<String>,
+
+        /// Clusters to leave unmerged
+        #[arg(long, value_delimiter = ',')]
+        skip: Vec<usize>,
+
+        /// Only merge clusters scoring at least this
+        #[arg(long, default_value_t = 0.85)]
+        min_score: f64,
+
+        /// Merge report path (kept bookmark, removed copies, titles and tags per cluster).
+        /// Always written when merging; defaults to <output>.merge-report.json
+        #[arg(long)]
+        report: Option<String>,
+    },
+
+    /// Merge sibling folders with near-identical names (case, emoji, punctuation, synonyms)
+    MergeFolders {
+        /// Bookmark file (HTML or JSON), e.g. an export
+        input: String,
+
+        /// Write the merged bookmarks here; without it the merges are only previewed
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Synonym table JSON (default: ~/.browser-sync/folder_synonyms.json, else built-in)
+        #[arg(long)]
+        synonyms: Option<String>,
     },
 
     /// Smart organize bookmarks by URL patterns
@@ -173,6 +310,58 @@ enum Commands {
         /// Verbose output
         #[arg(short = 'V', long)]
         verbose: bool,
+
+        /// Sort folders when writing back: title, date, domain, folders-first (combinable)
+        #[arg(long)]
+        sort: Option<String>,
+
+        /// Only sort these depths (0 = top level), e.g. "0" or "1-2" (default: all)
+        #[arg(long, requires = "sort")]
+        sort_depth: Option<String>,
+
+        /// Write the planned changes to a JSON changeset instead of applying (implies --dry-run)
+        #[arg(long)]
+        plan: Option<String>,
+    },
+
+    /// Compare bookmarks between two sources (browser, browser:profile or file)
+    Diff {
+        /// Base source, e.g. waterfox, "brave:Profile 1", ~/export.html
+        left: String,
+
+        /// Source to compare against the base
+        right: String,
+
+        /// Output format: human, json, unified-tree
+        #[arg(short, long, default_value = "human")]
+        format: String,
+
+        /// Write the diff to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Sync scenario folders matching path patterns, e.g. "Projects/*" "**/临时"
+    Scenario {
+        /// Folder patterns; append @browser to mirror that scenario from a master browser
+        #[arg(required = true)]
+        patterns: Vec<String>,
+
+        /// Target browsers
+        #[arg(short, long, default_value = "waterfox,brave-nightly")]
+        browsers: String,
+
+        /// Mirror every scenario from this browser instead of merging
+        #[arg(long)]
+        mirror_from: Option<String>,
+
+        /// Preview only, no changes
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Verbose output
+        #[arg(short = 'V', long)]
+        verbose: bool,
     },
 
     /// Validate bookmark integrity
@@ -201,6 +390,53 @@ enum Commands {
         /// Verbose output
         #[arg(short, long)]
         verbose: bool,
+
+        /// Conflict policy for this run: newest, prefer:<browser>, ask
+        #[arg(long)]
+        conflict_policy: Option<String>,
+    },
+
+    /// Show the last conflict report or set conflict policies
+    Conflicts {
+        /// Save policies, e.g. "newest" or "bookmarks=keep-both,history=prefer:safari"
+        #[arg(long)]
+        set: Option<String>,
+
+        /// Print the last report as JSON instead of the summary
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List or purge deletion tombstones (deletions that must not be re-synced)
+    #[command(alias = "tomb")]
+    Tombstones {
+        /// Action: list, purge
+        #[arg(default_value = "list")]
+        action: String,
+
+        /// Only this kind: bookmark, folder, history, reading-list
+        #[arg(short, long)]
+        kind: Option<String>,
+
+        /// Purge only tombstones older than N days
+        #[arg(long)]
+        older_than: Option<u32>,
+
+        /// Purge only the tombstone for this URL (or folder path)
+        #[arg(long)]
+        url: Option<String>,
+
+        /// Purge every tombstone
+        #[arg(long)]
+        all: bool,
+
+        /// Set how many days tombstones are kept (default: 90)
+        #[arg(long)]
+        retention_days: Option<u32>,
+
+        /// Print the list as JSON
+        #[arg(long)]
+        json: bool,
     },
 
     /// Show available classification rules
@@ -260,6 +496,98 @@ enum Commands {
         /// Keep empty folders after deletion (default: remove empty folders)
         #[arg(long)]
         keep_empty: bool,
+
+        /// Write the deletions to a JSON changeset instead of applying (implies --delete --dry-run)
+        #[arg(long)]
+        plan: Option<String>,
+
+        /// File mode: treat bookmarks that redirect to the same final URL as duplicates
+        #[arg(long)]
+        dedupe_redirects: bool,
+
+        /// File mode: point permanently redirected (301/308) bookmarks at their target
+        #[arg(long)]
+        rewrite_redirects: bool,
+
+        /// Which copy --dedupe-redirects keeps (see `export --keep-policy`)
+        #[arg(long, default_value = "deepest,newest")]
+        keep_policy: String,
+
+        /// Ignore cached results and re-check every URL (results are still cached)
+        #[arg(long)]
+        refresh: bool,
+
+        /// Cache lifetime per status, e.g. "valid=30d,invalid=7d,uncertain=1d"
+        #[arg(long, default_value = "valid=30d,invalid=7d,uncertain=1d")]
+        cache_ttl: String,
+    },
+
+    /// Apply a changeset written by --plan after checking it still matches the browsers
+    Apply {
+        /// Changeset file (JSON)
+        file: String,
+
+        /// Apply even if bookmarks changed since the plan, as long as every change still applies
+        #[arg(long)]
+        allow_drift: bool,
+
+        /// Check preconditions only, no changes
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Sync bookmarks between all browsers (three-way merge against the last sync)
+    Sync {
+        /// Preview only, no changes
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Verbose output
+        #[arg(short = 'V', long)]
+        verbose: bool,
+
+        /// Which duplicate copy survives (see `export --keep-policy`)
+        #[arg(long, default_value = "deepest,newest")]
+        keep_policy: String,
+
+        /// Merge sibling folders with near-identical names (see `merge-folders`)
+        #[arg(long)]
+        merge_folders: bool,
+
+        /// Write the planned changes to a JSON changeset instead of applying (implies --dry-run)
+        #[arg(long)]
+        plan: Option<String>,
+    },
+
+    /// Run scheduled and file-watch jobs from a config file
+    Daemon {
+        /// Job config (default: ~/.browser-sync/daemon.json)
+        #[arg(short, long)]
+        config: Option<String>,
+
+        /// List jobs and their next run, then exit
+        #[arg(long)]
+        list: bool,
+    },
+
+    /// Run a processing pipeline (read, filter, dedupe, organize, check, write) from a TOML file
+    Run {
+        /// Pipeline file
+        file: String,
+
+        /// Verbose output
+        #[arg(short = 'V', long)]
+        verbose: bool,
+    },
+
+    /// Query or control a running daemon
+    Ctl {
+        #[command(subcommand)]
+        action: CtlAction,
+
+        /// Print the raw JSON response
+        #[arg(long, global = true)]
+        json: bool,
     },
 
     /// Create full backup of all browser data
@@ -310,6 +638,23 @@ fn print_sync_warning() {
     warn!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
 }
 
+#[derive(Subcommand)]
+enum CtlAction {
+    /// Last run, next run and errors for each job
+    Status,
+    /// Run a job now
+    Trigger {
+        /// Job name from the daemon config ("watch" for the file-watch task)
+        job: String,
+    },
+    /// Hold scheduled and file-watch runs
+    Pause,
+    /// Resume after pause
+    Resume,
+    /// Re-read the daemon config file
+    ReloadConfig,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt()
@@ -349,6 +694,22 @@ async fn main() -> Result<()> {
             wrap,
             no_wrap,
             update,
+            deterministic,
+            format,
+            split,
+            added_after,
+            added_before,
+            modified_after,
+            modified_before,
+            include_domain,
+            exclude_domain,
+            include_scheme,
+            exclude_scheme,
+            title_regex,
+            sort,
+            sort_depth,
+            target_dialect,
+            keep_policy,
         } => {
             // Create sync flags from arguments
             let sync_flags = SyncFlags {
@@ -433,7 +794,7 @@ async fn main() -> Result<()> {
                                     date_modified: None,
                                 })
                                 .collect(),
-                            date_added: Some(chrono::Utc::now().timestamp_millis()),
+                            date_added: sync::generated_folder_date(deterministic),
                             date_modified: None,
                         };
                         extra_bookmarks.push(reading_folder);
@@ -467,7 +828,7 @@ async fn main() -> Result<()> {
                                     date_modified: None,
                                 })
                                 .collect(),
-                            date_added: Some(chrono::Utc::now().timestamp_millis()),
+                            date_added: sync::generated_folder_date(deterministic),
                             date_modified: None,
                         };
                         extra_bookmarks.push(history_folder);
@@ -501,7 +862,7 @@ async fn main() -> Result<()> {
                                     date_modified: None,
                                 })
                                 .collect(),
-                            date_added: Some(chrono::Utc::now().timestamp_millis()),
+                            date_added: sync::generated_folder_date(deterministic),
                             date_modified: None,
                         };
                         extra_bookmarks.push(cookies_folder);
@@ -511,6 +872,44 @@ async fn main() -> Result<()> {
                 }
             }
 
+            let format = match format {
+                Some(ref value) => sync::ExportFormat::parse(value)?,
+                None => sync::ExportFormat::from_path(&output),
+            };
+            let split = split.as_deref().map(sync::ExportSplit::parse).transpose()?;
+            if split.is_some() && update.is_some() {
+                error!("❌ --split cannot be combined with --update");
+                return Ok(());
+            }
+
+            let date_bound = |value: &Option<String>| {
+                value.as_deref().map(export_filter::parse_date_bound).transpose()
+            };
+            let filter = export_filter::ExportFilter {
+                added_after: date_bound(&added_after)?,
+                added_before: date_bound(&added_before)?,
+                modified_after: date_bound(&modified_after)?,
+                modified_before: date_bound(&modified_before)?,
+                include_domains: export_filter::parse_list(include_domain.as_deref()),
+                exclude_domains: export_filter::parse_list(exclude_domain.as_deref()),
+                include_schemes: export_filter::parse_list(include_scheme.as_deref()),
+                exclude_schemes: export_filter::parse_list(exclude_scheme.as_deref()),
+                title_regex: title_regex
+                    .as_deref()
+                    .map(regex::Regex::new)
+                    .transpose()
+                    .map_err(|e| anyhow::anyhow!("Invalid --title-regex: {}", e))?,
+            };
+
+            let sort = sort
+                .as_deref()
+                .map(|spec| bookmark_sort::SortConfig::parse(spec, sort_depth.as_deref()))
+                .transpose()?;
+
+            let dialect = sync::HtmlDialect::parse(&target_dialect)?;
+            // The dialect's root layout replaces the wrap-folder workaround
+            let no_wrap = no_wrap || (dialect != sync::HtmlDialect::Generic && wrap.is_none());
+
             let export_config = sync::ExportConfig {
                 merge,
                 deduplicate,
@@ -520,6 +919,13 @@ async fn main() -> Result<()> {
                 flat,
                 wrap_folder: wrap.clone(),
                 no_wrap,
+                deterministic,
+                format,
+                split,
+                filter,
+                sort: sort.clone(),
+                dialect,
+                keep_policy: keep_policy::KeepPolicy::parse(&keep_policy)?,
             };
 
             // Show folder filter info
@@ -544,11 +950,27 @@ async fn main() -> Result<()> {
                 info!("📝 Incremental update: merging with {}", update_file);
             }
 
+            if deterministic {
+                info!("🔒 Deterministic export: stable ids, no generated timestamps");
+            }
+
+            if !export_config.filter.is_empty() {
+                info!("🔎 Export filters enabled (applied before dedupe and flatten)");
+            }
+
+            if dialect != sync::HtmlDialect::Generic {
+                info!("🧭 Target dialect: {} (root layout tuned for import)", dialect.name());
+            }
+
+            if let Some(mode) = split {
+                info!("✂️  Split export by {}: one {} file per group + index", mode.name(), format.extension());
+            }
+
             // Handle incremental update mode
             let count = if let Some(ref update_file) = update {
                 // Read existing bookmarks from target file
                 let expanded_update = expand_path(update_file);
-                let mut existing_bookmarks = match sync::import_bookmarks_from_html(&expanded_update) {
+                let mut existing_bookmarks = match sync::read_bookmarks_file(&expanded_update, format) {
                     Ok(b) => b,
                     Err(e) => {
                         warn!("⚠️  Could not read existing file ({}), creating new file", e);
@@ -570,7 +992,13 @@ async fn main() -> Result<()> {
                     stats.new_added, stats.skipped_duplicates);
                 
                 // Export merged result
-                sync::export_bookmarks_to_html(&existing_bookmarks, &expand_path(&output))?;
+                if let Some(ref sort) = sort {
+                    bookmark_sort::sort_bookmarks(&mut existing_bookmarks, sort);
+                }
+                if deterministic {
+                    sync::assign_stable_ids(&mut existing_bookmarks);
+                }
+                sync::write_bookmarks_file(&existing_bookmarks, &expand_path(&output), format, dialect)?;
                 existing_count + stats.new_added
             } else {
                 engine
@@ -598,12 +1026,111 @@ async fn main() -> Result<()> {
             }
         }
 
-        Commands::Analyze { browsers } => {
+        Commands::Analyze {
+            browsers,
+            keep_policy,
+        } => {
             info!("🔍 Analyzing bookmarks...");
-            let engine = SyncEngine::new()?;
+            let mut engine = SyncEngine::new()?;
+            engine.set_keep_policy(keep_policy::KeepPolicy::parse(&keep_policy)?);
             engine.analyze_bookmarks(browsers.as_deref()).await?;
         }
 
+        Commands::MergeNear {
+            input,
+            output,
+            keep,
+            skip,
+            min_score,
+            report,
+        } => {
+            let input = expand_path(&input);
+            let mut bookmarks =
+                sync::read_bookmarks_file(&input, sync::ExportFormat::from_path(&input))?;
+            let clusters = near_dupes::find_clusters(&bookmarks);
+
+            let picks = near_dupes::parse_picks(&keep, &clusters)?;
+
+            let Some(output) = output else {
+                near_dupes::print_clusters(&clusters, None);
+                return Ok(());
+            };
+
+            let selected: Vec<(usize, &near_dupes::Cluster)> = clusters
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (i + 1, c))
+                .filter(|(n, c)| {
+                    !skip.contains(n) && (c.score >= min_score || picks.contains_key(n))
+                })
+                .collect();
+            let records = near_dupes::merge_clusters(&mut bookmarks, &selected, &picks);
+            for record in &records {
+                info!(
+                    "  🔗 #{} kept {} ({} removed)",
+                    record.cluster,
+                    record.kept.url,
+                    record.removed.len()
+                );
+            }
+            let removed: usize = records.iter().map(|r| r.removed.len()).sum();
+            info!(
+                "✅ Merged {} of {} clusters, removed {} bookmarks",
+                records.len(),
+                clusters.len(),
+                removed
+            );
+
+            let output = expand_path(&output);
+            sync::write_bookmarks_file(
+                &bookmarks,
+                &output,
+                sync::ExportFormat::from_path(&output),
+                sync::HtmlDialect::Generic,
+            )?;
+            info!("📤 Saved to {}", output);
+            // 书签没有标签/备注字段，被删副本的标题和所在文件夹只能保存在报告里
+            let report = match report {
+                Some(report) => expand_path(&report),
+                None => format!("{}.merge-report.json", output),
+            };
+            std::fs::write(&report, serde_json::to_string_pretty(&records)?)?;
+            info!("📝 Merge report saved to {}", report);
+        }
+
+        Commands::MergeFolders {
+            input,
+            output,
+            synonyms,
+        } => {
+            let synonyms = match synonyms {
+                Some(path) => {
+                    let path = std::path::PathBuf::from(expand_path(&path));
+                    if !path.exists() {
+                        anyhow::bail!("Synonym file not found: {}", path.display());
+                    }
+                    folder_merge::FolderSynonyms::load(&path)?
+                }
+                None => folder_merge::FolderSynonyms::load_default(),
+            };
+            let input = expand_path(&input);
+            let mut bookmarks =
+                sync::read_bookmarks_file(&input, sync::ExportFormat::from_path(&input))?;
+            let merges = folder_merge::merge_similar_folders(&mut bookmarks, &synonyms);
+            folder_merge::print_merges(&merges, None);
+
+            if let Some(output) = output {
+                let output = expand_path(&output);
+                sync::write_bookmarks_file(
+                    &bookmarks,
+                    &output,
+                    sync::ExportFormat::from_path(&output),
+                    sync::HtmlDialect::Generic,
+                )?;
+                info!("📤 Saved to {}", output);
+            }
+        }
+
         Commands::Organize {
             browsers,
             file,
@@ -612,8 +1139,23 @@ async fn main() -> Result<()> {
             stats,
             dry_run,
             verbose,
+            sort,
+            sort_depth,
+            plan,
         } => {
+            let dry_run = dry_run || plan.is_some();
+            let sort = sort
+                .as_deref()
+                .map(|spec| bookmark_sort::SortConfig::parse(spec, sort_depth.as_deref()))
+                .transpose()?;
+
             if let Some(input_file) = file {
+                if sort.is_some() {
+                    warn!("⚠️  --sort only applies when writing back to browsers, ignored for --file");
+                }
+                if plan.is_some() {
+                    warn!("⚠️  --plan only applies to browsers, ignored for --file");
+                }
                 // 从导出文件整理
                 let output_path = output.unwrap_or_else(|| {
                     let path = std::path::Path::new(&input_file);
@@ -636,6 +1178,13 @@ async fn main() -> Result<()> {
                 }
                 info!("🧠 Smart organizing bookmarks...");
                 let mut engine = SyncEngine::new()?;
+                if let Some(sort) = sort {
+                    info!("🔤 Sort on write: {}", sort.description());
+                    engine.set_write_sort(sort);
+                }
+                if plan.is_some() {
+                    engine.start_plan("organize");
+                }
                 engine
                     .smart_organize(
                         browsers.as_deref(),
@@ -646,10 +1195,72 @@ async fn main() -> Result<()> {
                         verbose,
                     )
                     .await?;
+                if let (Some(path), Some(changeset)) = (plan, engine.take_plan()) {
+                    let path = expand_path(&path);
+                    changeset.save(std::path::Path::new(&path))?;
+                    info!("{}", changeset.summary());
+                    info!("📝 Changeset written to {} (review, then `bsync apply {}`)", path, path);
+                }
                 info!("✅ Organization complete!");
             }
         }
 
+        Commands::Diff {
+            left,
+            right,
+            format,
+            output,
+        } => {
+            let format = bookmark_diff::DiffFormat::parse(&format)?;
+            let left = bookmark_diff::DiffSource::parse(&expand_path(&left));
+            let right = bookmark_diff::DiffSource::parse(&expand_path(&right));
+            info!("🔍 Comparing {} → {}", left.label(), right.label());
+            let left_bookmarks = left.load()?;
+            let right_bookmarks = right.load()?;
+            let report = bookmark_diff::diff_bookmarks(
+                &left.label(),
+                &left_bookmarks,
+                &right.label(),
+                &right_bookmarks,
+            );
+            let rendered = match format {
+                bookmark_diff::DiffFormat::Human => bookmark_diff::render_human(&report),
+                bookmark_diff::DiffFormat::Json => serde_json::to_string_pretty(&report)?,
+                bookmark_diff::DiffFormat::UnifiedTree => {
+                    bookmark_diff::render_unified_tree(&report, &right_bookmarks)
+                }
+            };
+            match output {
+                Some(path) => {
+                    let path = expand_path(&path);
+                    std::fs::write(&path, rendered)?;
+                    info!("✅ Diff written to {}", path);
+                }
+                None => println!("{}", rendered),
+            }
+        }
+
+        Commands::Scenario {
+            patterns,
+            browsers,
+            mirror_from,
+            dry_run,
+            verbose,
+        } => {
+            let default_mode = match mirror_from {
+                Some(master) => scenario::ScenarioMode::Mirror(master.trim().to_lowercase()),
+                None => scenario::ScenarioMode::Union,
+            };
+            let specs = patterns
+                .iter()
+                .map(|p| scenario::ScenarioSpec::parse(p, &default_mode))
+                .collect::<Result<Vec<_>>>()?;
+            let mut engine = SyncEngine::new()?;
+            engine
+                .sync_scenario_folders(&specs, &browsers, dry_run, verbose)
+                .await?;
+        }
+
         Commands::Validate { detailed } => {
             info!("🔍 Validating bookmarks...");
             let engine = SyncEngine::new()?;
@@ -662,15 +1273,112 @@ async fn main() -> Result<()> {
             days,
             dry_run,
             verbose,
+            conflict_policy,
         } => {
             info!("📜 Syncing browser history");
             info!("   Browsers: {}", browsers);
             info!("   Range: {} days", days);
             let mut engine = SyncEngine::new()?;
+            if let Some(spec) = conflict_policy {
+                let mut policies = engine.conflict_policies();
+                policies.history = conflicts::ConflictPolicy::parse(&spec)?;
+                engine.set_conflict_policies(policies);
+            }
             engine.sync_history(Some(days), dry_run, verbose).await?;
             info!("✅ History sync complete!");
         }
 
+        Commands::Conflicts { set, json } => {
+            let path = conflicts::ConflictPolicies::default_path()?;
+            if let Some(spec) = set {
+                let mut policies = conflicts::ConflictPolicies::load(&path)?;
+                policies.apply_spec(&spec)?;
+                policies.save(&path)?;
+                info!("✅ Conflict policies saved: {}", policies.description());
+                return Ok(());
+            }
+
+            let policies = conflicts::ConflictPolicies::load(&path)?;
+            println!("Policies: {}", policies.description());
+            if json {
+                match conflicts::latest_report_json()? {
+                    Some(report) => println!("{}", report),
+                    None => println!("No conflict report yet"),
+                }
+            } else {
+                match conflicts::latest_summary()? {
+                    Some(summary) => println!("\n{}", summary),
+                    None => println!("No conflict report yet"),
+                }
+            }
+        }
+
+        Commands::Tombstones {
+            action,
+            kind,
+            older_than,
+            url,
+            all,
+            retention_days,
+            json,
+        } => {
+            let path = tombstones::TombstoneStore::default_path()?;
+            let mut store = tombstones::TombstoneStore::load(&path)?;
+            let kind = kind
+                .as_deref()
+                .map(tombstones::TombstoneKind::parse)
+                .transpose()?;
+
+            if let Some(days) = retention_days {
+                store.retention_days = days;
+                let expired = store.purge_expired(chrono::Utc::now().timestamp_millis());
+                store.save()?;
+                info!("✅ Retention set to {} days ({} expired)", days, expired);
+            }
+
+            match action.as_str() {
+                "list" | "ls" => {
+                    if json {
+                        let items: Vec<_> = store
+                            .tombstones
+                            .iter()
+                            .filter(|t| kind.is_none_or(|k| t.kind == k))
+                            .collect();
+                        println!("{}", serde_json::to_string_pretty(&items)?);
+                    } else {
+                        store.print_list(kind);
+                    }
+                }
+                "purge" => {
+                    if !all && kind.is_none() && older_than.is_none() && url.is_none() {
+                        anyhow::bail!("purge needs --all, --kind, --older-than or --url");
+                    }
+                    let cutoff = older_than.map(|d| {
+                        chrono::Utc::now().timestamp_millis() - d as i64 * 24 * 60 * 60 * 1000
+                    });
+                    let url_key = url.as_deref().map(|u| {
+                        if u.contains("://") {
+                            url_canon::canonicalize(u)
+                        } else {
+                            u.to_string()
+                        }
+                    });
+                    let purged = store.purge_where(|t| {
+                        kind.is_none_or(|k| t.kind == k)
+                            && cutoff.is_none_or(|c| t.deleted_at < c)
+                            && url_key.as_ref().is_none_or(|u| &t.key == u)
+                    });
+                    store.save()?;
+                    info!(
+                        "🗑️  Purged {} tombstones ({} left)",
+                        purged,
+                        store.tombstones.len()
+                    );
+                }
+                other => anyhow::bail!("Unknown action '{}' (expected list or purge)", other),
+            }
+        }
+
         Commands::Rules => {
             SyncEngine::print_builtin_rules();
         }
@@ -689,7 +1397,21 @@ async fn main() -> Result<()> {
             export_invalid,
             export_dir,
             keep_empty,
+            plan,
+            dedupe_redirects,
+            rewrite_redirects,
+            keep_policy,
+            refresh,
+            cache_ttl,
         } => {
+            let cache_ttl = check_cache::CacheTtl::parse(&cache_ttl)?;
+            let keep_policy = keep_policy::KeepPolicy::parse(&keep_policy)?;
+            // 重定向去重/改写只作用于 --file 模式写出的 --output 文件，浏览器模式不会写回
+            if (dedupe_redirects || rewrite_redirects) && (file.is_none() || output.is_none()) {
+                anyhow::bail!("--dedupe-redirects and --rewrite-redirects need --file and --output");
+            }
+            let delete = delete || plan.is_some();
+            let dry_run = dry_run || plan.is_some();
             use url_checker::{
                 CheckerConfig, UrlChecker, CheckReport, ValidationStatus,
                 collect_urls_from_bookmarks, 
@@ -813,16 +1535,17 @@ async fn main() -> Result<()> {
                 return Ok(());
             }
 
-            // 去重URL
-            let mut unique_urls: Vec<String> = all_urls.into_iter()
-                .collect::<HashSet<_>>()
-                .into_iter()
-                .collect();
-            
-            // 应用限制
-            if limit > 0 && unique_urls.len() > limit {
-                info!("📊 共 {} 个唯一URL，限制检查前 {} 个", unique_urls.len(), limit);
-                unique_urls.truncate(limit);
+            // 查缓存：未过期的结果直接使用，其余按从未检查、最早检查的顺序应用限制
+            let mut cache = check_cache::CheckCache::open(&check_cache::CheckCache::default_path()?)?;
+            let now = chrono::Utc::now().timestamp();
+            let mut check_plan = cache.plan(all_urls, &cache_ttl, now, refresh, limit)?;
+            let unique_urls = std::mem::take(&mut check_plan.to_check);
+            if !check_plan.cached.is_empty() {
+                info!("🗄️  {} URLs answered from the check cache", check_plan.cached.len());
+            }
+            if check_plan.deferred > 0 {
+                info!("📊 共 {} 个URL待检查，限制检查前 {} 个（{} 个留到下次）",
+                    unique_urls.len() + check_plan.deferred, unique_urls.len(), check_plan.deferred);
             } else {
                 info!("\n📊 共 {} 个唯一URL待检查", unique_urls.len());
             }
@@ -843,9 +1566,12 @@ async fn main() -> Result<()> {
                 }
             }).await;
             pb.finish_with_message("检查完成");
+            cache.store(&results, now)?;
+            let results = check_plan.merge(results);
 
             let duration = start_time.elapsed().as_secs_f64();
             let report = CheckReport::from_results(&results, duration);
+            scheduler::report_result(scheduler::JobResult::Check(report.clone()));
 
             // 显示结果
             println!("\n📊 检查结果");
@@ -901,7 +1627,34 @@ async fn main() -> Result<()> {
                     if let Some(ref bookmarks) = file_bookmarks {
                         // 移除无效和不确定的书签，保留有效和跳过的
                         let keep_urls: HashSet<String> = valid_urls.union(&skipped_urls).cloned().collect();
-                        let valid_bookmarks = extract_by_status_preserve_structure(bookmarks, &keep_urls);
+                        let mut valid_bookmarks = extract_by_status_preserve_structure(bookmarks, &keep_urls);
+                        if dedupe_redirects || rewrite_redirects {
+                            let redirect_map = redirects::RedirectMap::from_results(&results);
+                            if redirect_map.is_empty() {
+                                info!("↪️  No checked URL redirects to a different page");
+                            } else {
+                                info!("↪️  {} URLs redirect to a different page", redirect_map.len());
+                            }
+                            if dedupe_redirects {
+                                let losses = redirects::dedupe_by_final_url(
+                                    &mut valid_bookmarks,
+                                    &redirect_map,
+                                    &keep_policy,
+                                );
+                                info!("🔄 Removed {} bookmarks with the same final URL", losses.len());
+                                keep_policy::print_losses(&losses, if verbose { None } else { Some(0) });
+                            }
+                            if rewrite_redirects {
+                                let rewrites =
+                                    redirects::rewrite_permanent(&mut valid_bookmarks, &redirect_map);
+                                info!("✏️  Rewrote {} permanently redirected bookmarks", rewrites.len());
+                                if verbose {
+                                    for rewrite in &rewrites {
+                                        println!("  • {}: {} → {}", rewrite.title, rewrite.from, rewrite.to);
+                                    }
+                                }
+                            }
+                        }
                         
                         match sync::export_bookmarks_to_html(&valid_bookmarks, &out_expanded) {
                             Ok(_) => {
@@ -1018,6 +1771,30 @@ async fn main() -> Result<()> {
                 }
             }
 
+            // 计划模式: 把删除写成变更集，稍后用 `bsync apply` 执行
+            if let Some(ref plan_path) = plan {
+                if from_file {
+                    warn!("⚠️  --plan only applies to browsers, ignored for --file");
+                } else {
+                    let remove_config = RemoveConfig { keep_empty_folders: keep_empty };
+                    let mut changeset = changeset::Changeset::new("check --delete");
+                    // apply 通过引擎读取（排除规则、文件夹映射），计划也要基于同样的树
+                    for adapter in sync::engine_adapters() {
+                        if !all_bookmarks.iter().any(|(browser_type, _)| *browser_type == adapter.browser_type()) {
+                            continue;
+                        }
+                        let bookmarks = &adapter.read_bookmarks()?;
+                        let mut cleaned = bookmarks.clone();
+                        remove_invalid_bookmarks_preserve_structure(&mut cleaned, &invalid_urls, &remove_config);
+                        changeset.record(adapter.as_ref(), bookmarks, &cleaned)?;
+                    }
+                    let plan_path = expand_path(plan_path);
+                    changeset.save(std::path::Path::new(&plan_path))?;
+                    info!("{}", changeset.summary());
+                    info!("📝 Changeset written to {} (review, then `bsync apply {}`)", plan_path, plan_path);
+                }
+            }
+
             // 处理删除
             if delete && report.invalid_count > 0 {
                 if dry_run {
@@ -1071,6 +1848,22 @@ async fn main() -> Result<()> {
                     println!("\n✅ 删除完成 (文件夹结构已保持)");
                 }
             }
+
+        }
+
+        Commands::Apply {
+            file,
+            allow_drift,
+            dry_run,
+        } => {
+            let path = expand_path(&file);
+            let changeset = changeset::Changeset::load(std::path::Path::new(&path))?;
+            if !dry_run {
+                print_sync_warning();
+            }
+            info!("📋 Applying changeset: {}", path);
+            let engine = SyncEngine::new()?;
+            engine.apply_changeset(&changeset, allow_drift, dry_run)?;
         }
 
         Commands::Backup { output } => {
@@ -1079,6 +1872,83 @@ async fn main() -> Result<()> {
             info!("✅ Backup complete: {}", output);
         }
 
+        Commands::Sync {
+            dry_run,
+            verbose,
+            keep_policy,
+            merge_folders,
+            plan,
+        } => {
+            let dry_run = dry_run || plan.is_some();
+            let mut engine = SyncEngine::new()?;
+            engine.set_keep_policy(keep_policy::KeepPolicy::parse(&keep_policy)?);
+            if merge_folders {
+                engine.set_folder_synonyms(folder_merge::FolderSynonyms::load_default());
+            }
+            if plan.is_some() {
+                engine.start_plan("sync");
+            }
+            let stats = engine
+                .sync(sync::SyncMode::Incremental, dry_run, verbose)
+                .await?;
+            if let (Some(path), Some(changeset)) = (plan, engine.take_plan()) {
+                let path = expand_path(&path);
+                changeset.save(std::path::Path::new(&path))?;
+                info!("{}", changeset.summary());
+                info!("📝 Changeset written to {} (review, then `bsync apply {}`)", path, path);
+            }
+            let errors = stats.errors;
+            scheduler::report_result(scheduler::JobResult::Sync(stats));
+            if errors > 0 {
+                anyhow::bail!("Sync finished with {} errors", errors);
+            }
+        }
+
+        Commands::Daemon { config, list } => {
+            let path = match config {
+                Some(path) => std::path::PathBuf::from(expand_path(&path)),
+                None => scheduler::DaemonConfig::default_path()?,
+            };
+            if list {
+                scheduler::print_jobs(&scheduler::DaemonConfig::load(&path)?);
+            } else {
+                scheduler::run_daemon(path, scheduler::default_runs_log()?).await?;
+            }
+        }
+
+        Commands::Run { file, verbose } => {
+            let path = std::path::PathBuf::from(expand_path(&file));
+            let pipeline = pipeline::Pipeline::load(&path)?;
+            info!(
+                "🧪 Running pipeline {} ({} stages)",
+                path.display(),
+                pipeline.stages.len()
+            );
+            let reports = pipeline.run(verbose).await?;
+            pipeline::print_summary(&reports);
+        }
+
+        Commands::Ctl { action, json } => {
+            let request = match action {
+                CtlAction::Status => control::Request::Status,
+                CtlAction::Trigger { job } => control::Request::Trigger { job },
+                CtlAction::Pause => control::Request::Pause,
+                CtlAction::Resume => control::Request::Resume,
+                CtlAction::ReloadConfig => control::Request::ReloadConfig,
+            };
+            let response = control::send(&control::socket_path()?, &request).await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&response)?);
+            } else if let Some(ref status) = response.status {
+                control::print_status(status);
+            } else if let Some(ref message) = response.message {
+                info!("✅ {}", message);
+            }
+            if let Some(error) = response.error {
+                anyhow::bail!(error);
+            }
+        }
+
         Commands::ExportData {
             browser,
             passwords,
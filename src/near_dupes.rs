@@ -0,0 +1,606 @@
+//! 近似重复检测 - 找出规范化 URL 不同但实际是同一页面的书签
+//!
+//! 精确去重（`url_canon`）之外，以下情况也视为候选：
+//! - AMP 版本：`/amp/` 路径段、`article.amp.html`、`?amp=1`、`amp.` 主机
+//! - 移动版主机：`m.`、`mobile.`
+//! - 目录首页：末尾的 `index.html`、`index.php`、`default.aspx` 等
+//! - 同一域名同一路径下标题相同、只有查询参数不同
+//!
+//! 候选按规范化 URL 聚成簇，每簇带相似度分数和原因，并推荐一个代表（URL 最“干净”的那个）。
+//! `analyze` 显示前几个簇；`bsync merge-near` 列出全部簇，或者合并：保留代表，删除其他副本，
+//! 并把其他副本的标题和标签收集到合并记录里（书签没有标签字段，标签取副本所在的文件夹名）。
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+use crate::browsers::Bookmark;
+use crate::url_canon::canonicalize;
+
+/// 被视为同一页面的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NearReason {
+    Amp,
+    MobileHost,
+    IndexPage,
+    SameTitle,
+}
+
+impl NearReason {
+    pub fn score(&self) -> f64 {
+        match self {
+            NearReason::Amp | NearReason::MobileHost => 0.95,
+            NearReason::IndexPage => 0.9,
+            NearReason::SameTitle => 0.85,
+        }
+    }
+}
+
+impl fmt::Display for NearReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NearReason::Amp => write!(f, "AMP variant"),
+            NearReason::MobileHost => write!(f, "mobile host"),
+            NearReason::IndexPage => write!(f, "index page"),
+            NearReason::SameTitle => write!(f, "same title, different query"),
+        }
+    }
+}
+
+/// 簇里的一个书签
+#[derive(Debug, Clone, Serialize)]
+pub struct Member {
+    pub title: String,
+    pub url: String,
+    /// 所在文件夹路径（"A/B"）
+    pub folder: String,
+    pub date_added: Option<i64>,
+    /// 书签在树中的先序编号（只数书签），合并时用来定位
+    #[serde(skip)]
+    position: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Cluster {
+    pub members: Vec<Member>,
+    /// 连接这个簇的最弱原因的分数
+    pub score: f64,
+    pub reasons: Vec<NearReason>,
+    /// 推荐保留的成员下标
+    pub representative: usize,
+}
+
+/// 去掉近似差异后的页面键，以及 URL 本身带有的差异
+fn loose_key(canonical: &str) -> (String, BTreeSet<NearReason>) {
+    let mut reasons = BTreeSet::new();
+    let (scheme, rest) = canonical.split_once("://").unwrap_or(("", canonical));
+    let (rest, query) = match rest.split_once('?') {
+        Some((rest, query)) => (rest, Some(query)),
+        None => (rest, None),
+    };
+    let (host, path) = match rest.find('/') {
+        Some(pos) => rest.split_at(pos),
+        None => (rest, ""),
+    };
+
+    let mut host = host.to_string();
+    for (prefix, reason) in [
+        ("m.", NearReason::MobileHost),
+        ("mobile.", NearReason::MobileHost),
+        ("amp.", NearReason::Amp),
+    ] {
+        if let Some(stripped) = host.strip_prefix(prefix) {
+            if stripped.contains('.') {
+                host = stripped.to_string();
+                reasons.insert(reason);
+                break;
+            }
+        }
+    }
+
+    let mut segments: Vec<String> = Vec::new();
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        if segment.eq_ignore_ascii_case("amp") {
+            reasons.insert(NearReason::Amp);
+            continue;
+        }
+        match segment.to_lowercase().strip_suffix(".amp.html") {
+            Some(stem) => {
+                reasons.insert(NearReason::Amp);
+                segments.push(format!("{}.html", stem));
+            }
+            None => segments.push(segment.to_string()),
+        }
+    }
+    if let Some(last) = segments.last() {
+        let last = last.to_lowercase();
+        if matches!(
+            last.as_str(),
+            "index.html"
+                | "index.htm"
+                | "index.php"
+                | "index.shtml"
+                | "default.aspx"
+                | "default.htm"
+        ) {
+            segments.pop();
+            reasons.insert(NearReason::IndexPage);
+        }
+    }
+
+    let query: Vec<&str> = query
+        .unwrap_or_default()
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .filter(|p| {
+            let is_amp = matches!(
+                p.to_lowercase().as_str(),
+                "amp" | "amp=1" | "amp=true" | "outputtype=amp"
+            );
+            if is_amp {
+                reasons.insert(NearReason::Amp);
+            }
+            !is_amp
+        })
+        .collect();
+
+    let mut key = format!("{}://{}/{}", scheme, host, segments.join("/"));
+    if !query.is_empty() {
+        key.push('?');
+        key.push_str(&query.join("&"));
+    }
+    (key, reasons)
+}
+
+/// 不含查询参数的页面键（同标题判断用）
+fn page_key(loose: &str) -> &str {
+    loose.split('?').next().unwrap_or(loose)
+}
+
+fn title_key(title: &str) -> Option<String> {
+    let key = title
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    // 太短的标题（"Home"、"首页"）不足以说明是同一页面
+    (key.chars().count() >= 6).then_some(key)
+}
+
+fn collect_members(bookmarks: &[Bookmark]) -> Vec<Member> {
+    fn walk(nodes: &[Bookmark], path: &mut Vec<String>, out: &mut Vec<Member>) {
+        for node in nodes {
+            if node.folder {
+                path.push(node.title.clone());
+                walk(&node.children, path, out);
+                path.pop();
+            } else if let Some(ref url) = node.url {
+                out.push(Member {
+                    title: node.title.clone(),
+                    url: url.clone(),
+                    folder: path.join("/"),
+                    date_added: node.date_added,
+                    position: out.len(),
+                });
+            }
+        }
+    }
+    let mut out = Vec::new();
+    walk(bookmarks, &mut Vec::new(), &mut out);
+    out
+}
+
+struct UnionFind(Vec<usize>);
+
+impl UnionFind {
+    fn find(&mut self, i: usize) -> usize {
+        if self.0[i] != i {
+            let root = self.find(self.0[i]);
+            self.0[i] = root;
+        }
+        self.0[i]
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.0[b] = a;
+        }
+    }
+}
+
+/// 找出近似重复的簇（至少两个不同的规范化 URL），按分数从高到低排列
+pub fn find_clusters(bookmarks: &[Bookmark]) -> Vec<Cluster> {
+    let members: Vec<Member> = collect_members(bookmarks)
+        .into_iter()
+        .filter(|m| m.url.contains("://"))
+        .collect();
+
+    // 节点是不同的规范化 URL
+    let mut canonicals: Vec<String> = Vec::new();
+    let mut node_of: HashMap<String, usize> = HashMap::new();
+    let member_nodes: Vec<usize> = members
+        .iter()
+        .map(|m| {
+            let canonical = canonicalize(&m.url);
+            *node_of.entry(canonical.clone()).or_insert_with(|| {
+                canonicals.push(canonical);
+                canonicals.len() - 1
+            })
+        })
+        .collect();
+    let loose: Vec<(String, BTreeSet<NearReason>)> =
+        canonicals.iter().map(|c| loose_key(c)).collect();
+
+    let mut uf = UnionFind((0..canonicals.len()).collect());
+    let mut edges: Vec<(usize, NearReason)> = Vec::new();
+
+    let mut by_loose: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (node, (key, _)) in loose.iter().enumerate() {
+        by_loose.entry(key.as_str()).or_default().push(node);
+    }
+    for nodes in by_loose.values().filter(|n| n.len() > 1) {
+        let reasons: BTreeSet<NearReason> = nodes
+            .iter()
+            .flat_map(|&n| loose[n].1.iter().copied())
+            .collect();
+        for &node in &nodes[1..] {
+            uf.union(nodes[0], node);
+        }
+        edges.extend(reasons.into_iter().map(|r| (nodes[0], r)));
+    }
+
+    let mut by_title: BTreeMap<(&str, String), BTreeSet<usize>> = BTreeMap::new();
+    for (member, &node) in members.iter().zip(&member_nodes) {
+        if let Some(title) = title_key(&member.title) {
+            by_title
+                .entry((page_key(&loose[node].0), title))
+                .or_default()
+                .insert(node);
+        }
+    }
+    for nodes in by_title.values() {
+        // 页面键相同的已经在上面连起来了，这里只看查询参数不同的
+        let distinct: HashSet<&str> = nodes.iter().map(|&n| loose[n].0.as_str()).collect();
+        if distinct.len() < 2 {
+            continue;
+        }
+        let nodes: Vec<usize> = nodes.iter().copied().collect();
+        for &node in &nodes[1..] {
+            uf.union(nodes[0], node);
+        }
+        edges.push((nodes[0], NearReason::SameTitle));
+    }
+
+    let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (member, &node) in member_nodes.iter().enumerate() {
+        groups.entry(uf.find(node)).or_default().push(member);
+    }
+    let mut reasons: HashMap<usize, BTreeSet<NearReason>> = HashMap::new();
+    for (node, reason) in edges {
+        reasons.entry(uf.find(node)).or_default().insert(reason);
+    }
+
+    let mut clusters: Vec<Cluster> = groups
+        .into_iter()
+        .filter(|(_, group)| {
+            group
+                .iter()
+                .map(|&m| member_nodes[m])
+                .collect::<HashSet<_>>()
+                .len()
+                > 1
+        })
+        .map(|(root, group)| {
+            let reasons: Vec<NearReason> = reasons
+                .remove(&root)
+                .unwrap_or_default()
+                .into_iter()
+                .collect();
+            let score = reasons.iter().map(NearReason::score).fold(1.0, f64::min);
+            // 代表：URL 自身带的差异最少，其次 URL 最短，再其次添加得最早
+            let representative = group
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, &m)| {
+                    let node = member_nodes[m];
+                    (
+                        loose[node].1.len(),
+                        canonicals[node].len(),
+                        members[m].date_added.unwrap_or(i64::MAX),
+                    )
+                })
+                .map(|(i, _)| i)
+                .unwrap_or_default();
+            Cluster {
+                members: group.iter().map(|&m| members[m].clone()).collect(),
+                score,
+                reasons,
+                representative,
+            }
+        })
+        .collect();
+    clusters.sort_by(|a, b| {
+        b.score.total_cmp(&a.score).then_with(|| {
+            a.members[a.representative]
+                .url
+                .cmp(&b.members[b.representative].url)
+        })
+    });
+    clusters
+}
+
+/// 一个簇的合并结果
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeRecord {
+    pub cluster: usize,
+    pub kept: Member,
+    pub removed: Vec<Member>,
+    /// 其他副本与代表不同的标题
+    pub titles: Vec<String>,
+    /// 其他副本所在文件夹的名称
+    pub tags: Vec<String>,
+}
+
+/// 解析 `--keep 3=2`（簇号=成员号，从 1 开始）
+pub fn parse_picks(specs: &[String], clusters: &[Cluster]) -> Result<HashMap<usize, usize>> {
+    let mut picks = HashMap::new();
+    for spec in specs {
+        let Some((cluster, member)) = spec
+            .split_once('=')
+            .and_then(|(c, m)| Some((c.trim().parse().ok()?, m.trim().parse().ok()?)))
+        else {
+            bail!(
+                "Invalid --keep '{}' (expected CLUSTER=MEMBER, e.g. 3=2)",
+                spec
+            );
+        };
+        let Some(size) = clusters
+            .get(usize::wrapping_sub(cluster, 1))
+            .map(|c| c.members.len())
+        else {
+            bail!(
+                "--keep {}: there are only {} clusters",
+                spec,
+                clusters.len()
+            );
+        };
+        if member == 0 || member > size {
+            bail!("--keep {}: cluster {} has {} members", spec, cluster, size);
+        }
+        picks.insert(cluster, member);
+    }
+    Ok(picks)
+}
+
+/// 合并簇：保留代表（`picks` 里的簇号 → 成员号，从 1 开始，覆盖推荐的代表），删除其他副本
+pub fn merge_clusters(
+    bookmarks: &mut Vec<Bookmark>,
+    clusters: &[(usize, &Cluster)],
+    picks: &HashMap<usize, usize>,
+) -> Vec<MergeRecord> {
+    let mut remove: HashSet<usize> = HashSet::new();
+    let mut records = Vec::new();
+
+    for &(number, cluster) in clusters {
+        let keep = picks
+            .get(&number)
+            .map(|m| m - 1)
+            .unwrap_or(cluster.representative);
+        let kept = cluster.members[keep].clone();
+        let removed: Vec<Member> = cluster
+            .members
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != keep)
+            .map(|(_, m)| m.clone())
+            .collect();
+        remove.extend(removed.iter().map(|m| m.position));
+
+        let mut titles: Vec<String> = Vec::new();
+        let mut tags: Vec<String> = Vec::new();
+        let kept_tag = kept.folder.rsplit('/').next().unwrap_or_default();
+        for member in &removed {
+            if member.title != kept.title && !titles.contains(&member.title) {
+                titles.push(member.title.clone());
+            }
+            let tag = member.folder.rsplit('/').next().unwrap_or_default();
+            if !tag.is_empty() && tag != kept_tag && !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+        records.push(MergeRecord {
+            cluster: number,
+            kept,
+            removed,
+            titles,
+            tags,
+        });
+    }
+
+    fn retain(nodes: &mut Vec<Bookmark>, position: &mut usize, remove: &HashSet<usize>) {
+        nodes.retain_mut(|node| {
+            if node.folder {
+                retain(&mut node.children, position, remove);
+                true
+            } else if node.url.is_some() {
+                let keep = !remove.contains(position);
+                *position += 1;
+                keep
+            } else {
+                true
+            }
+        });
+    }
+    retain(bookmarks, &mut 0, &remove);
+    records
+}
+
+pub fn print_clusters(clusters: &[Cluster], limit: Option<usize>) {
+    if clusters.is_empty() {
+        println!("🔗 No near-duplicate clusters");
+        return;
+    }
+    let bookmarks: usize = clusters.iter().map(|c| c.members.len()).sum();
+    println!(
+        "🔗 Near-duplicate clusters: {} ({} bookmarks)",
+        clusters.len(),
+        bookmarks
+    );
+    let shown = limit.unwrap_or(clusters.len()).min(clusters.len());
+    for (index, cluster) in clusters.iter().take(shown).enumerate() {
+        let reasons: Vec<String> = cluster.reasons.iter().map(|r| r.to_string()).collect();
+        println!(
+            "\n  #{}  score {:.2}  {}",
+            index + 1,
+            cluster.score,
+            reasons.join(", ")
+        );
+        for (i, member) in cluster.members.iter().enumerate() {
+            let marker = if i == cluster.representative {
+                "*"
+            } else {
+                " "
+            };
+            println!("    {}[{}] {}", marker, i + 1, member.title);
+            println!("         {}  ({})", member.url, member.folder);
+        }
+    }
+    if shown < clusters.len() {
+        println!(
+            "\n  … {} more, run `bsync merge-near` on an export to review all",
+            clusters.len() - shown
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{make_bookmark, make_folder};
+
+    fn bookmark(title: &str, url: &str) -> Bookmark {
+        Bookmark {
+            id: format!("b-{}", url),
+            ..make_bookmark(title, url)
+        }
+    }
+
+    fn folder(title: &str, children: Vec<Bookmark>) -> Bookmark {
+        Bookmark {
+            id: format!("f-{}", title),
+            ..make_folder(title, children)
+        }
+    }
+
+    #[test]
+    fn test_loose_key_variants() {
+        let key = |url: &str| loose_key(&canonicalize(url));
+        let (plain, reasons) = key("https://news.example.com/2024/story");
+        assert!(reasons.is_empty());
+
+        for (url, reason) in [
+            ("https://news.example.com/2024/story/amp/", NearReason::Amp),
+            ("https://news.example.com/amp/2024/story", NearReason::Amp),
+            ("https://news.example.com/2024/story?amp=1", NearReason::Amp),
+            (
+                "https://m.news.example.com/2024/story",
+                NearReason::MobileHost,
+            ),
+            (
+                "https://news.example.com/2024/story/index.html",
+                NearReason::IndexPage,
+            ),
+        ] {
+            let (loose, reasons) = key(url);
+            assert_eq!(loose, plain, "{}", url);
+            assert!(reasons.contains(&reason), "{}", url);
+        }
+
+        let (loose, reasons) = key("https://example.com/a/story.amp.html");
+        assert_eq!(loose, key("https://example.com/a/story.html").0);
+        assert!(reasons.contains(&NearReason::Amp));
+        // `m.com` 本身是域名
+        assert!(key("https://m.com/x").1.is_empty());
+    }
+
+    #[test]
+    fn test_find_clusters_with_reasons_and_representative() {
+        let tree = vec![
+            folder(
+                "News",
+                vec![
+                    bookmark("Big story", "https://news.example.com/2024/story"),
+                    bookmark("Big story (AMP)", "https://news.example.com/2024/story/amp"),
+                ],
+            ),
+            folder(
+                "Mobile",
+                vec![
+                    bookmark("Big story", "https://m.news.example.com/2024/story"),
+                    bookmark(
+                        "Search results for rust",
+                        "https://shop.com/search?q=rust&page=2",
+                    ),
+                ],
+            ),
+            bookmark("Search results for rust", "https://shop.com/search?q=rust"),
+            bookmark("Home", "https://shop.com/?a=1"),
+            bookmark("Home", "https://shop.com/?a=2"),
+            bookmark("Unrelated", "https://other.com/"),
+        ];
+
+        let clusters = find_clusters(&tree);
+        assert_eq!(clusters.len(), 2);
+
+        let news = &clusters[0];
+        assert_eq!(news.members.len(), 3);
+        assert_eq!(news.reasons, vec![NearReason::Amp, NearReason::MobileHost]);
+        assert_eq!(news.score, 0.95);
+        assert_eq!(
+            news.members[news.representative].url,
+            "https://news.example.com/2024/story"
+        );
+
+        let shop = &clusters[1];
+        assert_eq!(shop.reasons, vec![NearReason::SameTitle]);
+        assert_eq!(
+            shop.members[shop.representative].url,
+            "https://shop.com/search?q=rust"
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_representative_and_collects_titles() {
+        let mut tree = vec![
+            folder(
+                "Reading",
+                vec![bookmark("Story", "https://example.com/story")],
+            ),
+            folder(
+                "Phone",
+                vec![
+                    bookmark("Story - mobile", "https://m.example.com/story"),
+                    bookmark("Keep me", "https://example.com/other"),
+                ],
+            ),
+        ];
+        let clusters = find_clusters(&tree);
+        assert_eq!(clusters.len(), 1);
+
+        assert!(parse_picks(&["1=3".to_string()], &clusters).is_err());
+        assert!(parse_picks(&["2=1".to_string()], &clusters).is_err());
+        assert!(parse_picks(&["one".to_string()], &clusters).is_err());
+        // 手动选择移动版作为代表
+        let picks = parse_picks(&["1=2".to_string()], &clusters).unwrap();
+        let records = merge_clusters(&mut tree, &[(1, &clusters[0])], &picks);
+        assert_eq!(records[0].kept.url, "https://m.example.com/story");
+        assert_eq!(records[0].titles, vec!["Story"]);
+        assert_eq!(records[0].tags, vec!["Reading"]);
+
+        assert!(tree[0].children.is_empty());
+        assert_eq!(tree[1].children.len(), 2);
+    }
+}
@@ -0,0 +1,750 @@
+//! 处理流水线 - 用一个 TOML 文件描述 导出 → 过滤 → 去重 → 整理 → 检查 → 导出 的全过程
+//!
+//! 取代 `scripts/full_process*.sh` 里用临时文件串联多个命令的做法，所有步骤在内存里依次执行：
+//!
+//! ```toml
+//! [[stage]]
+//! kind = "read"
+//! browsers = "brave-nightly"
+//! files = ["~/Desktop/FINAL_ORGANIZED_BOOKMARKS.html"]
+//!
+//! [[stage]]
+//! kind = "filter"
+//! exclude_schemes = ["javascript", "file"]
+//!
+//! [[stage]]
+//! kind = "dedupe"
+//! keep = "not-from,deepest"
+//!
+//! [[stage]]
+//! kind = "organize"
+//! rules = "rules.json"
+//! snapshot = "out/03_organized.html"
+//!
+//! [[stage]]
+//! kind = "check"
+//! proxy = "http://127.0.0.1:6152"
+//! timeout = 15
+//!
+//! [[stage]]
+//! kind = "write"
+//! path = "out/FINAL_VALID.html"
+//! ```
+//!
+//! 阶段类型：`read`、`filter`、`dedupe`、`flatten`、`organize`、`check`、`clean`、`write`。
+//! 每个阶段可以设置 `name`（日志里显示）和 `snapshot`（阶段结束后把当前书签树写到文件）。
+//! 相对路径相对于流水线文件所在目录。
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::info;
+
+use crate::browsers::Bookmark;
+use crate::export_filter::{self, ExportFilter};
+use crate::keep_policy::{self, KeepPolicy};
+use crate::redirects::{self, RedirectMap};
+use crate::sync::{self, ExportConfig, ExportFormat, FlatExportConfig, HtmlDialect, SyncEngine};
+use crate::url_checker::{
+    collect_urls_from_bookmarks, remove_invalid_bookmarks_preserve_structure, CheckReport,
+    CheckerConfig, RemoveConfig, UrlChecker, ValidationStatus,
+};
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_timeout() -> u64 {
+    10
+}
+
+fn default_concurrency() -> usize {
+    10
+}
+
+fn default_dialect() -> String {
+    "generic".to_string()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Pipeline {
+    #[serde(rename = "stage", default)]
+    pub stages: Vec<Stage>,
+    /// 流水线文件所在目录，用来解析相对路径
+    #[serde(skip)]
+    pub base_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Stage {
+    /// 日志里显示的名称（默认为阶段类型）
+    pub name: Option<String>,
+    /// 阶段结束后保存中间结果（HTML 或 JSON，按扩展名）
+    pub snapshot: Option<String>,
+    #[serde(flatten)]
+    pub step: Step,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Step {
+    /// 读取浏览器和/或书签文件，追加到当前书签树
+    Read {
+        /// 逗号分隔的浏览器名称，`all` 表示全部
+        browsers: Option<String>,
+        #[serde(default)]
+        files: Vec<String>,
+        /// 不保留每个浏览器的顶层文件夹
+        #[serde(default = "default_true")]
+        merge: bool,
+    },
+    /// 与 `export` 的过滤参数相同
+    Filter {
+        added_after: Option<String>,
+        added_before: Option<String>,
+        modified_after: Option<String>,
+        modified_before: Option<String>,
+        #[serde(default)]
+        include_domains: Vec<String>,
+        #[serde(default)]
+        exclude_domains: Vec<String>,
+        #[serde(default)]
+        include_schemes: Vec<String>,
+        #[serde(default)]
+        exclude_schemes: Vec<String>,
+        title_regex: Option<String>,
+    },
+    /// 去重，`keep` 为保留策略（与 `--keep-policy` 相同，默认 deepest,newest）
+    Dedupe { keep: Option<String> },
+    /// 移除浏览器根文件夹
+    Flatten {
+        /// 要移除的根文件夹名称（默认使用内置列表）
+        root_folders: Option<Vec<String>>,
+    },
+    /// 按分类规则整理到文件夹（内置规则 + 可选的自定义规则 JSON）
+    Organize { rules: Option<String> },
+    /// 检查链接并移除无效书签
+    Check {
+        proxy: Option<String>,
+        #[serde(default = "default_timeout")]
+        timeout: u64,
+        #[serde(default = "default_concurrency")]
+        concurrency: usize,
+        /// 最多检查的唯一 URL 数（0 = 不限制）
+        #[serde(default)]
+        limit: usize,
+        /// 同时移除状态不确定的书签
+        #[serde(default)]
+        remove_uncertain: bool,
+        /// 重定向到同一最终 URL 的书签视为重复
+        #[serde(default)]
+        dedupe_redirects: bool,
+        /// 永久重定向（301/308）的书签改为指向最终 URL
+        #[serde(default)]
+        rewrite_redirects: bool,
+        /// 重定向去重的保留策略（同 dedupe 阶段的 keep）
+        keep: Option<String>,
+    },
+    /// 删除空文件夹
+    Clean,
+    /// 写出当前书签树
+    Write {
+        path: String,
+        /// html 或 json（默认按扩展名）
+        format: Option<String>,
+        #[serde(default = "default_dialect")]
+        dialect: String,
+        /// 把所有书签包在这个文件夹里
+        wrap_folder: Option<String>,
+    },
+}
+
+impl Step {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Step::Read { .. } => "read",
+            Step::Filter { .. } => "filter",
+            Step::Dedupe { .. } => "dedupe",
+            Step::Flatten { .. } => "flatten",
+            Step::Organize { .. } => "organize",
+            Step::Check { .. } => "check",
+            Step::Clean => "clean",
+            Step::Write { .. } => "write",
+        }
+    }
+}
+
+impl Stage {
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(self.step.kind())
+    }
+}
+
+/// 单个阶段的执行结果
+#[derive(Debug, Clone)]
+pub struct StageReport {
+    pub label: String,
+    pub summary: String,
+    pub bookmarks_after: usize,
+    pub duration_ms: u128,
+}
+
+fn count_bookmarks(bookmarks: &[Bookmark]) -> usize {
+    bookmarks
+        .iter()
+        .map(|b| {
+            if b.folder {
+                count_bookmarks(&b.children)
+            } else {
+                1
+            }
+        })
+        .sum()
+}
+
+impl Pipeline {
+    pub fn parse(content: &str, base_dir: &Path) -> Result<Self> {
+        let mut pipeline: Pipeline =
+            toml::from_str(content).context("Failed to parse pipeline file")?;
+        pipeline.base_dir = base_dir.to_path_buf();
+        pipeline.validate()?;
+        Ok(pipeline)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read pipeline file {}", path.display()))?;
+        let base_dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        Self::parse(&content, &base_dir)
+            .with_context(|| format!("Invalid pipeline {}", path.display()))
+    }
+
+    /// 在执行任何阶段之前检查配置，避免跑完耗时的检查后才发现写出参数有误
+    pub fn validate(&self) -> Result<()> {
+        if self.stages.is_empty() {
+            bail!("Pipeline has no [[stage]] entries");
+        }
+        if !matches!(self.stages[0].step, Step::Read { .. }) {
+            bail!("The first stage must be 'read'");
+        }
+        for stage in &self.stages {
+            match stage.step {
+                Step::Read {
+                    ref browsers,
+                    ref files,
+                    ..
+                } if browsers.is_none() && files.is_empty() => {
+                    bail!(
+                        "Stage '{}': read needs 'browsers' or 'files'",
+                        stage.label()
+                    );
+                }
+                Step::Filter {
+                    ref added_after,
+                    ref added_before,
+                    ref modified_after,
+                    ref modified_before,
+                    ref title_regex,
+                    ..
+                } => {
+                    for bound in [added_after, added_before, modified_after, modified_before]
+                        .into_iter()
+                        .flatten()
+                    {
+                        export_filter::parse_date_bound(bound)
+                            .with_context(|| format!("Stage '{}'", stage.label()))?;
+                    }
+                    if let Some(ref pattern) = title_regex {
+                        regex::Regex::new(pattern).with_context(|| {
+                            format!("Stage '{}': invalid title_regex", stage.label())
+                        })?;
+                    }
+                }
+                Step::Write {
+                    ref format,
+                    ref dialect,
+                    ..
+                } => {
+                    if let Some(ref format) = format {
+                        ExportFormat::parse(format)
+                            .with_context(|| format!("Stage '{}'", stage.label()))?;
+                    }
+                    HtmlDialect::parse(dialect)
+                        .with_context(|| format!("Stage '{}'", stage.label()))?;
+                }
+                Step::Dedupe {
+                    keep: Some(ref spec),
+                }
+                | Step::Check {
+                    keep: Some(ref spec),
+                    ..
+                } => {
+                    KeepPolicy::parse(spec)
+                        .with_context(|| format!("Stage '{}'", stage.label()))?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// `~/` 展开为主目录，其他相对路径相对于流水线文件所在目录
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        if let Some(rest) = path.strip_prefix("~/") {
+            PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(rest)
+        } else {
+            self.base_dir.join(path)
+        }
+    }
+
+    /// 依次执行所有阶段
+    pub async fn run(&self, verbose: bool) -> Result<Vec<StageReport>> {
+        let mut bookmarks: Vec<Bookmark> = Vec::new();
+        let mut reports = Vec::new();
+        let total = self.stages.len();
+
+        for (index, stage) in self.stages.iter().enumerate() {
+            match stage.name {
+                Some(ref name) => info!(
+                    "▶️  [{}/{}] {} ({})",
+                    index + 1,
+                    total,
+                    name,
+                    stage.step.kind()
+                ),
+                None => info!("▶️  [{}/{}] {}", index + 1, total, stage.step.kind()),
+            }
+            let started = Instant::now();
+            let summary = self
+                .run_stage(&stage.step, &mut bookmarks, verbose)
+                .await
+                .with_context(|| format!("Stage '{}' failed", stage.label()))?;
+            let report = StageReport {
+                label: stage.label().to_string(),
+                summary,
+                bookmarks_after: count_bookmarks(&bookmarks),
+                duration_ms: started.elapsed().as_millis(),
+            };
+            info!(
+                "  ✅ {}: {} ({} bookmarks, {} ms)",
+                report.label, report.summary, report.bookmarks_after, report.duration_ms
+            );
+
+            if let Some(ref snapshot) = stage.snapshot {
+                let path = self.resolve(snapshot);
+                let path = path.to_string_lossy();
+                write_tree(
+                    &bookmarks,
+                    &path,
+                    ExportFormat::from_path(&path),
+                    HtmlDialect::Generic,
+                )?;
+                info!("  📸 Snapshot saved to {}", path);
+            }
+            reports.push(report);
+        }
+
+        Ok(reports)
+    }
+
+    async fn run_stage(
+        &self,
+        step: &Step,
+        bookmarks: &mut Vec<Bookmark>,
+        verbose: bool,
+    ) -> Result<String> {
+        match step {
+            Step::Read {
+                browsers,
+                files,
+                merge,
+            } => {
+                let before = count_bookmarks(bookmarks);
+                if let Some(ref browsers) = browsers {
+                    let engine = SyncEngine::new()?;
+                    let config = ExportConfig {
+                        merge: *merge,
+                        verbose,
+                        ..Default::default()
+                    };
+                    let read = engine
+                        .collect_bookmarks_for_export(Some(browsers), &config, Vec::new())
+                        .await?;
+                    bookmarks.extend(read);
+                }
+                for file in files {
+                    let path = self.resolve(file);
+                    let path = path.to_string_lossy();
+                    let read = sync::read_bookmarks_file(&path, ExportFormat::from_path(&path))
+                        .with_context(|| format!("Failed to read {}", path))?;
+                    info!("  📖 {}: {} bookmarks", path, count_bookmarks(&read));
+                    bookmarks.extend(read);
+                }
+                Ok(format!(
+                    "{} bookmarks read",
+                    count_bookmarks(bookmarks) - before
+                ))
+            }
+
+            Step::Filter {
+                added_after,
+                added_before,
+                modified_after,
+                modified_before,
+                include_domains,
+                exclude_domains,
+                include_schemes,
+                exclude_schemes,
+                title_regex,
+            } => {
+                let date_bound = |value: &Option<String>| {
+                    value
+                        .as_deref()
+                        .map(export_filter::parse_date_bound)
+                        .transpose()
+                };
+                let filter = ExportFilter {
+                    added_after: date_bound(added_after)?,
+                    added_before: date_bound(added_before)?,
+                    modified_after: date_bound(modified_after)?,
+                    modified_before: date_bound(modified_before)?,
+                    include_domains: include_domains.clone(),
+                    exclude_domains: exclude_domains.clone(),
+                    include_schemes: include_schemes.clone(),
+                    exclude_schemes: exclude_schemes.clone(),
+                    title_regex: title_regex.as_deref().map(regex::Regex::new).transpose()?,
+                };
+                let stats = filter.apply(bookmarks);
+                if verbose {
+                    stats.print_summary();
+                }
+                Ok(format!("{} bookmarks removed", stats.total_removed()))
+            }
+
+            Step::Dedupe { keep } => {
+                let policy = match keep {
+                    Some(spec) => KeepPolicy::parse(spec)?,
+                    None => KeepPolicy::default(),
+                };
+                let stats = sync::deduplicate_bookmarks(bookmarks, &policy);
+                if verbose {
+                    keep_policy::print_losses(&stats.losses, None);
+                }
+                Ok(format!("{} duplicates removed", stats.duplicates_removed))
+            }
+
+            Step::Flatten { root_folders } => {
+                let config = FlatExportConfig {
+                    flatten_root: true,
+                    root_folders_to_remove: root_folders.clone(),
+                };
+                let (flattened, stats) = sync::flatten_bookmarks(bookmarks, &config);
+                *bookmarks = flattened;
+                Ok(format!(
+                    "{} root folders removed, {} items promoted",
+                    stats.root_folders_removed, stats.bookmarks_promoted
+                ))
+            }
+
+            Step::Organize { rules } => {
+                let rules_path = rules
+                    .as_deref()
+                    .map(|r| self.resolve(r).to_string_lossy().into_owned());
+                let rules = SyncEngine::load_classification_rules(rules_path.as_deref())?;
+                let stats = SyncEngine::classify_into_folders(bookmarks, &rules, verbose);
+                Ok(format!(
+                    "{} of {} classified into {} folders, {} unclassified",
+                    stats.total_classified,
+                    stats.total_processed,
+                    stats.by_category.len(),
+                    stats.unclassified
+                ))
+            }
+
+            Step::Check {
+                proxy,
+                timeout,
+                concurrency,
+                limit,
+                remove_uncertain,
+                dedupe_redirects,
+                rewrite_redirects,
+                keep,
+            } => {
+                let checker = UrlChecker::new(CheckerConfig {
+                    proxy_url: proxy.clone(),
+                    timeout_secs: *timeout,
+                    // 与 check 命令相同的并发上限
+                    concurrency: (*concurrency).clamp(1, 10),
+                    retry_count: 1,
+                })?;
+                let mut urls: Vec<String> = collect_urls_from_bookmarks(bookmarks)
+                    .into_iter()
+                    .collect::<HashSet<_>>()
+                    .into_iter()
+                    .collect();
+                urls.sort();
+                if *limit > 0 && urls.len() > *limit {
+                    info!("  📊 Checking {} of {} unique URLs", limit, urls.len());
+                    urls.truncate(*limit);
+                }
+
+                let started = Instant::now();
+                let results = checker
+                    .check_batch(urls, |current, total, url| {
+                        if verbose {
+                            info!("  🔍 [{}/{}] {}", current, total, url);
+                        }
+                    })
+                    .await;
+                let report = CheckReport::from_results(&results, started.elapsed().as_secs_f64());
+
+                let remove: HashSet<String> = results
+                    .iter()
+                    .filter(|r| {
+                        r.status == ValidationStatus::Invalid
+                            || (*remove_uncertain && r.status == ValidationStatus::Uncertain)
+                    })
+                    .map(|r| r.url.clone())
+                    .collect();
+                // 空文件夹交给 clean 阶段
+                let removed = remove_invalid_bookmarks_preserve_structure(
+                    bookmarks,
+                    &remove,
+                    &RemoveConfig {
+                        keep_empty_folders: true,
+                    },
+                );
+                let mut summary = format!(
+                    "{} checked: {} valid, {} invalid, {} uncertain, {} skipped; {} bookmarks removed",
+                    report.total_checked,
+                    report.valid_count,
+                    report.invalid_count,
+                    report.uncertain_count,
+                    report.skipped_count,
+                    removed.bookmarks_removed
+                );
+                let redirect_map = RedirectMap::from_results(&results);
+                if *dedupe_redirects {
+                    let policy = match keep {
+                        Some(spec) => KeepPolicy::parse(spec)?,
+                        None => KeepPolicy::default(),
+                    };
+                    let losses = redirects::dedupe_by_final_url(bookmarks, &redirect_map, &policy);
+                    if verbose {
+                        keep_policy::print_losses(&losses, None);
+                    }
+                    summary.push_str(&format!("; {} redirect duplicates removed", losses.len()));
+                }
+                if *rewrite_redirects {
+                    let rewrites = redirects::rewrite_permanent(bookmarks, &redirect_map);
+                    summary.push_str(&format!("; {} redirects rewritten", rewrites.len()));
+                }
+                Ok(summary)
+            }
+
+            Step::Clean => {
+                let stats = sync::clean_empty_folders(bookmarks);
+                Ok(format!(
+                    "{} empty folders removed",
+                    stats.empty_folders_removed
+                ))
+            }
+
+            Step::Write {
+                path,
+                format,
+                dialect,
+                wrap_folder,
+            } => {
+                let resolved = self.resolve(path);
+                let resolved = resolved.to_string_lossy();
+                let format = match format {
+                    Some(format) => ExportFormat::parse(format)?,
+                    None => ExportFormat::from_path(&resolved),
+                };
+                let dialect = HtmlDialect::parse(dialect)?;
+                match wrap_folder {
+                    Some(title) => {
+                        let wrapped = Bookmark {
+                            id: "pipeline-wrap-folder".to_string(),
+                            title: title.clone(),
+                            url: None,
+                            folder: true,
+                            children: bookmarks.clone(),
+                            date_added: Some(chrono::Utc::now().timestamp_millis()),
+                            date_modified: None,
+                        };
+                        write_tree(&[wrapped], &resolved, format, dialect)?;
+                    }
+                    None => write_tree(bookmarks, &resolved, format, dialect)?,
+                }
+                Ok(format!("written to {}", resolved))
+            }
+        }
+    }
+}
+
+fn write_tree(
+    bookmarks: &[Bookmark],
+    path: &str,
+    format: ExportFormat,
+    dialect: HtmlDialect,
+) -> Result<()> {
+    if let Some(parent) = Path::new(path).parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+    sync::write_bookmarks_file(bookmarks, path, format, dialect)
+}
+
+pub fn print_summary(reports: &[StageReport]) {
+    println!("\n🧪 Pipeline Summary:");
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    for (index, report) in reports.iter().enumerate() {
+        println!(
+            "  {}. {:<12} {:>6} bookmarks {:>7} ms  {}",
+            index + 1,
+            report.label,
+            report.bookmarks_after,
+            report.duration_ms,
+            report.summary
+        );
+    }
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{make_bookmark, make_folder};
+    use tempfile::TempDir;
+
+    fn bookmark(title: &str, url: &str) -> Bookmark {
+        Bookmark {
+            id: format!("b-{}", title),
+            ..make_bookmark(title, url)
+        }
+    }
+
+    fn folder(title: &str, children: Vec<Bookmark>) -> Bookmark {
+        Bookmark {
+            id: format!("f-{}", title),
+            ..make_folder(title, children)
+        }
+    }
+
+    #[test]
+    fn test_parse_and_validate() {
+        let base = Path::new("/work");
+        let pipeline = Pipeline::parse(
+            r#"
+            [[stage]]
+            kind = "read"
+            files = ["in.html"]
+
+            [[stage]]
+            name = "drop scripts"
+            kind = "filter"
+            exclude_schemes = ["javascript"]
+            snapshot = "out/01.json"
+
+            [[stage]]
+            kind = "dedupe"
+
+            [[stage]]
+            kind = "write"
+            path = "~/out.html"
+            dialect = "firefox"
+            "#,
+            base,
+        )
+        .unwrap();
+        assert_eq!(pipeline.stages.len(), 4);
+        assert_eq!(pipeline.stages[1].label(), "drop scripts");
+        assert_eq!(pipeline.stages[2].label(), "dedupe");
+        assert_eq!(pipeline.resolve("in.html"), PathBuf::from("/work/in.html"));
+
+        let err = |toml: &str| Pipeline::parse(toml, base).unwrap_err().to_string();
+        assert!(err("").contains("no [[stage]]"));
+        assert!(err("[[stage]]\nkind = \"dedupe\"").contains("first stage"));
+        assert!(err("[[stage]]\nkind = \"read\"").contains("needs"));
+        assert!(Pipeline::parse("[[stage]]\nkind = \"explode\"", base).is_err());
+        assert!(Pipeline::parse(
+            "[[stage]]\nkind = \"read\"\nfiles = [\"a.html\"]\n\n[[stage]]\nkind = \"dedupe\"\nkeep = \"biggest\"",
+            base
+        )
+        .is_err());
+        assert!(Pipeline::parse(
+            "[[stage]]\nkind = \"read\"\nfiles = [\"a.html\"]\n\n[[stage]]\nkind = \"write\"\npath = \"b.html\"\ndialect = \"lynx\"",
+            base
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_in_memory_with_snapshot() {
+        let dir = TempDir::new().unwrap();
+        let input = vec![
+            folder(
+                "Work",
+                vec![
+                    bookmark("Rust", "https://www.rust-lang.org/"),
+                    bookmark("Rust again", "https://www.rust-lang.org/"),
+                    bookmark("Bookmarklet", "javascript:alert(1)"),
+                ],
+            ),
+            folder("Empty", vec![]),
+        ];
+        std::fs::write(
+            dir.path().join("in.json"),
+            serde_json::to_string(&input).unwrap(),
+        )
+        .unwrap();
+
+        let pipeline = Pipeline::parse(
+            r#"
+            [[stage]]
+            kind = "read"
+            files = ["in.json"]
+
+            [[stage]]
+            kind = "filter"
+            exclude_schemes = ["javascript"]
+
+            [[stage]]
+            kind = "dedupe"
+            snapshot = "snap/deduped.json"
+
+            [[stage]]
+            kind = "clean"
+
+            [[stage]]
+            kind = "write"
+            path = "out.json"
+            "#,
+            dir.path(),
+        )
+        .unwrap();
+
+        let reports = pipeline.run(false).await.unwrap();
+        let after: Vec<usize> = reports.iter().map(|r| r.bookmarks_after).collect();
+        assert_eq!(after, vec![3, 2, 1, 1, 1]);
+
+        let snapshot: Vec<Bookmark> = serde_json::from_str(
+            &std::fs::read_to_string(dir.path().join("snap/deduped.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(snapshot.len(), 2);
+
+        let output: Vec<Bookmark> =
+            serde_json::from_str(&std::fs::read_to_string(dir.path().join("out.json")).unwrap())
+                .unwrap();
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].title, "Work");
+        assert_eq!(output[0].children.len(), 1);
+    }
+}
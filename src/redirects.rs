@@ -0,0 +1,211 @@
+//! 重定向感知去重 - 用链接检查结果里的最终 URL 找出跟随重定向后才相同的书签
+//!
+//! `http://` → `https://`、短链接、搬家的博客文章在规范化后仍然是不同的 URL，
+//! 检查时跟随重定向得到的最终 URL 才能说明它们是同一页面。
+//! - 去重：最终 URL 规范化后相同的书签视为重复，按保留策略留下一份
+//! - 改写：整条重定向链都是永久重定向（301/308）的书签改为指向最终 URL
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::browsers::Bookmark;
+use crate::keep_policy::{DedupeLoss, KeepPolicy};
+use crate::sync::SyncEngine;
+use crate::url_canon::canonicalize;
+use crate::url_checker::UrlCheckResult;
+
+/// 重定向目标
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedirectTarget {
+    pub final_url: String,
+    /// 整条链都是 301/308
+    pub permanent: bool,
+}
+
+/// 原始 URL（规范化）→ 重定向目标，只包含确实发生了重定向的成功结果
+#[derive(Debug, Clone, Default)]
+pub struct RedirectMap(HashMap<String, RedirectTarget>);
+
+impl RedirectMap {
+    pub fn from_results(results: &[UrlCheckResult]) -> Self {
+        let mut map = HashMap::new();
+        for result in results {
+            let Some(final_url) = result.final_url() else {
+                continue;
+            };
+            // 只是规范化差异（如末尾斜杠）的重定向不算
+            if canonicalize(final_url) == canonicalize(&result.url) {
+                continue;
+            }
+            map.insert(
+                canonicalize(&result.url),
+                RedirectTarget {
+                    final_url: final_url.to_string(),
+                    permanent: result.is_permanent_redirect(),
+                },
+            );
+        }
+        Self(map)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, url: &str) -> Option<&RedirectTarget> {
+        self.0.get(&canonicalize(url))
+    }
+}
+
+/// 把最终 URL 相同的书签视为重复，按保留策略去重
+pub fn dedupe_by_final_url(
+    bookmarks: &mut Vec<Bookmark>,
+    redirects: &RedirectMap,
+    policy: &KeepPolicy,
+) -> Vec<DedupeLoss> {
+    SyncEngine::deduplicate_bookmarks_by_key(bookmarks, policy, None, &|bookmark, url| {
+        let target = redirects.get(url).map(|t| t.final_url.as_str());
+        SyncEngine::dedupe_key(bookmark, target.unwrap_or(url))
+    })
+}
+
+/// 一次改写
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RedirectRewrite {
+    pub title: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// 把永久重定向的书签改为指向最终 URL
+pub fn rewrite_permanent(
+    bookmarks: &mut [Bookmark],
+    redirects: &RedirectMap,
+) -> Vec<RedirectRewrite> {
+    let mut rewrites = Vec::new();
+    rewrite_recursive(bookmarks, redirects, &mut rewrites);
+    rewrites
+}
+
+fn rewrite_recursive(
+    bookmarks: &mut [Bookmark],
+    redirects: &RedirectMap,
+    rewrites: &mut Vec<RedirectRewrite>,
+) {
+    for bookmark in bookmarks {
+        if bookmark.folder {
+            rewrite_recursive(&mut bookmark.children, redirects, rewrites);
+            continue;
+        }
+        let Some(ref url) = bookmark.url else {
+            continue;
+        };
+        if let Some(target) = redirects.get(url).filter(|t| t.permanent) {
+            rewrites.push(RedirectRewrite {
+                title: bookmark.title.clone(),
+                from: url.clone(),
+                to: target.final_url.clone(),
+            });
+            bookmark.url = Some(target.final_url.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::make_bookmark;
+    use crate::url_checker::{HttpResult, RedirectHop};
+
+    fn bookmark(title: &str, url: &str) -> Bookmark {
+        Bookmark {
+            id: title.to_string(),
+            ..make_bookmark(title, url)
+        }
+    }
+
+    fn redirected(url: &str, hops: &[(&str, u16)], final_url: &str) -> UrlCheckResult {
+        let hops = hops
+            .iter()
+            .map(|(url, code)| RedirectHop {
+                url: url.to_string(),
+                status_code: *code,
+            })
+            .collect();
+        let direct = HttpResult::success(200, 10).with_redirects(Some(final_url.to_string()), hops);
+        UrlCheckResult::from_results(url.to_string(), None, Some(direct))
+    }
+
+    #[test]
+    fn test_redirect_map_and_rewrite() {
+        let results = vec![
+            redirected(
+                "http://blog.example/old-post",
+                &[
+                    ("http://blog.example/old-post", 301),
+                    ("https://blog.example/old-post", 308),
+                ],
+                "https://blog.example/2024/new-post",
+            ),
+            redirected(
+                "https://sho.rt/x",
+                &[("https://sho.rt/x", 302)],
+                "https://blog.example/2024/new-post",
+            ),
+            // 只是补了末尾斜杠
+            redirected(
+                "https://a.example/docs",
+                &[("https://a.example/docs", 301)],
+                "https://a.example/docs/",
+            ),
+            UrlCheckResult::from_results(
+                "https://plain.example/".to_string(),
+                None,
+                Some(HttpResult::success(200, 5)),
+            ),
+        ];
+        let map = RedirectMap::from_results(&results);
+        assert_eq!(map.len(), 2);
+        assert!(map.get("http://blog.example/old-post").unwrap().permanent);
+        assert!(!map.get("https://sho.rt/x").unwrap().permanent);
+
+        let mut tree = vec![
+            bookmark("Old", "http://blog.example/old-post"),
+            bookmark("Short", "https://sho.rt/x"),
+            bookmark("Plain", "https://plain.example/"),
+        ];
+        let rewrites = rewrite_permanent(&mut tree, &map);
+        assert_eq!(rewrites.len(), 1);
+        assert_eq!(
+            tree[0].url.as_deref(),
+            Some("https://blog.example/2024/new-post")
+        );
+        // 临时重定向不改写
+        assert_eq!(tree[1].url.as_deref(), Some("https://sho.rt/x"));
+    }
+
+    #[test]
+    fn test_dedupe_by_final_url() {
+        let results = vec![redirected(
+            "https://sho.rt/x",
+            &[("https://sho.rt/x", 302)],
+            "https://blog.example/post",
+        )];
+        let map = RedirectMap::from_results(&results);
+        let mut tree = vec![
+            bookmark("Short", "https://sho.rt/x"),
+            bookmark("Post", "https://blog.example/post"),
+            bookmark("Other", "https://other.example/"),
+        ];
+        let losses = dedupe_by_final_url(&mut tree, &map, &KeepPolicy::default());
+        assert_eq!(losses.len(), 1);
+        assert_eq!(losses[0].url, "https://blog.example/post");
+        assert_eq!(losses[0].reason, "first-seen");
+        let titles: Vec<&str> = tree.iter().map(|b| b.title.as_str()).collect();
+        assert_eq!(titles, vec!["Short", "Other"]);
+    }
+}
@@ -0,0 +1,235 @@
+//! 场景文件夹同步 - 用通配路径一次同步多个场景文件夹
+//!
+//! 每个场景是一个文件夹路径模式，`*` 匹配一层文件夹（可带前后缀，如 `Client-*`），
+//! `**` 匹配任意多层，例如 `Projects/*`、`**/临时`。匹配不区分大小写。
+//!
+//! 每个场景可以选择合并方式：
+//! - 并集（默认）：合并所有浏览器里该文件夹的内容并去重，标题冲突按书签冲突策略处理
+//! - 镜像：以指定的主浏览器为准，其他浏览器的该文件夹被替换成主浏览器的内容
+//!
+//! 场景写法 `Projects/*@chrome` 表示以 chrome 为主浏览器镜像。
+
+use anyhow::{bail, Result};
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::browsers::Bookmark;
+use crate::export_filter::wildcard_match;
+
+/// 场景文件夹的合并方式
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScenarioMode {
+    /// 合并所有浏览器的内容
+    Union,
+    /// 以主浏览器为准
+    Mirror(String),
+}
+
+impl fmt::Display for ScenarioMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioMode::Union => write!(f, "union"),
+            ScenarioMode::Mirror(master) => write!(f, "mirror from {}", master),
+        }
+    }
+}
+
+/// 一个场景：路径模式 + 合并方式
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenarioSpec {
+    pub pattern: String,
+    pub mode: ScenarioMode,
+}
+
+impl ScenarioSpec {
+    /// 解析 `pattern` 或 `pattern@master`，没有指定主浏览器时使用 `default_mode`
+    pub fn parse(spec: &str, default_mode: &ScenarioMode) -> Result<Self> {
+        let (pattern, mode) = match spec.rsplit_once('@') {
+            Some((pattern, master)) => {
+                let master = master.trim().to_lowercase();
+                if master.is_empty() {
+                    bail!("Missing master browser after '@' in scenario '{}'", spec);
+                }
+                (pattern, ScenarioMode::Mirror(master))
+            }
+            None => (spec, default_mode.clone()),
+        };
+        let segments = split_path(pattern);
+        if segments.is_empty() {
+            bail!("Empty scenario folder pattern: '{}'", spec);
+        }
+        Ok(Self {
+            pattern: segments.join("/"),
+            mode,
+        })
+    }
+}
+
+/// 单个场景文件夹的同步结果
+#[derive(Debug, Clone, Default)]
+pub struct ScenarioStats {
+    pub path: String,
+    pub mode: String,
+    /// 含有该文件夹的浏览器数
+    pub browsers_found: usize,
+    /// 合并后的书签数
+    pub bookmarks: usize,
+    pub duplicates_removed: usize,
+    pub conflicts_resolved: usize,
+    /// 文件夹内容被更新的浏览器
+    pub browsers_updated: Vec<String>,
+    /// 跳过原因（例如主浏览器没有该文件夹）
+    pub skipped: Option<String>,
+}
+
+pub(crate) fn split_path(path: &str) -> Vec<String> {
+    path.split('/')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// 文件夹路径是否匹配模式
+pub fn path_matches(pattern: &[String], path: &[String]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|skip| path_matches(rest, &path[skip..]))
+        }
+        Some((first, rest)) => match path.split_first() {
+            Some((segment, path_rest)) => {
+                wildcard_match(&first.to_lowercase(), &segment.to_lowercase())
+                    && path_matches(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+/// 在所有书签树里展开模式，得到具体的文件夹路径
+///
+/// 大小写不同的同名路径只保留第一次出现的写法；已匹配文件夹里面的匹配会被忽略，
+/// 外层文件夹的同步已经包含它们。
+pub fn expand_pattern<'a>(
+    pattern: &str,
+    trees: impl IntoIterator<Item = &'a [Bookmark]>,
+) -> Vec<String> {
+    fn walk(
+        nodes: &[Bookmark],
+        pattern: &[String],
+        path: &mut Vec<String>,
+        out: &mut Vec<Vec<String>>,
+    ) {
+        for node in nodes.iter().filter(|b| b.folder) {
+            path.push(node.title.trim().to_string());
+            if path_matches(pattern, path) {
+                out.push(path.clone());
+            } else {
+                walk(&node.children, pattern, path, out);
+            }
+            path.pop();
+        }
+    }
+
+    let pattern = split_path(pattern);
+    let mut found = Vec::new();
+    for tree in trees {
+        walk(tree, &pattern, &mut Vec::new(), &mut found);
+    }
+
+    let mut seen = HashSet::new();
+    let mut paths: Vec<Vec<String>> = found
+        .into_iter()
+        .filter(|p| seen.insert(p.join("/").to_lowercase()))
+        .collect();
+    paths.sort_by_key(|p| p.join("/").to_lowercase());
+
+    // 不同浏览器的结构不同，一个浏览器里的匹配可能在另一个浏览器的匹配里面
+    let mut outer: Vec<String> = Vec::new();
+    for path in paths {
+        let lower = path.join("/").to_lowercase();
+        if !outer
+            .iter()
+            .any(|o| lower.starts_with(&format!("{}/", o.to_lowercase())))
+        {
+            outer.push(path.join("/"));
+        }
+    }
+    outer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::make_folder;
+
+    fn segments(path: &str) -> Vec<String> {
+        split_path(path)
+    }
+
+    #[test]
+    fn test_path_matches_globs() {
+        assert!(path_matches(
+            &segments("Projects/*"),
+            &segments("Projects/Alpha")
+        ));
+        assert!(path_matches(
+            &segments("projects/a*"),
+            &segments("Projects/Alpha")
+        ));
+        assert!(!path_matches(
+            &segments("Projects/*"),
+            &segments("Projects/Alpha/Docs")
+        ));
+        assert!(path_matches(&segments("**/临时"), &segments("临时")));
+        assert!(path_matches(&segments("**/临时"), &segments("Work/A/临时")));
+        assert!(path_matches(
+            &segments("Work/**/Docs"),
+            &segments("Work/Docs")
+        ));
+        assert!(!path_matches(
+            &segments("**/临时"),
+            &segments("Work/临时/Old")
+        ));
+    }
+
+    #[test]
+    fn test_expand_pattern_across_browsers() {
+        let chrome = vec![make_folder(
+            "Projects",
+            vec![make_folder("Alpha", vec![]), make_folder("Beta", vec![])],
+        )];
+        let firefox = vec![
+            make_folder(
+                "projects",
+                vec![make_folder("alpha", vec![]), make_folder("Gamma", vec![])],
+            ),
+            make_folder("临时", vec![make_folder("临时", vec![])]),
+        ];
+
+        let paths = expand_pattern("Projects/*", [chrome.as_slice(), firefox.as_slice()]);
+        assert_eq!(
+            paths,
+            vec!["Projects/Alpha", "Projects/Beta", "projects/Gamma"]
+        );
+
+        // 嵌套的匹配已经被外层文件夹包含
+        let paths = expand_pattern("**/临时", [chrome.as_slice(), firefox.as_slice()]);
+        assert_eq!(paths, vec!["临时"]);
+    }
+
+    #[test]
+    fn test_parse_spec() {
+        let spec = ScenarioSpec::parse(" Projects / * ", &ScenarioMode::Union).unwrap();
+        assert_eq!(spec.pattern, "Projects/*");
+        assert_eq!(spec.mode, ScenarioMode::Union);
+
+        let spec = ScenarioSpec::parse("Work/**@Chrome", &ScenarioMode::Union).unwrap();
+        assert_eq!(spec.pattern, "Work/**");
+        assert_eq!(spec.mode, ScenarioMode::Mirror("chrome".to_string()));
+
+        assert!(ScenarioSpec::parse("Work@", &ScenarioMode::Union).is_err());
+        assert!(ScenarioSpec::parse("/", &ScenarioMode::Union).is_err());
+    }
+}
@@ -1,71 +1,1030 @@
-// Scheduler module - reserved for future scheduled sync feature
-#![allow(dead_code)]
+//! 定时任务 - `bsync daemon` 按 cron 表达式运行多个任务
+//!
+//! 配置文件默认是 `~/.browser-sync/daemon.json`：
+//!
+//! ```json
+//! {
+//!   "missed_runs": "skip",
+//!   "jobs": [
+//!     { "name": "nightly-export", "schedule": "0 3 * * *", "task": "export",
+//!       "args": ["-o", "~/Desktop/bookmarks.html", "-d", "--merge"] },
+//!     { "name": "weekly-check", "schedule": "0 4 * * SUN", "task": "check",
+//!       "args": ["--delete"], "missed_runs": "catch_up" }
+//!   ]
+//! }
+//! ```
+//!
+//! 每个任务是 export、check、backup、organize、history、sync 之一，`args` 就是该子命令的命令行参数，
+//! 任务在子进程里运行（`bsync <task> <args>`），一个任务失败不会影响守护进程。
+//!
+//! - 同一任务上一次还没结束时，这一次被跳过（记录为 `skipped_overlap`）
+//! - 不同任务排队依次运行，避免同时写同一个浏览器的书签
+//! - 睡眠或守护进程停止期间错过的运行，按 `missed_runs` 补跑一次（`catch_up`）或跳过（`skip`）
+//!
+//! 配置了 `watch` 时，书签文件变化后也会运行任务，见 `watcher` 模块。
+//!
+//! 每次运行的结果以 JSON 行追加到 `~/.browser-sync/daemon-runs.jsonl`。子命令通过
+//! `BSYNC_RESULT_FILE` 交回结构化结果（`SyncStats`、`CheckReport`、`DedupeStats`）。
+//! 运行中的守护进程可以用 `bsync ctl` 查询和控制，见 `control` 模块。
 
-use anyhow::Result;
-use tokio_cron_scheduler::{Job, JobScheduler};
-use tracing::{error, info};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Local, Timelike};
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::task::JoinSet;
+use tracing::{error, info, warn};
 
-use crate::sync::{SyncEngine, SyncMode};
+use crate::control::{self, DaemonStatus, JobStatus, Request, Response};
+use crate::sync::{DedupeStats, SyncStats};
+use crate::url_checker::CheckReport;
+use crate::watcher::{self, WatchConfig, Watcher};
 
-pub struct SchedulerConfig {
-    pub cron_expression: String,
-    pub daemon: bool,
+/// 检查到期任务的间隔
+const TICK_SECONDS: u64 = 20;
+/// 到期后超过这个时间才被发现，视为错过（睡眠、守护进程未运行）
+const MISSED_GRACE_SECONDS: i64 = 90;
+/// 跳过错过的运行时最多数到这么多次
+const MISSED_COUNT_CAP: usize = 1000;
+/// 轮询书签文件的间隔
+const WATCH_POLL_SECONDS: u64 = 1;
+/// 文件监视触发的运行在日志里的任务名
+const WATCH_JOB: &str = "watch";
+/// 子进程写结构化结果的文件
+const RESULT_ENV: &str = "BSYNC_RESULT_FILE";
+/// 失败时记录的输出行数
+const OUTPUT_TAIL_LINES: usize = 20;
+
+/// 任务类型，对应同名子命令
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Task {
+    Export,
+    Check,
+    Backup,
+    Organize,
+    History,
+    Sync,
+}
+
+impl Task {
+    pub fn command(&self) -> &'static str {
+        match self {
+            Task::Export => "export",
+            Task::Check => "check",
+            Task::Backup => "backup",
+            Task::Organize => "organize",
+            Task::History => "history",
+            Task::Sync => "sync",
+        }
+    }
+}
+
+/// 错过的运行如何处理
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedRuns {
+    /// 跳过，等下一次
+    #[default]
+    Skip,
+    /// 补跑一次（多次错过也只补一次）
+    CatchUp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobConfig {
+    pub name: String,
+    /// cron 表达式，5 段（分 时 日 月 周）或带秒的 6/7 段
+    pub schedule: String,
+    pub task: Task,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// 覆盖全局的 `missed_runs`
+    #[serde(default)]
+    pub missed_runs: Option<MissedRuns>,
 }
 
-impl SchedulerConfig {
-    pub fn new(cron: String, daemon: bool) -> Self {
+impl JobConfig {
+    /// 这次运行可能改写浏览器的书签文件（history 会写 Firefox 系的 places.sqlite）
+    fn writes_bookmarks(&self) -> bool {
+        match self.task {
+            Task::Backup => false,
+            Task::Export => self.args.iter().any(|a| a == "--clear-after"),
+            _ => true,
+        }
+    }
+
+    fn command_line(&self) -> String {
+        std::iter::once(format!("bsync {}", self.task.command()))
+            .chain(self.args.iter().cloned())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DaemonConfig {
+    #[serde(default)]
+    pub missed_runs: MissedRuns,
+    #[serde(default)]
+    pub jobs: Vec<JobConfig>,
+    /// 书签文件变化时运行的任务
+    #[serde(default)]
+    pub watch: Option<WatchConfig>,
+}
+
+impl DaemonConfig {
+    pub fn default_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME")?;
+        Ok(PathBuf::from(format!("{}/.browser-sync/daemon.json", home)))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read daemon config {}", path.display()))?;
+        let config: Self =
+            serde_json::from_str(&content).context("Failed to parse daemon config")?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.jobs.is_empty() && self.watch.is_none() {
+            bail!("Daemon config defines no jobs and no watch");
+        }
+        let mut names = HashSet::new();
+        for job in &self.jobs {
+            if job.name == WATCH_JOB || !names.insert(job.name.as_str()) {
+                bail!("Duplicate job name '{}'", job.name);
+            }
+            parse_schedule(&job.schedule)
+                .with_context(|| format!("Invalid schedule for job '{}'", job.name))?;
+        }
+        Ok(())
+    }
+
+    fn missed_runs(&self, job: &JobConfig) -> MissedRuns {
+        job.missed_runs.unwrap_or(self.missed_runs)
+    }
+}
+
+/// 解析 cron 表达式；5 段的标准写法补上秒
+pub fn parse_schedule(expr: &str) -> Result<Schedule> {
+    let expr = expr.trim();
+    let expr = if expr.split_whitespace().count() == 5 {
+        format!("0 {}", expr)
+    } else {
+        expr.to_string()
+    };
+    Schedule::from_str(&expr).map_err(|e| anyhow::anyhow!("'{}': {}", expr, e))
+}
+
+/// 一次检查的结论
+#[derive(Debug, Clone, PartialEq)]
+pub enum Due {
+    Idle,
+    Run {
+        scheduled: DateTime<Local>,
+        caught_up: bool,
+    },
+    SkipMissed {
+        scheduled: DateTime<Local>,
+        /// 错过的次数，最多数到 `MISSED_COUNT_CAP`
+        missed: usize,
+    },
+}
+
+/// `(last, now]` 之间有没有到期的运行
+///
+/// 只取其中最后一次：从 now 往回找，不逐个枚举睡眠期间的每次运行
+pub fn check_due(
+    schedule: &Schedule,
+    last: DateTime<Local>,
+    now: DateTime<Local>,
+    policy: MissedRuns,
+) -> Due {
+    let Some(scheduled) = latest_due(schedule, now).filter(|t| *t > last) else {
+        return Due::Idle;
+    };
+    if now - scheduled <= Duration::seconds(MISSED_GRACE_SECONDS) {
+        return Due::Run {
+            scheduled,
+            caught_up: false,
+        };
+    }
+    match policy {
+        MissedRuns::CatchUp => Due::Run {
+            scheduled,
+            caught_up: true,
+        },
+        MissedRuns::Skip => Due::SkipMissed {
+            scheduled,
+            missed: schedule
+                .after(&last)
+                .take_while(|t| *t <= now)
+                .take(MISSED_COUNT_CAP)
+                .count(),
+        },
+    }
+}
+
+/// now 或之前最近的一次运行时间
+fn latest_due(schedule: &Schedule, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    // cron 的时间精确到秒，往回找时不含起点
+    let bound = now.with_nanosecond(0)? + Duration::seconds(1);
+    schedule.after(&bound).next_back()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Succeeded,
+    Failed,
+    SkippedOverlap,
+    SkippedMissed,
+    SkippedPaused,
+}
+
+/// 子命令写给守护进程的结构化结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobResult {
+    Sync(SyncStats),
+    Check(CheckReport),
+    Dedupe(DedupeStats),
+}
+
+/// 由守护进程启动时把结果写到 `BSYNC_RESULT_FILE`，否则什么也不做
+pub fn report_result(result: JobResult) {
+    let Some(path) = std::env::var_os(RESULT_ENV) else {
+        return;
+    };
+    let written = serde_json::to_string(&result)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| Ok(std::fs::write(&path, json)?));
+    if let Err(e) = written {
+        warn!("⚠️  Failed to report job result: {}", e);
+    }
+}
+
+/// 一次运行的结果，追加到运行日志
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub job: String,
+    pub task: Task,
+    pub status: RunStatus,
+    pub scheduled_for: DateTime<Local>,
+    pub started_at: DateTime<Local>,
+    pub duration_ms: i64,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub caught_up: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub missed: Option<usize>,
+    /// 文件监视触发时变化的浏览器
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changed: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<JobResult>,
+}
+
+impl RunRecord {
+    fn skipped(job: &JobConfig, status: RunStatus, scheduled: DateTime<Local>) -> Self {
         Self {
-            cron_expression: cron,
-            daemon,
+            job: job.name.clone(),
+            task: job.task,
+            status,
+            scheduled_for: scheduled,
+            started_at: Local::now(),
+            duration_ms: 0,
+            caught_up: false,
+            missed: None,
+            changed: None,
+            exit_code: None,
+            error: None,
+            result: None,
         }
     }
 }
 
-pub async fn start_scheduler(config: SchedulerConfig) -> Result<()> {
-    let mut scheduler = JobScheduler::new().await?;
+pub fn default_runs_log() -> Result<PathBuf> {
+    let home = std::env::var("HOME")?;
+    Ok(PathBuf::from(format!(
+        "{}/.browser-sync/daemon-runs.jsonl",
+        home
+    )))
+}
 
-    info!(
-        "⏰ Scheduler initialized with cron: {}",
-        config.cron_expression
-    );
+fn state_path() -> Result<PathBuf> {
+    let home = std::env::var("HOME")?;
+    Ok(PathBuf::from(format!(
+        "{}/.browser-sync/daemon-state.json",
+        home
+    )))
+}
 
-    let cron_expr = config.cron_expression.clone();
-    let job = Job::new_async(cron_expr.as_str(), move |_uuid, _l| {
-        Box::pin(async move {
-            info!("🔄 Scheduled sync triggered");
-
-            match SyncEngine::new() {
-                Ok(mut engine) => {
-                    // Use incremental mode for scheduled syncs
-                    match engine.sync(SyncMode::Incremental, false, false).await {
-                        Ok(stats) => {
-                            info!("✅ Scheduled sync completed: {} bookmarks synced, {} duplicates removed", 
-                                stats.bookmarks_synced, stats.duplicates_removed);
-                        }
-                        Err(e) => {
-                            error!("❌ Scheduled sync failed: {}", e);
-                        }
-                    }
+/// 每个任务最后检查到的时间，用来发现守护进程停止期间错过的运行
+fn load_state(path: &Path) -> BTreeMap<String, DateTime<Local>> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(path: &Path, state: &BTreeMap<String, DateTime<Local>>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(state)?)?;
+    Ok(())
+}
+
+fn append_record(path: &Path, record: &RunRecord) {
+    let result = (|| -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        warn!("⚠️  Failed to write run log {}: {}", path.display(), e);
+    }
+}
+
+fn log_record(record: &RunRecord) {
+    match record.status {
+        RunStatus::Succeeded => info!(
+            "✅ Job {} ({}) succeeded in {} ms",
+            record.job,
+            record.task.command(),
+            record.duration_ms
+        ),
+        RunStatus::Failed => error!(
+            "❌ Job {} ({}) failed: {}",
+            record.job,
+            record.task.command(),
+            record.error.as_deref().unwrap_or("unknown error")
+        ),
+        RunStatus::SkippedOverlap => warn!(
+            "⏭️  Job {} skipped: previous run still in progress",
+            record.job
+        ),
+        RunStatus::SkippedPaused => info!("⏸️  Job {} skipped: daemon is paused", record.job),
+        RunStatus::SkippedMissed => warn!(
+            "⏭️  Job {} skipped {} missed run(s), last due {}",
+            record.job,
+            record.missed.unwrap_or(1),
+            record.scheduled_for.format("%Y-%m-%d %H:%M")
+        ),
+    }
+}
+
+fn output_tail(output: &[u8]) -> String {
+    let text = String::from_utf8_lossy(output);
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].join("\n")
+}
+
+async fn run_job(
+    exe: PathBuf,
+    job: JobConfig,
+    scheduled: DateTime<Local>,
+    caught_up: bool,
+) -> RunRecord {
+    let started_at = Local::now();
+    info!("🔄 Running job {}: {}", job.name, job.command_line());
+    let result_file =
+        std::env::temp_dir().join(format!("bsync-result-{}.json", uuid::Uuid::new_v4()));
+    let output = tokio::process::Command::new(&exe)
+        .arg(job.task.command())
+        .args(&job.args)
+        .env(RESULT_ENV, &result_file)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await;
+    let result = std::fs::read_to_string(&result_file)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok());
+    let _ = std::fs::remove_file(&result_file);
+
+    let (status, exit_code, error) = match output {
+        Ok(output) if output.status.success() => (RunStatus::Succeeded, output.status.code(), None),
+        Ok(output) => {
+            let tail = match output_tail(&output.stderr) {
+                tail if tail.is_empty() => output_tail(&output.stdout),
+                tail => tail,
+            };
+            (RunStatus::Failed, output.status.code(), Some(tail))
+        }
+        Err(e) => (
+            RunStatus::Failed,
+            None,
+            Some(format!("Failed to start {}: {}", exe.display(), e)),
+        ),
+    };
+
+    RunRecord {
+        job: job.name,
+        task: job.task,
+        status,
+        scheduled_for: scheduled,
+        started_at,
+        duration_ms: (Local::now() - started_at).num_milliseconds(),
+        caught_up,
+        missed: None,
+        changed: None,
+        exit_code,
+        error,
+        result,
+    }
+}
+
+fn watch_job(watch: &WatchConfig) -> JobConfig {
+    JobConfig {
+        name: WATCH_JOB.to_string(),
+        schedule: String::new(),
+        task: watch.task,
+        args: watch.args.clone(),
+        missed_runs: None,
+    }
+}
+
+/// 打印任务和下一次运行时间
+pub fn print_jobs(config: &DaemonConfig) {
+    println!("\n⏰ Scheduled jobs:");
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    for job in &config.jobs {
+        let next = parse_schedule(&job.schedule)
+            .ok()
+            .and_then(|s| s.upcoming(Local).next())
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "never".to_string());
+        println!("  {} [{}] {}", job.name, job.schedule, job.command_line());
+        let missed = match config.missed_runs(job) {
+            MissedRuns::Skip => "skip",
+            MissedRuns::CatchUp => "catch up",
+        };
+        println!("     next: {}, missed runs: {}", next, missed);
+    }
+    if let Some(ref watch) = config.watch {
+        println!(
+            "  {} [on change, {}s debounce] {}",
+            WATCH_JOB,
+            watch.debounce_seconds,
+            watch_job(watch).command_line()
+        );
+        for target in watcher::detect_targets(&watch.browsers) {
+            for file in &target.files {
+                println!("     {}: {}", target.browser, file.display());
+            }
+        }
+    }
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+}
+
+/// 运行中的守护进程：定时任务、文件监视和控制请求都在主循环里处理
+struct Daemon {
+    config_path: PathBuf,
+    config: DaemonConfig,
+    schedules: Vec<(JobConfig, Schedule)>,
+    running: HashMap<String, Arc<AtomicBool>>,
+    last_runs: HashMap<String, RunRecord>,
+    failures: HashMap<String, usize>,
+    state: BTreeMap<String, DateTime<Local>>,
+    state_path: PathBuf,
+    watcher: Option<Watcher>,
+    /// 当前这批任务里有没有会写书签的，全部结束后交给 watcher
+    wrote_bookmarks: bool,
+    paused: bool,
+    started_at: DateTime<Local>,
+    exe: PathBuf,
+    /// 不同任务依次运行
+    queue: Arc<tokio::sync::Mutex<()>>,
+    tasks: JoinSet<RunRecord>,
+    runs_log: PathBuf,
+}
+
+impl Daemon {
+    fn new(config_path: PathBuf, config: DaemonConfig, runs_log: PathBuf) -> Result<Self> {
+        let state_path = state_path()?;
+        let mut daemon = Self {
+            config_path,
+            config: DaemonConfig::default(),
+            schedules: Vec::new(),
+            running: HashMap::new(),
+            last_runs: HashMap::new(),
+            failures: HashMap::new(),
+            state: load_state(&state_path),
+            state_path,
+            watcher: None,
+            wrote_bookmarks: false,
+            paused: false,
+            started_at: Local::now(),
+            exe: std::env::current_exe().context("Failed to locate the bsync executable")?,
+            queue: Arc::new(tokio::sync::Mutex::new(())),
+            tasks: JoinSet::new(),
+            runs_log,
+        };
+        daemon.apply_config(config)?;
+        Ok(daemon)
+    }
+
+    /// 启动和 `reload-config` 时调用；正在运行的任务不受影响
+    fn apply_config(&mut self, config: DaemonConfig) -> Result<()> {
+        let schedules: Vec<(JobConfig, Schedule)> = config
+            .jobs
+            .iter()
+            .map(|job| Ok((job.clone(), parse_schedule(&job.schedule)?)))
+            .collect::<Result<_>>()?;
+
+        // 新任务从现在开始计时，不补跑配置之前的时间
+        let now = Local::now();
+        self.state
+            .retain(|name, _| schedules.iter().any(|(job, _)| &job.name == name));
+        for (job, _) in &schedules {
+            self.state.entry(job.name.clone()).or_insert(now);
+        }
+
+        let names = schedules
+            .iter()
+            .map(|(job, _)| job.name.clone())
+            .chain(config.watch.as_ref().map(|_| WATCH_JOB.to_string()));
+        let mut running = HashMap::new();
+        for name in names {
+            let flag = self.running.get(&name).cloned().unwrap_or_default();
+            running.insert(name, flag);
+        }
+        self.running = running;
+
+        self.watcher = config.watch.as_ref().map(|watch| {
+            let targets = watcher::detect_targets(&watch.browsers);
+            for target in &targets {
+                info!("👀 Watching {} bookmarks", target.browser);
+            }
+            Watcher::new(
+                targets,
+                std::time::Duration::from_secs(watch.debounce_seconds),
+            )
+        });
+        self.schedules = schedules;
+        self.config = config;
+        Ok(())
+    }
+
+    fn record(&mut self, record: RunRecord) {
+        log_record(&record);
+        append_record(&self.runs_log, &record);
+        if record.status == RunStatus::Failed {
+            *self.failures.entry(record.job.clone()).or_default() += 1;
+        }
+        self.last_runs.insert(record.job.clone(), record);
+    }
+
+    /// 启动一次运行；同一任务还在运行或排队时返回 false
+    fn spawn(
+        &mut self,
+        job: JobConfig,
+        scheduled: DateTime<Local>,
+        caught_up: bool,
+        changed: Option<Vec<String>>,
+    ) -> bool {
+        let flag = self.running.entry(job.name.clone()).or_default().clone();
+        if flag.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        self.wrote_bookmarks |= job.writes_bookmarks();
+        if let Some(ref mut watcher) = self.watcher {
+            watcher.begin_job();
+        }
+        let (exe, queue) = (self.exe.clone(), self.queue.clone());
+        self.tasks.spawn(async move {
+            let _turn = queue.lock().await;
+            let record = run_job(exe, job, scheduled, caught_up).await;
+            flag.store(false, Ordering::SeqCst);
+            RunRecord { changed, ..record }
+        });
+        true
+    }
+
+    fn check_schedules(&mut self) {
+        let now = Local::now();
+        for (job, schedule) in self.schedules.clone() {
+            let last = self.state.get(&job.name).copied().unwrap_or(now);
+            let due = check_due(&schedule, last, now, self.config.missed_runs(&job));
+            self.state.insert(job.name.clone(), now);
+
+            let (scheduled, caught_up) = match due {
+                Due::Idle => continue,
+                Due::SkipMissed { scheduled, missed } => {
+                    self.record(RunRecord {
+                        missed: Some(missed),
+                        ..RunRecord::skipped(&job, RunStatus::SkippedMissed, scheduled)
+                    });
+                    continue;
                 }
-                Err(e) => {
-                    error!("❌ Failed to create sync engine: {}", e);
+                Due::Run {
+                    scheduled,
+                    caught_up,
+                } => (scheduled, caught_up),
+            };
+            if self.paused {
+                self.record(RunRecord::skipped(
+                    &job,
+                    RunStatus::SkippedPaused,
+                    scheduled,
+                ));
+                continue;
+            }
+            if caught_up {
+                info!(
+                    "⏪ Catching up job {} (was due {})",
+                    job.name,
+                    scheduled.format("%Y-%m-%d %H:%M")
+                );
+            }
+            if !self.spawn(job.clone(), scheduled, caught_up, None) {
+                self.record(RunRecord::skipped(
+                    &job,
+                    RunStatus::SkippedOverlap,
+                    scheduled,
+                ));
+            }
+        }
+
+        if let Err(e) = save_state(&self.state_path, &self.state) {
+            warn!("⚠️  Failed to save daemon state: {}", e);
+        }
+    }
+
+    fn poll_watcher(&mut self) {
+        // 暂停时不检查，恢复后再发现暂停期间的变化；
+        // 有任务在运行或排队时 watcher 只记录变化，结束后再决定是否触发
+        if self.paused {
+            return;
+        }
+        let Some(ref watch) = self.config.watch else {
+            return;
+        };
+        let Some(changed) = self
+            .watcher
+            .as_mut()
+            .and_then(|w| w.poll(std::time::Instant::now()))
+        else {
+            return;
+        };
+        info!("👀 Bookmarks changed in {}", changed.join(", "));
+        let job = watch_job(watch);
+        self.spawn(job, Local::now(), false, Some(changed));
+    }
+
+    fn finished(&mut self, record: RunRecord) {
+        self.record(record);
+        if self.tasks.is_empty() {
+            if let Some(ref mut watcher) = self.watcher {
+                watcher.end_job(self.wrote_bookmarks, std::time::Instant::now());
+            }
+            self.wrote_bookmarks = false;
+        }
+    }
+
+    fn handle(&mut self, request: Request) -> Response {
+        match request {
+            Request::Status => Response::status(self.status()),
+            Request::Trigger { job } => {
+                let config = if job == WATCH_JOB {
+                    self.config.watch.as_ref().map(watch_job)
+                } else {
+                    self.config.jobs.iter().find(|j| j.name == job).cloned()
+                };
+                let Some(config) = config else {
+                    return Response::error(format!("Unknown job '{}'", job));
+                };
+                info!("▶️  Job {} triggered manually", job);
+                if self.spawn(config, Local::now(), false, None) {
+                    Response::message(format!("Job {} started", job))
+                } else {
+                    Response::error(format!("Job {} is already running", job))
+                }
+            }
+            Request::Pause => {
+                self.paused = true;
+                info!("⏸️  Daemon paused");
+                Response::message("Paused: scheduled and file-watch runs are on hold")
+            }
+            Request::Resume => {
+                self.paused = false;
+                info!("▶️  Daemon resumed");
+                Response::message("Resumed")
+            }
+            Request::ReloadConfig => {
+                match DaemonConfig::load(&self.config_path).and_then(|c| self.apply_config(c)) {
+                    Ok(()) => {
+                        info!("🔁 Reloaded {}", self.config_path.display());
+                        Response::message(format!(
+                            "Reloaded {} jobs{}",
+                            self.config.jobs.len(),
+                            if self.config.watch.is_some() {
+                                " and file watching"
+                            } else {
+                                ""
+                            }
+                        ))
+                    }
+                    Err(e) => {
+                        Response::error(format!("Reload failed, keeping old config: {:#}", e))
+                    }
                 }
             }
-        })
-    })?;
+        }
+    }
 
-    scheduler.add(job).await?;
-    scheduler.start().await?;
+    fn status(&self) -> DaemonStatus {
+        let job_status = |name: &str, task: Task, schedule: String, next_run| JobStatus {
+            name: name.to_string(),
+            task,
+            schedule,
+            next_run,
+            running: self
+                .running
+                .get(name)
+                .is_some_and(|flag| flag.load(Ordering::SeqCst)),
+            last_run: self.last_runs.get(name).cloned(),
+            failures: self.failures.get(name).copied().unwrap_or(0),
+        };
+        let mut jobs: Vec<JobStatus> = self
+            .schedules
+            .iter()
+            .map(|(job, schedule)| {
+                job_status(
+                    &job.name,
+                    job.task,
+                    job.schedule.clone(),
+                    schedule.upcoming(Local).next(),
+                )
+            })
+            .collect();
+        if let Some(ref watch) = self.config.watch {
+            jobs.push(job_status(
+                WATCH_JOB,
+                watch.task,
+                "on change".to_string(),
+                None,
+            ));
+        }
+        DaemonStatus {
+            paused: self.paused,
+            started_at: self.started_at,
+            config_path: self.config_path.display().to_string(),
+            jobs,
+        }
+    }
+}
 
-    if config.daemon {
-        info!("🔄 Running as daemon. Press Ctrl+C to stop.");
-        tokio::signal::ctrl_c().await?;
-        info!("🛑 Shutting down scheduler...");
-    } else {
-        info!("⏰ Scheduler started. Keeping process alive...");
-        tokio::signal::ctrl_c().await?;
+/// 运行守护进程，直到 Ctrl+C
+pub async fn run_daemon(config_path: PathBuf, runs_log: PathBuf) -> Result<()> {
+    let config = DaemonConfig::load(&config_path)?;
+    let mut daemon = Daemon::new(config_path, config, runs_log)?;
+    let socket = control::socket_path()?;
+    let mut requests = control::listen(&socket)?;
+
+    info!(
+        "⏰ Daemon started with {} jobs{}, run log: {}",
+        daemon.schedules.len(),
+        if daemon.config.watch.is_some() {
+            " and file watching"
+        } else {
+            ""
+        },
+        daemon.runs_log.display()
+    );
+    info!("🛰️  Control socket: {}", socket.display());
+    info!("🔄 Running as daemon. Press Ctrl+C to stop.");
+
+    let shutdown = tokio::signal::ctrl_c();
+    tokio::pin!(shutdown);
+    let mut cron_tick = tokio::time::interval(std::time::Duration::from_secs(TICK_SECONDS));
+    let mut watch_tick = tokio::time::interval(std::time::Duration::from_secs(WATCH_POLL_SECONDS));
+    loop {
+        tokio::select! {
+            _ = cron_tick.tick() => daemon.check_schedules(),
+            _ = watch_tick.tick(), if daemon.watcher.is_some() => daemon.poll_watcher(),
+            Some(result) = daemon.tasks.join_next(), if !daemon.tasks.is_empty() => {
+                if let Ok(record) = result {
+                    daemon.finished(record);
+                }
+            }
+            Some((request, reply)) = requests.recv() => {
+                let _ = reply.send(daemon.handle(request));
+            }
+            _ = &mut shutdown => break,
+        }
     }
 
-    scheduler.shutdown().await?;
+    info!("🛑 Shutting down daemon...");
+    let _ = std::fs::remove_file(&socket);
+    if !daemon.tasks.is_empty() {
+        info!("⏳ Waiting for {} running jobs", daemon.tasks.len());
+    }
+    while let Some(result) = daemon.tasks.join_next().await {
+        if let Ok(record) = result {
+            daemon.record(record);
+        }
+    }
+    save_state(&daemon.state_path, &daemon.state)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(h: u32, m: u32, s: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 3, 10, h, m, s).unwrap()
+    }
+
+    #[test]
+    fn test_parse_schedule_accepts_five_fields() {
+        let schedule = parse_schedule("30 3 * * *").unwrap();
+        let next = schedule.after(&at(1, 0, 0)).next().unwrap();
+        assert_eq!(next, at(3, 30, 0));
+
+        assert!(parse_schedule("0 0 3 * * *").is_ok());
+        assert!(parse_schedule("not a schedule").is_err());
+    }
+
+    #[test]
+    fn test_check_due_on_time_and_missed() {
+        let schedule = parse_schedule("0 * * * *").unwrap();
+
+        assert_eq!(
+            check_due(&schedule, at(3, 10, 0), at(3, 40, 0), MissedRuns::Skip),
+            Due::Idle
+        );
+        assert_eq!(
+            check_due(&schedule, at(3, 59, 50), at(4, 0, 10), MissedRuns::Skip),
+            Due::Run {
+                scheduled: at(4, 0, 0),
+                caught_up: false
+            }
+        );
+
+        // 睡眠了几个小时
+        assert_eq!(
+            check_due(&schedule, at(1, 30, 0), at(4, 20, 0), MissedRuns::Skip),
+            Due::SkipMissed {
+                scheduled: at(4, 0, 0),
+                missed: 3
+            }
+        );
+        assert_eq!(
+            check_due(&schedule, at(1, 30, 0), at(4, 20, 0), MissedRuns::CatchUp),
+            Due::Run {
+                scheduled: at(4, 0, 0),
+                caught_up: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_check_due_catches_up_once_after_long_sleep() {
+        // 每秒一次，守护进程停了一个月：最后一次还在宽限期内，照常运行
+        let schedule = parse_schedule("* * * * * *").unwrap();
+        let last = at(4, 0, 0) - Duration::days(30);
+        let now = at(4, 0, 0);
+
+        assert_eq!(
+            check_due(&schedule, last, now, MissedRuns::CatchUp),
+            Due::Run {
+                scheduled: now,
+                caught_up: false
+            }
+        );
+        assert_eq!(
+            check_due(&schedule, last, now, MissedRuns::Skip),
+            Due::Run {
+                scheduled: now,
+                caught_up: false
+            }
+        );
+
+        // 补跑一次后，下一次检查从补跑时开始算
+        let schedule = parse_schedule("0 3 * * *").unwrap();
+        let now = at(12, 0, 0);
+        assert_eq!(
+            check_due(
+                &schedule,
+                now - Duration::days(30),
+                now,
+                MissedRuns::CatchUp
+            ),
+            Due::Run {
+                scheduled: at(3, 0, 0),
+                caught_up: true
+            }
+        );
+        assert_eq!(
+            check_due(
+                &schedule,
+                now,
+                now + Duration::seconds(20),
+                MissedRuns::CatchUp
+            ),
+            Due::Idle
+        );
+    }
+
+    #[test]
+    fn test_check_due_caps_missed_count() {
+        // 每小时一次，停了 60 天（1440 次）
+        let schedule = parse_schedule("0 * * * *").unwrap();
+        assert_eq!(
+            check_due(
+                &schedule,
+                at(4, 0, 0) - Duration::days(60),
+                at(4, 20, 0),
+                MissedRuns::Skip
+            ),
+            Due::SkipMissed {
+                scheduled: at(4, 0, 0),
+                missed: MISSED_COUNT_CAP
+            }
+        );
+    }
+
+    #[test]
+    fn test_check_due_bounds() {
+        let schedule = parse_schedule("0 * * * *").unwrap();
+
+        // 上次检查正好在到期时间：已经处理过
+        assert_eq!(
+            check_due(&schedule, at(4, 0, 0), at(4, 0, 30), MissedRuns::Skip),
+            Due::Idle
+        );
+        // 到期时间正好是现在，或者现在多出不到一秒
+        for now in [at(4, 0, 0), at(4, 0, 0) + Duration::milliseconds(999)] {
+            assert_eq!(
+                check_due(&schedule, at(3, 59, 40), now, MissedRuns::Skip),
+                Due::Run {
+                    scheduled: at(4, 0, 0),
+                    caught_up: false
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_writes_bookmarks() {
+        let job = |task: &str, args: &str| -> JobConfig {
+            serde_json::from_str(&format!(
+                r#"{{ "name": "j", "schedule": "0 3 * * *", "task": "{}", "args": [{}] }}"#,
+                task, args
+            ))
+            .unwrap()
+        };
+        assert!(!job("export", r#""-d""#).writes_bookmarks());
+        assert!(job("export", r#""--clear-after""#).writes_bookmarks());
+        assert!(!job("backup", "").writes_bookmarks());
+        assert!(job("sync", "").writes_bookmarks());
+        assert!(job("history", "").writes_bookmarks());
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let config: DaemonConfig = serde_json::from_str(
+            r#"{
+                "missed_runs": "catch_up",
+                "jobs": [
+                    { "name": "export", "schedule": "0 3 * * *", "task": "export", "args": ["-d"] },
+                    { "name": "backup", "schedule": "0 4 * * SUN", "task": "backup", "missed_runs": "skip" }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.missed_runs(&config.jobs[0]), MissedRuns::CatchUp);
+        assert_eq!(config.missed_runs(&config.jobs[1]), MissedRuns::Skip);
+
+        let mut duplicate = config.clone();
+        duplicate.jobs[1].name = "export".to_string();
+        assert!(duplicate.validate().is_err());
+
+        assert!(serde_json::from_str::<DaemonConfig>(
+            r#"{ "jobs": [{ "name": "x", "schedule": "* * * * *", "task": "launch" }] }"#
+        )
+        .is_err());
+    }
+}
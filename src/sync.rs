@@ -5,12 +5,29 @@ use anyhow::{Context, Result};
 use sha2::{Digest, Sha256};
 use std::collections::{HashMap, HashSet};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tracing::{debug, error, info, warn};
 
+use crate::atomic_write::{self, WriteTarget};
+use crate::bookmark_sort::{sort_bookmarks, SortConfig, SortingAdapter};
 use crate::browsers::{
     get_all_adapters, Bookmark, BrowserAdapter, BrowserType, Cookie, HistoryItem, ReadingListItem,
 };
+use crate::changeset::Changeset;
+use crate::conflicts::{
+    self, Choice, ConflictPolicies, ConflictResolver, ConflictVersion, DataKind,
+};
+use crate::exclusions::{ExcludingAdapter, ExclusionRules};
+use crate::export_filter::ExportFilter;
+use crate::folder_map::{FolderMap, MappingAdapter};
+use crate::folder_merge::{self, FolderSynonyms};
+use crate::keep_policy::{self, DedupeLoss, KeepCandidate, KeepPolicy};
+use crate::near_dupes;
+use crate::scenario::{self, ScenarioMode, ScenarioSpec, ScenarioStats};
+use crate::scheduler::{self, JobResult};
+use crate::three_way::{self, BaseSnapshot};
+use crate::tombstones::{self as tomb, TombstoneKind, TombstoneStore};
+use crate::url_canon::canonicalize;
 use crate::validator::ValidationReport;
 
 /// Configuration for hub browser operations
@@ -40,13 +57,191 @@ pub struct ExportConfig {
     pub wrap_folder: Option<String>,
     /// Disable wrapping (default: false, wrapping is enabled by default)
     pub no_wrap: bool,
+    /// Deterministic output: stable ids, no generated timestamps (diff-friendly)
+    pub deterministic: bool,
+    /// Output file format (HTML or JSON)
+    pub format: ExportFormat,
+    /// Split export into one file per group (output path becomes a directory)
+    pub split: Option<ExportSplit>,
+    /// Date / domain / scheme / title filters (applied before dedupe and flatten)
+    pub filter: ExportFilter,
+    /// Sort the exported tree (applied after flatten, before wrapping)
+    pub sort: Option<SortConfig>,
+    /// Netscape HTML dialect of the browser that will import the file
+    pub dialect: HtmlDialect,
+    /// Which copy of a duplicate survives deduplication
+    pub keep_policy: KeepPolicy,
+}
+
+/// Output format for exported bookmark files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    /// Netscape bookmark HTML (importable by every browser)
+    #[default]
+    Html,
+    /// JSON bookmark tree (same structure as `Bookmark`)
+    Json,
+}
+
+impl ExportFormat {
+    /// Parse from CLI value ("html" / "json")
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "html" | "htm" => Ok(ExportFormat::Html),
+            "json" => Ok(ExportFormat::Json),
+            other => anyhow::bail!("Unknown export format: {} (expected html or json)", other),
+        }
+    }
+
+    /// Guess format from output path extension (defaults to HTML)
+    pub fn from_path(path: &str) -> Self {
+        if path.to_lowercase().ends_with(".json") {
+            ExportFormat::Json
+        } else {
+            ExportFormat::Html
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Html => "html",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// Netscape HTML dialect: attribute set and root layout each browser imports cleanly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HtmlDialect {
+    /// Plain Netscape format (ADD_DATE only), works everywhere
+    #[default]
+    Generic,
+    /// Safari: no date attributes (ignored on import), FOLDED folders
+    Safari,
+    /// Firefox: ADD_DATE + LAST_MODIFIED, toolbar root marked PERSONAL_TOOLBAR_FOLDER
+    Firefox,
+    /// Chromium (Chrome, Brave, Edge): single PERSONAL_TOOLBAR_FOLDER root, so the
+    /// "Imported" folder Chromium creates holds the tree directly
+    Chromium,
+}
+
+impl HtmlDialect {
+    /// Parse from CLI value
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "generic" | "netscape" => Ok(HtmlDialect::Generic),
+            "safari" => Ok(HtmlDialect::Safari),
+            "firefox" | "waterfox" => Ok(HtmlDialect::Firefox),
+            "chromium" | "chrome" | "brave" | "edge" => Ok(HtmlDialect::Chromium),
+            other => anyhow::bail!(
+                "Unknown HTML dialect: {} (expected generic, safari, firefox or chromium)",
+                other
+            ),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HtmlDialect::Generic => "generic",
+            HtmlDialect::Safari => "safari",
+            HtmlDialect::Firefox => "firefox",
+            HtmlDialect::Chromium => "chromium",
+        }
+    }
+}
+
+/// How to split an export into multiple files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportSplit {
+    /// One file per top-level folder (after flattening browser roots)
+    Folder,
+    /// One file per source browser
+    Browser,
+    /// One file per registrable domain (folder structure is kept)
+    Domain,
+}
+
+impl ExportSplit {
+    /// Parse from CLI value ("folder" / "browser" / "domain")
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "folder" | "folders" => Ok(ExportSplit::Folder),
+            "browser" | "browsers" => Ok(ExportSplit::Browser),
+            "domain" | "domains" => Ok(ExportSplit::Domain),
+            other => anyhow::bail!(
+                "Unknown split mode: {} (expected folder, browser or domain)",
+                other
+            ),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportSplit::Folder => "folder",
+            ExportSplit::Browser => "browser",
+            ExportSplit::Domain => "domain",
+        }
+    }
+}
+
+/// All browser adapters as the engine sees them: exclusion rules and folder mappings applied
+///
+/// Exclusions wrap the browser directly (their paths are the browser's own), mappings wrap that.
+pub fn engine_adapters() -> Vec<Box<dyn BrowserAdapter + Send + Sync>> {
+    let exclusions = ExclusionRules::load_default();
+    let folder_map = FolderMap::load_default();
+    get_all_adapters()
+        .into_iter()
+        .map(|adapter| {
+            let browser = adapter.browser_type();
+            let mut adapter = adapter;
+            let rules = exclusions.for_browser(browser);
+            if !rules.is_empty() {
+                info!(
+                    "🔒 {}: exclusion rules active ({} folders, {} URL patterns, {} domains)",
+                    browser.name(),
+                    rules.folders.len(),
+                    rules.urls.len(),
+                    rules.domains.len()
+                );
+                adapter = Box::new(ExcludingAdapter::new(adapter, rules));
+            }
+            let mappings = folder_map.for_browser(browser);
+            if !mappings.is_empty() {
+                for mapping in &mappings {
+                    if mapping.keep_own {
+                        info!(
+                            "🔀 {}: /{} sends to {} only (one-way)",
+                            browser.name(),
+                            mapping.local.join("/"),
+                            mapping.peer
+                        );
+                    } else {
+                        info!(
+                            "🔀 {}: /{} ⇄ {}:/{}",
+                            browser.name(),
+                            mapping.local.join("/"),
+                            mapping.peer,
+                            mapping.shared.join("/")
+                        );
+                    }
+                }
+                adapter = Box::new(MappingAdapter::new(adapter, mappings));
+            }
+            adapter
+        })
+        .collect()
 }
 
 /// Location information for a bookmark in the tree
-struct BookmarkLocation {
+pub(crate) struct BookmarkLocation {
     path: BookmarkPath, // Vector of indices representing the path in the tree
     depth: usize,
     date_added: Option<i64>,
+    /// Titles of the enclosing folders, root first
+    folders: Vec<String>,
+    title: String,
+    url: String,
 }
 
 /// Path to a bookmark in the tree (sequence of indices)
@@ -62,29 +257,109 @@ pub enum SyncMode {
 
 /// Sync statistics (reserved for future sync feature)
 #[allow(dead_code)]
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct SyncStats {
     pub bookmarks_synced: usize,
     pub duplicates_removed: usize,
     pub conflicts_resolved: usize,
+    /// 读取、备份、写入和写入后校验的失败数（未安装的浏览器不读不写，也不计）
     pub errors: usize,
+    /// 三方合并应用的变更数
+    pub changes_added: usize,
+    pub changes_deleted: usize,
+    pub changes_edited: usize,
+    pub changes_moved: usize,
+    /// 去重移除的每一份书签及原因
+    #[serde(default)]
+    pub dedupe_losses: Vec<DedupeLoss>,
 }
 
 pub struct SyncEngine {
     adapters: Vec<Box<dyn BrowserAdapter + Send + Sync>>,
-    #[allow(dead_code)]
-    last_sync_time: Option<i64>,
+    tombstones: TombstoneStore,
+    /// Interior mutability: merges run behind `&self` while adapters are borrowed
+    conflicts: std::sync::Mutex<ConflictResolver>,
+    /// 计划模式：预演时把结果记录成变更集而不是只打印
+    plan: Option<Changeset>,
+    /// 去重时保留哪一份
+    keep_policy: KeepPolicy,
+    /// 合并后把名称相似的同级文件夹合并（None = 不合并）
+    folder_synonyms: Option<FolderSynonyms>,
 }
 
 impl SyncEngine {
     pub fn new() -> Result<Self> {
-        let adapters = get_all_adapters();
         Ok(Self {
-            adapters,
-            last_sync_time: None,
+            adapters: engine_adapters(),
+            tombstones: TombstoneStore::load_default(),
+            conflicts: std::sync::Mutex::new(ConflictResolver::load_default()),
+            plan: None,
+            keep_policy: KeepPolicy::default(),
+            folder_synonyms: None,
         })
     }
 
+    /// 测试用：指定适配器，墓碑和冲突策略不读写用户目录
+    #[cfg(test)]
+    pub(crate) fn with_adapters(adapters: Vec<Box<dyn BrowserAdapter + Send + Sync>>) -> Self {
+        Self {
+            adapters,
+            tombstones: TombstoneStore::default(),
+            conflicts: std::sync::Mutex::new(ConflictResolver::default()),
+            plan: None,
+            keep_policy: KeepPolicy::default(),
+            folder_synonyms: None,
+        }
+    }
+
+    pub fn set_keep_policy(&mut self, policy: KeepPolicy) {
+        if policy != KeepPolicy::default() {
+            info!("🧷 Keep policy: {}", policy.description());
+        }
+        self.keep_policy = policy;
+    }
+
+    /// Merge similar sibling folders (`Dev`, `💻 dev`, `开发`) in the merged tree during sync
+    pub fn set_folder_synonyms(&mut self, synonyms: FolderSynonyms) {
+        self.folder_synonyms = Some(synonyms);
+    }
+
+    /// Record dry-run results of the next command into a changeset
+    pub fn start_plan(&mut self, command: &str) {
+        self.plan = Some(Changeset::new(command));
+    }
+
+    pub fn take_plan(&mut self) -> Option<Changeset> {
+        self.plan.take()
+    }
+
+    /// Override the conflict policies loaded from `~/.browser-sync/conflict_policy.json`
+    pub fn set_conflict_policies(&mut self, policies: ConflictPolicies) {
+        self.resolver().policies = policies;
+    }
+
+    pub fn conflict_policies(&self) -> ConflictPolicies {
+        self.resolver().policies.clone()
+    }
+
+    fn resolver(&self) -> std::sync::MutexGuard<'_, ConflictResolver> {
+        self.conflicts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Sort bookmark trees before every `write_bookmarks` call
+    pub fn set_write_sort(&mut self, sort: SortConfig) {
+        if sort.is_noop() {
+            return;
+        }
+        let adapters = std::mem::take(&mut self.adapters);
+        self.adapters = adapters
+            .into_iter()
+            .map(|adapter| {
+                Box::new(SortingAdapter::new(adapter, sort.clone())) as Box<dyn BrowserAdapter + Send + Sync>
+            })
+            .collect();
+    }
+
     /// Get Safari reading list items
     pub fn get_safari_reading_list(&self) -> Result<Vec<ReadingListItem>> {
         for adapter in &self.adapters {
@@ -149,37 +424,60 @@ impl SyncEngine {
         Ok(all_cookies)
     }
 
-    /// Load last sync timestamp from state file
-    fn load_last_sync_time(&mut self) -> Result<()> {
-        let state_file = Self::get_state_file_path()?;
-        if state_file.exists() {
-            let content = std::fs::read_to_string(&state_file)?;
-            if let Ok(timestamp) = content.trim().parse::<i64>() {
-                self.last_sync_time = Some(timestamp);
-                debug!("Loaded last sync time: {}", timestamp);
+    /// Record bookmarks that disappeared from a browser since the last run as tombstones
+    fn observe_bookmark_deletions(&mut self, browser_bookmarks: &HashMap<BrowserType, Vec<Bookmark>>) {
+        let now = chrono::Utc::now().timestamp_millis();
+        for (browser, bookmarks) in Self::sorted_by_browser(browser_bookmarks) {
+            let stats = self
+                .tombstones
+                .observe_bookmarks(browser.name(), bookmarks, now);
+            if stats.created > 0 || stats.revived > 0 {
+                info!(
+                    "🪦 {} : {} new deletions, {} re-added",
+                    browser.name(),
+                    stats.created,
+                    stats.revived
+                );
             }
         }
-        Ok(())
     }
 
-    /// Save current sync timestamp to state file
-    fn save_sync_time(&self) -> Result<()> {
-        let state_file = Self::get_state_file_path()?;
-        if let Some(parent) = state_file.parent() {
-            std::fs::create_dir_all(parent)?;
+    /// Save the base snapshot for the next three-way sync
+    ///
+    /// Each browser in `written` is re-read after writing so the base matches what the
+    /// browser actually stored; if the read fails the merged tree is used instead.
+    /// Browsers that were not written this run keep their previous base.
+    fn save_base_snapshot(
+        &self,
+        path: &Path,
+        merged: &[Bookmark],
+        written: &HashSet<BrowserType>,
+    ) -> Result<()> {
+        let mut browsers = match BaseSnapshot::load(path) {
+            Ok(Some(previous)) => previous.browsers,
+            _ => std::collections::BTreeMap::new(),
+        };
+        for adapter in self
+            .adapters
+            .iter()
+            .filter(|a| written.contains(&a.browser_type()))
+        {
+            let name = adapter.browser_type().name().to_string();
+            let tree = adapter.read_bookmarks().unwrap_or_else(|e| {
+                warn!("⚠️  Failed to re-read {} for base snapshot: {}", name, e);
+                merged.to_vec()
+            });
+            browsers.insert(name, tree);
+        }
+        BaseSnapshot::new(merged.to_vec(), browsers).save(path)?;
+
+        // 旧版时间戳状态文件已被快照取代
+        if let Ok(legacy) = BaseSnapshot::legacy_state_path() {
+            let _ = std::fs::remove_file(legacy);
         }
-        let timestamp = chrono::Utc::now().timestamp_millis();
-        std::fs::write(&state_file, timestamp.to_string())?;
-        debug!("Saved sync time: {}", timestamp);
         Ok(())
     }
 
-    /// Get state file path
-    fn get_state_file_path() -> Result<PathBuf> {
-        let home = std::env::var("HOME")?;
-        Ok(PathBuf::from(format!("{}/.browser-sync/last_sync", home)))
-    }
-
     pub async fn sync(
         &mut self,
         mode: SyncMode,
@@ -188,21 +486,24 @@ impl SyncEngine {
     ) -> Result<SyncStats> {
         let mut stats = SyncStats::default();
 
-        // Load last sync time for incremental mode
-        if mode == SyncMode::Incremental {
-            let _ = self.load_last_sync_time();
-            if let Some(last_sync) = self.last_sync_time {
-                info!(
-                    "🔄 Incremental sync mode (last sync: {})",
-                    chrono::DateTime::from_timestamp_millis(last_sync)
-                        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
-                        .unwrap_or_else(|| "unknown".to_string())
-                );
-            } else {
-                info!("🔄 First sync - performing full sync");
-            }
-        } else {
-            info!("🔄 Full sync mode");
+        let snapshot_path = BaseSnapshot::default_path()?;
+        let base = match mode {
+            SyncMode::Incremental => match BaseSnapshot::load(&snapshot_path) {
+                Ok(base) => base,
+                Err(e) => {
+                    warn!("⚠️  Ignoring unreadable base snapshot: {}", e);
+                    None
+                }
+            },
+        };
+        match base {
+            Some(ref base) => info!(
+                "🔄 Three-way sync (base snapshot: {})",
+                chrono::DateTime::from_timestamp_millis(base.saved_at)
+                    .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_else(|| "unknown".to_string())
+            ),
+            None => info!("🔄 First sync - no base snapshot, performing full merge"),
         }
 
         info!("🔍 Phase 1: Pre-sync validation");
@@ -211,7 +512,8 @@ impl SyncEngine {
         info!("📖 Phase 2: Reading bookmarks from all browsers");
         let mut browser_bookmarks = HashMap::new();
 
-        for adapter in &self.adapters {
+        // Browsers that are not installed are skipped, not counted as errors
+        for adapter in self.adapters.iter().filter(|a| a.is_installed()) {
             let browser_type = adapter.browser_type();
             match adapter.read_bookmarks() {
                 Ok(bookmarks) => {
@@ -234,21 +536,33 @@ impl SyncEngine {
             error!("❌ No bookmarks could be read from any browser");
             anyhow::bail!("No bookmarks available for synchronization");
         }
+        self.observe_bookmark_deletions(&browser_bookmarks);
 
-        info!("🧹 Phase 3: Pre-merge deduplication (smart selection)");
         let before_dedup = browser_bookmarks
             .values()
             .map(|b| Self::count_all_bookmarks(b))
             .sum::<usize>();
 
-        // Smart deduplication for each browser (depth > date > root)
-        for (browser_type, bookmarks) in browser_bookmarks.iter_mut() {
-            let before = Self::count_all_bookmarks(bookmarks);
-            Self::deduplicate_bookmarks_global(bookmarks);
-            let after = Self::count_all_bookmarks(bookmarks);
-            let removed = before.saturating_sub(after);
-            if removed > 0 && verbose {
-                debug!("  {} : removed {} duplicates", browser_type.name(), removed);
+        // Three-way merge diffs the raw trees against the base; deduping them first
+        // would show up as deletions and moves that never happened
+        if base.is_some() {
+            info!("🧹 Phase 3: Pre-merge deduplication skipped (three-way merge)");
+        } else {
+            info!("🧹 Phase 3: Pre-merge deduplication (smart selection)");
+            // Smart deduplication for each browser (keep policy, default depth > date)
+            for (browser_type, bookmarks) in browser_bookmarks.iter_mut() {
+                let before = Self::count_all_bookmarks(bookmarks);
+                let losses = Self::deduplicate_bookmarks_global(
+                    bookmarks,
+                    &self.keep_policy,
+                    Some(browser_type.name()),
+                );
+                stats.dedupe_losses.extend(losses);
+                let after = Self::count_all_bookmarks(bookmarks);
+                let removed = before.saturating_sub(after);
+                if removed > 0 && verbose {
+                    debug!("  {} : removed {} duplicates", browser_type.name(), removed);
+                }
             }
         }
 
@@ -267,13 +581,35 @@ impl SyncEngine {
         }
 
         info!("🔄 Phase 4: Merging bookmarks");
-        let mut merged = self.merge_bookmarks(&browser_bookmarks, verbose)?;
+        let mut merged = match base {
+            Some(ref base) => {
+                let current = browser_bookmarks
+                    .iter()
+                    .map(|(browser_type, tree)| (browser_type.name().to_string(), tree.clone()))
+                    .collect();
+                let (mut merged, changes) =
+                    three_way::merge_three_way(base, &current, &mut self.resolver());
+                stats.changes_added = changes.added;
+                stats.changes_deleted = changes.deleted;
+                stats.changes_edited = changes.edited;
+                stats.changes_moved = changes.moved + changes.folders_moved;
+                self.tombstones.filter_bookmarks(&mut merged);
+                merged
+            }
+            None => self.merge_bookmarks(&browser_bookmarks, verbose)?,
+        };
+        if let Some(ref synonyms) = self.folder_synonyms {
+            info!("📁 Phase 4b: Merging similar folders");
+            let merges = folder_merge::merge_similar_folders(&mut merged, synonyms);
+            folder_merge::print_merges(&merges, None);
+        }
         let merged_count = Self::count_all_bookmarks(&merged);
         info!("📊 Merged result: {} unique bookmarks", merged_count);
 
         info!("🧹 Phase 5: Post-merge deduplication (final cleanup)");
         let before_final_dedup = Self::count_all_bookmarks(&merged);
-        Self::deduplicate_bookmarks_global(&mut merged);
+        let losses = Self::deduplicate_bookmarks_global(&mut merged, &self.keep_policy, None);
+        stats.dedupe_losses.extend(losses);
         let after_final_dedup = Self::count_all_bookmarks(&merged);
 
         let final_dedup_count = before_final_dedup.saturating_sub(after_final_dedup);
@@ -304,12 +640,41 @@ impl SyncEngine {
         if dry_run {
             info!("🏃 Dry run mode - no changes will be made");
             self.print_sync_preview(&browser_bookmarks, &merged);
+            if let Some(plan) = self.plan.as_mut() {
+                // 只为读取成功的浏览器记录变更；首次同步时 browser_bookmarks 已去重，
+                // 计划需要浏览器里的原始树
+                for adapter in self
+                    .adapters
+                    .iter()
+                    .filter(|a| browser_bookmarks.contains_key(&a.browser_type()))
+                {
+                    match adapter.read_bookmarks() {
+                        Ok(before) => {
+                            plan.record(adapter.as_ref(), &before, &merged)?;
+                        }
+                        Err(e) => warn!(
+                            "⚠️  Skipping {} in the plan: {}",
+                            adapter.browser_type().name(),
+                            e
+                        ),
+                    }
+                }
+            }
+            stats.conflicts_resolved = self.resolver().finish_report("sync", true);
             self.print_sync_stats(&stats);
             return Ok(stats);
         }
 
+        // Only browsers that were read are written: an unreadable browser's own
+        // bookmarks are not part of the merge and would be overwritten
+        let targets: Vec<&Box<dyn BrowserAdapter + Send + Sync>> = self
+            .adapters
+            .iter()
+            .filter(|a| browser_bookmarks.contains_key(&a.browser_type()))
+            .collect();
+
         info!("💾 Phase 6: Creating backups");
-        for adapter in &self.adapters {
+        for adapter in &targets {
             match adapter.backup_bookmarks() {
                 Ok(backup_path) => {
                     info!(
@@ -330,11 +695,15 @@ impl SyncEngine {
         }
 
         info!("✍️  Phase 7: Writing merged bookmarks");
-        for adapter in &self.adapters {
+        let mut written = HashSet::new();
+        for adapter in &targets {
             let browser_type = adapter.browser_type();
             match adapter.write_bookmarks(&merged) {
                 Ok(_) => {
                     info!("✅ Wrote bookmarks to {}", browser_type.name());
+                    written.insert(browser_type);
+                    self.tombstones
+                        .record_bookmarks(browser_type.name(), &merged);
                 }
                 Err(e) => {
                     error!(
@@ -356,10 +725,17 @@ impl SyncEngine {
             }
         }
 
-        // Save sync time
-        if let Err(e) = self.save_sync_time() {
-            warn!("⚠️  Failed to save sync time: {}", e);
+        if let Err(e) = self.tombstones.save() {
+            warn!("⚠️  Failed to save tombstones: {}", e);
+        }
+
+        // Save base snapshot for the next three-way sync
+        if written.is_empty() {
+            warn!("⚠️  No browser was written, keeping previous base snapshot");
+        } else if let Err(e) = self.save_base_snapshot(&snapshot_path, &merged, &written) {
+            warn!("⚠️  Failed to save base snapshot: {}", e);
         }
+        stats.conflicts_resolved = self.resolver().finish_report("sync", false);
 
         self.print_sync_stats(&stats);
 
@@ -371,7 +747,12 @@ impl SyncEngine {
         println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
         println!("  Bookmarks synced:     {}", stats.bookmarks_synced);
         println!("  Duplicates removed:   {}", stats.duplicates_removed);
+        keep_policy::print_losses(&stats.dedupe_losses, Some(0));
         println!("  Conflicts resolved:   {}", stats.conflicts_resolved);
+        println!(
+            "  Changes applied:      +{} -{} ~{} ↷{}",
+            stats.changes_added, stats.changes_deleted, stats.changes_edited, stats.changes_moved
+        );
         println!("  Errors encountered:   {}", stats.errors);
         println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
     }
@@ -417,7 +798,7 @@ impl SyncEngine {
             expected_count, expected_folders
         );
 
-        for adapter in &self.adapters {
+        for adapter in self.adapters.iter().filter(|a| a.is_installed()) {
             let browser_name = adapter.browser_type().name();
 
             match adapter.read_bookmarks() {
@@ -498,7 +879,7 @@ impl SyncEngine {
             if bookmark.folder {
                 Self::check_duplicates_recursive(&bookmark.children, url_set, duplicate_count);
             } else if let Some(ref url) = bookmark.url {
-                let normalized = Self::normalize_url(url);
+                let normalized = canonicalize(url);
                 if !url_set.insert(normalized) {
                     *duplicate_count += 1;
                 }
@@ -511,11 +892,13 @@ impl SyncEngine {
         browser_bookmarks: &HashMap<BrowserType, Vec<Bookmark>>,
         verbose: bool,
     ) -> Result<Vec<Bookmark>> {
+        let resolutions = self.resolve_bookmark_conflicts(browser_bookmarks);
+
         // Find the browser with the best folder structure (most folders + most bookmarks)
         let mut best_browser: Option<BrowserType> = None;
         let mut best_score = 0i64;
 
-        for (browser, bookmarks) in browser_bookmarks {
+        for (browser, bookmarks) in Self::sorted_by_browser(browser_bookmarks) {
             let url_count = Self::count_all_bookmarks(bookmarks);
             let folder_count = Self::count_all_folders(bookmarks);
             // Score: folders are worth 1000x more than URLs (prefer structure)
@@ -560,6 +943,12 @@ impl SyncEngine {
             Vec::new()
         };
 
+        // Deleted elsewhere: don't resurrect from the base browser
+        self.tombstones.filter_bookmarks(&mut merged);
+
+        // Moved elsewhere: the new location wins over the dedupe depth heuristic
+        self.propagate_moves(browser_bookmarks, &mut merged);
+
         // 🔧 Phase 1: Clean up empty folders and invalid names
         info!("🧹 Phase 1: Cleaning up empty folders...");
         let empty_removed = Self::cleanup_empty_folders(&mut merged);
@@ -579,7 +968,7 @@ impl SyncEngine {
         let before_count = Self::count_all_bookmarks(&merged);
 
         // Global deduplication - track all URLs across entire tree with smart selection
-        Self::deduplicate_bookmarks_global(&mut merged);
+        Self::deduplicate_bookmarks_global(&mut merged, &self.keep_policy, None);
 
         let after_count = Self::count_all_bookmarks(&merged);
 
@@ -598,54 +987,268 @@ impl SyncEngine {
             info!("✨ Cleanup complete: removed {} items total", total_removed);
         }
 
+        if !resolutions.is_empty() {
+            info!(
+                "⚖️  Resolved {} title conflicts between browsers",
+                resolutions.len()
+            );
+            Self::apply_bookmark_resolutions(&mut merged, &resolutions, &mut HashSet::new());
+        }
+
         Ok(merged)
     }
 
-    /// Recursively deduplicate bookmarks with smart selection
-    /// Priority: 1. Deeper in folder structure, 2. Newer bookmarks, 3. Root level keeps newest
-    fn deduplicate_bookmarks_global(bookmarks: &mut Vec<Bookmark>) {
+    /// Apply bookmark and folder moves detected against the base snapshot to the merged tree
+    fn propagate_moves(
+        &self,
+        browser_bookmarks: &HashMap<BrowserType, Vec<Bookmark>>,
+        merged: &mut Vec<Bookmark>,
+    ) {
+        let base = match BaseSnapshot::default_path().and_then(|p| BaseSnapshot::load(&p)) {
+            Ok(Some(base)) => base,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("⚠️  Ignoring unreadable base snapshot: {}", e);
+                return;
+            }
+        };
+        let current = browser_bookmarks
+            .iter()
+            .map(|(browser, tree)| (browser.name().to_string(), tree.clone()))
+            .collect();
+        let moves = three_way::detect_moves(&base, &current);
+        if moves.is_empty() {
+            return;
+        }
+        let stats = three_way::apply_changes(merged, &moves, &mut self.resolver());
+        info!(
+            "↷ Propagated {} bookmark moves, {} folder moves/renames",
+            stats.moved, stats.folders_moved
+        );
+    }
+
+    /// Find URLs whose title differs between browsers and resolve them by the bookmark policy
+    fn resolve_bookmark_conflicts(
+        &self,
+        browser_bookmarks: &HashMap<BrowserType, Vec<Bookmark>>,
+    ) -> HashMap<String, (Choice, Vec<ConflictVersion>)> {
+        fn collect(
+            nodes: &[Bookmark],
+            browser: &str,
+            path: &mut Vec<String>,
+            out: &mut HashMap<String, Vec<ConflictVersion>>,
+        ) {
+            for node in nodes {
+                if node.folder {
+                    path.push(node.title.clone());
+                    collect(&node.children, browser, path, out);
+                    path.pop();
+                } else if let Some(ref url) = node.url {
+                    if conflicts::conflict_copy_browser(&node.title).is_some() {
+                        continue;
+                    }
+                    let versions = out.entry(canonicalize(url)).or_default();
+                    if versions.iter().any(|v| v.browser == browser) {
+                        continue;
+                    }
+                    versions.push(ConflictVersion {
+                        browser: browser.to_string(),
+                        title: node.title.clone(),
+                        detail: Some(format!("/{}", path.join("/"))),
+                        modified: node.date_modified.or(node.date_added),
+                    });
+                }
+            }
+        }
+
+        let mut by_url = HashMap::new();
+        for (browser, bookmarks) in Self::sorted_by_browser(browser_bookmarks) {
+            collect(bookmarks, browser.name(), &mut Vec::new(), &mut by_url);
+        }
+
+        let mut keys: Vec<String> = by_url
+            .iter()
+            .filter(|(_, versions)| {
+                let titles: HashSet<&str> = versions.iter().map(|v| v.title.trim()).collect();
+                titles.len() > 1
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.sort();
+
+        let mut resolver = self.resolver();
+        keys.into_iter()
+            .map(|key| {
+                let versions = by_url.remove(&key).unwrap_or_default();
+                let choice = resolver.resolve(DataKind::Bookmarks, &key, versions.clone());
+                (key, (choice, versions))
+            })
+            .collect()
+    }
+
+    /// Apply resolved titles to the merged tree; keep-both inserts suffixed copies next to the bookmark
+    fn apply_bookmark_resolutions(
+        bookmarks: &mut Vec<Bookmark>,
+        resolutions: &HashMap<String, (Choice, Vec<ConflictVersion>)>,
+        applied: &mut HashSet<String>,
+    ) {
+        let mut index = 0;
+        while index < bookmarks.len() {
+            if bookmarks[index].folder {
+                Self::apply_bookmark_resolutions(&mut bookmarks[index].children, resolutions, applied);
+                index += 1;
+                continue;
+            }
+            let resolution = bookmarks[index]
+                .url
+                .as_deref()
+                .filter(|_| conflicts::conflict_copy_browser(&bookmarks[index].title).is_none())
+                .map(canonicalize)
+                .and_then(|key| resolutions.get(&key).map(|r| (key, r)));
+            let Some((key, (choice, versions))) = resolution else {
+                index += 1;
+                continue;
+            };
+            if !applied.insert(key) {
+                index += 1;
+                continue;
+            }
+
+            match choice {
+                Choice::Version(i) => {
+                    if let Some(version) = versions.get(*i) {
+                        bookmarks[index].title = version.title.clone();
+                        if version.modified.is_some() {
+                            bookmarks[index].date_modified = version.modified;
+                        }
+                    }
+                }
+                Choice::KeepBoth => {
+                    let kept = bookmarks[index].title.trim().to_string();
+                    let mut seen: HashSet<String> = HashSet::from([kept]);
+                    let copies: Vec<Bookmark> = versions
+                        .iter()
+                        .filter(|v| seen.insert(v.title.trim().to_string()))
+                        .map(|v| Bookmark {
+                            id: format!("conflict-{}", uuid::Uuid::new_v4()),
+                            title: conflicts::conflict_copy_title(&v.title, &v.browser),
+                            ..bookmarks[index].clone()
+                        })
+                        .collect();
+                    let count = copies.len();
+                    bookmarks.splice(index + 1..index + 1, copies);
+                    index += count;
+                }
+            }
+            index += 1;
+        }
+    }
+
+    /// Dedupe key: keep-both copies are kept apart from the bookmark they were copied from
+    pub(crate) fn dedupe_key(bookmark: &Bookmark, url: &str) -> String {
+        let normalized = canonicalize(url);
+        match conflicts::conflict_copy_browser(&bookmark.title) {
+            // Canonical keys drop fragments unless configured otherwise, and no real
+            // fragment looks like `conflict:<browser>`
+            Some(browser) => format!("{}#conflict:{}", normalized, browser),
+            None => normalized,
+        }
+    }
+
+    /// Recursively deduplicate bookmarks, keeping one copy per URL chosen by the keep policy
+    /// (default: 1. Deeper in folder structure, 2. Newer bookmarks)
+    /// `browser` names the source browser; without it the top-level folder title is used
+    /// Returns every removed copy with the rule it lost on
+    fn deduplicate_bookmarks_global(
+        bookmarks: &mut Vec<Bookmark>,
+        policy: &KeepPolicy,
+        browser: Option<&str>,
+    ) -> Vec<DedupeLoss> {
+        Self::deduplicate_bookmarks_by_key(bookmarks, policy, browser, &Self::dedupe_key)
+    }
+
+    /// Same as `deduplicate_bookmarks_global`, with a custom dedupe key (bookmark, url) → key
+    pub(crate) fn deduplicate_bookmarks_by_key(
+        bookmarks: &mut Vec<Bookmark>,
+        policy: &KeepPolicy,
+        browser: Option<&str>,
+        key: &dyn Fn(&Bookmark, &str) -> String,
+    ) -> Vec<DedupeLoss> {
         // Two-pass strategy:
         // Pass 1: Collect all bookmarks with their metadata
         // Pass 2: For each URL, decide which one to keep, mark others for deletion
         let mut url_map: HashMap<String, Vec<BookmarkLocation>> = HashMap::new();
-        Self::collect_all_bookmarks(bookmarks, &mut url_map, 0, &[]);
+        Self::collect_locations(bookmarks, &mut url_map, &[], &[], key);
 
         // Determine which bookmark to keep for each URL
         let mut urls_to_keep: HashMap<String, BookmarkPath> = HashMap::new();
+        let mut losses = Vec::new();
         for (url, locations) in url_map.iter() {
             if locations.len() > 1 {
-                // Find the best bookmark according to priority rules
-                let best = Self::select_best_bookmark(locations);
-                urls_to_keep.insert(url.clone(), best.path.clone());
+                let (best, losers) = Self::select_best_bookmark(locations, policy, browser);
+                urls_to_keep.insert(url.clone(), locations[best].path.clone());
+                for (index, reason) in losers {
+                    let loser = &locations[index];
+                    losses.push(DedupeLoss {
+                        url: loser.url.clone(),
+                        title: loser.title.clone(),
+                        folder: loser.folders.join("/"),
+                        kept_folder: locations[best].folders.join("/"),
+                        reason: reason.to_string(),
+                    });
+                }
             }
         }
+        losses.sort_by(|a, b| a.url.cmp(&b.url).then_with(|| a.folder.cmp(&b.folder)));
 
         // Pass 2: Remove duplicates based on decision
-        Self::remove_duplicates_by_path(bookmarks, &urls_to_keep, &[]);
+        Self::remove_duplicates_by_path(bookmarks, &urls_to_keep, &[], key);
+        losses
     }
 
     /// Collect all bookmarks with their locations and metadata
-    fn collect_all_bookmarks(
+    pub(crate) fn collect_all_bookmarks(
         bookmarks: &[Bookmark],
         url_map: &mut HashMap<String, Vec<BookmarkLocation>>,
-        depth: usize,
+        folders: &[String],
+        parent_path: &[usize],
+    ) {
+        Self::collect_locations(bookmarks, url_map, folders, parent_path, &Self::dedupe_key);
+    }
+
+    fn collect_locations(
+        bookmarks: &[Bookmark],
+        url_map: &mut HashMap<String, Vec<BookmarkLocation>>,
+        folders: &[String],
         parent_path: &[usize],
+        key: &dyn Fn(&Bookmark, &str) -> String,
     ) {
         for (index, bookmark) in bookmarks.iter().enumerate() {
             if bookmark.folder {
                 // Recurse into folder
                 let mut current_path = parent_path.to_vec();
                 current_path.push(index);
-                Self::collect_all_bookmarks(&bookmark.children, url_map, depth + 1, &current_path);
+                let mut current_folders = folders.to_vec();
+                current_folders.push(bookmark.title.clone());
+                Self::collect_locations(
+                    &bookmark.children,
+                    url_map,
+                    &current_folders,
+                    &current_path,
+                    key,
+                );
             } else if let Some(ref url) = bookmark.url {
-                let normalized = Self::normalize_url(url);
+                let normalized = key(bookmark, url);
                 let mut current_path = parent_path.to_vec();
                 current_path.push(index);
 
                 let location = BookmarkLocation {
                     path: current_path,
-                    depth,
+                    depth: folders.len(),
                     date_added: bookmark.date_added,
+                    folders: folders.to_vec(),
+                    title: bookmark.title.clone(),
+                    url: url.clone(),
                 };
 
                 url_map.entry(normalized).or_default().push(location);
@@ -653,27 +1256,23 @@ impl SyncEngine {
         }
     }
 
-    /// Select the best bookmark from duplicates
-    /// Rules:
-    /// 1. Prefer bookmarks in deeper folder structure
-    /// 2. If same depth, prefer newer (larger date_added)
-    /// 3. If depth=0 for all, prefer newest
-    fn select_best_bookmark(locations: &[BookmarkLocation]) -> &BookmarkLocation {
-        locations
+    /// Select the best bookmark from duplicates with the keep policy
+    /// Returns the index of the kept location and the losers with the rule each lost on
+    fn select_best_bookmark(
+        locations: &[BookmarkLocation],
+        policy: &KeepPolicy,
+        browser: Option<&str>,
+    ) -> (usize, Vec<(usize, &'static str)>) {
+        let candidates: Vec<KeepCandidate> = locations
             .iter()
-            .max_by(|a, b| {
-                // Compare depth first (higher is better)
-                match a.depth.cmp(&b.depth) {
-                    std::cmp::Ordering::Equal => {
-                        // Same depth, compare date (newer is better)
-                        let a_date = a.date_added.unwrap_or(0);
-                        let b_date = b.date_added.unwrap_or(0);
-                        a_date.cmp(&b_date)
-                    }
-                    other => other,
-                }
+            .map(|location| KeepCandidate {
+                depth: location.depth,
+                date_added: location.date_added,
+                folders: &location.folders,
+                browser: browser.or(location.folders.first().map(String::as_str)),
             })
-            .unwrap()
+            .collect();
+        policy.select(&candidates)
     }
 
     /// Remove duplicates by keeping only the specified paths
@@ -681,6 +1280,7 @@ impl SyncEngine {
         bookmarks: &mut Vec<Bookmark>,
         urls_to_keep: &HashMap<String, BookmarkPath>,
         parent_path: &[usize],
+        key: &dyn Fn(&Bookmark, &str) -> String,
     ) {
         // First, recursively process children
         for (index, bookmark) in bookmarks.iter_mut().enumerate() {
@@ -691,6 +1291,7 @@ impl SyncEngine {
                     &mut bookmark.children,
                     urls_to_keep,
                     &current_path,
+                    key,
                 );
             }
         }
@@ -700,7 +1301,7 @@ impl SyncEngine {
         for (index, bookmark) in bookmarks.iter().enumerate() {
             if !bookmark.folder {
                 if let Some(ref url) = bookmark.url {
-                    let normalized = Self::normalize_url(url);
+                    let normalized = key(bookmark, url);
                     if let Some(keep_path) = urls_to_keep.get(&normalized) {
                         // This URL has duplicates, check if this is the one to keep
                         let mut current_path = parent_path.to_vec();
@@ -721,20 +1322,6 @@ impl SyncEngine {
         }
     }
 
-    /// Normalize URL for deduplication comparison
-    fn normalize_url(url: &str) -> String {
-        let mut normalized = url.trim().to_lowercase();
-        // Remove trailing slash
-        if normalized.ends_with('/') {
-            normalized.pop();
-        }
-        // Remove fragment
-        if let Some(pos) = normalized.find('#') {
-            normalized.truncate(pos);
-        }
-        normalized
-    }
-
     fn count_all_folders(bookmarks: &[Bookmark]) -> usize {
         let mut count = 0;
         for b in bookmarks {
@@ -895,9 +1482,42 @@ impl SyncEngine {
         removed_count
     }
 
+    /// Iterate per-browser data in a stable order (by browser name), so merge
+    /// results never depend on HashMap iteration order
+    fn sorted_by_browser<T>(map: &HashMap<BrowserType, T>) -> Vec<(&BrowserType, &T)> {
+        let mut entries: Vec<_> = map.iter().collect();
+        entries.sort_by_key(|(browser, _)| browser.name());
+        entries
+    }
+
+    /// Group items from all browsers by key, in first-seen order, one item per browser
+    fn group_by_key<T>(
+        map: &HashMap<BrowserType, Vec<T>>,
+        key: impl Fn(&T) -> String,
+    ) -> Vec<Vec<(BrowserType, &T)>> {
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let mut groups: Vec<Vec<(BrowserType, &T)>> = Vec::new();
+        for (browser, items) in Self::sorted_by_browser(map) {
+            for item in items {
+                let slot = *index.entry(key(item)).or_insert_with(|| {
+                    groups.push(Vec::new());
+                    groups.len() - 1
+                });
+                if groups[slot].iter().all(|(b, _)| b != browser) {
+                    groups[slot].push((*browser, item));
+                }
+            }
+        }
+        groups
+    }
+
     fn hash_url(&self, url: &str) -> String {
+        self.hash_value(&canonicalize(url))
+    }
+
+    fn hash_value(&self, value: &str) -> String {
         let mut hasher = Sha256::new();
-        hasher.update(url.as_bytes());
+        hasher.update(value.as_bytes());
         format!("{:x}", hasher.finalize())
     }
 
@@ -909,7 +1529,7 @@ impl SyncEngine {
         println!("\n📊 Sync Preview:");
         println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
 
-        for (browser, bookmarks) in browser_bookmarks {
+        for (browser, bookmarks) in Self::sorted_by_browser(browser_bookmarks) {
             let url_count = Self::count_all_bookmarks(bookmarks);
             let folder_count = Self::count_all_folders(bookmarks);
             println!(
@@ -1001,6 +1621,9 @@ impl SyncEngine {
 
         info!("📖 Phase 1: Reading history from all browsers");
         let mut browser_history = HashMap::new();
+        // Tombstones compare each browser's full history: entries older than the
+        // day window are missing from the merge input but were not deleted
+        let mut seen_history = HashMap::new();
 
         for adapter in &self.adapters {
             if !adapter.supports_history() {
@@ -1019,6 +1642,22 @@ impl SyncEngine {
                         history.len(),
                         browser_type.name()
                     );
+                    let seen = match days {
+                        None => Ok(tomb::history_items(&history)),
+                        Some(_) => adapter
+                            .read_history(None)
+                            .map(|all| tomb::history_items(&all)),
+                    };
+                    match seen {
+                        Ok(seen) => {
+                            seen_history.insert(browser_type, seen);
+                        }
+                        Err(e) => warn!(
+                            "⚠️  Failed to read full history from {}, deletions not tracked: {}",
+                            browser_type.name(),
+                            e
+                        ),
+                    }
                     browser_history.insert(browser_type, history);
                 }
                 Err(e) => {
@@ -1036,12 +1675,19 @@ impl SyncEngine {
             return Ok(());
         }
 
+        let now = chrono::Utc::now().timestamp_millis();
+        for (browser, seen) in Self::sorted_by_browser(&seen_history) {
+            self.tombstones
+                .observe(TombstoneKind::History, browser.name(), seen.clone(), now);
+        }
+
         info!("🔄 Phase 2: Merging history");
         let merged = self.merge_history(&browser_history, verbose)?;
         info!("📊 Merged result: {} unique history items", merged.len());
 
         if dry_run {
             info!("🏃 Dry run mode - no changes will be made");
+            self.resolver().finish_report("history", true);
             return Ok(());
         }
 
@@ -1055,6 +1701,17 @@ impl SyncEngine {
             match adapter.write_history(&merged) {
                 Ok(_) => {
                     info!("✅ Wrote history to {}", browser_type.name());
+                    // Writing only adds or updates entries, so the browser now holds
+                    // its previous history plus the merged items
+                    if let Some(seen) = seen_history.get(&browser_type) {
+                        let mut items = seen.clone();
+                        items.extend(tomb::history_items(&merged));
+                        self.tombstones.record_seen(
+                            TombstoneKind::History,
+                            browser_type.name(),
+                            items,
+                        );
+                    }
                 }
                 Err(e) => {
                     error!(
@@ -1065,7 +1722,11 @@ impl SyncEngine {
                 }
             }
         }
+        if let Err(e) = self.tombstones.save() {
+            warn!("⚠️  Failed to save tombstones: {}", e);
+        }
 
+        self.resolver().finish_report("history", false);
         info!("✅ History synchronization complete");
         Ok(())
     }
@@ -1110,6 +1771,16 @@ impl SyncEngine {
             return Ok(());
         }
 
+        let now = chrono::Utc::now().timestamp_millis();
+        for (browser, items) in Self::sorted_by_browser(&browser_reading_lists) {
+            self.tombstones.observe(
+                TombstoneKind::ReadingList,
+                browser.name(),
+                tomb::reading_list_items(items),
+                now,
+            );
+        }
+
         info!("🔄 Phase 2: Merging reading lists");
         let merged = self.merge_reading_lists(&browser_reading_lists, verbose)?;
         info!(
@@ -1119,6 +1790,7 @@ impl SyncEngine {
 
         if dry_run {
             info!("🏃 Dry run mode - no changes will be made");
+            self.resolver().finish_report("reading-list", true);
             return Ok(());
         }
 
@@ -1132,6 +1804,11 @@ impl SyncEngine {
             match adapter.write_reading_list(&merged) {
                 Ok(_) => {
                     info!("✅ Wrote reading list to {}", browser_type.name());
+                    self.tombstones.record_seen(
+                        TombstoneKind::ReadingList,
+                        browser_type.name(),
+                        tomb::reading_list_items(&merged),
+                    );
                 }
                 Err(e) => {
                     error!(
@@ -1142,7 +1819,11 @@ impl SyncEngine {
                 }
             }
         }
+        if let Err(e) = self.tombstones.save() {
+            warn!("⚠️  Failed to save tombstones: {}", e);
+        }
 
+        self.resolver().finish_report("reading-list", false);
         info!("✅ Reading list synchronization complete");
         Ok(())
     }
@@ -1152,30 +1833,59 @@ impl SyncEngine {
         browser_history: &HashMap<BrowserType, Vec<HistoryItem>>,
         verbose: bool,
     ) -> Result<Vec<HistoryItem>> {
-        let mut merged = Vec::new();
-        let mut seen_urls = HashSet::new();
-
-        for (browser, history) in browser_history {
-            if verbose {
+        if verbose {
+            for (browser, history) in Self::sorted_by_browser(browser_history) {
                 debug!(
                     "Processing {} history items from {}",
                     history.len(),
                     browser.name()
                 );
             }
-
-            for item in history {
-                let url_hash = self.hash_url(&item.url);
-                if seen_urls.insert(url_hash) {
-                    merged.push(item.clone());
-                } else if verbose {
-                    debug!("Skipping duplicate URL: {}", item.url);
-                }
-            }
         }
 
-        // Sort by last visit time (most recent first)
-        merged.sort_by(|a, b| b.last_visit.unwrap_or(0).cmp(&a.last_visit.unwrap_or(0)));
+        let mut merged = Vec::new();
+        let mut resolver = self.resolver();
+        for group in Self::group_by_key(browser_history, |item| self.hash_url(&item.url)) {
+            let (_, first) = group[0];
+            if verbose && group.len() > 1 {
+                debug!("Skipping duplicate URL: {}", first.url);
+            }
+            let titles: HashSet<&str> = group
+                .iter()
+                .map(|(_, item)| item.title.as_deref().unwrap_or("").trim())
+                .collect();
+            if titles.len() < 2 {
+                merged.push(first.clone());
+                continue;
+            }
+
+            let versions = group
+                .iter()
+                .map(|(browser, item)| ConflictVersion {
+                    browser: browser.name().to_string(),
+                    title: item.title.clone().unwrap_or_default(),
+                    detail: Some(format!("{} visits", item.visit_count)),
+                    modified: item.last_visit,
+                })
+                .collect();
+            match resolver.resolve(DataKind::History, &first.url, versions) {
+                Choice::Version(i) => merged.push(group[i].1.clone()),
+                // History can't hold the same URL twice; the resolver never returns this
+                Choice::KeepBoth => merged.push(first.clone()),
+            }
+        }
+        drop(resolver);
+
+        let tombstoned = self.tombstones.filter_history(&mut merged);
+        if tombstoned > 0 {
+            info!(
+                "🪦 Skipped {} deleted history items (tombstones)",
+                tombstoned
+            );
+        }
+
+        // Sort by last visit time (most recent first)
+        merged.sort_by(|a, b| b.last_visit.unwrap_or(0).cmp(&a.last_visit.unwrap_or(0)));
 
         Ok(merged)
     }
@@ -1185,27 +1895,65 @@ impl SyncEngine {
         browser_reading_lists: &HashMap<BrowserType, Vec<ReadingListItem>>,
         verbose: bool,
     ) -> Result<Vec<ReadingListItem>> {
-        let mut merged = Vec::new();
-        let mut seen_urls = HashSet::new();
-
-        for (browser, items) in browser_reading_lists {
-            if verbose {
+        if verbose {
+            for (browser, items) in Self::sorted_by_browser(browser_reading_lists) {
                 debug!(
                     "Processing {} reading list items from {}",
                     items.len(),
                     browser.name()
                 );
             }
+        }
 
-            for item in items {
-                let url_hash = self.hash_url(&item.url);
-                if seen_urls.insert(url_hash) {
-                    merged.push(item.clone());
-                } else if verbose {
-                    debug!("Skipping duplicate URL: {}", item.url);
+        let mut merged = Vec::new();
+        let mut resolver = self.resolver();
+        for group in Self::group_by_key(browser_reading_lists, |item| self.hash_url(&item.url)) {
+            let (_, first) = group[0];
+            if verbose && group.len() > 1 {
+                debug!("Skipping duplicate URL: {}", first.url);
+            }
+            let titles: HashSet<&str> = group.iter().map(|(_, item)| item.title.trim()).collect();
+            if titles.len() < 2 {
+                merged.push(first.clone());
+                continue;
+            }
+
+            let versions = group
+                .iter()
+                .map(|(browser, item)| ConflictVersion {
+                    browser: browser.name().to_string(),
+                    title: item.title.clone(),
+                    detail: None,
+                    modified: item.date_added,
+                })
+                .collect();
+            match resolver.resolve(DataKind::ReadingList, &first.url, versions) {
+                Choice::Version(i) => merged.push(group[i].1.clone()),
+                Choice::KeepBoth => {
+                    let mut seen = HashSet::new();
+                    for (i, (browser, item)) in group.iter().enumerate() {
+                        if !seen.insert(item.title.trim()) {
+                            continue;
+                        }
+                        let mut item = (*item).clone();
+                        if i > 0 {
+                            item.title =
+                                conflicts::conflict_copy_title(&item.title, browser.name());
+                        }
+                        merged.push(item);
+                    }
                 }
             }
         }
+        drop(resolver);
+
+        let tombstoned = self.tombstones.filter_reading_list(&mut merged);
+        if tombstoned > 0 {
+            info!(
+                "🪦 Skipped {} deleted reading list items (tombstones)",
+                tombstoned
+            );
+        }
 
         // Sort by date added (most recent first)
         merged.sort_by(|a, b| b.date_added.unwrap_or(0).cmp(&a.date_added.unwrap_or(0)));
@@ -1259,6 +2007,7 @@ impl SyncEngine {
 
         if dry_run {
             info!("🏃 Dry run mode - no changes will be made");
+            self.resolver().finish_report("cookies", true);
             return Ok(());
         }
 
@@ -1283,6 +2032,7 @@ impl SyncEngine {
             }
         }
 
+        self.resolver().finish_report("cookies", false);
         info!("✅ Cookies synchronization complete");
         Ok(())
     }
@@ -1434,28 +2184,46 @@ impl SyncEngine {
         browser_cookies: &HashMap<BrowserType, Vec<Cookie>>,
         verbose: bool,
     ) -> Result<Vec<Cookie>> {
-        let mut merged = Vec::new();
-        let mut seen_keys = HashSet::new();
-
-        for (browser, cookies) in browser_cookies {
-            if verbose {
+        if verbose {
+            for (browser, cookies) in Self::sorted_by_browser(browser_cookies) {
                 debug!(
                     "Processing {} cookies from {}",
                     cookies.len(),
                     browser.name()
                 );
             }
+        }
 
-            for cookie in cookies {
-                let key = format!("{}|{}|{}", cookie.host, cookie.name, cookie.path);
-                let key_hash = self.hash_url(&key);
-                if seen_keys.insert(key_hash) {
-                    merged.push(cookie.clone());
-                } else if verbose {
-                    debug!("Skipping duplicate cookie: {}:{}", cookie.host, cookie.name);
-                }
+        let mut merged = Vec::new();
+        let mut resolver = self.resolver();
+        let cookie_key = |c: &Cookie| format!("{}|{}|{}", c.host, c.name, c.path);
+        for group in Self::group_by_key(browser_cookies, |c| self.hash_value(&cookie_key(c))) {
+            let (_, first) = group[0];
+            if verbose && group.len() > 1 {
+                debug!("Skipping duplicate cookie: {}:{}", first.host, first.name);
+            }
+            let values: HashSet<&str> = group.iter().map(|(_, c)| c.value.as_str()).collect();
+            if values.len() < 2 {
+                merged.push(first.clone());
+                continue;
+            }
+
+            // Never put cookie values in the report, only a fingerprint
+            let versions = group
+                .iter()
+                .map(|(browser, c)| ConflictVersion {
+                    browser: browser.name().to_string(),
+                    title: c.name.clone(),
+                    detail: Some(format!("value {}", &self.hash_value(&c.value)[..12])),
+                    modified: c.expiry,
+                })
+                .collect();
+            match resolver.resolve(DataKind::Cookies, &cookie_key(first), versions) {
+                Choice::Version(i) => merged.push(group[i].1.clone()),
+                Choice::KeepBoth => merged.push(first.clone()),
             }
         }
+        drop(resolver);
 
         merged.sort_by(|a, b| a.host.cmp(&b.host));
 
@@ -1520,7 +2288,12 @@ impl SyncEngine {
         let mut hub_adapters: Vec<&Box<dyn BrowserAdapter + Send + Sync>> = Vec::new();
         let mut non_hub_adapters: Vec<&Box<dyn BrowserAdapter + Send + Sync>> = Vec::new();
 
+        // Browsers that are not installed are neither written nor cleared
         for adapter in &self.adapters {
+            if !adapter.is_installed() {
+                info!("  ⏭️  Not installed: {}", adapter.browser_type().name());
+                continue;
+            }
             let name = adapter.browser_type().name().to_lowercase();
             let is_hub = hub_list.iter().any(|h| {
                 // Exact matching to avoid "brave" matching "brave nightly"
@@ -1625,6 +2398,29 @@ impl SyncEngine {
             }
         }
 
+        // Deletions since the last run become tombstones before merging
+        let now = chrono::Utc::now().timestamp_millis();
+        for (browser, bookmarks) in Self::sorted_by_browser(&all_bookmarks) {
+            self.tombstones
+                .observe_bookmarks(browser.name(), bookmarks, now);
+        }
+        for (browser, history) in Self::sorted_by_browser(&all_history) {
+            self.tombstones.observe(
+                TombstoneKind::History,
+                browser.name(),
+                tomb::history_items(history),
+                now,
+            );
+        }
+        for (browser, items) in Self::sorted_by_browser(&all_reading_lists) {
+            self.tombstones.observe(
+                TombstoneKind::ReadingList,
+                browser.name(),
+                tomb::reading_list_items(items),
+                now,
+            );
+        }
+
         // Phase 2: Merge and deduplicate
         info!("\n🔄 Phase 2: Merging and deduplicating...");
 
@@ -1684,37 +2480,64 @@ impl SyncEngine {
                     println!("    🗑️  {}", adapter.browser_type().name());
                 }
             }
+            let conflicts = self.resolver().finish_report("hub", true);
+            println!("  Conflicts resolved: {}", conflicts);
             println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
             return Ok(());
         }
 
-        // Phase 3: Backup everything
-        info!("\n💾 Phase 3: Creating backups...");
-        for adapter in &self.adapters {
-            if let Ok(path) = adapter.backup_bookmarks() {
-                info!(
-                    "  ✅ Backup: {} -> {:?}",
-                    adapter.browser_type().name(),
-                    path
-                );
-            }
+        // Phase 3: Write bookmarks to every target at once; any failure rolls all of them back
+        info!("\n💾 Phase 3: Writing bookmarks (backup → stage → validate → swap)...");
+        let empty_bookmarks: Vec<Bookmark> = Vec::new();
+        let mut targets: Vec<WriteTarget> = hub_adapters
+            .iter()
+            .map(|adapter| WriteTarget {
+                adapter: adapter.as_ref(),
+                bookmarks: &merged_bookmarks,
+            })
+            .collect();
+        if clear_others {
+            targets.extend(non_hub_adapters.iter().map(|adapter| WriteTarget {
+                adapter: adapter.as_ref(),
+                bookmarks: &empty_bookmarks,
+            }));
         }
+        atomic_write::write_bookmarks_atomic(&targets, &atomic_write::default_staging_root()?)?;
+        drop(targets);
 
-        // Phase 4: Write to hub browsers
-        info!("\n✍️  Phase 4: Writing to hub browsers...");
         for adapter in &hub_adapters {
             let browser_name = adapter.browser_type().name();
-
-            // Write bookmarks
-            match adapter.write_bookmarks(&merged_bookmarks) {
-                Ok(_) => info!("  ✅ {} : bookmarks written", browser_name),
-                Err(e) => error!("  ❌ {} : failed to write bookmarks: {}", browser_name, e),
+            info!("  ✅ {} : bookmarks written", browser_name);
+            self.tombstones
+                .record_bookmarks(browser_name, &merged_bookmarks);
+        }
+        if clear_others {
+            for adapter in &non_hub_adapters {
+                let browser_name = adapter.browser_type().name();
+                info!("  ✅ {} : bookmarks cleared", browser_name);
+                // Cleared on purpose, not deleted by the user
+                self.tombstones
+                    .record_bookmarks(browser_name, &empty_bookmarks);
             }
+        }
+
+        // Phase 4: Write other data to hub browsers, one browser at a time (not atomic;
+        // a failure is only logged and does not roll back the bookmarks)
+        info!("\n✍️  Phase 4: Writing history, reading list and cookies to hub browsers...");
+        for adapter in &hub_adapters {
+            let browser_name = adapter.browser_type().name();
 
             // Write history
             if sync_history && adapter.supports_history() {
                 match adapter.write_history(&merged_history) {
-                    Ok(_) => info!("  ✅ {} : history written", browser_name),
+                    Ok(_) => {
+                        info!("  ✅ {} : history written", browser_name);
+                        self.tombstones.record_seen(
+                            TombstoneKind::History,
+                            browser_name,
+                            tomb::history_items(&merged_history),
+                        );
+                    }
                     Err(e) => warn!("  ⚠️  {} : failed to write history: {}", browser_name, e),
                 }
             }
@@ -1722,7 +2545,14 @@ impl SyncEngine {
             // Write reading list
             if sync_reading_list && adapter.supports_reading_list() {
                 match adapter.write_reading_list(&merged_reading_list) {
-                    Ok(_) => info!("  ✅ {} : reading list written", browser_name),
+                    Ok(_) => {
+                        info!("  ✅ {} : reading list written", browser_name);
+                        self.tombstones.record_seen(
+                            TombstoneKind::ReadingList,
+                            browser_name,
+                            tomb::reading_list_items(&merged_reading_list),
+                        );
+                    }
                     Err(e) => warn!(
                         "  ⚠️  {} : failed to write reading list: {}",
                         browser_name, e
@@ -1739,23 +2569,21 @@ impl SyncEngine {
             }
         }
 
-        // Phase 5: Clear non-hub browsers if requested
-        if clear_others {
-            info!("\n🗑️  Phase 5: Clearing non-hub browsers...");
-            for adapter in &non_hub_adapters {
-                let browser_name = adapter.browser_type().name();
+        if let Err(e) = self.tombstones.save() {
+            warn!("  ⚠️  Failed to save tombstones: {}", e);
+        }
 
-                // Clear bookmarks by writing empty structure
-                let empty_bookmarks: Vec<Bookmark> = Vec::new();
-                match adapter.write_bookmarks(&empty_bookmarks) {
-                    Ok(_) => info!("  ✅ {} : bookmarks cleared", browser_name),
-                    Err(e) => warn!("  ⚠️  {} : failed to clear bookmarks: {}", browser_name, e),
-                }
-            }
+        // Refresh the base so later moves are detected against what was just written
+        let written: HashSet<BrowserType> =
+            hub_adapters.iter().map(|a| a.browser_type()).collect();
+        if let Err(e) = BaseSnapshot::default_path()
+            .and_then(|path| self.save_base_snapshot(&path, &merged_bookmarks, &written))
+        {
+            warn!("  ⚠️  Failed to save base snapshot: {}", e);
         }
 
-        // Phase 6: Verification
-        info!("\n🔍 Phase 6: Verification...");
+        // Phase 5: Verification
+        info!("\n🔍 Phase 5: Verification...");
         for adapter in &hub_adapters {
             if let Ok(bookmarks) = adapter.read_bookmarks() {
                 let url_count = Self::count_all_bookmarks(&bookmarks);
@@ -1788,21 +2616,26 @@ impl SyncEngine {
         if clear_others {
             println!("  Non-hub browsers: CLEARED");
         }
+        let conflicts = self.resolver().finish_report("hub", false);
+        println!("  Conflicts resolved: {}", conflicts);
         println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
 
         Ok(())
     }
 
-    /// Synchronize specific scenario folders across browsers
+    /// Synchronize scenario folders across browsers
+    ///
+    /// Each spec is a folder path pattern (`Projects/*`, `**/临时`) that expands to every
+    /// matching folder in the target browsers; each folder is merged and reported separately.
+    /// Adapters apply the folder mappings, so patterns use the mapped (`to`) names.
     pub async fn sync_scenario_folders(
         &mut self,
-        scenario_path: &str,
+        specs: &[ScenarioSpec],
         browser_names: &str,
         dry_run: bool,
         verbose: bool,
-    ) -> Result<()> {
+    ) -> Result<Vec<ScenarioStats>> {
         info!("📁 Starting scenario folder synchronization");
-        info!("🎯 Scenario path: {}", scenario_path);
 
         // Parse browser names
         let browser_list: Vec<String> = browser_names
@@ -1826,26 +2659,34 @@ impl SyncEngine {
             anyhow::bail!("No matching browsers found for: {:?}", browser_list);
         }
 
-        // Read scenario folders from all target browsers
-        info!("\n📖 Phase 1: Reading scenario folders from browsers...");
-        let mut scenario_folders: HashMap<BrowserType, Option<Bookmark>> = HashMap::new();
+        for spec in specs {
+            if let ScenarioMode::Mirror(ref master) = spec.mode {
+                if !target_adapters
+                    .iter()
+                    .any(|a| a.browser_type().name().to_lowercase().contains(master))
+                {
+                    anyhow::bail!(
+                        "Master browser '{}' for scenario '{}' is not among the target browsers",
+                        master,
+                        spec.pattern
+                    );
+                }
+            }
+        }
 
+        // Read all target browsers once; every scenario folder is merged into these trees
+        info!("\n📖 Phase 1: Reading bookmarks from browsers...");
+        let mut trees: HashMap<BrowserType, Vec<Bookmark>> = HashMap::new();
         for adapter in &target_adapters {
             let browser_type = adapter.browser_type();
             match adapter.read_bookmarks() {
                 Ok(bookmarks) => {
-                    let folder = Self::find_folder_by_path(&bookmarks, scenario_path);
-                    if let Some(ref f) = folder {
-                        let count = Self::count_all_bookmarks(&f.children);
-                        info!(
-                            "  ✅ {} : found folder with {} bookmarks",
-                            browser_type.name(),
-                            count
-                        );
-                    } else {
-                        info!("  ⚠️  {} : scenario folder not found", browser_type.name());
-                    }
-                    scenario_folders.insert(browser_type, folder);
+                    info!(
+                        "  ✅ {} : {} bookmarks",
+                        browser_type.name(),
+                        Self::count_all_bookmarks(&bookmarks)
+                    );
+                    trees.insert(browser_type, bookmarks);
                 }
                 Err(e) => {
                     warn!(
@@ -1857,69 +2698,156 @@ impl SyncEngine {
             }
         }
 
-        // Merge scenario folders
+        // Expand patterns; a folder claimed by an earlier scenario keeps that scenario's mode
+        let mut scenarios: Vec<(String, ScenarioMode)> = Vec::new();
+        for spec in specs {
+            let paths = scenario::expand_pattern(
+                &spec.pattern,
+                Self::sorted_by_browser(&trees)
+                    .into_iter()
+                    .map(|(_, tree)| tree.as_slice()),
+            );
+            if paths.is_empty() {
+                warn!("  ⚠️  No folders match scenario '{}'", spec.pattern);
+            }
+            for path in paths {
+                if scenarios.iter().any(|(p, _)| p.eq_ignore_ascii_case(&path)) {
+                    warn!(
+                        "  ⚠️  {} already matched by an earlier scenario, skipping '{}'",
+                        path, spec.pattern
+                    );
+                    continue;
+                }
+                scenarios.push((path, spec.mode.clone()));
+            }
+        }
+        info!("🎯 {} scenario folders matched", scenarios.len());
+
         info!("\n🔄 Phase 2: Merging scenario folders...");
-        let merged_folder =
-            self.merge_scenario_folders(&scenario_folders, scenario_path, verbose)?;
-        let merged_count = Self::count_all_bookmarks(&merged_folder.children);
-        info!("  📊 Merged folder contains {} bookmarks", merged_count);
+        let mut results = Vec::new();
+        let mut changed: HashSet<BrowserType> = HashSet::new();
+        for (path, mode) in scenarios {
+            let mut stats = ScenarioStats {
+                path: path.clone(),
+                mode: mode.to_string(),
+                ..Default::default()
+            };
+            let scenario_folders: HashMap<BrowserType, Option<Bookmark>> = trees
+                .iter()
+                .map(|(browser, tree)| (*browser, Self::find_folder_by_path(tree, &path)))
+                .collect();
+            stats.browsers_found = scenario_folders.values().filter(|f| f.is_some()).count();
+
+            let merged_folder = match mode {
+                ScenarioMode::Union => {
+                    let (folder, duplicates) =
+                        self.merge_scenario_folders(&scenario_folders, &path, verbose)?;
+                    stats.duplicates_removed = duplicates;
+                    folder
+                }
+                ScenarioMode::Mirror(ref master) => {
+                    let master_folder = Self::sorted_by_browser(&scenario_folders)
+                        .into_iter()
+                        .find(|(browser, _)| browser.name().to_lowercase().contains(master))
+                        .and_then(|(_, folder)| folder.clone());
+                    match master_folder {
+                        Some(folder) => folder,
+                        None => {
+                            // Never mirror a missing folder: that would wipe it everywhere
+                            warn!("  ⚠️  {} : not found in master browser {}", path, master);
+                            stats.skipped = Some(format!("not found in {}", master));
+                            results.push(stats);
+                            continue;
+                        }
+                    }
+                }
+            };
+            stats.conflicts_resolved = self
+                .resolver()
+                .finish_report(&format!("scenario:{}", path), dry_run);
+            stats.bookmarks = Self::count_all_bookmarks(&merged_folder.children);
+            info!(
+                "  📊 {} ({}) : {} bookmarks from {} browsers",
+                path, stats.mode, stats.bookmarks, stats.browsers_found
+            );
+
+            for (browser, tree) in trees.iter_mut() {
+                let before = serde_json::to_string(&scenario_folders[browser]).ok();
+                if !Self::replace_folder_by_path(tree, &path, &merged_folder) {
+                    // Intermediate folders are missing in this browser
+                    three_way::ensure_folder(tree, &scenario::split_path(&path));
+                    Self::replace_folder_by_path(tree, &path, &merged_folder);
+                }
+                let after = serde_json::to_string(&Self::find_folder_by_path(tree, &path)).ok();
+                if before != after {
+                    stats.browsers_updated.push(browser.name().to_string());
+                    changed.insert(*browser);
+                }
+            }
+            stats.browsers_updated.sort();
+            results.push(stats);
+        }
 
+        Self::print_scenario_summary(&results, dry_run);
         if dry_run {
             info!("\n🏃 Dry run mode - no changes will be made");
-            println!("\n📊 Scenario Sync Preview:");
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            println!("  Scenario: {}", scenario_path);
-            println!("  Merged bookmarks: {}", merged_count);
-            println!("  Target browsers:");
-            for adapter in &target_adapters {
-                println!("    - {}", adapter.browser_type().name());
-            }
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            return Ok(());
+            return Ok(results);
         }
 
         // Backup and write
         info!("\n💾 Phase 3: Creating backups...");
         for adapter in &target_adapters {
-            if let Ok(path) = adapter.backup_bookmarks() {
-                info!("  ✅ Backup: {:?}", path);
+            if changed.contains(&adapter.browser_type()) {
+                if let Ok(path) = adapter.backup_bookmarks() {
+                    info!("  ✅ Backup: {:?}", path);
+                }
             }
         }
 
         info!("\n✍️  Phase 4: Updating scenario folders...");
         for adapter in &target_adapters {
-            match adapter.read_bookmarks() {
-                Ok(mut bookmarks) => {
-                    // Replace or create scenario folder
-                    if Self::replace_folder_by_path(&mut bookmarks, scenario_path, &merged_folder) {
-                        match adapter.write_bookmarks(&bookmarks) {
-                            Ok(_) => info!(
-                                "  ✅ {} : scenario folder updated",
-                                adapter.browser_type().name()
-                            ),
-                            Err(e) => error!(
-                                "  ❌ {} : failed to write: {}",
-                                adapter.browser_type().name(),
-                                e
-                            ),
-                        }
-                    } else {
-                        warn!(
-                            "  ⚠️  {} : failed to locate/create scenario folder",
-                            adapter.browser_type().name()
-                        );
-                    }
-                }
-                Err(e) => error!(
-                    "  ❌ {} : failed to read bookmarks: {}",
-                    adapter.browser_type().name(),
-                    e
-                ),
+            let browser_type = adapter.browser_type();
+            let Some(bookmarks) = trees.get(&browser_type).filter(|_| changed.contains(&browser_type))
+            else {
+                continue;
+            };
+            match adapter.write_bookmarks(bookmarks) {
+                Ok(_) => info!("  ✅ {} : scenario folders updated", browser_type.name()),
+                Err(e) => error!("  ❌ {} : failed to write: {}", browser_type.name(), e),
             }
         }
 
         info!("\n✅ Scenario folder synchronization complete!");
-        Ok(())
+        Ok(results)
+    }
+
+    fn print_scenario_summary(results: &[ScenarioStats], dry_run: bool) {
+        println!(
+            "\n📊 Scenario Sync {}:",
+            if dry_run { "Preview" } else { "Summary" }
+        );
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        for stats in results {
+            println!("  📁 {} ({})", stats.path, stats.mode);
+            if let Some(ref reason) = stats.skipped {
+                println!("     Skipped: {}", reason);
+                continue;
+            }
+            println!(
+                "     Found in {} browsers, {} bookmarks after merge",
+                stats.browsers_found, stats.bookmarks
+            );
+            println!(
+                "     Duplicates removed: {}, conflicts resolved: {}",
+                stats.duplicates_removed, stats.conflicts_resolved
+            );
+            if stats.browsers_updated.is_empty() {
+                println!("     Already in sync");
+            } else {
+                println!("     Updated: {}", stats.browsers_updated.join(", "));
+            }
+        }
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
     }
 
     /// Find a folder by path (e.g., "Work/Projects")
@@ -2011,11 +2939,18 @@ impl SyncEngine {
         scenario_folders: &HashMap<BrowserType, Option<Bookmark>>,
         scenario_path: &str,
         verbose: bool,
-    ) -> Result<Bookmark> {
+    ) -> Result<(Bookmark, usize)> {
+        let resolutions = self.resolve_bookmark_conflicts(
+            &scenario_folders
+                .iter()
+                .filter_map(|(browser, folder)| Some((*browser, folder.as_ref()?.children.clone())))
+                .collect(),
+        );
+
         // Collect all valid folders
         let mut all_children = Vec::new();
 
-        for (browser, folder_opt) in scenario_folders {
+        for (browser, folder_opt) in Self::sorted_by_browser(scenario_folders) {
             if let Some(folder) = folder_opt {
                 if verbose {
                     let count = Self::count_all_bookmarks(&folder.children);
@@ -2030,13 +2965,16 @@ impl SyncEngine {
         }
 
         // Deduplicate globally with smart selection
-        Self::deduplicate_bookmarks_global(&mut all_children);
+        let before = Self::count_all_bookmarks(&all_children);
+        Self::deduplicate_bookmarks_global(&mut all_children, &self.keep_policy, None);
+        let duplicates = before - Self::count_all_bookmarks(&all_children);
+        Self::apply_bookmark_resolutions(&mut all_children, &resolutions, &mut HashSet::new());
 
         // Create merged folder
         let path_parts: Vec<&str> = scenario_path.split('/').collect();
         let folder_name = path_parts.last().unwrap_or(&"Scenario").to_string();
 
-        Ok(Bookmark {
+        let folder = Bookmark {
             id: format!("scenario-{}", chrono::Utc::now().timestamp_millis()),
             title: folder_name,
             url: None,
@@ -2044,7 +2982,8 @@ impl SyncEngine {
             children: all_children,
             date_added: Some(chrono::Utc::now().timestamp_millis()),
             date_modified: Some(chrono::Utc::now().timestamp_millis()),
-        })
+        };
+        Ok((folder, duplicates))
     }
 
     /// Clean up duplicates and empty folders
@@ -2101,7 +3040,11 @@ impl SyncEngine {
 
                     // Step 1: Remove duplicates with smart selection
                     if remove_duplicates {
-                        Self::deduplicate_bookmarks_global(&mut bookmarks);
+                        Self::deduplicate_bookmarks_global(
+                            &mut bookmarks,
+                            &self.keep_policy,
+                            Some(browser_name),
+                        );
                         let after_dedup = Self::count_all_bookmarks(&bookmarks);
                         stats.duplicates_removed = initial_count.saturating_sub(after_dedup);
 
@@ -4973,11 +5916,11 @@ pub fn get_builtin_rules() -> Vec<ClassificationRule> {
 
 /// Classification statistics
 #[derive(Default)]
-struct ClassificationStats {
-    total_processed: usize,
-    total_classified: usize,
-    by_category: HashMap<String, usize>,
-    unclassified: usize,
+pub(crate) struct ClassificationStats {
+    pub total_processed: usize,
+    pub total_classified: usize,
+    pub by_category: HashMap<String, usize>,
+    pub unclassified: usize,
 }
 
 impl SyncEngine {
@@ -5131,6 +6074,7 @@ impl SyncEngine {
             match adapter.read_bookmarks() {
                 Ok(mut bookmarks) => {
                     info!("\n📊 {} : Processing...", browser_name);
+                    let original = self.plan.is_some().then(|| bookmarks.clone());
 
                     let mut stats = ClassificationStats::default();
 
@@ -5287,6 +6231,10 @@ impl SyncEngine {
                             stats.total_classified,
                             classified.len()
                         );
+                        if let (Some(plan), Some(original)) = (self.plan.as_mut(), &original) {
+                            let count = plan.record(adapter.as_ref(), original, &bookmarks)?;
+                            info!("  📝 Planned {} changes", count);
+                        }
                     } else if stats.total_classified > 0 {
                         // Backup first
                         if let Ok(backup_path) = adapter.backup_bookmarks() {
@@ -5495,19 +6443,10 @@ impl SyncEngine {
         }
     }
 
-    /// Smart organize bookmarks from an exported file (HTML/JSON)
-    pub async fn smart_organize_file(
-        &mut self,
-        input_file: &str,
-        output_file: &str,
+    /// Built-in rules plus the optional custom rules file, in matching order
+    pub(crate) fn load_classification_rules(
         rules_file: Option<&str>,
-        show_stats: bool,
-        dry_run: bool,
-        verbose: bool,
-    ) -> Result<()> {
-        info!("🧠 Organizing bookmarks from file: {}", input_file);
-
-        // Load rules
+    ) -> Result<Vec<ClassificationRule>> {
         let mut rules = get_builtin_rules();
         if let Some(file_path) = rules_file {
             info!("📂 Loading custom rules from: {}", file_path);
@@ -5527,46 +6466,20 @@ impl SyncEngine {
         });
 
         info!("📋 Loaded {} classification rules", rules.len());
+        Ok(rules)
+    }
 
-        // Read input file
-        let content = std::fs::read_to_string(input_file)
-            .context(format!("Failed to read input file: {}", input_file))?;
-
-        // Parse bookmarks based on file type
-        let mut bookmarks = if input_file.ends_with(".json") {
-            serde_json::from_str::<Vec<Bookmark>>(&content)
-                .context("Failed to parse JSON bookmark file")?
-        } else if input_file.ends_with(".html") || input_file.ends_with(".htm") {
-            parse_safari_html(&content)?
-        } else {
-            // Try JSON first, then HTML
-            serde_json::from_str::<Vec<Bookmark>>(&content)
-                .or_else(|_| parse_safari_html(&content))
-                .context("Failed to parse bookmark file (tried JSON and HTML)")?
-        };
-
-        info!("📖 Loaded {} bookmarks from file", Self::count_all_bookmarks(&bookmarks));
-
-        // Flatten: remove browser root folders (Brave, Brave Nightly, Waterfox, etc.)
-        info!("📦 Flattening: removing browser root folders...");
-        let flat_config = FlatExportConfig {
-            flatten_root: true,
-            root_folders_to_remove: None,
-        };
-        let (flattened, flatten_stats) = flatten_bookmarks(&bookmarks, &flat_config);
-        bookmarks = flattened;
-        if flatten_stats.root_folders_removed > 0 {
-            info!(
-                "  ✅ Removed {} browser root folders, promoted {} items",
-                flatten_stats.root_folders_removed, flatten_stats.bookmarks_promoted
-            );
-        }
-
+    /// Move every bookmark into the folder of the first matching rule ("未分类" if none)
+    pub(crate) fn classify_into_folders(
+        bookmarks: &mut Vec<Bookmark>,
+        rules: &[ClassificationRule],
+        verbose: bool,
+    ) -> ClassificationStats {
         let mut stats = ClassificationStats::default();
 
         // Collect all bookmarks for classification
         let mut to_classify: Vec<Bookmark> = Vec::new();
-        Self::collect_all_bookmarks_for_classification(&mut bookmarks, &mut to_classify);
+        Self::collect_all_bookmarks_for_classification(bookmarks, &mut to_classify);
         stats.total_processed = to_classify.len();
 
         info!("  📖 Found {} bookmarks to classify", to_classify.len());
@@ -5582,7 +6495,7 @@ impl SyncEngine {
             let mut matched = false;
             let mut tried_rules: Vec<String> = Vec::new();
             
-            for rule in &rules {
+            for rule in rules {
                 if let Some(reason) = rule.matches_with_reason(url, title) {
                     if verbose {
                         info!("  ✓ '{}' -> {} (rule: {}, reason: {})", title, rule.folder_name, rule.name, reason);
@@ -5650,38 +6563,91 @@ impl SyncEngine {
             }
         }
 
-        // Show statistics
-        if show_stats {
-            println!("\n📊 Classification Statistics:");
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            println!("  Total processed:  {}", stats.total_processed);
-            println!(
-                "  Total classified: {} ({:.1}%)",
-                stats.total_classified,
-                if stats.total_processed > 0 {
-                    stats.total_classified as f64 / stats.total_processed as f64 * 100.0
-                } else {
-                    0.0
-                }
-            );
-            println!("  Unclassified:     {}", stats.unclassified);
-            println!("\n  By category:");
-
-            let mut categories: Vec<_> = stats.by_category.iter().collect();
-            categories.sort_by(|a, b| b.1.cmp(a.1));
-            for (category, count) in categories {
-                println!("    📁 {} : {}", category, count);
-            }
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-        }
+        stats
+    }
 
-        if dry_run {
-            info!(
-                "  🏃 Dry run - would classify {} bookmarks into {} folders",
-                stats.total_classified,
-                classified.len()
-            );
-        } else {
+    /// Smart organize bookmarks from an exported file (HTML/JSON)
+    pub async fn smart_organize_file(
+        &mut self,
+        input_file: &str,
+        output_file: &str,
+        rules_file: Option<&str>,
+        show_stats: bool,
+        dry_run: bool,
+        verbose: bool,
+    ) -> Result<()> {
+        info!("🧠 Organizing bookmarks from file: {}", input_file);
+
+        let rules = Self::load_classification_rules(rules_file)?;
+
+        // Read input file
+        let content = std::fs::read_to_string(input_file)
+            .context(format!("Failed to read input file: {}", input_file))?;
+
+        // Parse bookmarks based on file type
+        let mut bookmarks = if input_file.ends_with(".json") {
+            serde_json::from_str::<Vec<Bookmark>>(&content)
+                .context("Failed to parse JSON bookmark file")?
+        } else if input_file.ends_with(".html") || input_file.ends_with(".htm") {
+            parse_safari_html(&content)?
+        } else {
+            // Try JSON first, then HTML
+            serde_json::from_str::<Vec<Bookmark>>(&content)
+                .or_else(|_| parse_safari_html(&content))
+                .context("Failed to parse bookmark file (tried JSON and HTML)")?
+        };
+
+        info!("📖 Loaded {} bookmarks from file", Self::count_all_bookmarks(&bookmarks));
+
+        // Flatten: remove browser root folders (Brave, Brave Nightly, Waterfox, etc.)
+        info!("📦 Flattening: removing browser root folders...");
+        let flat_config = FlatExportConfig {
+            flatten_root: true,
+            root_folders_to_remove: None,
+        };
+        let (flattened, flatten_stats) = flatten_bookmarks(&bookmarks, &flat_config);
+        bookmarks = flattened;
+        if flatten_stats.root_folders_removed > 0 {
+            info!(
+                "  ✅ Removed {} browser root folders, promoted {} items",
+                flatten_stats.root_folders_removed, flatten_stats.bookmarks_promoted
+            );
+        }
+
+        let stats = Self::classify_into_folders(&mut bookmarks, &rules, verbose);
+
+        // Show statistics
+        if show_stats {
+            println!("\n📊 Classification Statistics:");
+            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            println!("  Total processed:  {}", stats.total_processed);
+            println!(
+                "  Total classified: {} ({:.1}%)",
+                stats.total_classified,
+                if stats.total_processed > 0 {
+                    stats.total_classified as f64 / stats.total_processed as f64 * 100.0
+                } else {
+                    0.0
+                }
+            );
+            println!("  Unclassified:     {}", stats.unclassified);
+            println!("\n  By category:");
+
+            let mut categories: Vec<_> = stats.by_category.iter().collect();
+            categories.sort_by(|a, b| b.1.cmp(a.1));
+            for (category, count) in categories {
+                println!("    📁 {} : {}", category, count);
+            }
+            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        }
+
+        if dry_run {
+            info!(
+                "  🏃 Dry run - would classify {} bookmarks into {} folders",
+                stats.total_classified,
+                stats.by_category.len()
+            );
+        } else {
             // Wrap all bookmarks in 📁镜像文件夹 + merge temp folders
             info!("📦 Wrapping all bookmarks in root folder: \"📁镜像文件夹\"");
             
@@ -5839,30 +6805,6 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_normalize_url() {
-        assert_eq!(
-            SyncEngine::normalize_url("https://example.com/"),
-            "https://example.com"
-        );
-        assert_eq!(
-            SyncEngine::normalize_url("https://example.com"),
-            "https://example.com"
-        );
-        assert_eq!(
-            SyncEngine::normalize_url("HTTPS://EXAMPLE.COM/"),
-            "https://example.com"
-        );
-        assert_eq!(
-            SyncEngine::normalize_url("https://example.com#section"),
-            "https://example.com"
-        );
-        assert_eq!(
-            SyncEngine::normalize_url("  https://example.com/  "),
-            "https://example.com"
-        );
-    }
-
     #[test]
     fn test_count_all_bookmarks() {
         let bookmarks = vec![
@@ -5944,10 +6886,15 @@ mod tests {
             create_bookmark("4", "Other", Some("https://other.com")),
         ];
 
-        SyncEngine::deduplicate_bookmarks_global(&mut bookmarks);
+        let losses =
+            SyncEngine::deduplicate_bookmarks_global(&mut bookmarks, &KeepPolicy::default(), None);
 
         let total = SyncEngine::count_all_bookmarks(&bookmarks);
         assert_eq!(total, 2); // One example.com and one other.com
+        assert_eq!(losses.len(), 1);
+        assert_eq!(losses[0].title, "Dup1");
+        assert_eq!(losses[0].reason, "deepest");
+        assert_eq!(losses[0].kept_folder, "Folder");
     }
 
     #[test]
@@ -6165,6 +7112,114 @@ mod tests {
         // 不应该匹配不相关的域名
         assert!(!ClassificationRule::match_domain_exact("fakegithub.com", "*.github.com"));
     }
+
+    /// 读回测试适配器里的书签 URL
+    fn file_urls(path: &Path) -> Vec<String> {
+        fn walk(nodes: &[Bookmark], urls: &mut Vec<String>) {
+            for node in nodes {
+                urls.extend(node.url.clone());
+                walk(&node.children, urls);
+            }
+        }
+        let tree: Vec<Bookmark> = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        let mut urls = Vec::new();
+        walk(&tree, &mut urls);
+        urls.sort();
+        urls
+    }
+
+    #[tokio::test]
+    async fn test_hub_skips_browser_that_is_not_installed() {
+        use crate::test_support::{isolated_home, make_bookmark, FileAdapter};
+
+        isolated_home();
+        let dir = tempfile::tempdir().unwrap();
+        let brave = dir.path().join("Brave");
+        let chrome = dir.path().join("Chrome");
+        let safari = dir.path().join("Safari");
+        let mut engine = SyncEngine::with_adapters(vec![
+            Box::new(FileAdapter::with_bookmarks(BrowserType::Brave, brave.clone(), &[make_bookmark("Hub", "https://hub-brave.example/")])),
+            Box::new(FileAdapter::with_bookmarks(BrowserType::Chrome, chrome.clone(), &[make_bookmark("Other", "https://hub-chrome.example/")])),
+            // Safari has no bookmark file: not installed
+            Box::new(FileAdapter::new(BrowserType::Safari, safari.clone())),
+        ]);
+        let config = HubConfig {
+            hub_names: "brave".to_string(),
+            sync_history: false,
+            sync_reading_list: false,
+            sync_cookies: false,
+            clear_others: true,
+            dry_run: false,
+            verbose: false,
+        };
+
+        engine.set_hub_browsers(&config).await.unwrap();
+
+        // Written (merged) and cleared as usual; the missing browser is no target and no failure
+        assert!(!file_urls(&brave).is_empty());
+        assert!(file_urls(&chrome).is_empty());
+        assert!(!safari.exists());
+    }
+
+    #[tokio::test]
+    async fn test_migrate_to_safari_skips_browser_that_is_not_installed() {
+        use crate::test_support::{isolated_home, make_bookmark, FileAdapter};
+
+        isolated_home();
+        let dir = tempfile::tempdir().unwrap();
+        let safari = dir.path().join("Safari");
+        let chrome = dir.path().join("Chrome");
+        let brave = dir.path().join("Brave");
+        let mut engine = SyncEngine::with_adapters(vec![
+            Box::new(FileAdapter::with_bookmarks(BrowserType::Safari, safari.clone(), &[make_bookmark("Safari", "https://migrate-safari.example/")])),
+            Box::new(FileAdapter::with_bookmarks(BrowserType::Chrome, chrome.clone(), &[make_bookmark("Chrome", "https://migrate-chrome.example/")])),
+            // Brave has no bookmark file: not installed
+            Box::new(FileAdapter::new(BrowserType::Brave, brave.clone())),
+        ]);
+
+        engine.migrate_to_safari(false, false, false).await.unwrap();
+
+        assert!(!file_urls(&safari).is_empty());
+        assert!(file_urls(&chrome).is_empty());
+        assert!(!brave.exists());
+    }
+
+    #[tokio::test]
+    async fn test_history_sync_with_day_window_records_tombstones() {
+        use crate::test_support::{isolated_home, FileAdapter};
+        use crate::url_canon::canonicalize;
+        use std::sync::{Arc, Mutex};
+
+        isolated_home();
+        let dir = tempfile::tempdir().unwrap();
+        let now = chrono::Utc::now().timestamp_millis();
+        let visit = |url: &str, days_ago: i64| HistoryItem {
+            url: url.to_string(),
+            title: None,
+            visit_count: 1,
+            last_visit: Some(now - days_ago * 24 * 60 * 60 * 1000),
+        };
+        let history = vec![visit("https://recent.example/", 1), visit("https://old.example/", 60)];
+        let chrome_history = Arc::new(Mutex::new(history.clone()));
+        let brave_history = Arc::new(Mutex::new(history));
+        let mut engine = SyncEngine::with_adapters(vec![
+            Box::new(FileAdapter::new(BrowserType::Chrome, dir.path().join("Chrome")).with_history(chrome_history.clone())),
+            Box::new(FileAdapter::new(BrowserType::Brave, dir.path().join("Brave")).with_history(brave_history)),
+        ]);
+        engine.sync_history(Some(30), false, false).await.unwrap();
+        assert!(engine.tombstones.tombstones.is_empty());
+
+        // The user deletes a recent entry in Chrome; Brave still has it
+        chrome_history.lock().unwrap().retain(|i| i.url != "https://recent.example/");
+        engine.sync_history(Some(30), false, false).await.unwrap();
+
+        let kind = TombstoneKind::History;
+        assert!(engine.tombstones.is_tombstoned(kind, &canonicalize("https://recent.example/")));
+        // Outside the 30-day window, but still in Chrome: not a deletion
+        assert!(!engine.tombstones.is_tombstoned(kind, &canonicalize("https://old.example/")));
+        let urls: Vec<String> = chrome_history.lock().unwrap().iter().map(|i| i.url.clone()).collect();
+        assert_eq!(urls, vec!["https://old.example/"]);
+    }
 }
 
 /// Property-based tests for organize-accuracy-improvement
@@ -6678,6 +7733,7 @@ impl SyncEngine {
             if !keep_source {
                 info!("  🗑️  Clear bookmarks, history, reading list from other browsers");
             }
+            self.resolver().finish_report("migrate-to-safari", true);
             return Ok(());
         }
 
@@ -6687,20 +7743,39 @@ impl SyncEngine {
         info!("💾 Phase 3: Writing to Safari");
         info!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
 
-        for adapter in &self.adapters {
-            if adapter.browser_type() == BrowserType::Safari {
-                // Backup
-                if let Ok(backup_path) = adapter.backup_bookmarks() {
-                    info!("  💾 Safari backup: {:?}", backup_path);
-                }
+        if !self
+            .adapters
+            .iter()
+            .any(|a| a.browser_type() == BrowserType::Safari && a.is_installed())
+        {
+            anyhow::bail!("Safari is not available, nothing was migrated");
+        }
 
-                // Write bookmarks
-                if let Err(e) = adapter.write_bookmarks(&merged_bookmarks) {
-                    warn!("  ⚠️  Failed to write Safari bookmarks: {}", e);
-                } else {
-                    info!("  ✅ Wrote {} bookmarks to Safari", bookmark_count);
-                }
+        // Bookmarks go to Safari and are cleared elsewhere in one step; any failure rolls all back.
+        // Browsers that are not installed have nothing to clear.
+        let empty_bookmarks: Vec<Bookmark> = Vec::new();
+        let mut targets = Vec::new();
+        for adapter in self.adapters.iter().filter(|a| a.is_installed()) {
+            if adapter.browser_type() == BrowserType::Safari {
+                targets.push(WriteTarget {
+                    adapter: adapter.as_ref(),
+                    bookmarks: &merged_bookmarks,
+                });
+            } else if !keep_source {
+                targets.push(WriteTarget {
+                    adapter: adapter.as_ref(),
+                    bookmarks: &empty_bookmarks,
+                });
+            }
+        }
+        atomic_write::write_bookmarks_atomic(&targets, &atomic_write::default_staging_root()?)?;
+        drop(targets);
+        info!("  ✅ Wrote {} bookmarks to Safari", bookmark_count);
+        self.tombstones
+            .record_bookmarks(BrowserType::Safari.name(), &merged_bookmarks);
 
+        for adapter in &self.adapters {
+            if adapter.browser_type() == BrowserType::Safari {
                 // Write reading list
                 if adapter.supports_reading_list() {
                     if let Err(e) = adapter.write_reading_list(&merged_reading_list) {
@@ -6710,6 +7785,11 @@ impl SyncEngine {
                             "  ✅ Wrote {} reading list items to Safari",
                             merged_reading_list.len()
                         );
+                        self.tombstones.record_seen(
+                            TombstoneKind::ReadingList,
+                            BrowserType::Safari.name(),
+                            tomb::reading_list_items(&merged_reading_list),
+                        );
                     }
                 }
 
@@ -6727,25 +7807,17 @@ impl SyncEngine {
             info!("🗑️  Phase 4: Clearing other browsers");
             info!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
 
-            for adapter in &self.adapters {
+            for adapter in self.adapters.iter().filter(|a| a.is_installed()) {
                 if adapter.browser_type() == BrowserType::Safari {
                     continue; // Skip Safari
                 }
 
                 let browser_name = adapter.browser_type().name();
 
-                // Backup
-                if let Ok(backup_path) = adapter.backup_bookmarks() {
-                    info!("  💾 {} backup: {:?}", browser_name, backup_path);
-                }
-
-                // Clear bookmarks (write empty list)
-                let empty_bookmarks: Vec<Bookmark> = vec![];
-                if let Err(e) = adapter.write_bookmarks(&empty_bookmarks) {
-                    warn!("  ⚠️  Failed to clear {} bookmarks: {}", browser_name, e);
-                } else {
-                    info!("  ✅ Cleared {} bookmarks", browser_name);
-                }
+                // Bookmarks were cleared together with the Safari write
+                info!("  ✅ Cleared {} bookmarks", browser_name);
+                self.tombstones
+                    .record_bookmarks(browser_name, &empty_bookmarks);
 
                 // Clear history
                 if adapter.supports_history() {
@@ -6754,6 +7826,11 @@ impl SyncEngine {
                         warn!("  ⚠️  Failed to clear {} history: {}", browser_name, e);
                     } else {
                         info!("  ✅ Cleared {} history", browser_name);
+                        self.tombstones.record_seen(
+                            TombstoneKind::History,
+                            browser_name,
+                            Default::default(),
+                        );
                     }
                 }
 
@@ -6764,11 +7841,21 @@ impl SyncEngine {
                         warn!("  ⚠️  Failed to clear {} reading list: {}", browser_name, e);
                     } else {
                         info!("  ✅ Cleared {} reading list", browser_name);
+                        self.tombstones.record_seen(
+                            TombstoneKind::ReadingList,
+                            browser_name,
+                            Default::default(),
+                        );
                     }
                 }
             }
         }
 
+        // Migration clears sources on purpose; don't read that as deletions next time
+        if let Err(e) = self.tombstones.save() {
+            warn!("  ⚠️  Failed to save tombstones: {}", e);
+        }
+
         // Verify
         info!("");
         info!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
@@ -6792,6 +7879,7 @@ impl SyncEngine {
         if !keep_source {
             info!("  Other browser data has been cleared (backups saved)");
         }
+        self.resolver().finish_report("migrate-to-safari", false);
 
         Ok(())
     }
@@ -6824,7 +7912,7 @@ impl SyncEngine {
             let browser_name = adapter.browser_type().name();
 
             match adapter.read_bookmarks() {
-                Ok(bookmarks) => {
+                Ok(mut bookmarks) => {
                     let total = Self::count_all_bookmarks(&bookmarks);
                     let folders = Self::count_all_folders(&bookmarks);
 
@@ -6835,6 +7923,34 @@ impl SyncEngine {
 
                     let report = detect_anomalies(&bookmarks);
                     report.print_summary();
+                    near_dupes::print_clusters(&near_dupes::find_clusters(&bookmarks), Some(10));
+
+                    // Preview only: merge on a copy
+                    let synonyms = self
+                        .folder_synonyms
+                        .clone()
+                        .unwrap_or_else(FolderSynonyms::load_default);
+                    let merges =
+                        folder_merge::merge_similar_folders(&mut bookmarks.clone(), &synonyms);
+                    if !merges.is_empty() {
+                        println!();
+                        folder_merge::print_merges(&merges, Some(10));
+                    }
+
+                    // Dry run of dedupe on the local copy: which copies the keep policy drops
+                    let losses = Self::deduplicate_bookmarks_global(
+                        &mut bookmarks,
+                        &self.keep_policy,
+                        Some(browser_name),
+                    );
+                    if !losses.is_empty() {
+                        println!(
+                            "\n🧹 Dedupe preview (keep policy {}): {} copies would be removed",
+                            self.keep_policy.description(),
+                            losses.len()
+                        );
+                        keep_policy::print_losses(&losses, Some(10));
+                    }
                 }
                 Err(e) => {
                     warn!("⚠️  Cannot read {} bookmarks: {}", browser_name, e);
@@ -6910,7 +8026,7 @@ impl SyncEngine {
                             url: None,
                             folder: true,
                             children: bookmarks,
-                            date_added: Some(chrono::Utc::now().timestamp_millis()),
+                            date_added: generated_folder_date(config.deterministic),
                             date_modified: None,
                         };
                         all_bookmarks.push(browser_folder);
@@ -6922,9 +8038,21 @@ impl SyncEngine {
             }
         }
 
+        Self::apply_export_filter(&mut all_bookmarks, &config.filter);
+
         Ok(all_bookmarks)
     }
 
+    /// Apply export filters and log what each filter removed
+    fn apply_export_filter(bookmarks: &mut Vec<Bookmark>, filter: &ExportFilter) {
+        if filter.is_empty() {
+            return;
+        }
+        info!("🔎 Applying export filters...");
+        let stats = filter.apply(bookmarks);
+        stats.print_summary();
+    }
+
     /// Export all bookmarks with additional bookmarks from external sources
     pub async fn export_to_html_with_extra(
         &self,
@@ -6933,7 +8061,11 @@ impl SyncEngine {
         config: &ExportConfig,
         extra_bookmarks: Vec<Bookmark>,
     ) -> Result<usize> {
-        let merge = config.merge;
+        // Browser split needs the per-browser folders, so it overrides merge
+        let merge = config.merge && config.split != Some(ExportSplit::Browser);
+        if config.merge && !merge {
+            warn!("⚠️  --merge ignored: splitting by browser keeps browser folders");
+        }
         let deduplicate = config.deduplicate;
         let clean_empty = config.clean_empty;
         let verbose = config.verbose;
@@ -6970,6 +8102,12 @@ impl SyncEngine {
             anyhow::bail!("No matching browsers found");
         }
 
+        // Deterministic export merges browsers in name order, whatever the adapter order
+        let mut target_adapters = target_adapters;
+        if config.deterministic {
+            target_adapters.sort_by_key(|a| a.browser_type().name());
+        }
+
         info!("🎯 Target browsers:");
         for adapter in &target_adapters {
             info!("  - {}", adapter.browser_type().name());
@@ -7009,7 +8147,7 @@ impl SyncEngine {
                             url: None,
                             folder: true,
                             children: bookmarks,
-                            date_added: Some(chrono::Utc::now().timestamp_millis()),
+                            date_added: generated_folder_date(config.deterministic),
                             date_modified: None,
                         };
                         all_bookmarks.push(browser_folder);
@@ -7033,18 +8171,29 @@ impl SyncEngine {
             all_bookmarks = filtered;
         }
 
+        // Apply export filters (date range, domain, scheme, title) before dedupe
+        Self::apply_export_filter(&mut all_bookmarks, &config.filter);
+
         let before_dedup = Self::count_all_bookmarks(&all_bookmarks);
         info!("\n📊 Collection complete: {} bookmarks", before_dedup);
 
         // Deduplicate if requested
         if deduplicate {
             info!("🧹 Deduplicating...");
-            Self::deduplicate_bookmarks_global(&mut all_bookmarks);
+            let losses =
+                Self::deduplicate_bookmarks_global(&mut all_bookmarks, &config.keep_policy, None);
             let after_dedup = Self::count_all_bookmarks(&all_bookmarks);
             let removed = before_dedup.saturating_sub(after_dedup);
             if removed > 0 {
                 info!("  ✅ Removed {} duplicate bookmarks", removed);
+                keep_policy::print_losses(&losses, if config.verbose { None } else { Some(0) });
             }
+            scheduler::report_result(JobResult::Dedupe(DedupeStats {
+                total_before: before_dedup,
+                duplicates_removed: removed,
+                total_after: after_dedup,
+                losses,
+            }));
         }
 
         // Clean empty folders if requested
@@ -7063,23 +8212,80 @@ impl SyncEngine {
             }
         }
 
+        // Split export: one file per folder / browser / domain + index
+        if let Some(split) = config.split {
+            Self::log_source_stats(&browser_stats, verbose);
+            return Self::write_split_export(all_bookmarks, output_path, config, split);
+        }
+
         // Flatten export: remove browser root folders
-        if config.flat {
-            info!("📦 Flattening: removing browser root folders...");
-            let flat_config = FlatExportConfig {
-                flatten_root: true,
-                root_folders_to_remove: None,
-            };
-            let (flattened, stats) = flatten_bookmarks(&all_bookmarks, &flat_config);
-            all_bookmarks = flattened;
-            if stats.root_folders_removed > 0 {
-                info!("  ✅ Removed {} browser root folders, promoted {} items", 
-                    stats.root_folders_removed, stats.bookmarks_promoted);
+        all_bookmarks = Self::flatten_export_tree(all_bookmarks, config);
+
+        // Sort before wrapping so depth 0 is the user's top level
+        Self::sort_export_tree(&mut all_bookmarks, config);
+
+        // Wrap all bookmarks in a single root folder (default behavior)
+        all_bookmarks = Self::wrap_export_tree(all_bookmarks, config);
+
+        // Deterministic export: ids derived from URL + folder path
+        if config.deterministic {
+            assign_stable_ids(&mut all_bookmarks);
+        }
+
+        let final_count = Self::count_all_bookmarks(&all_bookmarks);
+
+        // Export to HTML / JSON
+        let output = expand_home(output_path);
+        write_bookmarks_file(&all_bookmarks, &output, config.format, config.dialect)?;
+
+        info!("\n✅ Export complete!");
+        info!("   📄 File: {}", output);
+        info!("   📊 Bookmarks: {}", final_count);
+
+        Self::log_source_stats(&browser_stats, verbose);
+
+        Ok(final_count)
+    }
+
+    fn log_source_stats(browser_stats: &[(String, usize)], verbose: bool) {
+        if verbose {
+            info!("\n📊 Source statistics:");
+            for (browser, count) in browser_stats {
+                info!("   {} : {}", browser, count);
             }
         }
+    }
 
-        // Wrap all bookmarks in a single root folder (default behavior)
-        // Use wrap_folder name or default "📁镜像文件夹"
+    /// Flatten export: remove browser root folders (no-op unless `config.flat`)
+    fn flatten_export_tree(bookmarks: Vec<Bookmark>, config: &ExportConfig) -> Vec<Bookmark> {
+        if !config.flat {
+            return bookmarks;
+        }
+        info!("📦 Flattening: removing browser root folders...");
+        let flat_config = FlatExportConfig {
+            flatten_root: true,
+            root_folders_to_remove: None,
+        };
+        let (flattened, stats) = flatten_bookmarks(&bookmarks, &flat_config);
+        if stats.root_folders_removed > 0 {
+            info!("  ✅ Removed {} browser root folders, promoted {} items", 
+                stats.root_folders_removed, stats.bookmarks_promoted);
+        }
+        flattened
+    }
+
+    /// Sort the export tree if a sort mode is configured
+    fn sort_export_tree(bookmarks: &mut [Bookmark], config: &ExportConfig) {
+        if let Some(ref sort) = config.sort {
+            info!("🔤 Sorting: {}", sort.description());
+            sort_bookmarks(bookmarks, sort);
+        }
+    }
+
+    /// Wrap all bookmarks in a single root folder (default behavior)
+    /// Use wrap_folder name or default "📁镜像文件夹"; with `no_wrap` only
+    /// ensure protected folders exist
+    fn wrap_export_tree(mut all_bookmarks: Vec<Bookmark>, config: &ExportConfig) -> Vec<Bookmark> {
         let wrap_name = config.wrap_folder.clone().unwrap_or_else(|| "📁镜像文件夹".to_string());
         
         if !config.no_wrap {
@@ -7103,7 +8309,7 @@ impl SyncEngine {
                 url: None,
                 folder: true,
                 children: all_bookmarks,
-                date_added: Some(chrono::Utc::now().timestamp_millis()),
+                date_added: generated_folder_date(config.deterministic),
                 date_modified: None,
             };
             
@@ -7114,42 +8320,151 @@ impl SyncEngine {
                 url: None,
                 folder: true,
                 children: merged_temp_children,
-                date_added: Some(chrono::Utc::now().timestamp_millis()),
+                date_added: generated_folder_date(config.deterministic),
                 date_modified: None,
             };
             
             // Top level: wrap folder + single merged temp folder
-            all_bookmarks = vec![wrapped, temp_folder];
             info!("  ✅ Merged all temp folders into single 👀临时");
+            vec![wrapped, temp_folder]
         } else {
             // No wrap - just ensure protected folders exist
-            ensure_protected_folders(&mut all_bookmarks);
+            ensure_protected_folders(&mut all_bookmarks, config.deterministic);
+            all_bookmarks
         }
+    }
 
-        let final_count = Self::count_all_bookmarks(&all_bookmarks);
+    /// Write a split export: one file per group plus an index file
+    /// 输出路径作为目录；若带 .html/.json 扩展名则去掉扩展名作为目录名
+    fn write_split_export(
+        all_bookmarks: Vec<Bookmark>,
+        output_path: &str,
+        config: &ExportConfig,
+        split: ExportSplit,
+    ) -> Result<usize> {
+        let output_dir = split_output_dir(&expand_home(output_path));
+        std::fs::create_dir_all(&output_dir)
+            .with_context(|| format!("Failed to create output directory: {}", output_dir.display()))?;
+
+        info!("✂️  Splitting export by {} into {}", split.name(), output_dir.display());
+
+        // Browser split groups before flattening (browser folders are the groups);
+        // folder and domain split group the flattened tree
+        let groups = match split {
+            ExportSplit::Browser => split_by_browser(&all_bookmarks)
+                .into_iter()
+                .map(|(name, children)| (name, Self::flatten_export_tree(children, config)))
+                .collect(),
+            ExportSplit::Folder => split_by_top_folder(&Self::flatten_export_tree(all_bookmarks, config)),
+            ExportSplit::Domain => split_by_domain(&Self::flatten_export_tree(all_bookmarks, config)),
+        };
 
-        // Export to HTML
-        let output = if output_path.starts_with("~/") {
-            let home = std::env::var("HOME").unwrap_or_default();
-            output_path.replacen("~", &home, 1)
-        } else {
-            output_path.to_string()
+        let format = config.format;
+        let mut used_names: HashSet<String> = HashSet::new();
+        let mut index = SplitExportIndex {
+            split: split.name().to_string(),
+            format: format.extension().to_string(),
+            total_bookmarks: 0,
+            files: Vec::new(),
         };
 
-        export_bookmarks_to_html(&all_bookmarks, &output)?;
+        for (name, mut group) in groups {
+            Self::sort_export_tree(&mut group, config);
+            let mut group = Self::wrap_export_tree(group, config);
+            if config.deterministic {
+                assign_stable_ids(&mut group);
+            }
 
-        info!("\n✅ Export complete!");
-        info!("   📄 File: {}", output);
-        info!("   📊 Bookmarks: {}", final_count);
+            let count = Self::count_all_bookmarks(&group);
+            let file_name = unique_split_file_name(&name, format, &mut used_names);
+            let path = output_dir.join(&file_name);
+            write_bookmarks_file(&group, &path.to_string_lossy(), format, config.dialect)?;
+            info!("  ✅ {} : {} bookmarks → {}", name, count, file_name);
 
-        if verbose {
-            info!("\n📊 Source statistics:");
-            for (browser, count) in &browser_stats {
-                info!("   {} : {}", browser, count);
+            index.total_bookmarks += count;
+            index.files.push(SplitIndexEntry { name, file: file_name, bookmarks: count });
+        }
+
+        let index_path = output_dir.join(format!("index.{}", format.extension()));
+        write_split_index(&index, &index_path.to_string_lossy(), format)?;
+
+        info!("\n✅ Split export complete!");
+        info!("   📂 Directory: {}", output_dir.display());
+        info!("   📄 Files: {} (+ index)", index.files.len());
+        info!("   📊 Bookmarks: {}", index.total_bookmarks);
+
+        Ok(index.total_bookmarks)
+    }
+
+    /// Apply a changeset produced by `--plan` after re-checking its preconditions
+    pub fn apply_changeset(
+        &self,
+        changeset: &Changeset,
+        allow_drift: bool,
+        dry_run: bool,
+    ) -> Result<()> {
+        info!("{}", changeset.summary());
+
+        let mut problems = Vec::new();
+        let mut planned = Vec::new();
+        for target in &changeset.targets {
+            let Some(adapter) = self
+                .adapters
+                .iter()
+                .find(|a| a.browser_type().name() == target.browser)
+            else {
+                problems.push(format!("{}: browser is not available", target.browser));
+                continue;
+            };
+            let path = adapter.detect_bookmark_path()?;
+            if path.display().to_string() != target.path {
+                problems.push(format!(
+                    "{}: bookmark file is {} but the plan targets {} (profile {})",
+                    target.browser,
+                    path.display(),
+                    target.path,
+                    target.profile
+                ));
+                continue;
             }
+            let current = adapter.read_bookmarks()?;
+            problems.extend(changeset.check(target, &current, allow_drift));
+            planned.push((adapter, current));
         }
 
-        Ok(final_count)
+        if !problems.is_empty() {
+            for problem in &problems {
+                error!("  ❌ {}", problem);
+            }
+            anyhow::bail!(
+                "Changeset preconditions failed ({} problems), nothing was changed",
+                problems.len()
+            );
+        }
+
+        let mut results = Vec::new();
+        for (adapter, mut tree) in planned {
+            let name = adapter.browser_type().name();
+            let applied = changeset.apply_to(name, &mut tree);
+            info!("  ✅ {}: {} changes applied", name, applied);
+            results.push((adapter, tree));
+        }
+
+        if dry_run {
+            info!("🏃 Dry run mode - no changes will be made");
+            return Ok(());
+        }
+
+        let targets: Vec<WriteTarget> = results
+            .iter()
+            .map(|(adapter, tree)| WriteTarget {
+                adapter: adapter.as_ref(),
+                bookmarks: tree,
+            })
+            .collect();
+        atomic_write::write_bookmarks_atomic(&targets, &atomic_write::default_staging_root()?)?;
+        info!("✅ Changeset applied to {} browsers", targets.len());
+        Ok(())
     }
 
     /// Clear bookmarks from specified browsers (use with caution!)
@@ -7410,61 +8725,151 @@ fn collect_urls_recursive(
 
 /// Export bookmarks to Netscape HTML format (standard bookmark format)
 pub fn export_bookmarks_to_html(bookmarks: &[Bookmark], output_path: &str) -> Result<()> {
-    use std::io::Write;
+    export_bookmarks_to_html_dialect(bookmarks, output_path, HtmlDialect::Generic)
+}
+
+/// Export bookmarks as Netscape HTML tuned for the browser that will import it
+pub fn export_bookmarks_to_html_dialect(
+    bookmarks: &[Bookmark],
+    output_path: &str,
+    dialect: HtmlDialect,
+) -> Result<()> {
+    let html = render_bookmarks_html(bookmarks, dialect)?;
+    std::fs::write(output_path, html).with_context(|| format!("Failed to write {}", output_path))
+}
+
+/// Titles that browsers use for their toolbar root
+const TOOLBAR_FOLDER_TITLES: &[&str] = &[
+    "bookmarks bar", "bookmark bar", "bookmarks toolbar", "favorites bar", "favourites bar",
+    "toolbar", "书签栏", "书签工具栏", "收藏夹栏",
+];
+
+fn is_toolbar_folder(bookmark: &Bookmark) -> bool {
+    bookmark.folder && TOOLBAR_FOLDER_TITLES.contains(&bookmark.title.trim().to_lowercase().as_str())
+}
 
-    let mut file = std::fs::File::create(output_path)?;
+/// Render bookmarks as Netscape HTML in the given dialect
+pub fn render_bookmarks_html(bookmarks: &[Bookmark], dialect: HtmlDialect) -> Result<String> {
+    let mut out: Vec<u8> = Vec::new();
+    let file = &mut out;
 
     // Write HTML header
     writeln!(file, "<!DOCTYPE NETSCAPE-Bookmark-file-1>")?;
-    writeln!(file, "<!-- This is an automatically generated file.")?;
-    writeln!(file, "     It will be read and overwritten.")?;
-    writeln!(file, "     DO NOT EDIT! -->")?;
+    if dialect == HtmlDialect::Safari {
+        writeln!(file, "<HTML>")?;
+    } else {
+        writeln!(file, "<!-- This is an automatically generated file.")?;
+        writeln!(file, "     It will be read and overwritten.")?;
+        writeln!(file, "     DO NOT EDIT! -->")?;
+    }
     writeln!(
         file,
         "<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">"
     )?;
+    if dialect == HtmlDialect::Firefox {
+        writeln!(
+            file,
+            "<meta http-equiv=\"Content-Security-Policy\" content=\"default-src 'self'; script-src 'none'; img-src data: *; object-src 'none'\"></meta>"
+        )?;
+    }
     writeln!(file, "<TITLE>Bookmarks</TITLE>")?;
-    writeln!(file, "<H1>Bookmarks</H1>")?;
+    if dialect == HtmlDialect::Firefox {
+        writeln!(file, "<H1>Bookmarks Menu</H1>")?;
+        writeln!(file)?;
+    } else {
+        writeln!(file, "<H1>Bookmarks</H1>")?;
+    }
     writeln!(file, "<DL><p>")?;
 
-    // Write bookmarks recursively
-    write_bookmarks_html_recursive(&mut file, bookmarks, 1)?;
+    // Root layout: which top-level folder is the toolbar
+    match dialect {
+        HtmlDialect::Generic | HtmlDialect::Safari => {
+            write_bookmarks_html_recursive(file, bookmarks, 1, dialect, None)?;
+        }
+        HtmlDialect::Firefox => {
+            // Only the first toolbar root becomes PERSONAL_TOOLBAR_FOLDER; the rest go to the menu
+            let toolbar = bookmarks.iter().position(is_toolbar_folder);
+            write_bookmarks_html_recursive(file, bookmarks, 1, dialect, toolbar)?;
+        }
+        HtmlDialect::Chromium => {
+            // Chromium puts every import under one "Imported" folder; a single toolbar root
+            // keeps the tree in one place instead of scattering top-level items
+            if let Some(toolbar) = bookmarks.iter().position(is_toolbar_folder) {
+                write_bookmarks_html_recursive(file, bookmarks, 1, dialect, Some(toolbar))?;
+            } else {
+                let bar = Bookmark {
+                    id: "toolbar".to_string(),
+                    title: "Bookmarks bar".to_string(),
+                    url: None,
+                    folder: true,
+                    children: bookmarks.to_vec(),
+                    date_added: None,
+                    date_modified: None,
+                };
+                write_bookmarks_html_recursive(file, std::slice::from_ref(&bar), 1, dialect, Some(0))?;
+            }
+        }
+    }
 
     writeln!(file, "</DL><p>")?;
+    if dialect == HtmlDialect::Safari {
+        writeln!(file, "</HTML>")?;
+    }
 
-    Ok(())
+    Ok(String::from_utf8(out)?)
 }
 
 fn write_bookmarks_html_recursive<W: Write>(
     writer: &mut W,
     bookmarks: &[Bookmark],
     indent: usize,
+    dialect: HtmlDialect,
+    toolbar_index: Option<usize>,
 ) -> Result<()> {
     let indent_str = "    ".repeat(indent);
 
-    for bookmark in bookmarks {
+    for (index, bookmark) in bookmarks.iter().enumerate() {
+        let add_date = bookmark.date_added.unwrap_or(0) / 1000; // Convert to seconds
+        let last_modified = bookmark.date_modified.or(bookmark.date_added).unwrap_or(0) / 1000;
+
         if bookmark.folder {
             // Write folder
-            let add_date = bookmark.date_added.unwrap_or(0) / 1000; // Convert to seconds
+            let attrs = match dialect {
+                HtmlDialect::Generic => format!(" ADD_DATE=\"{}\"", add_date),
+                HtmlDialect::Safari => " FOLDED".to_string(),
+                HtmlDialect::Firefox | HtmlDialect::Chromium => {
+                    let mut attrs = format!(" ADD_DATE=\"{}\" LAST_MODIFIED=\"{}\"", add_date, last_modified);
+                    if toolbar_index == Some(index) {
+                        attrs.push_str(" PERSONAL_TOOLBAR_FOLDER=\"true\"");
+                    }
+                    attrs
+                }
+            };
             writeln!(
                 writer,
-                "{}<DT><H3 ADD_DATE=\"{}\">{}</H3>",
+                "{}<DT><H3{}>{}</H3>",
                 indent_str,
-                add_date,
+                attrs,
                 html_escape(&bookmark.title)
             )?;
             writeln!(writer, "{}<DL><p>", indent_str)?;
-            write_bookmarks_html_recursive(writer, &bookmark.children, indent + 1)?;
+            write_bookmarks_html_recursive(writer, &bookmark.children, indent + 1, dialect, None)?;
             writeln!(writer, "{}</DL><p>", indent_str)?;
         } else if let Some(url) = &bookmark.url {
             // Write bookmark
-            let add_date = bookmark.date_added.unwrap_or(0) / 1000;
+            let attrs = match dialect {
+                HtmlDialect::Generic | HtmlDialect::Chromium => format!(" ADD_DATE=\"{}\"", add_date),
+                HtmlDialect::Safari => String::new(),
+                HtmlDialect::Firefox => {
+                    format!(" ADD_DATE=\"{}\" LAST_MODIFIED=\"{}\"", add_date, last_modified)
+                }
+            };
             writeln!(
                 writer,
-                "{}<DT><A HREF=\"{}\" ADD_DATE=\"{}\">{}</A>",
+                "{}<DT><A HREF=\"{}\"{}>{}</A>",
                 indent_str,
                 html_escape(url),
-                add_date,
+                attrs,
                 html_escape(&bookmark.title)
             )?;
         }
@@ -7741,76 +9146,26 @@ fn flatten_bookmarks_inplace_impl(
 }
 
 /// 去重统计
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct DedupeStats {
     pub total_before: usize,
     pub duplicates_removed: usize,
     pub total_after: usize,
+    /// 每一份被移除的书签及原因
+    #[serde(default)]
+    pub losses: Vec<DedupeLoss>,
 }
 
-/// 去除重复书签（基于 URL），保留第一个出现的
-pub fn deduplicate_bookmarks(bookmarks: &mut Vec<Bookmark>) -> DedupeStats {
-    let mut stats = DedupeStats::default();
-    let mut seen_urls: HashSet<String> = HashSet::new();
-    
-    fn count_bookmarks(bookmarks: &[Bookmark]) -> usize {
-        bookmarks.iter().map(|b| {
-            if b.folder { count_bookmarks(&b.children) } else { 1 }
-        }).sum()
-    }
-    
-    stats.total_before = count_bookmarks(bookmarks);
-    
-    fn dedupe_recursive(bookmarks: &mut Vec<Bookmark>, seen: &mut HashSet<String>) -> usize {
-        let mut removed = 0;
-        
-        // 先递归处理子文件夹
-        for bookmark in bookmarks.iter_mut() {
-            if bookmark.folder {
-                removed += dedupe_recursive(&mut bookmark.children, seen);
-            }
-        }
-        
-        // 然后处理当前层级
-        bookmarks.retain(|b| {
-            if b.folder {
-                true // 保留文件夹
-            } else if let Some(ref url) = b.url {
-                let normalized = normalize_url_for_dedupe(url);
-                if seen.contains(&normalized) {
-                    removed += 1;
-                    false // 移除重复
-                } else {
-                    seen.insert(normalized);
-                    true
-                }
-            } else {
-                true // 保留无 URL 的书签
-            }
-        });
-        
-        removed
-    }
-    
-    stats.duplicates_removed = dedupe_recursive(bookmarks, &mut seen_urls);
-    stats.total_after = count_bookmarks(bookmarks);
-    stats
-}
-
-/// 标准化 URL 用于去重比较
-fn normalize_url_for_dedupe(url: &str) -> String {
-    let mut normalized = url.trim().to_lowercase();
-    // 移除尾部斜杠
-    while normalized.ends_with('/') {
-        normalized.pop();
-    }
-    // 移除 fragment
-    if let Some(pos) = normalized.find('#') {
-        normalized.truncate(pos);
+/// 去除重复书签（基于 URL），按保留策略决定留哪一份
+pub fn deduplicate_bookmarks(bookmarks: &mut Vec<Bookmark>, policy: &KeepPolicy) -> DedupeStats {
+    let total_before = SyncEngine::count_all_bookmarks(bookmarks);
+    let losses = SyncEngine::deduplicate_bookmarks_global(bookmarks, policy, None);
+    DedupeStats {
+        total_before,
+        duplicates_removed: losses.len(),
+        total_after: SyncEngine::count_all_bookmarks(bookmarks),
+        losses,
     }
-    // 移除 www. 前缀
-    normalized = normalized.replace("://www.", "://");
-    normalized
 }
 
 /// 清理统计
@@ -7866,7 +9221,7 @@ pub fn clean_empty_folders(bookmarks: &mut Vec<Bookmark>) -> CleanStats {
 }
 
 /// 确保受保护的文件夹存在（如 👀临时）
-pub fn ensure_protected_folders(bookmarks: &mut Vec<Bookmark>) {
+pub fn ensure_protected_folders(bookmarks: &mut Vec<Bookmark>, deterministic: bool) {
     // 检查 👀临时 文件夹是否存在
     let temp_folder_exists = bookmarks.iter().any(|b| {
         b.folder && is_protected_folder(&b.title)
@@ -7880,7 +9235,7 @@ pub fn ensure_protected_folders(bookmarks: &mut Vec<Bookmark>) {
             url: None,
             folder: true,
             children: vec![],
-            date_added: Some(chrono::Utc::now().timestamp_millis()),
+            date_added: generated_folder_date(deterministic),
             date_modified: None,
         };
         bookmarks.push(temp_folder);
@@ -7908,7 +9263,7 @@ pub fn merge_bookmarks_incremental(
             if b.folder {
                 collect_urls(&b.children, urls);
             } else if let Some(ref url) = b.url {
-                urls.insert(normalize_url_for_dedupe(url));
+                urls.insert(canonicalize(url));
             }
         }
     }
@@ -7943,7 +9298,7 @@ pub fn merge_bookmarks_incremental(
                     }
                 }
             } else if let Some(ref url) = bookmark.url {
-                let normalized = normalize_url_for_dedupe(url);
+                let normalized = canonicalize(url);
                 if existing_urls.contains(&normalized) {
                     stats.skipped_duplicates += 1;
                 } else {
@@ -7958,6 +9313,306 @@ pub fn merge_bookmarks_incremental(
     stats
 }
 
+/// 生成文件夹（包装、浏览器根、临时）的时间戳；确定性导出时不写入当前时间
+pub fn generated_folder_date(deterministic: bool) -> Option<i64> {
+    if deterministic {
+        None
+    } else {
+        Some(chrono::Utc::now().timestamp_millis())
+    }
+}
+
+/// 基于文件夹路径和 URL 生成稳定 ID（同一数据多次导出 ID 不变）
+fn stable_id(prefix: &str, folder_path: &str, key: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(folder_path.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(key.as_bytes());
+    let digest = format!("{:x}", hasher.finalize());
+    format!("{}-{}", prefix, &digest[..16])
+}
+
+/// 为整棵书签树分配稳定 ID（只有 JSON 导出写出 ID，HTML 格式没有 ID 属性）
+/// 文件夹 ID 由路径决定，书签 ID 由所在路径 + URL 决定；同级重复项追加序号
+/// 同级顺序保持浏览器里的原始顺序，不在这里重新排序
+pub fn assign_stable_ids(bookmarks: &mut [Bookmark]) {
+    fn assign_recursive(bookmarks: &mut [Bookmark], parent_path: &str) {
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for bookmark in bookmarks.iter_mut() {
+            let (prefix, key) = if bookmark.folder {
+                ("folder", bookmark.title.clone())
+            } else {
+                ("bm", bookmark.url.clone().unwrap_or_else(|| bookmark.title.clone()))
+            };
+            let occurrence = seen.entry(format!("{}|{}", prefix, key)).or_insert(0);
+            *occurrence += 1;
+            let mut id = stable_id(prefix, parent_path, &key);
+            if *occurrence > 1 {
+                id = format!("{}-{}", id, occurrence);
+            }
+            bookmark.id = id;
+
+            if bookmark.folder {
+                let path = format!("{}/{}", parent_path, bookmark.title);
+                assign_recursive(&mut bookmark.children, &path);
+            }
+        }
+    }
+
+    assign_recursive(bookmarks, "");
+}
+
+/// 展开输出路径中的 `~/`
+fn expand_home(path: &str) -> String {
+    if path.starts_with("~/") {
+        let home = std::env::var("HOME").unwrap_or_default();
+        path.replacen("~", &home, 1)
+    } else {
+        path.to_string()
+    }
+}
+
+/// 按格式写出书签文件（HTML 按目标浏览器方言，或 JSON）
+pub fn write_bookmarks_file(
+    bookmarks: &[Bookmark],
+    output_path: &str,
+    format: ExportFormat,
+    dialect: HtmlDialect,
+) -> Result<()> {
+    match format {
+        ExportFormat::Html => export_bookmarks_to_html_dialect(bookmarks, output_path, dialect),
+        ExportFormat::Json => {
+            let json = serde_json::to_string_pretty(bookmarks)?;
+            std::fs::write(output_path, json)
+                .with_context(|| format!("Failed to write {}", output_path))
+        }
+    }
+}
+
+/// 按格式读取书签文件（HTML 或 JSON）
+pub fn read_bookmarks_file(input_path: &str, format: ExportFormat) -> Result<Vec<Bookmark>> {
+    match format {
+        ExportFormat::Html => import_bookmarks_from_html(input_path),
+        ExportFormat::Json => {
+            let content = std::fs::read_to_string(input_path)
+                .with_context(|| format!("Failed to read {}", input_path))?;
+            serde_json::from_str(&content).context("Failed to parse JSON bookmark file")
+        }
+    }
+}
+
+/// 拆分导出中无法归类的书签所在分组
+pub const SPLIT_OTHER_GROUP: &str = "Other";
+
+/// 拆分导出的索引条目
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SplitIndexEntry {
+    pub name: String,
+    pub file: String,
+    pub bookmarks: usize,
+}
+
+/// 拆分导出的索引文件内容
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SplitExportIndex {
+    pub split: String,
+    pub format: String,
+    pub total_bookmarks: usize,
+    pub files: Vec<SplitIndexEntry>,
+}
+
+/// 拆分导出的输出目录：`bookmarks.html` → `bookmarks/`，无扩展名则原样使用
+fn split_output_dir(output_path: &str) -> PathBuf {
+    let path = PathBuf::from(output_path);
+    match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()) {
+        Some(ext) if ext == "html" || ext == "htm" || ext == "json" => path.with_extension(""),
+        _ => path,
+    }
+}
+
+/// 把同名分组合并，保持首次出现的顺序
+fn push_group(groups: &mut Vec<(String, Vec<Bookmark>)>, name: &str, items: Vec<Bookmark>) {
+    if let Some((_, existing)) = groups.iter_mut().find(|(n, _)| n == name) {
+        existing.extend(items);
+    } else {
+        groups.push((name.to_string(), items));
+    }
+}
+
+/// 按顶层文件夹拆分；顶层散落的书签归入 "Other"
+pub fn split_by_top_folder(bookmarks: &[Bookmark]) -> Vec<(String, Vec<Bookmark>)> {
+    let mut groups: Vec<(String, Vec<Bookmark>)> = Vec::new();
+    let mut loose: Vec<Bookmark> = Vec::new();
+
+    for bookmark in bookmarks {
+        if bookmark.folder {
+            push_group(&mut groups, &bookmark.title, bookmark.children.clone());
+        } else {
+            loose.push(bookmark.clone());
+        }
+    }
+
+    if !loose.is_empty() {
+        push_group(&mut groups, SPLIT_OTHER_GROUP, loose);
+    }
+    groups
+}
+
+/// 按来源浏览器拆分（依赖收集阶段生成的 `browser-*` 文件夹）
+/// HTML 导入、阅读列表、历史等额外数据归入 "Other"
+pub fn split_by_browser(bookmarks: &[Bookmark]) -> Vec<(String, Vec<Bookmark>)> {
+    let mut groups: Vec<(String, Vec<Bookmark>)> = Vec::new();
+    let mut other: Vec<Bookmark> = Vec::new();
+
+    for bookmark in bookmarks {
+        if bookmark.folder && bookmark.id.starts_with("browser-") {
+            push_group(&mut groups, &bookmark.title, bookmark.children.clone());
+        } else {
+            other.push(bookmark.clone());
+        }
+    }
+
+    if !other.is_empty() {
+        push_group(&mut groups, SPLIT_OTHER_GROUP, other);
+    }
+    groups
+}
+
+/// 按可注册域名拆分，保留文件夹结构（每个域名只含包含该域名书签的文件夹）
+/// 分组按域名排序；没有主机名的链接（javascript:、file:// 等）归入 "Other"
+pub fn split_by_domain(bookmarks: &[Bookmark]) -> Vec<(String, Vec<Bookmark>)> {
+    fn split_recursive(bookmarks: &[Bookmark]) -> std::collections::BTreeMap<String, Vec<Bookmark>> {
+        let mut groups: std::collections::BTreeMap<String, Vec<Bookmark>> = Default::default();
+        for bookmark in bookmarks {
+            if bookmark.folder {
+                for (domain, children) in split_recursive(&bookmark.children) {
+                    groups.entry(domain).or_default().push(Bookmark {
+                        id: bookmark.id.clone(),
+                        title: bookmark.title.clone(),
+                        url: None,
+                        folder: true,
+                        children,
+                        date_added: bookmark.date_added,
+                        date_modified: bookmark.date_modified,
+                    });
+                }
+            } else {
+                let domain = bookmark
+                    .url
+                    .as_deref()
+                    .and_then(registrable_domain)
+                    .unwrap_or_else(|| SPLIT_OTHER_GROUP.to_string());
+                groups.entry(domain).or_default().push(bookmark.clone());
+            }
+        }
+        groups
+    }
+
+    split_recursive(bookmarks).into_iter().collect()
+}
+
+/// 提取 URL 主机名（小写，去掉端口和用户信息）
+pub fn url_host(url: &str) -> Option<String> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host_port = authority.rsplit('@').next().unwrap_or("");
+    let host = if host_port.starts_with('[') {
+        // IPv6 literal
+        host_port.split(']').next().map(|h| format!("{}]", h)).unwrap_or_default()
+    } else {
+        host_port.split(':').next().unwrap_or("").to_string()
+    };
+    let host = host.trim_end_matches('.').to_lowercase();
+    if host.is_empty() {
+        None
+    } else {
+        Some(host)
+    }
+}
+
+/// 常见的二级公共后缀（co.uk、com.cn 等），用于近似计算可注册域名
+const SECOND_LEVEL_SUFFIXES: &[&str] = &["co", "com", "net", "org", "gov", "edu", "ac", "or", "ne", "go"];
+
+/// 可注册域名（近似 eTLD+1）：`docs.github.com` → `github.com`，`news.bbc.co.uk` → `bbc.co.uk`
+/// IP 地址和单标签主机（localhost）原样返回
+pub fn registrable_domain(url: &str) -> Option<String> {
+    let host = url_host(url)?;
+    if host.starts_with('[') || host.parse::<std::net::Ipv4Addr>().is_ok() {
+        return Some(host);
+    }
+
+    let labels: Vec<&str> = host.split('.').filter(|l| !l.is_empty()).collect();
+    if labels.len() <= 2 {
+        return Some(labels.join("."));
+    }
+
+    let tld = labels[labels.len() - 1];
+    let sld = labels[labels.len() - 2];
+    let keep = if tld.len() == 2 && SECOND_LEVEL_SUFFIXES.contains(&sld) { 3 } else { 2 };
+    Some(labels[labels.len() - keep..].join("."))
+}
+
+/// 分组名 → 安全文件名（保留中文和 emoji，替换路径分隔符等非法字符）
+pub fn sanitize_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').trim();
+    if cleaned.is_empty() {
+        "untitled".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// 生成不重复的拆分文件名（大小写不敏感文件系统下也不冲突，避开 index）
+fn unique_split_file_name(name: &str, format: ExportFormat, used: &mut HashSet<String>) -> String {
+    let base = sanitize_file_name(name);
+    let mut candidate = base.clone();
+    let mut n = 2;
+    while candidate.eq_ignore_ascii_case("index") || used.contains(&candidate.to_lowercase()) {
+        candidate = format!("{}-{}", base, n);
+        n += 1;
+    }
+    used.insert(candidate.to_lowercase());
+    format!("{}.{}", candidate, format.extension())
+}
+
+/// 写出拆分导出的索引文件（HTML 为链接列表页面，JSON 为结构化索引）
+fn write_split_index(index: &SplitExportIndex, output_path: &str, format: ExportFormat) -> Result<()> {
+    let content = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(index)?,
+        ExportFormat::Html => {
+            let mut html = String::new();
+            html.push_str("<!DOCTYPE html>\n<html>\n<head>\n");
+            html.push_str("<meta charset=\"UTF-8\">\n");
+            html.push_str(&format!("<title>Bookmarks by {}</title>\n", html_escape(&index.split)));
+            html.push_str("</head>\n<body>\n");
+            html.push_str(&format!(
+                "<h1>Bookmarks by {} ({} bookmarks)</h1>\n<ul>\n",
+                html_escape(&index.split),
+                index.total_bookmarks
+            ));
+            for entry in &index.files {
+                html.push_str(&format!(
+                    "<li><a href=\"{}\">{}</a> ({})</li>\n",
+                    html_escape(&entry.file),
+                    html_escape(&entry.name),
+                    entry.bookmarks
+                ));
+            }
+            html.push_str("</ul>\n</body>\n</html>\n");
+            html
+        }
+    };
+    std::fs::write(output_path, content).with_context(|| format!("Failed to write {}", output_path))
+}
+
 /// 带扁平导出选项的 HTML 导出
 pub fn export_bookmarks_to_html_flat(
     bookmarks: &[Bookmark],
@@ -7965,6 +9620,7 @@ pub fn export_bookmarks_to_html_flat(
     flat_config: Option<&FlatExportConfig>,
     dedupe: bool,
     clean_empty: bool,
+    deterministic: bool,
 ) -> Result<(usize, FlattenStats, DedupeStats, CleanStats)> {
     let mut working_bookmarks = bookmarks.to_vec();
     let mut flatten_stats = FlattenStats::default();
@@ -7984,7 +9640,7 @@ pub fn export_bookmarks_to_html_flat(
     
     // 2. 去重（如果启用）
     if dedupe {
-        dedupe_stats = deduplicate_bookmarks(&mut working_bookmarks);
+        dedupe_stats = deduplicate_bookmarks(&mut working_bookmarks, &KeepPolicy::default());
         if dedupe_stats.duplicates_removed > 0 {
             info!("🔄 去重: 移除 {} 个重复书签 ({} → {})", 
                 dedupe_stats.duplicates_removed, dedupe_stats.total_before, dedupe_stats.total_after);
@@ -7999,7 +9655,12 @@ pub fn export_bookmarks_to_html_flat(
         }
     }
     
-    // 4. 导出
+    // 4. 确定性导出：稳定 ID
+    if deterministic {
+        assign_stable_ids(&mut working_bookmarks);
+    }
+    
+    // 5. 导出
     export_bookmarks_to_html(&working_bookmarks, output_path)?;
     
     // 计算最终书签数
@@ -8108,12 +9769,12 @@ mod export_quality_tests {
             let b2 = make_bookmark(&title2, &url);
             let mut bookmarks = vec![b1, b2];
 
-            let stats = deduplicate_bookmarks(&mut bookmarks);
+            let stats = deduplicate_bookmarks(&mut bookmarks, &KeepPolicy::default());
 
             // Property: no duplicate URLs after deduplication
             let urls: Vec<_> = bookmarks.iter()
                 .filter_map(|b| b.url.as_ref())
-                .map(|u| normalize_url_for_dedupe(u))
+                .map(|u| canonicalize(u))
                 .collect();
             let unique: std::collections::HashSet<_> = urls.iter().collect();
             prop_assert_eq!(urls.len(), unique.len());
@@ -8192,6 +9853,284 @@ mod export_quality_tests {
         }
     }
 
+    // **Feature: deterministic-export, Property 1: 稳定 ID**
+    #[test]
+    fn test_assign_stable_ids_reproducible() {
+        let build = |suffix: &str| {
+            let mut b1 = make_bookmark("Rust", "https://rust-lang.org");
+            b1.id = format!("imported-1{}", suffix);
+            let mut b2 = make_bookmark("Rust again", "https://rust-lang.org");
+            b2.id = format!("imported-2{}", suffix);
+            let mut folder = make_folder("Dev", vec![b1, b2]);
+            folder.id = format!("folder-3{}", suffix);
+            vec![folder]
+        };
+
+        let mut first = build("");
+        let mut second = build("-other-run");
+        assign_stable_ids(&mut first);
+        assign_stable_ids(&mut second);
+
+        assert_eq!(first[0].id, second[0].id);
+        assert_eq!(first[0].children[0].id, second[0].children[0].id);
+        assert_eq!(first[0].children[1].id, second[0].children[1].id);
+        // Same URL in the same folder still gets a unique id
+        assert_ne!(first[0].children[0].id, first[0].children[1].id);
+
+        // Same URL under a different folder path gets a different id
+        let mut moved = vec![make_folder("Archive", vec![make_bookmark("Rust", "https://rust-lang.org")])];
+        assign_stable_ids(&mut moved);
+        assert_ne!(moved[0].children[0].id, first[0].children[0].id);
+    }
+
+    // **Feature: deterministic-export, Property 2: 两次导出字节一致**
+    #[test]
+    fn test_deterministic_flat_export_byte_identical() {
+        let dir = tempfile::tempdir().unwrap();
+        let bookmarks = vec![make_folder(
+            "Waterfox",
+            vec![make_folder(
+                "Dev",
+                vec![
+                    make_bookmark("Rust", "https://rust-lang.org"),
+                    make_bookmark("Rust dup", "https://www.rust-lang.org/"),
+                ],
+            )],
+        )];
+        let flat_config = FlatExportConfig {
+            flatten_root: true,
+            root_folders_to_remove: None,
+        };
+
+        let first = dir.path().join("first.html");
+        let second = dir.path().join("second.html");
+        for path in [&first, &second] {
+            export_bookmarks_to_html_flat(
+                &bookmarks,
+                path.to_str().unwrap(),
+                Some(&flat_config),
+                true,
+                true,
+                true,
+            )
+            .unwrap();
+        }
+
+        assert_eq!(std::fs::read(&first).unwrap(), std::fs::read(&second).unwrap());
+    }
+
+    // **Feature: deterministic-export, Property 3: 浏览器顺序不影响输出**
+    #[tokio::test]
+    async fn test_deterministic_export_ignores_browser_order() {
+        use crate::test_support::FileAdapter;
+
+        let dir = tempfile::tempdir().unwrap();
+        let chrome = vec![make_folder(
+            "Dev",
+            vec![
+                make_bookmark("Rust", "https://rust-lang.org"),
+                make_bookmark("Python", "https://python.org"),
+            ],
+        )];
+        let firefox = vec![
+            make_bookmark("Python docs", "https://www.python.org/"),
+            make_bookmark("Go", "https://go.dev"),
+        ];
+        let adapter = |browser: BrowserType, bookmarks: &[Bookmark]| -> Box<dyn BrowserAdapter + Send + Sync> {
+            let path = dir.path().join(format!("{}.json", browser.name()));
+            Box::new(FileAdapter::with_bookmarks(browser, path, bookmarks))
+        };
+        let config = ExportConfig {
+            merge: true,
+            deduplicate: true,
+            deterministic: true,
+            ..Default::default()
+        };
+
+        let mut outputs = Vec::new();
+        for order in [[BrowserType::Chrome, BrowserType::Waterfox], [BrowserType::Waterfox, BrowserType::Chrome]] {
+            let adapters = order
+                .iter()
+                .map(|&browser| adapter(browser, if browser == BrowserType::Chrome { &chrome } else { &firefox }))
+                .collect();
+            let engine = SyncEngine::with_adapters(adapters);
+            let output = dir.path().join(format!("{}.html", order[0].name()));
+            engine.export_to_html(None, output.to_str().unwrap(), &config).await.unwrap();
+            outputs.push(std::fs::read(&output).unwrap());
+        }
+
+        assert_eq!(outputs[0], outputs[1]);
+    }
+
+    #[test]
+    fn test_generated_folder_date_deterministic() {
+        assert_eq!(generated_folder_date(true), None);
+        assert!(generated_folder_date(false).is_some());
+    }
+
+    // Sample tree shared by the dialect golden files
+    fn dialect_sample() -> Vec<Bookmark> {
+        let mut bar = make_folder("Bookmarks Bar", vec![make_bookmark("Rust & Cargo", "https://www.rust-lang.org/?a=1&b=2")]);
+        bar.date_modified = Some(1700000500000);
+        let mut undated = make_bookmark("中文 <标题>", "https://example.com/\"q\"");
+        undated.date_added = None;
+        vec![
+            bar,
+            make_folder("Reading", vec![undated]),
+            make_bookmark("Loose", "https://loose.example"),
+        ]
+    }
+
+    // Compare against tests/golden/html_dialect_<name>.html
+    // Set UPDATE_GOLDEN=1 to regenerate after an intentional format change
+    fn assert_dialect_golden(dialect: HtmlDialect, golden: &str) {
+        let rendered = render_bookmarks_html(&dialect_sample(), dialect).unwrap();
+        if std::env::var("UPDATE_GOLDEN").is_ok() {
+            let path = format!("{}/tests/golden/html_dialect_{}.html", env!("CARGO_MANIFEST_DIR"), dialect.name());
+            std::fs::write(path, &rendered).unwrap();
+            return;
+        }
+        assert_eq!(rendered, golden, "{} dialect output changed", dialect.name());
+
+        // Every dialect must stay importable by our own parser
+        let parsed = parse_html_bookmarks(&rendered).unwrap();
+        let count: usize = parsed.iter().map(|b| if b.folder { SyncEngine::count_all_bookmarks(&b.children) } else { 1 }).sum();
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_html_dialect_generic_golden() {
+        assert_dialect_golden(HtmlDialect::Generic, include_str!("../tests/golden/html_dialect_generic.html"));
+    }
+
+    #[test]
+    fn test_html_dialect_safari_golden() {
+        assert_dialect_golden(HtmlDialect::Safari, include_str!("../tests/golden/html_dialect_safari.html"));
+    }
+
+    #[test]
+    fn test_html_dialect_firefox_golden() {
+        assert_dialect_golden(HtmlDialect::Firefox, include_str!("../tests/golden/html_dialect_firefox.html"));
+    }
+
+    #[test]
+    fn test_html_dialect_chromium_golden() {
+        assert_dialect_golden(HtmlDialect::Chromium, include_str!("../tests/golden/html_dialect_chromium.html"));
+    }
+
+    #[test]
+    fn test_chromium_dialect_synthesizes_single_toolbar_root() {
+        let bookmarks = vec![make_folder("Dev", vec![]), make_bookmark("a", "https://a.com")];
+        let html = render_bookmarks_html(&bookmarks, HtmlDialect::Chromium).unwrap();
+        assert_eq!(html.matches("PERSONAL_TOOLBAR_FOLDER").count(), 1);
+        let parsed = parse_html_bookmarks(&html).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].title, "Bookmarks bar");
+        assert_eq!(parsed[0].children.len(), 2);
+    }
+
+    #[test]
+    fn test_registrable_domain() {
+        assert_eq!(registrable_domain("https://docs.github.com/en").as_deref(), Some("github.com"));
+        assert_eq!(registrable_domain("https://news.bbc.co.uk/x").as_deref(), Some("bbc.co.uk"));
+        assert_eq!(registrable_domain("http://user@WWW.Example.com:8080/").as_deref(), Some("example.com"));
+        assert_eq!(registrable_domain("http://192.168.1.1/admin").as_deref(), Some("192.168.1.1"));
+        assert_eq!(registrable_domain("http://localhost:3000").as_deref(), Some("localhost"));
+        assert_eq!(registrable_domain("javascript:void(0)"), None);
+        assert_eq!(registrable_domain("file:///Users/me/a.html"), None);
+    }
+
+    #[test]
+    fn test_split_by_top_folder() {
+        let bookmarks = vec![
+            make_folder("Dev", vec![make_bookmark("a", "https://a.com")]),
+            make_bookmark("loose", "https://loose.com"),
+            make_folder("News", vec![make_bookmark("b", "https://b.com")]),
+            make_folder("Dev", vec![make_bookmark("c", "https://c.com")]),
+        ];
+        let groups = split_by_top_folder(&bookmarks);
+        let names: Vec<_> = groups.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, vec!["Dev", "News", SPLIT_OTHER_GROUP]);
+        assert_eq!(groups[0].1.len(), 2, "same-named top folders merge into one file");
+    }
+
+    #[test]
+    fn test_split_by_browser_uses_browser_folders() {
+        let mut chrome = make_folder("Chrome", vec![make_bookmark("a", "https://a.com")]);
+        chrome.id = "browser-chrome".to_string();
+        let reading = make_folder("Reading List", vec![make_bookmark("r", "https://r.com")]);
+        let groups = split_by_browser(&[reading, chrome]);
+        let names: Vec<_> = groups.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, vec!["Chrome", SPLIT_OTHER_GROUP]);
+        assert_eq!(groups[0].1[0].title, "a");
+    }
+
+    #[test]
+    fn test_split_by_domain_keeps_structure() {
+        let bookmarks = vec![make_folder(
+            "Dev",
+            vec![
+                make_folder("Git", vec![make_bookmark("gh", "https://github.com/x")]),
+                make_bookmark("docs", "https://docs.github.com/y"),
+                make_bookmark("rust", "https://www.rust-lang.org"),
+                make_bookmark("js", "javascript:alert(1)"),
+            ],
+        )];
+        let groups = split_by_domain(&bookmarks);
+        let names: Vec<_> = groups.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, vec!["Other", "github.com", "rust-lang.org"]);
+
+        let github = &groups[1].1;
+        assert_eq!(github.len(), 1);
+        assert_eq!(github[0].title, "Dev");
+        assert_eq!(github[0].children.len(), 2);
+        assert_eq!(github[0].children[0].title, "Git");
+    }
+
+    #[test]
+    fn test_unique_split_file_names() {
+        let mut used = HashSet::new();
+        assert_eq!(unique_split_file_name("a/b", ExportFormat::Html, &mut used), "a_b.html");
+        assert_eq!(unique_split_file_name("A/B", ExportFormat::Html, &mut used), "A_B-2.html");
+        assert_eq!(unique_split_file_name("index", ExportFormat::Json, &mut used), "index-2.json");
+        assert_eq!(unique_split_file_name("  ", ExportFormat::Json, &mut used), "untitled.json");
+        assert_eq!(sanitize_file_name("👀临时"), "👀临时");
+    }
+
+    #[test]
+    fn test_split_output_dir_strips_extension() {
+        assert_eq!(split_output_dir("/tmp/bookmarks.html"), PathBuf::from("/tmp/bookmarks"));
+        assert_eq!(split_output_dir("/tmp/out.json"), PathBuf::from("/tmp/out"));
+        assert_eq!(split_output_dir("/tmp/split"), PathBuf::from("/tmp/split"));
+    }
+
+    #[test]
+    fn test_write_split_export_creates_files_and_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let bookmarks = vec![
+            make_folder("Dev", vec![make_bookmark("a", "https://a.com")]),
+            make_folder("News", vec![make_bookmark("b", "https://b.com"), make_bookmark("c", "https://c.com")]),
+        ];
+        let config = ExportConfig {
+            no_wrap: true,
+            format: ExportFormat::Json,
+            deterministic: true,
+            ..Default::default()
+        };
+        let out = dir.path().join("export");
+        let total = SyncEngine::write_split_export(bookmarks, &out.to_string_lossy(), &config, ExportSplit::Folder).unwrap();
+        assert_eq!(total, 3);
+
+        let index: SplitExportIndex =
+            serde_json::from_str(&std::fs::read_to_string(out.join("index.json")).unwrap()).unwrap();
+        assert_eq!(index.files.len(), 2);
+        assert_eq!(index.files[1].file, "News.json");
+        assert_eq!(index.files[1].bookmarks, 2);
+
+        let news = read_bookmarks_file(&out.join("News.json").to_string_lossy(), ExportFormat::Json).unwrap();
+        assert_eq!(SyncEngine::count_all_bookmarks(&news), 2);
+    }
+
     // **Feature: export-quality-improvement, Property 7: 增量更新正确性**
     // **Validates: Requirements 6.1, 6.2**
     proptest! {
@@ -0,0 +1,144 @@
+//! 测试辅助 - 单元测试共用的书签构造函数和以 JSON 文件为存储的适配器
+
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::browsers::{Bookmark, BrowserAdapter, BrowserType, HistoryItem};
+
+/// 把 HOME 指向进程内共用的临时目录，驱动同步引擎的测试不会写入用户的 ~/.browser-sync
+pub(crate) fn isolated_home() -> &'static Path {
+    static HOME: OnceLock<tempfile::TempDir> = OnceLock::new();
+    HOME.get_or_init(|| {
+        let dir = tempfile::tempdir().unwrap();
+        std::env::set_var("HOME", dir.path());
+        dir
+    })
+    .path()
+}
+
+/// 构造书签（id 为 `id-<标题>`，无添加时间）
+pub(crate) fn make_bookmark(title: &str, url: &str) -> Bookmark {
+    Bookmark {
+        id: format!("id-{}", title),
+        title: title.to_string(),
+        url: Some(url.to_string()),
+        folder: false,
+        children: vec![],
+        date_added: None,
+        date_modified: None,
+    }
+}
+
+/// 构造文件夹（id 为 `folder-<标题>`，无添加时间）
+pub(crate) fn make_folder(title: &str, children: Vec<Bookmark>) -> Bookmark {
+    Bookmark {
+        id: format!("folder-{}", title),
+        title: title.to_string(),
+        url: None,
+        folder: true,
+        children,
+        date_added: None,
+        date_modified: None,
+    }
+}
+
+/// 以 JSON 文件为书签存储的测试适配器；文件不存在即视为未安装
+/// 历史记录（可选）存在共享内存里，测试可以直接修改；`last_visit` 以毫秒计
+pub(crate) struct FileAdapter {
+    pub browser: BrowserType,
+    pub path: PathBuf,
+    pub fail_read: bool,
+    pub history: Option<Arc<Mutex<Vec<HistoryItem>>>>,
+}
+
+impl FileAdapter {
+    pub fn new(browser: BrowserType, path: PathBuf) -> Self {
+        Self {
+            browser,
+            path,
+            fail_read: false,
+            history: None,
+        }
+    }
+
+    pub fn with_history(mut self, history: Arc<Mutex<Vec<HistoryItem>>>) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// 创建适配器并写入初始书签
+    pub fn with_bookmarks(browser: BrowserType, path: PathBuf, bookmarks: &[Bookmark]) -> Self {
+        let adapter = Self::new(browser, path);
+        adapter.write_bookmarks(bookmarks).unwrap();
+        adapter
+    }
+}
+
+impl BrowserAdapter for FileAdapter {
+    fn browser_type(&self) -> BrowserType {
+        self.browser
+    }
+
+    fn detect_bookmark_path(&self) -> Result<PathBuf> {
+        Ok(self.path.clone())
+    }
+
+    fn read_bookmarks(&self) -> Result<Vec<Bookmark>> {
+        self.read_bookmarks_from(&self.path)
+    }
+
+    fn write_bookmarks(&self, bookmarks: &[Bookmark]) -> Result<()> {
+        self.write_bookmarks_to(&self.path, bookmarks)
+    }
+
+    fn backup_bookmarks(&self) -> Result<PathBuf> {
+        let backup = self.path.with_extension("backup");
+        fs::copy(&self.path, &backup)?;
+        Ok(backup)
+    }
+
+    fn validate_bookmarks(&self, _bookmarks: &[Bookmark]) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn write_bookmarks_to(&self, path: &Path, bookmarks: &[Bookmark]) -> Result<()> {
+        fs::write(path, serde_json::to_string(bookmarks)?)?;
+        Ok(())
+    }
+
+    fn read_bookmarks_from(&self, path: &Path) -> Result<Vec<Bookmark>> {
+        if self.fail_read {
+            anyhow::bail!("unreadable");
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    fn supports_history(&self) -> bool {
+        self.history.is_some()
+    }
+
+    fn read_history(&self, days: Option<i32>) -> Result<Vec<HistoryItem>> {
+        let cutoff =
+            days.map(|d| chrono::Utc::now().timestamp_millis() - d as i64 * 24 * 60 * 60 * 1000);
+        let history = self.history.as_ref().unwrap().lock().unwrap();
+        Ok(history
+            .iter()
+            .filter(|i| cutoff.is_none_or(|c| i.last_visit.unwrap_or(0) > c))
+            .cloned()
+            .collect())
+    }
+
+    /// 与真实浏览器一样只新增或更新条目
+    fn write_history(&self, items: &[HistoryItem]) -> Result<()> {
+        let mut history = self.history.as_ref().unwrap().lock().unwrap();
+        for item in items {
+            match history.iter_mut().find(|h| h.url == item.url) {
+                Some(existing) => *existing = item.clone(),
+                None => history.push(item.clone()),
+            }
+        }
+        Ok(())
+    }
+}
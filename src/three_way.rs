@@ -0,0 +1,1450 @@
+//! 三方合并同步 - 基于上次同步快照计算每个浏览器的变更
+//!
+//! 每次同步成功后保存基准快照（合并结果 + 每个浏览器写入后的书签树）。
+//! 下次同步时把每个浏览器的当前书签树与它自己的基准比较，得到
+//! 新增 / 删除 / 修改标题 / 移动，再把这些变更应用到上次的合并结果上。
+//! 这样在一个浏览器里删除的书签不会被其他浏览器"复活"。
+//!
+//! 书签身份：规范化 URL；文件夹身份：标题路径。
+//! 文件夹移动/改名通过稳定 ID（或完全相同的书签集合）识别，
+//! 作为一次整体移动传播，而不是"删除旧文件夹 + 新建文件夹"。
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+use crate::browsers::Bookmark;
+use crate::conflicts::{
+    conflict_copy_browser, conflict_copy_title, Choice, ConflictResolver, ConflictVersion, DataKind,
+};
+use crate::url_canon::canonicalize;
+
+/// 快照格式版本
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// 上次成功同步后的基准快照
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BaseSnapshot {
+    pub version: u32,
+    /// 保存时间（毫秒）
+    pub saved_at: i64,
+    /// 上次同步的合并结果（三方合并的起点）
+    pub merged: Vec<Bookmark>,
+    /// 每个浏览器写入后重新读取的书签树（按浏览器名）
+    pub browsers: BTreeMap<String, Vec<Bookmark>>,
+}
+
+impl BaseSnapshot {
+    pub fn new(merged: Vec<Bookmark>, browsers: BTreeMap<String, Vec<Bookmark>>) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            saved_at: chrono::Utc::now().timestamp_millis(),
+            merged,
+            browsers,
+        }
+    }
+
+    /// 默认路径：~/.browser-sync/base_snapshot.json
+    pub fn default_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME")?;
+        Ok(PathBuf::from(format!(
+            "{}/.browser-sync/base_snapshot.json",
+            home
+        )))
+    }
+
+    /// 旧版时间戳状态文件（已被快照取代）
+    pub fn legacy_state_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME")?;
+        Ok(PathBuf::from(format!("{}/.browser-sync/last_sync", home)))
+    }
+
+    /// 读取快照；文件不存在时返回 None
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read base snapshot: {}", path.display()))?;
+        let snapshot: BaseSnapshot =
+            serde_json::from_str(&content).context("Failed to parse base snapshot")?;
+        if snapshot.version > SNAPSHOT_VERSION {
+            anyhow::bail!(
+                "Base snapshot version {} is newer than supported ({})",
+                snapshot.version,
+                SNAPSHOT_VERSION
+            );
+        }
+        Ok(Some(snapshot))
+    }
+
+    /// 原子写入快照（先写临时文件再重命名）
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to replace {}", path.display()))?;
+        debug!("Saved base snapshot: {}", path.display());
+        Ok(())
+    }
+}
+
+/// 单个书签/文件夹的变更
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BookmarkChange {
+    Added {
+        key: String,
+        path: Vec<String>,
+        bookmark: Bookmark,
+    },
+    Deleted {
+        key: String,
+        path: Vec<String>,
+    },
+    Edited {
+        key: String,
+        old_title: String,
+        new_title: String,
+        modified: Option<i64>,
+    },
+    Moved {
+        key: String,
+        from: Vec<String>,
+        to: Vec<String>,
+    },
+    FolderAdded {
+        path: Vec<String>,
+    },
+    /// 文件夹移动或改名（from / to 都是完整路径）
+    FolderMoved {
+        from: Vec<String>,
+        to: Vec<String>,
+    },
+    FolderDeleted {
+        path: Vec<String>,
+    },
+}
+
+impl BookmarkChange {
+    /// 书签变更的身份键（文件夹变更返回 None）
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            BookmarkChange::Added { key, .. }
+            | BookmarkChange::Deleted { key, .. }
+            | BookmarkChange::Edited { key, .. }
+            | BookmarkChange::Moved { key, .. } => Some(key),
+            BookmarkChange::FolderAdded { .. }
+            | BookmarkChange::FolderMoved { .. }
+            | BookmarkChange::FolderDeleted { .. } => None,
+        }
+    }
+
+    /// 应用顺序：删除 → 移动文件夹 → 新建文件夹 → 移动 → 改名 → 新增 → 删除文件夹
+    fn apply_order(&self) -> u8 {
+        match self {
+            BookmarkChange::Deleted { .. } => 0,
+            BookmarkChange::FolderMoved { .. } => 1,
+            BookmarkChange::FolderAdded { .. } => 2,
+            BookmarkChange::Moved { .. } => 3,
+            BookmarkChange::Edited { .. } => 4,
+            BookmarkChange::Added { .. } => 5,
+            BookmarkChange::FolderDeleted { .. } => 6,
+        }
+    }
+}
+
+/// 三方合并统计
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ThreeWayStats {
+    pub added: usize,
+    pub deleted: usize,
+    pub edited: usize,
+    pub moved: usize,
+    pub folders_added: usize,
+    pub folders_moved: usize,
+    pub folders_deleted: usize,
+    pub conflicts: usize,
+}
+
+/// 书签树中一个书签的位置信息
+struct Entry<'a> {
+    path: Vec<String>,
+    bookmark: &'a Bookmark,
+}
+
+/// 书签身份键：规范化 URL（无 URL 时用标题）；keep-both 副本单独成键
+pub fn bookmark_key(bookmark: &Bookmark) -> String {
+    let key = match bookmark.url {
+        Some(ref url) => canonicalize(url),
+        None => format!("title:{}", bookmark.title),
+    };
+    match conflict_copy_browser(&bookmark.title) {
+        Some(browser) => format!("{}#conflict:{}", key, browser),
+        None => key,
+    }
+}
+
+/// 书签树索引
+struct TreeIndex<'a> {
+    /// 书签键 → 位置（同一 URL 出现多次时取第一个）
+    entries: BTreeMap<String, Entry<'a>>,
+    /// 文件夹路径 → 文件夹节点
+    folders: BTreeMap<Vec<String>, &'a Bookmark>,
+}
+
+fn index_tree(tree: &[Bookmark]) -> TreeIndex<'_> {
+    fn walk<'a>(nodes: &'a [Bookmark], path: &mut Vec<String>, index: &mut TreeIndex<'a>) {
+        for node in nodes {
+            if node.folder {
+                path.push(node.title.clone());
+                index.folders.entry(path.clone()).or_insert(node);
+                walk(&node.children, path, index);
+                path.pop();
+            } else {
+                index
+                    .entries
+                    .entry(bookmark_key(node))
+                    .or_insert_with(|| Entry {
+                        path: path.clone(),
+                        bookmark: node,
+                    });
+            }
+        }
+    }
+
+    let mut index = TreeIndex {
+        entries: BTreeMap::new(),
+        folders: BTreeMap::new(),
+    };
+    walk(tree, &mut Vec::new(), &mut index);
+    index
+}
+
+/// 文件夹内容签名：每个书签的（相对路径, 键）
+fn folder_signature(folder: &Bookmark) -> BTreeSet<(Vec<String>, String)> {
+    fn walk(
+        nodes: &[Bookmark],
+        path: &mut Vec<String>,
+        signature: &mut BTreeSet<(Vec<String>, String)>,
+    ) {
+        for node in nodes {
+            if node.folder {
+                path.push(node.title.clone());
+                walk(&node.children, path, signature);
+                path.pop();
+            } else {
+                signature.insert((path.clone(), bookmark_key(node)));
+            }
+        }
+    }
+    let mut signature = BTreeSet::new();
+    walk(&folder.children, &mut Vec::new(), &mut signature);
+    signature
+}
+
+/// 用文件夹移动表重写路径（取最长匹配的 from 前缀）
+pub(crate) fn rewrite_path<'a>(
+    path: &[String],
+    moves: impl IntoIterator<Item = (&'a Vec<String>, &'a Vec<String>)>,
+) -> Vec<String> {
+    moves
+        .into_iter()
+        .filter(|(from, _)| path.starts_with(from))
+        .max_by_key(|(from, _)| from.len())
+        .map(|(from, to)| {
+            let mut rewritten = to.clone();
+            rewritten.extend_from_slice(&path[from.len()..]);
+            rewritten
+        })
+        .unwrap_or_else(|| path.to_vec())
+}
+
+/// 其他浏览器的路径还指向移动前的位置；发起移动的浏览器自己的路径已经是新位置
+fn relocate(
+    moves: &[(String, Vec<String>, Vec<String>)],
+    browser: &str,
+    path: &[String],
+) -> Vec<String> {
+    rewrite_path(
+        path,
+        moves
+            .iter()
+            .filter(|(by, _, _)| by != browser)
+            .map(|(_, from, to)| (from, to)),
+    )
+}
+
+/// 识别文件夹移动/改名：消失的基准文件夹与新出现的文件夹配对
+///
+/// 先按稳定 ID 配对（可识别改名）；没有可用 ID 时，同名且内容（非空）
+/// 完全相同的文件夹视为移动。
+/// 父文件夹的移动已经解释了的子文件夹不再单独记录。
+fn detect_folder_moves(base: &TreeIndex, current: &TreeIndex) -> Vec<(Vec<String>, Vec<String>)> {
+    let mut appeared: Vec<&Vec<String>> = current
+        .folders
+        .keys()
+        .filter(|p| !base.folders.contains_key(*p))
+        .collect();
+    let mut moves: Vec<(Vec<String>, Vec<String>)> = Vec::new();
+
+    // BTreeMap 按路径排序，父文件夹先于子文件夹
+    for (from, old) in base
+        .folders
+        .iter()
+        .filter(|(p, _)| !current.folders.contains_key(*p))
+    {
+        let implied = rewrite_path(from, moves.iter().map(|(f, t)| (f, t)));
+        if let Some(pos) = appeared.iter().position(|p| **p == implied) {
+            appeared.remove(pos);
+            continue;
+        }
+
+        let by_id = appeared
+            .iter()
+            .position(|p| !old.id.is_empty() && current.folders[*p].id == old.id);
+        let matched = by_id.or_else(|| {
+            let signature = folder_signature(old);
+            if signature.is_empty() {
+                return None;
+            }
+            let candidates: Vec<usize> = appeared
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| {
+                    let folder = current.folders[**p];
+                    folder.title == old.title && folder_signature(folder) == signature
+                })
+                .map(|(i, _)| i)
+                .collect();
+            (candidates.len() == 1).then(|| candidates[0])
+        });
+        if let Some(pos) = matched {
+            let to = appeared.remove(pos);
+            moves.push((from.clone(), to.clone()));
+        }
+    }
+    moves
+}
+
+/// 计算从 base 到 current 的变更
+pub fn diff_trees(base: &[Bookmark], current: &[Bookmark]) -> Vec<BookmarkChange> {
+    let base_index = index_tree(base);
+    let current_index = index_tree(current);
+    let folder_moves = detect_folder_moves(&base_index, &current_index);
+    let moved = |path: &[String]| rewrite_path(path, folder_moves.iter().map(|(f, t)| (f, t)));
+    let mut changes = Vec::new();
+
+    for (from, to) in &folder_moves {
+        changes.push(BookmarkChange::FolderMoved {
+            from: from.clone(),
+            to: to.clone(),
+        });
+    }
+    let moved_targets: BTreeSet<Vec<String>> =
+        base_index.folders.keys().map(|p| moved(p)).collect();
+    for path in current_index.folders.keys() {
+        if !base_index.folders.contains_key(path) && !moved_targets.contains(path) {
+            changes.push(BookmarkChange::FolderAdded { path: path.clone() });
+        }
+    }
+    for path in base_index.folders.keys() {
+        if !current_index.folders.contains_key(path)
+            && !current_index.folders.contains_key(&moved(path))
+        {
+            changes.push(BookmarkChange::FolderDeleted { path: path.clone() });
+        }
+    }
+
+    for (key, entry) in &current_index.entries {
+        match base_index.entries.get(key) {
+            None => changes.push(BookmarkChange::Added {
+                key: key.clone(),
+                path: entry.path.clone(),
+                bookmark: entry.bookmark.clone(),
+            }),
+            Some(old) => {
+                // 随文件夹一起移动的书签不单独记录
+                if moved(&old.path) != entry.path {
+                    changes.push(BookmarkChange::Moved {
+                        key: key.clone(),
+                        from: old.path.clone(),
+                        to: entry.path.clone(),
+                    });
+                }
+                if old.bookmark.title != entry.bookmark.title {
+                    changes.push(BookmarkChange::Edited {
+                        key: key.clone(),
+                        old_title: old.bookmark.title.clone(),
+                        new_title: entry.bookmark.title.clone(),
+                        modified: entry.bookmark.date_modified,
+                    });
+                }
+            }
+        }
+    }
+
+    for (key, entry) in &base_index.entries {
+        if !current_index.entries.contains_key(key) {
+            changes.push(BookmarkChange::Deleted {
+                key: key.clone(),
+                path: entry.path.clone(),
+            });
+        }
+    }
+
+    changes
+}
+
+/// 把各浏览器的变更应用到上次的合并结果上
+///
+/// 冲突处理（保守，不丢数据），全部记入冲突报告：
+/// - 一个浏览器删除、另一个修改或移动 → 保留书签
+/// - 多个浏览器改成不同标题 → 按书签冲突策略选择
+/// - 多个浏览器移到不同位置 → 按浏览器名顺序取第一个
+/// - 文件夹只有在应用完其他变更后为空时才删除
+pub fn apply_changes(
+    working: &mut Vec<Bookmark>,
+    changes: &[(String, BookmarkChange)],
+    resolver: &mut ConflictResolver,
+) -> ThreeWayStats {
+    let mut stats = ThreeWayStats::default();
+    let edit_titles = resolve_competing_edits(changes, resolver, &mut stats);
+
+    // 被任一浏览器修改过的书签不能被删除
+    let modified: HashMap<&str, &str> = changes
+        .iter()
+        .filter(|(_, c)| {
+            matches!(
+                c,
+                BookmarkChange::Edited { .. } | BookmarkChange::Moved { .. }
+            )
+        })
+        .filter_map(|(browser, c)| c.key().map(|k| (k, browser.as_str())))
+        .collect();
+
+    let mut ordered: Vec<&(String, BookmarkChange)> = changes.iter().collect();
+    ordered.sort_by_key(|(_, c)| c.apply_order());
+    let mut index = KeyIndex::build(working);
+
+    let mut applied_edits: HashSet<String> = HashSet::new();
+    let mut applied_moves: HashMap<String, (String, Vec<String>)> = HashMap::new();
+    let mut applied_folder_moves: HashMap<Vec<String>, (String, Vec<String>)> = HashMap::new();
+    // (浏览器, from, to)：其他浏览器的路径仍是旧位置，需要经过这些移动重写
+    let mut folder_moves: Vec<(String, Vec<String>, Vec<String>)> = Vec::new();
+
+    for (browser, change) in ordered {
+        match change {
+            BookmarkChange::Deleted { key, .. } => {
+                if let Some(by) = modified.get(key.as_str()) {
+                    debug!(
+                        "Conflict: {} deleted {} but {} modified it, keeping",
+                        browser, key, by
+                    );
+                    let title = index
+                        .find_mut(working, key)
+                        .map(|b| b.title.clone())
+                        .unwrap_or_default();
+                    resolver.record(
+                        DataKind::Bookmarks,
+                        key,
+                        vec![
+                            version(browser, "(deleted)", None),
+                            version(by, &title, None),
+                        ],
+                        Choice::Version(1),
+                        "keep-modified",
+                        "modified in another browser, deletion skipped",
+                    );
+                    stats.conflicts += 1;
+                } else if index.remove(working, key).is_some() {
+                    stats.deleted += 1;
+                }
+            }
+            BookmarkChange::FolderMoved { from, to } => {
+                if let Some((first, previous)) = applied_folder_moves.get(from) {
+                    if previous != to {
+                        debug!(
+                            "Conflict: {} moved folder {:?} to {:?}, already moved to {:?}",
+                            browser, from, to, previous
+                        );
+                        resolver.record(
+                            DataKind::Bookmarks,
+                            &format!("/{}", from.join("/")),
+                            vec![move_version(first, previous), move_version(browser, to)],
+                            Choice::Version(0),
+                            "first-browser",
+                            "folder moved to different places, first browser wins",
+                        );
+                        stats.conflicts += 1;
+                    }
+                    continue;
+                }
+                let source = rewrite_path(from, folder_moves.iter().map(|(_, f, t)| (f, t)));
+                if move_folder(working, &source, to) {
+                    index.move_prefix(&source, to);
+                    applied_folder_moves.insert(from.clone(), (browser.clone(), to.clone()));
+                    folder_moves.push((browser.clone(), from.clone(), to.clone()));
+                    stats.folders_moved += 1;
+                }
+            }
+            BookmarkChange::FolderAdded { path } => {
+                if ensure_folder(working, &relocate(&folder_moves, browser, path)) {
+                    stats.folders_added += 1;
+                }
+            }
+            BookmarkChange::Moved { key, to, .. } => {
+                if let Some((first, previous)) = applied_moves.get(key) {
+                    if previous != to {
+                        debug!(
+                            "Conflict: {} moved {} to {:?}, already moved to {:?}",
+                            browser, key, to, previous
+                        );
+                        resolver.record(
+                            DataKind::Bookmarks,
+                            key,
+                            vec![move_version(first, previous), move_version(browser, to)],
+                            Choice::Version(0),
+                            "first-browser",
+                            "moved to different folders, first browser wins",
+                        );
+                        stats.conflicts += 1;
+                    }
+                    continue;
+                }
+                let to = relocate(&folder_moves, browser, to);
+                if index.relocate(working, key, &to) {
+                    stats.moved += 1;
+                }
+                applied_moves.insert(key.clone(), (browser.clone(), to));
+            }
+            BookmarkChange::Edited { key, .. } => {
+                if !applied_edits.insert(key.clone()) {
+                    continue;
+                }
+                let Some((title, copies)) = edit_titles.get(key.as_str()) else {
+                    continue;
+                };
+                if let Some(bookmark) = index.find_mut(working, key) {
+                    bookmark.title = title.clone();
+                    let new_key = bookmark_key(bookmark);
+                    let copies: Vec<Bookmark> = copies
+                        .iter()
+                        .map(|copy_title| Bookmark {
+                            id: format!("conflict-{}", uuid::Uuid::new_v4()),
+                            title: copy_title.clone(),
+                            ..bookmark.clone()
+                        })
+                        .collect();
+                    index.insert_after(working, key, copies);
+                    index.rekey(key, new_key);
+                    stats.edited += 1;
+                }
+            }
+            BookmarkChange::Added {
+                key,
+                path,
+                bookmark,
+            } => {
+                if !index.contains(key) {
+                    let path = relocate(&folder_moves, browser, path);
+                    insert_at_path(working, &path, bookmark.clone());
+                    index.add(key.clone(), path, bookmark.url.clone());
+                    stats.added += 1;
+                }
+            }
+            BookmarkChange::FolderDeleted { path } => {
+                if remove_empty_folder(working, &relocate(&folder_moves, browser, path)) {
+                    stats.folders_deleted += 1;
+                }
+            }
+        }
+    }
+
+    stats
+}
+
+/// (浏览器, 新标题, 修改时间)
+type RenameVersion<'a> = (&'a str, &'a str, Option<i64>);
+
+/// 每个书签最终的标题，以及 keep-both 时要额外插入的副本标题
+fn resolve_competing_edits<'a>(
+    changes: &'a [(String, BookmarkChange)],
+    resolver: &mut ConflictResolver,
+    stats: &mut ThreeWayStats,
+) -> HashMap<&'a str, (String, Vec<String>)> {
+    let mut groups: BTreeMap<&str, Vec<RenameVersion>> = BTreeMap::new();
+    for (browser, change) in changes {
+        if let BookmarkChange::Edited {
+            key,
+            new_title,
+            modified,
+            ..
+        } = change
+        {
+            groups
+                .entry(key)
+                .or_default()
+                .push((browser, new_title, *modified));
+        }
+    }
+
+    let mut titles = HashMap::new();
+    for (key, edits) in groups {
+        let distinct: BTreeSet<&str> = edits.iter().map(|(_, t, _)| *t).collect();
+        if distinct.len() < 2 {
+            titles.insert(key, (edits[0].1.to_string(), Vec::new()));
+            continue;
+        }
+
+        debug!(
+            "Conflict: {} renamed differently in {} browsers",
+            key,
+            edits.len()
+        );
+        stats.conflicts += 1;
+        let versions = edits
+            .iter()
+            .map(|(browser, title, modified)| version(browser, title, *modified))
+            .collect();
+        let resolved = match resolver.resolve(DataKind::Bookmarks, key, versions) {
+            Choice::Version(i) => (edits[i].1.to_string(), Vec::new()),
+            Choice::KeepBoth => {
+                let winner = edits[0].1;
+                let mut copies = Vec::new();
+                for (browser, title, _) in &edits[1..] {
+                    let copy = conflict_copy_title(title, browser);
+                    if *title != winner && !copies.contains(&copy) {
+                        copies.push(copy);
+                    }
+                }
+                (winner.to_string(), copies)
+            }
+        };
+        titles.insert(key, resolved);
+    }
+    titles
+}
+
+fn version(browser: &str, title: &str, modified: Option<i64>) -> ConflictVersion {
+    ConflictVersion {
+        browser: browser.to_string(),
+        title: title.to_string(),
+        detail: None,
+        modified,
+    }
+}
+
+fn move_version(browser: &str, path: &[String]) -> ConflictVersion {
+    ConflictVersion {
+        browser: browser.to_string(),
+        title: format!("/{}", path.join("/")),
+        detail: Some("folder".to_string()),
+        modified: None,
+    }
+}
+
+/// 三方合并：从上次合并结果出发，依次应用每个浏览器相对自己基准的变更
+/// 没有基准的浏览器（新加入）相当于全部新增
+pub fn merge_three_way(
+    base: &BaseSnapshot,
+    current: &BTreeMap<String, Vec<Bookmark>>,
+    resolver: &mut ConflictResolver,
+) -> (Vec<Bookmark>, ThreeWayStats) {
+    let mut all_changes: Vec<(String, BookmarkChange)> = Vec::new();
+
+    for (browser, tree) in current {
+        let browser_base = base.browsers.get(browser).map(Vec::as_slice).unwrap_or(&[]);
+        let changes = diff_trees(browser_base, tree);
+        if !changes.is_empty() {
+            info!("   {} : {} changes since last sync", browser, changes.len());
+        }
+        all_changes.extend(changes.into_iter().map(|c| (browser.clone(), c)));
+    }
+
+    let mut working = base.merged.clone();
+    let stats = apply_changes(&mut working, &all_changes, resolver);
+    (working, stats)
+}
+
+/// 只取移动类变更（书签移动、文件夹移动/改名）
+///
+/// 全量合并没有三方合并的起点，但仍可用基准快照识别各浏览器里的移动，
+/// 让新位置胜出而不是由去重的深度启发式决定。
+pub fn detect_moves(
+    base: &BaseSnapshot,
+    current: &BTreeMap<String, Vec<Bookmark>>,
+) -> Vec<(String, BookmarkChange)> {
+    let mut moves = Vec::new();
+    for (browser, tree) in current {
+        let Some(browser_base) = base.browsers.get(browser) else {
+            continue;
+        };
+        moves.extend(
+            diff_trees(browser_base, tree)
+                .into_iter()
+                .filter(|c| {
+                    matches!(
+                        c,
+                        BookmarkChange::Moved { .. } | BookmarkChange::FolderMoved { .. }
+                    )
+                })
+                .map(|c| (browser.clone(), c)),
+        );
+    }
+    moves
+}
+
+/// 书签所在位置：文件夹路径 + 原始 URL（定位时先比原始 URL，避免逐个规范化同级书签）
+#[derive(Debug, Clone)]
+struct Occurrence {
+    path: Vec<String>,
+    url: Option<String>,
+}
+
+/// 应用变更时的书签键索引：键 → 所有出现位置（按树的先序）
+///
+/// 只在开始时规范化一遍全部 URL，之后随每个变更增量更新，
+/// 避免每个变更都扫描整棵树（首次三方同步时 N 个书签全是新增）。
+struct KeyIndex {
+    entries: HashMap<String, Vec<Occurrence>>,
+}
+
+impl KeyIndex {
+    fn build(tree: &[Bookmark]) -> Self {
+        fn walk(nodes: &[Bookmark], path: &mut Vec<String>, index: &mut KeyIndex) {
+            for node in nodes {
+                if node.folder {
+                    path.push(node.title.clone());
+                    walk(&node.children, path, index);
+                    path.pop();
+                } else {
+                    index.add(bookmark_key(node), path.clone(), node.url.clone());
+                }
+            }
+        }
+        let mut index = KeyIndex {
+            entries: HashMap::new(),
+        };
+        walk(tree, &mut Vec::new(), &mut index);
+        index
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    fn add(&mut self, key: String, path: Vec<String>, url: Option<String>) {
+        self.entries
+            .entry(key)
+            .or_default()
+            .push(Occurrence { path, url });
+    }
+
+    /// 同级书签里键为 key 的位置
+    fn position(children: &[Bookmark], key: &str, url: Option<&str>) -> Option<usize> {
+        children
+            .iter()
+            .position(|b| !b.folder && b.url.as_deref() == url && bookmark_key(b) == key)
+            .or_else(|| {
+                children
+                    .iter()
+                    .position(|b| !b.folder && bookmark_key(b) == key)
+            })
+    }
+
+    /// 第一个出现位置的书签
+    fn find_mut<'a>(&self, tree: &'a mut Vec<Bookmark>, key: &str) -> Option<&'a mut Bookmark> {
+        let first = self.entries.get(key)?.first()?;
+        let children = folder_children_mut(tree, &first.path)?;
+        let pos = Self::position(children, key, first.url.as_deref())?;
+        children.get_mut(pos)
+    }
+
+    /// 书签改名后键可能变化（keep-both 副本标题），同步更新索引
+    fn rekey(&mut self, old: &str, new: String) {
+        if old == new {
+            return;
+        }
+        if let Some(occurrences) = self.entries.get_mut(old) {
+            let first = occurrences.remove(0);
+            if occurrences.is_empty() {
+                self.entries.remove(old);
+            }
+            self.add(new, first.path, first.url);
+        }
+    }
+
+    /// 在第一个出现位置后面插入同级书签
+    fn insert_after(&mut self, tree: &mut Vec<Bookmark>, key: &str, new: Vec<Bookmark>) {
+        let Some(first) = self.entries.get(key).and_then(|o| o.first()).cloned() else {
+            return;
+        };
+        let Some(children) = folder_children_mut(tree, &first.path) else {
+            return;
+        };
+        let Some(pos) = Self::position(children, key, first.url.as_deref()) else {
+            return;
+        };
+        for bookmark in &new {
+            self.add(
+                bookmark_key(bookmark),
+                first.path.clone(),
+                bookmark.url.clone(),
+            );
+        }
+        children.splice(pos + 1..pos + 1, new);
+    }
+
+    /// 移除第一个出现位置的书签
+    fn remove(&mut self, tree: &mut Vec<Bookmark>, key: &str) -> Option<Bookmark> {
+        let occurrences = self.entries.get_mut(key)?;
+        let first = occurrences.remove(0);
+        if occurrences.is_empty() {
+            self.entries.remove(key);
+        }
+        let children = folder_children_mut(tree, &first.path)?;
+        let pos = Self::position(children, key, first.url.as_deref())?;
+        Some(children.remove(pos))
+    }
+
+    /// 把书签放到目标文件夹，其他位置的同一书签一并移除；返回是否真的换了位置
+    fn relocate(&mut self, tree: &mut Vec<Bookmark>, key: &str, to: &[String]) -> bool {
+        let Some(occurrences) = self.entries.remove(key) else {
+            return false;
+        };
+        let position = occurrences
+            .iter()
+            .find(|o| o.path == to)
+            .and_then(|o| Self::position(folder_children_mut(tree, to)?, key, o.url.as_deref()));
+        let mut removed = Vec::new();
+        for occurrence in &occurrences {
+            if let Some(children) = folder_children_mut(tree, &occurrence.path) {
+                if let Some(pos) = Self::position(children, key, occurrence.url.as_deref()) {
+                    removed.push(children.remove(pos));
+                }
+            }
+        }
+        let Some(bookmark) = removed.into_iter().next() else {
+            return false;
+        };
+        ensure_folder(tree, to);
+        let url = bookmark.url.clone();
+        if let Some(children) = folder_children_mut(tree, to) {
+            let index = position.unwrap_or(children.len()).min(children.len());
+            children.insert(index, bookmark);
+        }
+        self.add(key.to_string(), to.to_vec(), url);
+        position.is_none()
+    }
+
+    /// 文件夹从 from 移到 to 后，改写其下书签的路径
+    fn move_prefix(&mut self, from: &[String], to: &[String]) {
+        for occurrence in self.entries.values_mut().flatten() {
+            if occurrence.path.starts_with(from) {
+                let rest = occurrence.path.split_off(from.len());
+                occurrence.path = to.iter().cloned().chain(rest).collect();
+            }
+        }
+    }
+}
+
+/// 把 from 处的文件夹移动/改名到 to；目标位置已有同名文件夹时合并内容
+pub(crate) fn move_folder(tree: &mut Vec<Bookmark>, from: &[String], to: &[String]) -> bool {
+    let (Some((name, parent)), Some((new_name, new_parent))) = (from.split_last(), to.split_last())
+    else {
+        return false;
+    };
+    if from == to || to.starts_with(from) {
+        return false;
+    }
+    let Some(siblings) = folder_children_mut(tree, parent) else {
+        return false;
+    };
+    let Some(pos) = siblings.iter().position(|b| b.folder && &b.title == name) else {
+        return false;
+    };
+    let mut folder = siblings.remove(pos);
+    folder.title = new_name.clone();
+    ensure_folder(tree, new_parent);
+    let Some(target) = folder_children_mut(tree, new_parent) else {
+        return false;
+    };
+    match target.iter_mut().find(|b| b.folder && &b.title == new_name) {
+        Some(existing) => existing.children.append(&mut folder.children),
+        None => target.push(folder),
+    }
+    true
+}
+
+/// 确保路径上的文件夹存在，返回是否新建了文件夹
+pub(crate) fn ensure_folder(tree: &mut Vec<Bookmark>, path: &[String]) -> bool {
+    let Some((first, rest)) = path.split_first() else {
+        return false;
+    };
+    let mut created = false;
+    let pos = match tree.iter().position(|b| b.folder && &b.title == first) {
+        Some(pos) => pos,
+        None => {
+            tree.push(Bookmark {
+                id: format!("folder-{}", uuid::Uuid::new_v4()),
+                title: first.clone(),
+                url: None,
+                folder: true,
+                children: Vec::new(),
+                date_added: Some(chrono::Utc::now().timestamp_millis()),
+                date_modified: None,
+            });
+            created = true;
+            tree.len() - 1
+        }
+    };
+    ensure_folder(&mut tree[pos].children, rest) || created
+}
+
+pub(crate) fn folder_children_mut<'a>(
+    tree: &'a mut Vec<Bookmark>,
+    path: &[String],
+) -> Option<&'a mut Vec<Bookmark>> {
+    match path.split_first() {
+        None => Some(tree),
+        Some((first, rest)) => {
+            let folder = tree.iter_mut().find(|b| b.folder && &b.title == first)?;
+            folder_children_mut(&mut folder.children, rest)
+        }
+    }
+}
+
+pub(crate) fn insert_at_path(tree: &mut Vec<Bookmark>, path: &[String], bookmark: Bookmark) {
+    ensure_folder(tree, path);
+    if let Some(children) = folder_children_mut(tree, path) {
+        children.push(bookmark);
+    }
+}
+
+pub(crate) fn remove_empty_folder(tree: &mut Vec<Bookmark>, path: &[String]) -> bool {
+    let Some((last, parent)) = path.split_last() else {
+        return false;
+    };
+    let Some(siblings) = folder_children_mut(tree, parent) else {
+        return false;
+    };
+    match siblings
+        .iter()
+        .position(|b| b.folder && &b.title == last && b.children.is_empty())
+    {
+        Some(pos) => {
+            siblings.remove(pos);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn make_bookmark(title: &str, url: &str) -> Bookmark {
+        Bookmark {
+            date_added: Some(1000000),
+            ..test_support::make_bookmark(title, url)
+        }
+    }
+
+    fn make_folder(title: &str, children: Vec<Bookmark>) -> Bookmark {
+        Bookmark {
+            date_added: Some(1000000),
+            ..test_support::make_folder(title, children)
+        }
+    }
+
+    fn urls(tree: &[Bookmark]) -> Vec<String> {
+        index_tree(tree).entries.keys().cloned().collect()
+    }
+
+    fn has(changes: &[BookmarkChange], expected: BookmarkChange) -> bool {
+        let expected = serde_json::to_value(expected).unwrap();
+        changes
+            .iter()
+            .any(|c| serde_json::to_value(c).unwrap() == expected)
+    }
+
+    fn path(parts: &[&str]) -> Vec<String> {
+        parts.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn base_tree() -> Vec<Bookmark> {
+        vec![make_folder(
+            "Dev",
+            vec![
+                make_bookmark("Rust", "https://rust-lang.org"),
+                make_bookmark("Go", "https://go.dev"),
+            ],
+        )]
+    }
+
+    #[test]
+    fn test_diff_detects_add_delete_edit_move() {
+        let current = vec![
+            make_folder(
+                "Dev",
+                vec![make_bookmark("Rust Lang", "https://rust-lang.org")],
+            ),
+            make_folder("Archive", vec![make_bookmark("Zig", "https://ziglang.org")]),
+        ];
+        let changes = diff_trees(&base_tree(), &current);
+
+        assert!(has(
+            &changes,
+            BookmarkChange::FolderAdded {
+                path: path(&["Archive"])
+            }
+        ));
+        assert!(changes.iter().any(
+            |c| matches!(c, BookmarkChange::Added { key, .. } if key == "https://ziglang.org")
+        ));
+        assert!(has(
+            &changes,
+            BookmarkChange::Deleted {
+                key: "https://go.dev".into(),
+                path: path(&["Dev"])
+            }
+        ));
+        assert!(has(
+            &changes,
+            BookmarkChange::Edited {
+                key: "https://rust-lang.org".into(),
+                old_title: "Rust".into(),
+                new_title: "Rust Lang".into(),
+                modified: None,
+            }
+        ));
+        assert_eq!(changes.len(), 4);
+
+        let moved = vec![make_folder(
+            "Archive",
+            vec![make_bookmark("Rust", "https://rust-lang.org")],
+        )];
+        let changes = diff_trees(
+            &[make_folder(
+                "Dev",
+                vec![make_bookmark("Rust", "https://rust-lang.org")],
+            )],
+            &moved,
+        );
+        assert!(has(
+            &changes,
+            BookmarkChange::Moved {
+                key: "https://rust-lang.org".into(),
+                from: path(&["Dev"]),
+                to: path(&["Archive"]),
+            }
+        ));
+    }
+
+    #[test]
+    fn test_deletion_is_not_resurrected() {
+        let base = BaseSnapshot::new(
+            base_tree(),
+            BTreeMap::from([
+                ("Brave".to_string(), base_tree()),
+                ("Waterfox".to_string(), base_tree()),
+            ]),
+        );
+        // Brave deleted Go; Waterfox unchanged
+        let brave = vec![make_folder(
+            "Dev",
+            vec![make_bookmark("Rust", "https://rust-lang.org")],
+        )];
+        let current = BTreeMap::from([
+            ("Brave".to_string(), brave),
+            ("Waterfox".to_string(), base_tree()),
+        ]);
+
+        let (merged, stats) = merge_three_way(&base, &current, &mut ConflictResolver::default());
+        assert_eq!(urls(&merged), vec!["https://rust-lang.org"]);
+        assert_eq!(stats.deleted, 1);
+        assert_eq!(stats.conflicts, 0);
+    }
+
+    #[test]
+    fn test_delete_vs_edit_keeps_bookmark() {
+        let base = BaseSnapshot::new(
+            base_tree(),
+            BTreeMap::from([
+                ("Brave".to_string(), base_tree()),
+                ("Waterfox".to_string(), base_tree()),
+            ]),
+        );
+        let brave = vec![make_folder(
+            "Dev",
+            vec![make_bookmark("Rust", "https://rust-lang.org")],
+        )];
+        let waterfox = vec![make_folder(
+            "Dev",
+            vec![
+                make_bookmark("Rust", "https://rust-lang.org"),
+                make_bookmark("Go (golang)", "https://go.dev"),
+            ],
+        )];
+        let current = BTreeMap::from([
+            ("Brave".to_string(), brave),
+            ("Waterfox".to_string(), waterfox),
+        ]);
+
+        let (merged, stats) = merge_three_way(&base, &current, &mut ConflictResolver::default());
+        assert_eq!(urls(&merged).len(), 2);
+        assert_eq!(stats.conflicts, 1);
+        assert_eq!(stats.edited, 1);
+        let go = &merged[0].children[1];
+        assert_eq!(go.title, "Go (golang)");
+    }
+
+    #[test]
+    fn test_move_and_new_browser_union() {
+        let base = BaseSnapshot::new(
+            base_tree(),
+            BTreeMap::from([("Brave".to_string(), base_tree())]),
+        );
+        let brave = vec![
+            make_folder("Dev", vec![make_bookmark("Rust", "https://rust-lang.org")]),
+            make_folder("Archive", vec![make_bookmark("Go", "https://go.dev")]),
+        ];
+        // Chrome has no base yet: everything counts as added, existing URLs are skipped
+        let chrome = vec![
+            make_bookmark("Zig", "https://ziglang.org"),
+            make_bookmark("Rust", "https://rust-lang.org"),
+        ];
+        let current =
+            BTreeMap::from([("Brave".to_string(), brave), ("Chrome".to_string(), chrome)]);
+
+        let (merged, stats) = merge_three_way(&base, &current, &mut ConflictResolver::default());
+        assert_eq!(stats.moved, 1);
+        assert_eq!(stats.added, 1);
+        assert_eq!(merged[0].title, "Dev");
+        assert_eq!(merged[0].children.len(), 1);
+        assert_eq!(merged[1].title, "Archive");
+        assert_eq!(merged[1].children[0].title, "Go");
+        assert_eq!(merged[2].title, "Zig");
+    }
+
+    #[test]
+    fn test_competing_edits_follow_conflict_policy() {
+        let base = BaseSnapshot::new(
+            base_tree(),
+            BTreeMap::from([
+                ("Brave".to_string(), base_tree()),
+                ("Safari".to_string(), base_tree()),
+            ]),
+        );
+        let renamed = |title: &str, modified: i64| {
+            let mut rust = make_bookmark(title, "https://rust-lang.org");
+            rust.date_modified = Some(modified);
+            vec![make_folder(
+                "Dev",
+                vec![rust, make_bookmark("Go", "https://go.dev")],
+            )]
+        };
+        let current = BTreeMap::from([
+            ("Brave".to_string(), renamed("Rust (Brave)", 100)),
+            ("Safari".to_string(), renamed("Rust (Safari)", 200)),
+        ]);
+
+        let mut resolver = ConflictResolver::default();
+        let (merged, stats) = merge_three_way(&base, &current, &mut resolver);
+        assert_eq!(stats.conflicts, 1);
+        assert_eq!(merged[0].children[0].title, "Rust (Safari)");
+        assert_eq!(resolver.conflicts()[0].versions.len(), 2);
+
+        let mut resolver = ConflictResolver::default();
+        resolver.policies.apply_spec("bookmarks=keep-both").unwrap();
+        let (merged, _) = merge_three_way(&base, &current, &mut resolver);
+        let titles: Vec<&str> = merged[0]
+            .children
+            .iter()
+            .map(|b| b.title.as_str())
+            .collect();
+        assert_eq!(
+            titles,
+            vec!["Rust (Brave)", "Rust (Safari) (conflict: Safari)", "Go"]
+        );
+        // The copy has its own identity, so the next diff doesn't collapse it
+        assert_eq!(urls(&merged).len(), 3);
+    }
+
+    #[test]
+    fn test_folder_rename_detected_by_id() {
+        let mut renamed = make_folder(
+            "Development",
+            vec![
+                make_bookmark("Rust", "https://rust-lang.org"),
+                make_bookmark("Zig", "https://ziglang.org"),
+            ],
+        );
+        renamed.id = "folder-Dev".to_string();
+        let changes = diff_trees(&base_tree(), &[renamed]);
+
+        assert!(has(
+            &changes,
+            BookmarkChange::FolderMoved {
+                from: path(&["Dev"]),
+                to: path(&["Development"]),
+            }
+        ));
+        // Go was deleted and Zig added inside the renamed folder; nothing else changed
+        assert!(!changes.iter().any(|c| matches!(
+            c,
+            BookmarkChange::FolderAdded { .. }
+                | BookmarkChange::FolderDeleted { .. }
+                | BookmarkChange::Moved { .. }
+        )));
+        assert_eq!(changes.len(), 3);
+    }
+
+    #[test]
+    fn test_nested_folder_move_detected_by_content() {
+        let base = vec![make_folder(
+            "Dev",
+            vec![make_folder(
+                "Rust",
+                vec![
+                    make_bookmark("Book", "https://doc.rust-lang.org/book"),
+                    make_folder("Crates", vec![make_bookmark("Serde", "https://serde.rs")]),
+                ],
+            )],
+        )];
+        // Moved Dev/Rust to Archive/Rust; ids differ, content is identical
+        let current = vec![
+            make_folder("Dev", vec![]),
+            make_folder(
+                "Archive",
+                vec![make_folder(
+                    "Rust",
+                    vec![
+                        make_bookmark("Book", "https://doc.rust-lang.org/book"),
+                        make_folder("Crates", vec![make_bookmark("Serde", "https://serde.rs")]),
+                    ],
+                )],
+            ),
+        ];
+        let changes = diff_trees(&base, &current);
+
+        assert!(has(
+            &changes,
+            BookmarkChange::FolderMoved {
+                from: path(&["Dev", "Rust"]),
+                to: path(&["Archive", "Rust"]),
+            }
+        ));
+        assert!(has(
+            &changes,
+            BookmarkChange::FolderAdded {
+                path: path(&["Archive"])
+            }
+        ));
+        // Crates moved with its parent: no separate move, add or delete
+        assert_eq!(changes.len(), 2);
+    }
+
+    #[test]
+    fn test_folder_move_propagates_to_other_browsers() {
+        let base = BaseSnapshot::new(
+            base_tree(),
+            BTreeMap::from([
+                ("Brave".to_string(), base_tree()),
+                ("Waterfox".to_string(), base_tree()),
+            ]),
+        );
+        let mut archived = make_folder(
+            "Rust",
+            vec![
+                make_bookmark("Rust", "https://rust-lang.org"),
+                make_bookmark("Go", "https://go.dev"),
+            ],
+        );
+        archived.id = "folder-Dev".to_string();
+        let brave = vec![make_folder("Archive", vec![archived])];
+        // Waterfox still sees the old location and adds a bookmark there
+        let waterfox = vec![make_folder(
+            "Dev",
+            vec![
+                make_bookmark("Rust", "https://rust-lang.org"),
+                make_bookmark("Go", "https://go.dev"),
+                make_bookmark("Zig", "https://ziglang.org"),
+            ],
+        )];
+        let current = BTreeMap::from([
+            ("Brave".to_string(), brave),
+            ("Waterfox".to_string(), waterfox),
+        ]);
+
+        let (merged, stats) = merge_three_way(&base, &current, &mut ConflictResolver::default());
+        assert_eq!(stats.folders_moved, 1);
+        assert_eq!(stats.moved, 0);
+        assert_eq!(stats.added, 1);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].title, "Archive");
+        let folder = &merged[0].children[0];
+        assert_eq!(folder.title, "Rust");
+        assert_eq!(urls(&folder.children).len(), 3);
+    }
+
+    #[test]
+    fn test_edit_and_add_follow_folder_move() {
+        let base = BaseSnapshot::new(
+            base_tree(),
+            BTreeMap::from([
+                ("Brave".to_string(), base_tree()),
+                ("Waterfox".to_string(), base_tree()),
+            ]),
+        );
+        let mut renamed = base_tree().remove(0);
+        renamed.title = "Code".to_string();
+        // Waterfox still sees the old folder: it renames one bookmark and adds another
+        let waterfox = vec![make_folder(
+            "Dev",
+            vec![
+                make_bookmark("Rust Lang", "https://rust-lang.org"),
+                make_bookmark("Go", "https://go.dev"),
+                make_bookmark("Zig", "https://ziglang.org"),
+            ],
+        )];
+        let current = BTreeMap::from([
+            ("Brave".to_string(), vec![renamed]),
+            ("Waterfox".to_string(), waterfox),
+        ]);
+
+        let (merged, stats) = merge_three_way(&base, &current, &mut ConflictResolver::default());
+        assert_eq!(stats.folders_moved, 1);
+        assert_eq!(stats.edited, 1);
+        assert_eq!(stats.added, 1);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].title, "Code");
+        let titles: Vec<&str> = merged[0]
+            .children
+            .iter()
+            .map(|b| b.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["Rust Lang", "Go", "Zig"]);
+    }
+
+    #[test]
+    fn test_competing_folder_moves_first_browser_wins() {
+        let base = BaseSnapshot::new(
+            base_tree(),
+            BTreeMap::from([
+                ("Brave".to_string(), base_tree()),
+                ("Waterfox".to_string(), base_tree()),
+            ]),
+        );
+        let renamed = |title: &str| {
+            let mut folder = base_tree().remove(0);
+            folder.title = title.to_string();
+            vec![folder]
+        };
+        let current = BTreeMap::from([
+            ("Brave".to_string(), renamed("Code")),
+            ("Waterfox".to_string(), renamed("Programming")),
+        ]);
+
+        let mut resolver = ConflictResolver::default();
+        let (merged, stats) = merge_three_way(&base, &current, &mut resolver);
+        assert_eq!(stats.folders_moved, 1);
+        assert_eq!(stats.conflicts, 1);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].title, "Code");
+        assert_eq!(resolver.conflicts()[0].key, "/Dev");
+    }
+
+    #[test]
+    fn test_detect_moves_relocates_bookmark_in_full_merge() {
+        let base = BaseSnapshot::new(
+            base_tree(),
+            BTreeMap::from([("Brave".to_string(), base_tree())]),
+        );
+        let brave = vec![
+            make_folder("Dev", vec![make_bookmark("Go", "https://go.dev")]),
+            make_folder(
+                "Archive",
+                vec![make_bookmark("Rust", "https://rust-lang.org")],
+            ),
+        ];
+        let current = BTreeMap::from([
+            ("Brave".to_string(), brave),
+            ("Chrome".to_string(), base_tree()),
+        ]);
+        let moves = detect_moves(&base, &current);
+        assert_eq!(moves.len(), 1);
+
+        // The full merge picked a tree that still has Rust in Dev and also in Archive
+        let mut merged = vec![
+            make_folder(
+                "Dev",
+                vec![
+                    make_bookmark("Rust", "https://rust-lang.org"),
+                    make_bookmark("Go", "https://go.dev"),
+                ],
+            ),
+            make_folder(
+                "Archive",
+                vec![make_bookmark("Rust", "https://rust-lang.org")],
+            ),
+        ];
+        let stats = apply_changes(&mut merged, &moves, &mut ConflictResolver::default());
+        assert_eq!(stats.moved, 0);
+        assert_eq!(merged[0].children.len(), 1);
+        assert_eq!(merged[0].children[0].title, "Go");
+        assert_eq!(merged[1].children.len(), 1);
+    }
+
+    #[test]
+    fn test_folder_deleted_only_when_empty() {
+        let mut working = vec![
+            make_folder("Old", vec![]),
+            make_folder("Busy", vec![make_bookmark("a", "https://a.com")]),
+        ];
+        let changes = vec![
+            (
+                "Brave".to_string(),
+                BookmarkChange::FolderDeleted {
+                    path: path(&["Old"]),
+                },
+            ),
+            (
+                "Brave".to_string(),
+                BookmarkChange::FolderDeleted {
+                    path: path(&["Busy"]),
+                },
+            ),
+        ];
+        let stats = apply_changes(&mut working, &changes, &mut ConflictResolver::default());
+        assert_eq!(stats.folders_deleted, 1);
+        assert_eq!(working.len(), 1);
+        assert_eq!(working[0].title, "Busy");
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state").join("base_snapshot.json");
+        assert!(BaseSnapshot::load(&path).unwrap().is_none());
+
+        let snapshot = BaseSnapshot::new(
+            base_tree(),
+            BTreeMap::from([("Safari".to_string(), base_tree())]),
+        );
+        snapshot.save(&path).unwrap();
+        let loaded = BaseSnapshot::load(&path).unwrap().unwrap();
+        assert_eq!(loaded.version, SNAPSHOT_VERSION);
+        assert_eq!(loaded.browsers.len(), 1);
+        assert_eq!(urls(&loaded.merged), urls(&snapshot.merged));
+    }
+}
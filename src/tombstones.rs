@@ -0,0 +1,715 @@
+//! 删除墓碑 - 让删除操作在浏览器之间传播
+//!
+//! 每次同步读取浏览器数据时，与该浏览器上次出现过的条目比较：
+//! 上次有、这次没有的条目记为墓碑（规范化 URL + 所在文件夹路径）。
+//! 合并时跳过有墓碑的条目，避免从其他浏览器"复活"；书签只在墓碑记录的文件夹里被跳过，
+//! 同一 URL 在其他文件夹里的副本不受影响。
+//! 同一 URL 在一个文件夹消失、在另一个文件夹出现视为移动，不生成墓碑。
+//! 墓碑在保留期后过期；用户在任一浏览器重新添加同一 URL 时墓碑自动失效。
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use crate::browsers::{Bookmark, HistoryItem, ReadingListItem};
+use crate::url_canon::canonicalize;
+
+/// 默认保留天数
+pub const DEFAULT_RETENTION_DAYS: u32 = 90;
+
+/// 一个浏览器一次丢失超过该比例的条目时视为重置（清空/重装），不生成墓碑
+const MASS_DELETE_RATIO: f64 = 0.5;
+/// 低于该数量的删除不做重置判断
+const MASS_DELETE_MIN: usize = 20;
+
+const MS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+
+/// 墓碑所属数据类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TombstoneKind {
+    Bookmark,
+    Folder,
+    History,
+    ReadingList,
+}
+
+impl TombstoneKind {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "bookmark" | "bookmarks" => Ok(TombstoneKind::Bookmark),
+            "folder" | "folders" => Ok(TombstoneKind::Folder),
+            "history" => Ok(TombstoneKind::History),
+            "reading-list" | "reading_list" | "readinglist" => Ok(TombstoneKind::ReadingList),
+            other => anyhow::bail!(
+                "Unknown tombstone kind '{}' (expected bookmark, folder, history or reading-list)",
+                other
+            ),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TombstoneKind::Bookmark => "bookmark",
+            TombstoneKind::Folder => "folder",
+            TombstoneKind::History => "history",
+            TombstoneKind::ReadingList => "reading-list",
+        }
+    }
+}
+
+/// 一条删除记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tombstone {
+    pub kind: TombstoneKind,
+    /// 规范化 URL（文件夹为标题路径）
+    pub key: String,
+    /// 删除前所在的文件夹路径（"A/B"）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder: Option<String>,
+    /// 发现删除的浏览器
+    pub browser: String,
+    /// 发现删除的时间（毫秒）
+    pub deleted_at: i64,
+}
+
+/// 一次观察的结果
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ObserveStats {
+    pub created: usize,
+    pub revived: usize,
+}
+
+/// 浏览器里的条目：键 → 所在的文件夹路径（同一 URL 可以在多个文件夹里）
+pub type SeenItems = BTreeMap<String, BTreeSet<String>>;
+
+/// 墓碑存储：~/.browser-sync/tombstones.json
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TombstoneStore {
+    #[serde(default = "default_retention")]
+    pub retention_days: u32,
+    #[serde(default)]
+    pub tombstones: Vec<Tombstone>,
+    /// 每种数据 → 每个浏览器 → 上次见到的条目
+    #[serde(default, deserialize_with = "deserialize_seen")]
+    seen: BTreeMap<TombstoneKind, BTreeMap<String, SeenItems>>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+fn default_retention() -> u32 {
+    DEFAULT_RETENTION_DAYS
+}
+
+/// 旧版每个键只记一个文件夹路径（字符串），新版是路径列表
+fn deserialize_seen<'de, D>(
+    deserializer: D,
+) -> std::result::Result<BTreeMap<TombstoneKind, BTreeMap<String, SeenItems>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Folders {
+        One(String),
+        Many(BTreeSet<String>),
+    }
+
+    let raw: BTreeMap<TombstoneKind, BTreeMap<String, BTreeMap<String, Folders>>> =
+        Deserialize::deserialize(deserializer)?;
+    Ok(raw
+        .into_iter()
+        .map(|(kind, browsers)| {
+            let browsers = browsers
+                .into_iter()
+                .map(|(browser, items)| {
+                    let items = items
+                        .into_iter()
+                        .map(|(key, folders)| match folders {
+                            Folders::One(folder) => (key, BTreeSet::from([folder])),
+                            Folders::Many(folders) => (key, folders),
+                        })
+                        .collect();
+                    (browser, items)
+                })
+                .collect();
+            (kind, browsers)
+        })
+        .collect())
+}
+
+impl TombstoneStore {
+    pub fn default_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME")?;
+        Ok(PathBuf::from(format!(
+            "{}/.browser-sync/tombstones.json",
+            home
+        )))
+    }
+
+    /// 读取存储并清理过期墓碑；文件不存在时返回空存储
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut store = if path.exists() {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read tombstones: {}", path.display()))?;
+            serde_json::from_str(&content).context("Failed to parse tombstones")?
+        } else {
+            TombstoneStore {
+                retention_days: DEFAULT_RETENTION_DAYS,
+                ..Default::default()
+            }
+        };
+        store.path = Some(path.to_path_buf());
+        store.rekey();
+        let expired = store.purge_expired(chrono::Utc::now().timestamp_millis());
+        if expired > 0 {
+            debug!("Expired {} tombstones", expired);
+        }
+        Ok(store)
+    }
+
+    /// 用当前的 URL 规范化规则重算保存的键（规则升级或 `url-canon.json` 改动后仍能匹配）
+    ///
+    /// 旧键本身是规范化过的 URL，规范化是幂等的，所以重算结果与从原始 URL 计算的一致。
+    fn rekey(&mut self) {
+        for tombstone in &mut self.tombstones {
+            if tombstone.kind != TombstoneKind::Folder {
+                tombstone.key = canonicalize(&tombstone.key);
+            }
+        }
+        for (kind, browsers) in &mut self.seen {
+            if *kind == TombstoneKind::Folder {
+                continue;
+            }
+            for items in browsers.values_mut() {
+                let mut rekeyed = SeenItems::new();
+                for (key, folders) in std::mem::take(items) {
+                    rekeyed
+                        .entry(canonicalize(&key))
+                        .or_default()
+                        .extend(folders);
+                }
+                *items = rekeyed;
+            }
+        }
+    }
+
+    /// 读取默认位置；失败时返回不会保存的空存储
+    pub fn load_default() -> Self {
+        match Self::default_path().and_then(|p| Self::load(&p)) {
+            Ok(store) => store,
+            Err(e) => {
+                warn!(
+                    "⚠️  Tombstones unavailable, deletions won't propagate: {}",
+                    e
+                );
+                TombstoneStore {
+                    retention_days: DEFAULT_RETENTION_DAYS,
+                    ..Default::default()
+                }
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(ref path) = self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to replace {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn is_tombstoned(&self, kind: TombstoneKind, key: &str) -> bool {
+        self.tombstones
+            .iter()
+            .any(|t| t.kind == kind && t.key == key)
+    }
+
+    /// 比较浏览器当前条目与上次见到的条目，生成墓碑 / 撤销被重新添加的墓碑
+    ///
+    /// 同一个键少了几个文件夹、又多了几个文件夹时，先按移动抵消，剩下的才是删除。
+    /// 第一次见到的浏览器只记录，不生成墓碑。
+    pub fn observe(
+        &mut self,
+        kind: TombstoneKind,
+        browser: &str,
+        items: SeenItems,
+        now: i64,
+    ) -> ObserveStats {
+        let mut stats = ObserveStats::default();
+        let seen = self.seen.entry(kind).or_default();
+
+        if let Some(previous) = seen.get(browser) {
+            let mut removed: Vec<(&String, &String)> = Vec::new();
+            for (key, folders) in previous {
+                let current = items.get(key);
+                let gone: Vec<&String> = folders
+                    .iter()
+                    .filter(|f| !current.is_some_and(|c| c.contains(*f)))
+                    .collect();
+                let moved_to = current.map_or(0, |c| c.difference(folders).count());
+                removed.extend(gone.into_iter().skip(moved_to).map(|f| (key, f)));
+            }
+            let previous_count: usize = previous.values().map(BTreeSet::len).sum();
+
+            if removed.len() >= MASS_DELETE_MIN
+                && removed.len() as f64 > previous_count as f64 * MASS_DELETE_RATIO
+            {
+                warn!(
+                    "⚠️  {} lost {} of {} {} entries, treating as a reset (no tombstones)",
+                    browser,
+                    removed.len(),
+                    previous_count,
+                    kind.name()
+                );
+            } else {
+                for (key, folder) in removed {
+                    let folder = (!folder.is_empty()).then(|| folder.clone());
+                    if self
+                        .tombstones
+                        .iter()
+                        .any(|t| t.kind == kind && &t.key == key && t.folder == folder)
+                    {
+                        continue;
+                    }
+                    debug!("🪦 {} deleted {} {}", browser, kind.name(), key);
+                    self.tombstones.push(Tombstone {
+                        kind,
+                        key: key.clone(),
+                        folder,
+                        browser: browser.to_string(),
+                        deleted_at: now,
+                    });
+                    stats.created += 1;
+                }
+            }
+
+            // 浏览器里新出现的 URL 或重新出现在原文件夹里 = 用户重新添加，撤销墓碑
+            let before = self.tombstones.len();
+            self.tombstones.retain(|t| {
+                if t.kind != kind {
+                    return true;
+                }
+                let Some(current) = items.get(&t.key) else {
+                    return true;
+                };
+                let Some(old) = previous.get(&t.key) else {
+                    return false;
+                };
+                let folder = t.folder.as_deref().unwrap_or("");
+                old.contains(folder) || !current.contains(folder)
+            });
+            stats.revived = before - self.tombstones.len();
+        }
+
+        seen.insert(browser.to_string(), items);
+        stats
+    }
+
+    /// 记录写入后的状态（不生成墓碑），下次同步以此为比较基准
+    pub fn record_seen(&mut self, kind: TombstoneKind, browser: &str, items: SeenItems) {
+        self.seen
+            .entry(kind)
+            .or_default()
+            .insert(browser.to_string(), items);
+    }
+
+    pub fn observe_bookmarks(
+        &mut self,
+        browser: &str,
+        tree: &[Bookmark],
+        now: i64,
+    ) -> ObserveStats {
+        let mut stats = self.observe(TombstoneKind::Bookmark, browser, bookmark_items(tree), now);
+        let folders = self.observe(TombstoneKind::Folder, browser, folder_items(tree), now);
+        stats.created += folders.created;
+        stats.revived += folders.revived;
+        stats
+    }
+
+    pub fn record_bookmarks(&mut self, browser: &str, tree: &[Bookmark]) {
+        self.record_seen(TombstoneKind::Bookmark, browser, bookmark_items(tree));
+        self.record_seen(TombstoneKind::Folder, browser, folder_items(tree));
+    }
+
+    /// 从合并结果中移除有墓碑的书签（同一文件夹内），以及有墓碑且已变空的文件夹
+    pub fn filter_bookmarks(&self, tree: &mut Vec<Bookmark>) -> usize {
+        let urls: HashSet<(&str, &str)> = self
+            .tombstones
+            .iter()
+            .filter(|t| t.kind == TombstoneKind::Bookmark)
+            .map(|t| (t.key.as_str(), t.folder.as_deref().unwrap_or("")))
+            .collect();
+        let folders: HashSet<&str> = self.keys(TombstoneKind::Folder);
+        if urls.is_empty() && folders.is_empty() {
+            return 0;
+        }
+
+        fn walk(
+            nodes: &mut Vec<Bookmark>,
+            path: &mut Vec<String>,
+            urls: &HashSet<(&str, &str)>,
+            folders: &HashSet<&str>,
+        ) -> usize {
+            let mut removed = 0;
+            nodes.retain_mut(|node| {
+                if node.folder {
+                    path.push(node.title.clone());
+                    removed += walk(&mut node.children, path, urls, folders);
+                    let dead =
+                        node.children.is_empty() && folders.contains(path.join("/").as_str());
+                    path.pop();
+                    !dead
+                } else {
+                    let folder = path.join("/");
+                    let dead = node.url.as_deref().is_some_and(|u| {
+                        urls.contains(&(canonicalize(u).as_str(), folder.as_str()))
+                    });
+                    if dead {
+                        removed += 1;
+                    }
+                    !dead
+                }
+            });
+            removed
+        }
+
+        let removed = walk(tree, &mut Vec::new(), &urls, &folders);
+        if removed > 0 {
+            info!("🪦 Skipped {} deleted bookmarks (tombstones)", removed);
+        }
+        removed
+    }
+
+    pub fn filter_history(&self, items: &mut Vec<HistoryItem>) -> usize {
+        let keys = self.keys(TombstoneKind::History);
+        let before = items.len();
+        items.retain(|i| !keys.contains(canonicalize(&i.url).as_str()));
+        before - items.len()
+    }
+
+    pub fn filter_reading_list(&self, items: &mut Vec<ReadingListItem>) -> usize {
+        let keys = self.keys(TombstoneKind::ReadingList);
+        let before = items.len();
+        items.retain(|i| !keys.contains(canonicalize(&i.url).as_str()));
+        before - items.len()
+    }
+
+    fn keys(&self, kind: TombstoneKind) -> HashSet<&str> {
+        self.tombstones
+            .iter()
+            .filter(|t| t.kind == kind)
+            .map(|t| t.key.as_str())
+            .collect()
+    }
+
+    pub fn print_list(&self, kind: Option<TombstoneKind>) {
+        let mut items: Vec<&Tombstone> = self
+            .tombstones
+            .iter()
+            .filter(|t| kind.is_none_or(|k| t.kind == k))
+            .collect();
+        items.sort_by_key(|t| std::cmp::Reverse(t.deleted_at));
+
+        println!(
+            "\n🪦 Tombstones ({}, kept {} days):",
+            items.len(),
+            self.retention_days
+        );
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        for t in items {
+            let when = chrono::DateTime::from_timestamp_millis(t.deleted_at)
+                .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+            match t.folder {
+                Some(ref folder) => println!(
+                    "  {}  {:<12} {}  [{}]  ({})",
+                    when,
+                    t.kind.name(),
+                    t.key,
+                    folder,
+                    t.browser
+                ),
+                None => println!(
+                    "  {}  {:<12} {}  ({})",
+                    when,
+                    t.kind.name(),
+                    t.key,
+                    t.browser
+                ),
+            }
+        }
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    }
+
+    /// 清理超过保留期的墓碑
+    pub fn purge_expired(&mut self, now: i64) -> usize {
+        let cutoff = now - self.retention_days as i64 * MS_PER_DAY;
+        self.purge_where(|t| t.deleted_at < cutoff)
+    }
+
+    pub fn purge_where(&mut self, mut predicate: impl FnMut(&Tombstone) -> bool) -> usize {
+        let before = self.tombstones.len();
+        self.tombstones.retain(|t| !predicate(t));
+        before - self.tombstones.len()
+    }
+}
+
+pub fn bookmark_items(tree: &[Bookmark]) -> SeenItems {
+    fn walk(nodes: &[Bookmark], path: &mut Vec<String>, out: &mut SeenItems) {
+        for node in nodes {
+            if node.folder {
+                path.push(node.title.clone());
+                walk(&node.children, path, out);
+                path.pop();
+            } else if let Some(ref url) = node.url {
+                out.entry(canonicalize(url))
+                    .or_default()
+                    .insert(path.join("/"));
+            }
+        }
+    }
+    let mut out = SeenItems::new();
+    walk(tree, &mut Vec::new(), &mut out);
+    out
+}
+
+fn folder_items(tree: &[Bookmark]) -> SeenItems {
+    fn walk(nodes: &[Bookmark], path: &mut Vec<String>, out: &mut SeenItems) {
+        for node in nodes.iter().filter(|n| n.folder) {
+            let parent = path.join("/");
+            path.push(node.title.clone());
+            out.entry(path.join("/")).or_default().insert(parent);
+            walk(&node.children, path, out);
+            path.pop();
+        }
+    }
+    let mut out = SeenItems::new();
+    walk(tree, &mut Vec::new(), &mut out);
+    out
+}
+
+pub fn history_items(items: &[HistoryItem]) -> SeenItems {
+    items
+        .iter()
+        .map(|i| (canonicalize(&i.url), BTreeSet::from([String::new()])))
+        .collect()
+}
+
+pub fn reading_list_items(items: &[ReadingListItem]) -> SeenItems {
+    items
+        .iter()
+        .map(|i| (canonicalize(&i.url), BTreeSet::from([String::new()])))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn make_bookmark(title: &str, url: &str) -> Bookmark {
+        Bookmark {
+            date_added: Some(1000000),
+            ..test_support::make_bookmark(title, url)
+        }
+    }
+
+    fn make_folder(title: &str, children: Vec<Bookmark>) -> Bookmark {
+        Bookmark {
+            date_added: Some(1000000),
+            ..test_support::make_folder(title, children)
+        }
+    }
+
+    fn tree() -> Vec<Bookmark> {
+        vec![make_folder(
+            "Dev",
+            vec![
+                make_bookmark("Rust", "https://rust-lang.org/"),
+                make_bookmark("Go", "https://go.dev"),
+            ],
+        )]
+    }
+
+    #[test]
+    fn test_deletion_creates_tombstone_and_filters_merge() {
+        let mut store = TombstoneStore::default();
+        assert_eq!(store.observe_bookmarks("Brave", &tree(), 1).created, 0);
+
+        let brave = vec![make_folder(
+            "Dev",
+            vec![make_bookmark("Rust", "https://rust-lang.org")],
+        )];
+        let stats = store.observe_bookmarks("Brave", &brave, 2);
+        assert_eq!(stats.created, 1);
+        let t = &store.tombstones[0];
+        assert_eq!(
+            (t.kind, t.key.as_str(), t.folder.as_deref()),
+            (TombstoneKind::Bookmark, "https://go.dev", Some("Dev"))
+        );
+
+        // Waterfox still has it, the merge must not resurrect it
+        let mut merged = tree();
+        assert_eq!(store.filter_bookmarks(&mut merged), 1);
+        assert_eq!(merged[0].children.len(), 1);
+    }
+
+    #[test]
+    fn test_readd_revives_and_moves_are_not_deletions() {
+        let mut store = TombstoneStore::default();
+        store.observe_bookmarks("Brave", &tree(), 1);
+        store.observe_bookmarks(
+            "Brave",
+            &[make_folder(
+                "Dev",
+                vec![make_bookmark("Rust", "https://rust-lang.org")],
+            )],
+            2,
+        );
+        assert!(store.is_tombstoned(TombstoneKind::Bookmark, "https://go.dev"));
+
+        // Moving a bookmark to another folder keeps its URL, so no tombstone
+        let moved = vec![
+            make_folder("Dev", vec![]),
+            make_folder(
+                "Archive",
+                vec![make_bookmark("Rust", "https://rust-lang.org")],
+            ),
+        ];
+        assert_eq!(store.observe_bookmarks("Brave", &moved, 3).created, 0);
+
+        let readded = vec![make_folder(
+            "Archive",
+            vec![
+                make_bookmark("Rust", "https://rust-lang.org"),
+                make_bookmark("Go", "https://go.dev"),
+            ],
+        )];
+        let stats = store.observe_bookmarks("Brave", &readded, 4);
+        assert_eq!(stats.revived, 1);
+        assert!(!store.is_tombstoned(TombstoneKind::Bookmark, "https://go.dev"));
+        // The emptied "Dev" folder got a folder tombstone
+        assert!(store.is_tombstoned(TombstoneKind::Folder, "Dev"));
+    }
+
+    #[test]
+    fn test_deleting_one_copy_keeps_other_folders() {
+        let mut store = TombstoneStore::default();
+        let both = vec![
+            make_folder(
+                "Dev",
+                vec![make_folder(
+                    "Rust",
+                    vec![make_bookmark("Book", "https://doc.rust-lang.org/book/")],
+                )],
+            ),
+            make_folder(
+                "Archive",
+                vec![make_bookmark("Book", "https://doc.rust-lang.org/book/")],
+            ),
+        ];
+        store.observe_bookmarks("Brave", &both, 1);
+
+        // Brave deletes only the Archive copy
+        let mut brave = both.clone();
+        brave[1].children.clear();
+        assert_eq!(store.observe_bookmarks("Brave", &brave, 2).created, 1);
+        assert_eq!(store.tombstones[0].folder.as_deref(), Some("Archive"));
+
+        // Waterfox still has both; only the Archive copy is dropped from the merge
+        let mut merged = both;
+        assert_eq!(store.filter_bookmarks(&mut merged), 1);
+        assert_eq!(merged[0].children[0].children.len(), 1);
+        assert!(merged[1].children.is_empty());
+    }
+
+    #[test]
+    fn test_mass_delete_is_treated_as_reset() {
+        let mut store = TombstoneStore::default();
+        let many: Vec<Bookmark> = (0..30)
+            .map(|i| make_bookmark("x", &format!("https://site{}.com", i)))
+            .collect();
+        store.observe_bookmarks("Chrome", &many, 1);
+        assert_eq!(store.observe_bookmarks("Chrome", &[], 2).created, 0);
+        assert!(store.tombstones.is_empty());
+    }
+
+    #[test]
+    fn test_retention_and_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tombstones.json");
+        let mut store = TombstoneStore::load(&path).unwrap();
+        assert_eq!(store.retention_days, DEFAULT_RETENTION_DAYS);
+
+        let now = chrono::Utc::now().timestamp_millis();
+        store.observe(
+            TombstoneKind::History,
+            "Safari",
+            BTreeMap::from([("https://a.com".into(), BTreeSet::from([String::new()]))]),
+            now,
+        );
+        store.observe(TombstoneKind::History, "Safari", SeenItems::new(), now);
+        store.tombstones.push(Tombstone {
+            kind: TombstoneKind::ReadingList,
+            key: "https://old.com".into(),
+            folder: None,
+            browser: "Safari".into(),
+            deleted_at: now - 365 * MS_PER_DAY,
+        });
+        store.save().unwrap();
+
+        let loaded = TombstoneStore::load(&path).unwrap();
+        assert_eq!(loaded.tombstones.len(), 1);
+        assert!(loaded.is_tombstoned(TombstoneKind::History, "https://a.com"));
+
+        let mut history = vec![HistoryItem {
+            url: "https://A.com/".into(),
+            title: None,
+            visit_count: 1,
+            last_visit: None,
+        }];
+        assert_eq!(loaded.filter_history(&mut history), 1);
+    }
+
+    #[test]
+    fn test_keys_are_recanonicalized_on_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tombstones.json");
+        // Keys written by the old normalizer (lowercased, no www, tracking params kept)
+        std::fs::write(
+            &path,
+            serde_json::json!({
+                "retention_days": 90,
+                "tombstones": [
+                    {"kind": "bookmark", "key": "http://example.com/page?utm_source=x",
+                     "browser": "Brave", "deleted_at": chrono::Utc::now().timestamp_millis()},
+                    {"kind": "folder", "key": "Dev/Old", "browser": "Brave",
+                     "deleted_at": chrono::Utc::now().timestamp_millis()}
+                ],
+                "seen": {"bookmark": {"Brave": {"http://go.dev/?fbclid=1": "Dev"}}}
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let mut store = TombstoneStore::load(&path).unwrap();
+        assert!(store.is_tombstoned(TombstoneKind::Bookmark, "https://example.com/page"));
+        assert!(store.is_tombstoned(TombstoneKind::Folder, "Dev/Old"));
+        // Same bookmark under the new key: not a deletion
+        let brave = vec![make_folder(
+            "Dev",
+            vec![make_bookmark("Go", "https://www.go.dev/")],
+        )];
+        assert_eq!(store.observe_bookmarks("Brave", &brave, 2).created, 0);
+    }
+}
@@ -0,0 +1,585 @@
+//! URL 规范化 - 去重、同步、墓碑、分析共用的唯一 URL 身份键
+//!
+//! 规范化结果只用作比较键，书签里保存的 URL 不会被修改。步骤按顺序执行，每一步都可以在
+//! `~/.browser-sync/url-canon.json` 里关闭：
+//!
+//! ```json
+//! { "fold_scheme": false, "tracking_params": ["utm_*", "fbclid", "ref"], "fragment": "keep-routes" }
+//! ```
+//!
+//! - `fold_scheme`：`http` 视为 `https`
+//! - `strip_www`：去掉主机名开头的 `www.`
+//! - `remove_default_port`：去掉协议的默认端口（80、443、21）
+//! - `idn_to_punycode`：国际化域名转为 punycode（`例子.com` → `xn--fsqu00a.com`）
+//! - `normalize_percent_encoding`：解码不需要编码的字符（`%7E` → `~`），其余十六进制统一大写
+//! - `strip_tracking_params`：移除 `tracking_params` 里的查询参数（支持 `*` 通配符）
+//! - `sort_query`：查询参数按名称排序
+//! - `fragment`：`drop`（默认）、`keep`，或 `keep-routes`（只保留 `#/…`、`#!…` 这类单页应用路由）
+//! - `strip_trailing_slash`：去掉路径末尾的 `/`
+//! - `ignore_case`：路径和查询不区分大小写（主机名和协议总是不区分）
+//!
+//! 不含 `://` 的 URL（`javascript:`、`data:` 等）只做大小写、片段和尾部斜杠处理。
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::warn;
+
+use crate::export_filter::wildcard_match;
+
+/// 片段（`#` 之后）的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FragmentPolicy {
+    #[default]
+    Drop,
+    Keep,
+    /// 只保留单页应用路由（`#/path`、`#!path`）
+    KeepRoutes,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_tracking_params() -> Vec<String> {
+    [
+        "utm_*", "fbclid", "gclid", "dclid", "msclkid", "yclid", "spm", "mc_cid", "mc_eid",
+        "igshid",
+    ]
+    .iter()
+    .map(|p| p.to_string())
+    .collect()
+}
+
+/// 规范化步骤开关
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CanonConfig {
+    #[serde(default = "default_true")]
+    pub fold_scheme: bool,
+    #[serde(default = "default_true")]
+    pub strip_www: bool,
+    #[serde(default = "default_true")]
+    pub remove_default_port: bool,
+    #[serde(default = "default_true")]
+    pub idn_to_punycode: bool,
+    #[serde(default = "default_true")]
+    pub normalize_percent_encoding: bool,
+    #[serde(default = "default_true")]
+    pub strip_tracking_params: bool,
+    #[serde(default = "default_tracking_params")]
+    pub tracking_params: Vec<String>,
+    #[serde(default = "default_true")]
+    pub sort_query: bool,
+    #[serde(default)]
+    pub fragment: FragmentPolicy,
+    #[serde(default = "default_true")]
+    pub strip_trailing_slash: bool,
+    #[serde(default = "default_true")]
+    pub ignore_case: bool,
+}
+
+impl Default for CanonConfig {
+    fn default() -> Self {
+        Self {
+            fold_scheme: true,
+            strip_www: true,
+            remove_default_port: true,
+            idn_to_punycode: true,
+            normalize_percent_encoding: true,
+            strip_tracking_params: true,
+            tracking_params: default_tracking_params(),
+            sort_query: true,
+            fragment: FragmentPolicy::Drop,
+            strip_trailing_slash: true,
+            ignore_case: true,
+        }
+    }
+}
+
+impl CanonConfig {
+    pub fn default_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME")?;
+        Ok(PathBuf::from(format!(
+            "{}/.browser-sync/url-canon.json",
+            home
+        )))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content).context("Failed to parse URL canonicalization config")
+    }
+
+    pub fn load_default() -> Self {
+        Self::default_path()
+            .and_then(|p| Self::load(&p))
+            .unwrap_or_else(|e| {
+                warn!("⚠️  Using default URL canonicalization: {}", e);
+                Self::default()
+            })
+    }
+}
+
+fn default_port(scheme: &str) -> Option<&'static str> {
+    match scheme {
+        "http" => Some("80"),
+        "https" => Some("443"),
+        "ftp" => Some("21"),
+        _ => None,
+    }
+}
+
+/// RFC 3986 的非保留字符，编码与否含义相同
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~')
+}
+
+/// 解码非保留字符的 `%XX`，其余 `%xx` 统一为大写
+fn normalize_percent(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = String::with_capacity(input.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit()
+        {
+            let hex = &input[i + 1..i + 3];
+            let byte = u8::from_str_radix(hex, 16).unwrap_or_default();
+            if is_unreserved(byte) {
+                out.push(byte as char);
+            } else {
+                out.push('%');
+                out.push_str(&hex.to_ascii_uppercase());
+            }
+            i += 3;
+            continue;
+        }
+        let ch = input[i..].chars().next().unwrap_or_default();
+        out.push(ch);
+        i += ch.len_utf8();
+    }
+    out
+}
+
+fn strip_trailing_slashes(path: &mut String) {
+    while path.ends_with('/') {
+        path.pop();
+    }
+}
+
+/// 按配置规范化 URL
+#[derive(Debug, Clone, Default)]
+pub struct UrlCanonicalizer {
+    config: CanonConfig,
+}
+
+impl UrlCanonicalizer {
+    pub fn new(config: CanonConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn canonicalize(&self, url: &str) -> String {
+        let config = &self.config;
+        let url = url.trim();
+
+        let (rest, fragment) = match url.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment)),
+            None => (url, None),
+        };
+        let fragment = fragment.filter(|f| match config.fragment {
+            FragmentPolicy::Drop => false,
+            FragmentPolicy::Keep => !f.is_empty(),
+            FragmentPolicy::KeepRoutes => f.starts_with('/') || f.starts_with('!'),
+        });
+
+        let Some((scheme, after_scheme)) = rest.split_once("://") else {
+            return self.canonicalize_opaque(rest, fragment);
+        };
+        let original_scheme = scheme.to_ascii_lowercase();
+        let scheme = if config.fold_scheme && original_scheme == "http" {
+            "https".to_string()
+        } else {
+            original_scheme.clone()
+        };
+
+        let authority_end = after_scheme.find(['/', '?']).unwrap_or(after_scheme.len());
+        let (authority, path_and_query) = after_scheme.split_at(authority_end);
+        let (path, query) = match path_and_query.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (path_and_query, None),
+        };
+
+        let mut out = format!("{}://", scheme);
+        out.push_str(&self.canonicalize_authority(authority, &original_scheme));
+
+        let mut path = if config.normalize_percent_encoding {
+            normalize_percent(path)
+        } else {
+            path.to_string()
+        };
+        if config.ignore_case {
+            path = path.to_lowercase();
+        }
+        if config.strip_trailing_slash {
+            strip_trailing_slashes(&mut path);
+        }
+        out.push_str(&path);
+
+        if let Some(query) = query.map(|q| self.canonicalize_query(q)) {
+            if !query.is_empty() {
+                out.push('?');
+                out.push_str(&query);
+            }
+        }
+        if let Some(fragment) = fragment {
+            out.push('#');
+            out.push_str(&self.case(fragment));
+        }
+        out
+    }
+
+    fn case(&self, value: &str) -> String {
+        if self.config.ignore_case {
+            value.to_lowercase()
+        } else {
+            value.to_string()
+        }
+    }
+
+    /// `javascript:`、`mailto:` 之类没有主机名的 URL
+    fn canonicalize_opaque(&self, rest: &str, fragment: Option<&str>) -> String {
+        let mut out = self.case(rest);
+        if self.config.strip_trailing_slash {
+            strip_trailing_slashes(&mut out);
+        }
+        if let Some(fragment) = fragment {
+            out.push('#');
+            out.push_str(&self.case(fragment));
+        }
+        out
+    }
+
+    fn canonicalize_authority(&self, authority: &str, scheme: &str) -> String {
+        let config = &self.config;
+        let (userinfo, host_port) = match authority.rsplit_once('@') {
+            Some((userinfo, host_port)) => (Some(userinfo), host_port),
+            None => (None, authority),
+        };
+        // IPv6 地址里有冒号，端口在 `]` 之后
+        let port_start = match host_port.rfind(':') {
+            Some(pos) if !host_port[pos..].contains(']') => Some(pos),
+            _ => None,
+        };
+        let (host, port) = match port_start {
+            Some(pos) => (&host_port[..pos], Some(&host_port[pos + 1..])),
+            None => (host_port, None),
+        };
+
+        let mut host = host.to_lowercase();
+        if host.ends_with('.') {
+            host.pop();
+        }
+        if config.idn_to_punycode && !host.is_ascii() {
+            if let Ok(ascii) = idna::domain_to_ascii(&host) {
+                host = ascii;
+            }
+        }
+        if config.strip_www {
+            if let Some(stripped) = host.strip_prefix("www.") {
+                // `www.com` 本身就是域名
+                if stripped.contains('.') {
+                    host = stripped.to_string();
+                }
+            }
+        }
+        let port = port.filter(|p| {
+            !(p.is_empty() || config.remove_default_port && default_port(scheme) == Some(*p))
+        });
+
+        let mut out = String::new();
+        if let Some(userinfo) = userinfo {
+            out.push_str(userinfo);
+            out.push('@');
+        }
+        out.push_str(&host);
+        if let Some(port) = port {
+            out.push(':');
+            out.push_str(port);
+        }
+        out
+    }
+
+    fn is_tracking_param(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.config
+            .tracking_params
+            .iter()
+            .any(|pattern| wildcard_match(&pattern.to_lowercase(), &name))
+    }
+
+    fn canonicalize_query(&self, query: &str) -> String {
+        let config = &self.config;
+        let mut params: Vec<String> = query
+            .split('&')
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                let p = if config.normalize_percent_encoding {
+                    normalize_percent(p)
+                } else {
+                    p.to_string()
+                };
+                self.case(&p)
+            })
+            .filter(|p| {
+                let name = p.split('=').next().unwrap_or_default();
+                !(config.strip_tracking_params && self.is_tracking_param(name))
+            })
+            .collect();
+        if config.sort_query {
+            // 只按名称排序，同名参数保持原来的顺序
+            params.sort_by_key(|p| p.split('=').next().unwrap_or_default().to_string());
+        }
+        params.join("&")
+    }
+}
+
+static CANONICALIZER: OnceLock<UrlCanonicalizer> = OnceLock::new();
+
+/// 使用 `~/.browser-sync/url-canon.json`（不存在时用默认配置）规范化 URL
+pub fn canonicalize(url: &str) -> String {
+    CANONICALIZER
+        .get_or_init(|| UrlCanonicalizer::new(CanonConfig::load_default()))
+        .canonicalize(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with(config: CanonConfig, url: &str) -> String {
+        UrlCanonicalizer::new(config).canonicalize(url)
+    }
+
+    fn canon(url: &str) -> String {
+        with(CanonConfig::default(), url)
+    }
+
+    /// 只开启 `ignore_case` 以外的某一步时的配置
+    fn only(step: impl FnOnce(&mut CanonConfig)) -> CanonConfig {
+        let mut config = CanonConfig {
+            fold_scheme: false,
+            strip_www: false,
+            remove_default_port: false,
+            idn_to_punycode: false,
+            normalize_percent_encoding: false,
+            strip_tracking_params: false,
+            tracking_params: default_tracking_params(),
+            sort_query: false,
+            fragment: FragmentPolicy::Keep,
+            strip_trailing_slash: false,
+            ignore_case: false,
+        };
+        step(&mut config);
+        config
+    }
+
+    #[test]
+    fn test_legacy_normalizer_behavior() {
+        // 以前 SyncEngine::normalize_url、cleanup::normalize_url 和 normalize_url_for_dedupe 的用例
+        assert_eq!(canon("https://example.com/"), "https://example.com");
+        assert_eq!(canon("https://example.com"), "https://example.com");
+        assert_eq!(canon("HTTPS://EXAMPLE.COM/"), "https://example.com");
+        assert_eq!(canon("https://example.com#section"), "https://example.com");
+        assert_eq!(canon("  https://example.com/  "), "https://example.com");
+        assert_eq!(canon("https://www.example.com//"), "https://example.com");
+    }
+
+    #[test]
+    fn test_scheme_folding() {
+        let config = only(|c| c.fold_scheme = true);
+        assert_eq!(with(config.clone(), "http://a.com/x"), "https://a.com/x");
+        assert_eq!(with(config.clone(), "HTTP://a.com"), "https://a.com");
+        assert_eq!(with(config, "ftp://a.com"), "ftp://a.com");
+        assert_eq!(with(only(|_| {}), "http://a.com"), "http://a.com");
+    }
+
+    #[test]
+    fn test_www_stripping() {
+        let config = only(|c| c.strip_www = true);
+        assert_eq!(
+            with(config.clone(), "https://WWW.a.com/x"),
+            "https://a.com/x"
+        );
+        assert_eq!(with(config.clone(), "https://www.com"), "https://www.com");
+        assert_eq!(with(config, "https://www2.a.com"), "https://www2.a.com");
+        assert_eq!(with(only(|_| {}), "https://www.a.com"), "https://www.a.com");
+    }
+
+    #[test]
+    fn test_default_port_removal() {
+        let config = only(|c| c.remove_default_port = true);
+        assert_eq!(
+            with(config.clone(), "https://a.com:443/x"),
+            "https://a.com/x"
+        );
+        assert_eq!(with(config.clone(), "http://a.com:80"), "http://a.com");
+        assert_eq!(with(config.clone(), "http://a.com:443"), "http://a.com:443");
+        assert_eq!(
+            with(config.clone(), "https://a.com:8443"),
+            "https://a.com:8443"
+        );
+        assert_eq!(with(config, "http://[::1]:80/x"), "http://[::1]/x");
+        // 折叠协议时按原来的协议判断默认端口
+        assert_eq!(canon("http://a.com:80/x"), "https://a.com/x");
+    }
+
+    #[test]
+    fn test_idn_to_punycode() {
+        let config = only(|c| c.idn_to_punycode = true);
+        assert_eq!(
+            with(config.clone(), "https://例子.测试/路径"),
+            "https://xn--fsqu00a.xn--0zwm56d/路径"
+        );
+        assert_eq!(
+            with(config, "https://Bücher.de"),
+            "https://xn--bcher-kva.de"
+        );
+        assert_eq!(with(only(|_| {}), "https://bücher.de"), "https://bücher.de");
+    }
+
+    #[test]
+    fn test_percent_encoding_normalization() {
+        let config = only(|c| c.normalize_percent_encoding = true);
+        assert_eq!(
+            with(config.clone(), "https://a.com/%7euser/a%2fb"),
+            "https://a.com/~user/a%2Fb"
+        );
+        assert_eq!(
+            with(config.clone(), "https://a.com/?q=%41%20b"),
+            "https://a.com/?q=A%20b"
+        );
+        // 不完整的转义保持原样
+        assert_eq!(with(config, "https://a.com/100%"), "https://a.com/100%");
+        assert_eq!(canon("https://a.com/%7Euser"), canon("https://a.com/~user"));
+    }
+
+    #[test]
+    fn test_query_sorting() {
+        let config = only(|c| c.sort_query = true);
+        assert_eq!(
+            with(config.clone(), "https://a.com/?b=2&a=1&c=3"),
+            "https://a.com/?a=1&b=2&c=3"
+        );
+        // 同名参数保持顺序
+        assert_eq!(
+            with(config, "https://a.com/?t=2&a=1&t=1"),
+            "https://a.com/?a=1&t=2&t=1"
+        );
+        assert_eq!(
+            with(only(|_| {}), "https://a.com/?b=2&a=1"),
+            "https://a.com/?b=2&a=1"
+        );
+    }
+
+    #[test]
+    fn test_tracking_param_removal() {
+        let config = only(|c| c.strip_tracking_params = true);
+        assert_eq!(
+            with(
+                config.clone(),
+                "https://a.com/p?id=7&utm_source=x&UTM_Medium=y&fbclid=1&gclid=2&spm=a.b"
+            ),
+            "https://a.com/p?id=7"
+        );
+        assert_eq!(
+            with(config, "https://a.com/p?utm_source=x"),
+            "https://a.com/p"
+        );
+
+        let custom = only(|c| {
+            c.strip_tracking_params = true;
+            c.tracking_params = vec!["ref".to_string()];
+        });
+        assert_eq!(
+            with(custom, "https://a.com/?ref=hn&utm_source=x"),
+            "https://a.com/?utm_source=x"
+        );
+    }
+
+    #[test]
+    fn test_fragment_policy() {
+        let url = "https://a.com/app#/settings";
+        assert_eq!(
+            with(only(|c| c.fragment = FragmentPolicy::Drop), url),
+            "https://a.com/app"
+        );
+        assert_eq!(with(only(|c| c.fragment = FragmentPolicy::Keep), url), url);
+
+        let routes = only(|c| c.fragment = FragmentPolicy::KeepRoutes);
+        assert_eq!(with(routes.clone(), url), url);
+        assert_eq!(
+            with(routes.clone(), "https://a.com/#!/inbox"),
+            "https://a.com/#!/inbox"
+        );
+        assert_eq!(
+            with(routes, "https://a.com/doc#section-2"),
+            "https://a.com/doc"
+        );
+    }
+
+    #[test]
+    fn test_trailing_slash_and_case() {
+        assert_eq!(
+            with(
+                only(|c| c.strip_trailing_slash = true),
+                "https://a.com/Docs/"
+            ),
+            "https://a.com/Docs"
+        );
+        assert_eq!(
+            with(only(|c| c.ignore_case = true), "https://A.com/Docs?Q=1"),
+            "https://a.com/docs?q=1"
+        );
+        assert_eq!(
+            with(only(|_| {}), "https://A.com/Docs?Q=1"),
+            "https://a.com/Docs?Q=1"
+        );
+    }
+
+    #[test]
+    fn test_opaque_urls_and_idempotence() {
+        assert_eq!(canon("JavaScript:alert(1)"), "javascript:alert(1)");
+        assert_eq!(canon("mailto:Me@Example.com"), "mailto:me@example.com");
+
+        for url in [
+            "http://www.Example.com:80/a/%7Eb/?utm_source=x&b=2&a=1#top",
+            "https://例子.测试/",
+            "javascript:void(0)",
+        ] {
+            let once = canon(url);
+            assert_eq!(canon(&once), once, "not idempotent for {}", url);
+        }
+        assert_eq!(
+            canon("http://www.Example.com:80/a/%7Eb/?utm_source=x&b=2&a=1#top"),
+            "https://example.com/a/~b?a=1&b=2"
+        );
+    }
+
+    #[test]
+    fn test_config_defaults_from_json() {
+        let config: CanonConfig =
+            serde_json::from_str(r#"{"fold_scheme": false, "fragment": "keep-routes"}"#).unwrap();
+        assert!(!config.fold_scheme);
+        assert!(config.strip_www);
+        assert_eq!(config.fragment, FragmentPolicy::KeepRoutes);
+        assert_eq!(config.tracking_params, default_tracking_params());
+    }
+}
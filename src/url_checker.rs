@@ -61,6 +61,18 @@ impl ValidationStatus {
     }
 }
 
+/// 最多跟随的重定向次数
+const MAX_REDIRECTS: usize = 5;
+
+/// 重定向链中的一跳
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedirectHop {
+    /// 返回重定向的 URL
+    pub url: String,
+    /// 重定向状态码 (301/302/303/307/308)
+    pub status_code: u16,
+}
+
 /// HTTP请求结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HttpResult {
@@ -72,6 +84,12 @@ pub struct HttpResult {
     pub latency_ms: u64,
     /// 是否超时
     pub is_timeout: bool,
+    /// 跟随重定向后最终响应的 URL (None表示没有收到响应)
+    #[serde(default)]
+    pub final_url: Option<String>,
+    /// 重定向链（按顺序，不含最终响应）
+    #[serde(default)]
+    pub redirects: Vec<RedirectHop>,
 }
 
 impl HttpResult {
@@ -82,6 +100,8 @@ impl HttpResult {
             error: None,
             latency_ms,
             is_timeout: false,
+            final_url: None,
+            redirects: Vec::new(),
         }
     }
     
@@ -92,6 +112,8 @@ impl HttpResult {
             error: Some(error),
             latency_ms,
             is_timeout: false,
+            final_url: None,
+            redirects: Vec::new(),
         }
     }
     
@@ -102,8 +124,24 @@ impl HttpResult {
             error: Some("Request timeout".to_string()),
             latency_ms,
             is_timeout: true,
+            final_url: None,
+            redirects: Vec::new(),
         }
     }
+
+    /// 附上最终 URL 和重定向链
+    pub fn with_redirects(mut self, final_url: Option<String>, redirects: Vec<RedirectHop>) -> Self {
+        self.final_url = final_url;
+        self.redirects = redirects;
+        self
+    }
+
+    /// 是否成功且整条重定向链都是永久重定向 (301/308)
+    pub fn is_permanent_redirect(&self) -> bool {
+        self.is_success()
+            && !self.redirects.is_empty()
+            && self.redirects.iter().all(|hop| matches!(hop.status_code, 301 | 308))
+    }
     
     /// 判断是否成功 (HTTP 2xx/3xx)
     pub fn is_success(&self) -> bool {
@@ -177,6 +215,28 @@ pub struct UrlCheckResult {
 }
 
 impl UrlCheckResult {
+    /// 成功的那次请求（直连优先）
+    fn successful_result(&self) -> Option<&HttpResult> {
+        [self.direct_result.as_ref(), self.proxy_result.as_ref()]
+            .into_iter()
+            .flatten()
+            .find(|r| r.is_success())
+    }
+
+    /// 重定向后的最终 URL（没有重定向或请求失败时为 None）
+    pub fn final_url(&self) -> Option<&str> {
+        self.successful_result()
+            .filter(|r| !r.redirects.is_empty())
+            .and_then(|r| r.final_url.as_deref())
+    }
+
+    /// 是否经由永久重定向 (301/308) 到达最终 URL
+    pub fn is_permanent_redirect(&self) -> bool {
+        self.successful_result()
+            .map(|r| r.is_permanent_redirect())
+            .unwrap_or(false)
+    }
+
     /// 创建跳过结果
     pub fn skipped(url: String, reason: &str) -> Self {
         Self {
@@ -432,7 +492,8 @@ impl UrlChecker {
             .pool_idle_timeout(Duration::from_secs(30))
             .pool_max_idle_per_host(10)
             .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36")
-            .redirect(reqwest::redirect::Policy::limited(5))
+            // 手动跟随重定向，以便记录重定向链
+            .redirect(reqwest::redirect::Policy::none())
             .build()?;
         
         // 创建代理客户端 (如果配置了代理)
@@ -446,7 +507,7 @@ impl UrlChecker {
                 .pool_max_idle_per_host(10)
                 .proxy(proxy)
                 .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36")
-                .redirect(reqwest::redirect::Policy::limited(5))
+                .redirect(reqwest::redirect::Policy::none())
                 .build()?)
         } else {
             debug!("未配置代理，仅使用直连模式");
@@ -494,52 +555,62 @@ impl UrlChecker {
         None
     }
     
-    /// 执行单个HTTP请求
-    /// 先尝试 HEAD 请求，如果返回 405 则回退到 GET 请求
+    /// 执行单个HTTP请求，手动跟随最多 5 次重定向并记录重定向链
     async fn do_request(client: &Client, url: &str) -> HttpResult {
         let start = std::time::Instant::now();
-        
-        // 先尝试 HEAD 请求
-        match client.head(url).send().await {
-            Ok(response) => {
-                let status = response.status().as_u16();
-                let latency = start.elapsed().as_millis() as u64;
-                
-                // 如果 HEAD 返回 405 (Method Not Allowed)，回退到 GET
-                if status == 405 {
-                    debug!("HEAD 返回 405，回退到 GET: {}", url);
-                    return Self::do_get_request(client, url, start).await;
+        let mut current = url.to_string();
+        let mut redirects = Vec::new();
+
+        loop {
+            let response = match Self::send(client, &current).await {
+                Ok(response) => response,
+                Err(e) => {
+                    let latency = start.elapsed().as_millis() as u64;
+                    let result = if e.is_timeout() {
+                        HttpResult::timeout(latency)
+                    } else {
+                        HttpResult::failure(e.to_string(), latency)
+                    };
+                    return result.with_redirects(None, redirects);
                 }
-                
-                HttpResult::success(status, latency)
-            }
-            Err(e) => {
+            };
+
+            let status = response.status().as_u16();
+            let location = if response.status().is_redirection() {
+                response
+                    .headers()
+                    .get(reqwest::header::LOCATION)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|location| response.url().join(location).ok())
+            } else {
+                None
+            };
+
+            let Some(next) = location else {
                 let latency = start.elapsed().as_millis() as u64;
-                if e.is_timeout() {
-                    HttpResult::timeout(latency)
-                } else {
-                    HttpResult::failure(e.to_string(), latency)
-                }
+                return HttpResult::success(status, latency).with_redirects(Some(current), redirects);
+            };
+            if redirects.len() == MAX_REDIRECTS {
+                let latency = start.elapsed().as_millis() as u64;
+                return HttpResult::failure("Too many redirects".to_string(), latency)
+                    .with_redirects(None, redirects);
             }
+            redirects.push(RedirectHop {
+                url: current,
+                status_code: status,
+            });
+            current = next.to_string();
         }
     }
-    
-    /// 执行 GET 请求（仅获取响应头，不下载 body）
-    async fn do_get_request(client: &Client, url: &str, start: std::time::Instant) -> HttpResult {
-        match client.get(url).send().await {
-            Ok(response) => {
-                let latency = start.elapsed().as_millis() as u64;
-                HttpResult::success(response.status().as_u16(), latency)
-            }
-            Err(e) => {
-                let latency = start.elapsed().as_millis() as u64;
-                if e.is_timeout() {
-                    HttpResult::timeout(latency)
-                } else {
-                    HttpResult::failure(e.to_string(), latency)
-                }
-            }
+
+    /// 先尝试 HEAD 请求，如果返回 405 则回退到 GET 请求（仅获取响应头，不下载 body）
+    async fn send(client: &Client, url: &str) -> reqwest::Result<reqwest::Response> {
+        let response = client.head(url).send().await?;
+        if response.status().as_u16() == 405 {
+            debug!("HEAD 返回 405，回退到 GET: {}", url);
+            return client.get(url).send().await;
         }
+        Ok(response)
     }
     
     /// 检查单个URL
@@ -0,0 +1,350 @@
+//! 文件监视 - 浏览器书签文件变化后自动运行同步或导出
+//!
+//! 在 `daemon.json` 里配置 `watch`：
+//!
+//! ```json
+//! { "watch": { "task": "sync", "debounce_seconds": 10, "browsers": ["waterfox", "chrome"] } }
+//! ```
+//!
+//! 监视每个浏览器 `detect_bookmark_path` 指向的文件（`Bookmarks`、`Bookmarks.plist`，
+//! Firefox 系的 `places.sqlite` 还会监视 `places.sqlite-wal`），变化停止 `debounce_seconds`
+//! 秒后运行一次任务。
+//!
+//! - 轮询文件的修改时间和大小，浏览器用重命名替换文件时也能发现
+//! - 任务（包括定时任务）运行期间照常检查，但先不触发；全部任务结束后，写过书签的任务
+//!   运行期间的变化视为 bsync 自己的写入，只读任务（export、backup）期间的变化照常触发。
+//!   任务开始前还在防抖的变化不受影响
+//! - SQLite 数据库被浏览器锁定时（`db_safety::is_database_locked`）推迟运行，直到锁释放
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, info};
+
+use crate::browsers::get_all_adapters;
+use crate::db_safety;
+use crate::scheduler::Task;
+
+fn default_task() -> Task {
+    Task::Sync
+}
+
+fn default_debounce() -> u64 {
+    10
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchConfig {
+    #[serde(default = "default_task")]
+    pub task: Task,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// 最后一次变化后等待的秒数
+    #[serde(default = "default_debounce")]
+    pub debounce_seconds: u64,
+    /// 只监视这些浏览器（名称包含匹配），为空时监视全部
+    #[serde(default)]
+    pub browsers: Vec<String>,
+}
+
+/// 一个浏览器的监视对象
+#[derive(Debug, Clone)]
+pub struct WatchTarget {
+    pub browser: String,
+    pub files: Vec<PathBuf>,
+    /// 需要检查锁的 SQLite 数据库
+    pub database: Option<PathBuf>,
+}
+
+impl WatchTarget {
+    pub fn new(browser: &str, bookmark_path: PathBuf) -> Self {
+        let is_sqlite = bookmark_path.extension().and_then(|e| e.to_str()) == Some("sqlite");
+        let mut files = vec![bookmark_path.clone()];
+        if is_sqlite {
+            let mut wal = bookmark_path.clone().into_os_string();
+            wal.push("-wal");
+            files.push(PathBuf::from(wal));
+        }
+        Self {
+            browser: browser.to_string(),
+            files,
+            database: is_sqlite.then_some(bookmark_path),
+        }
+    }
+}
+
+/// 检测到的浏览器里需要监视的文件
+pub fn detect_targets(browsers: &[String]) -> Vec<WatchTarget> {
+    get_all_adapters()
+        .into_iter()
+        .filter(|adapter| {
+            let name = adapter.browser_type().name().to_lowercase();
+            browsers.is_empty()
+                || browsers
+                    .iter()
+                    .any(|b| name.contains(&b.trim().to_lowercase()))
+        })
+        .filter_map(|adapter| {
+            let path = adapter.detect_bookmark_path().ok()?;
+            Some(WatchTarget::new(adapter.browser_type().name(), path))
+        })
+        .collect()
+}
+
+type Fingerprint = Option<(SystemTime, u64)>;
+
+fn fingerprint(path: &PathBuf) -> Fingerprint {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// 轮询文件变化并做防抖
+pub struct Watcher {
+    targets: Vec<WatchTarget>,
+    debounce: Duration,
+    seen: HashMap<PathBuf, Fingerprint>,
+    /// 最后一次变化的时间和变化的浏览器
+    pending: Option<(Instant, BTreeSet<String>)>,
+    /// 任务运行期间变化的浏览器；None 表示没有任务在运行
+    during_job: Option<BTreeSet<String>>,
+    waiting_for_lock: bool,
+}
+
+impl Watcher {
+    pub fn new(targets: Vec<WatchTarget>, debounce: Duration) -> Self {
+        let mut watcher = Self {
+            targets,
+            debounce,
+            seen: HashMap::new(),
+            pending: None,
+            during_job: None,
+            waiting_for_lock: false,
+        };
+        watcher.rebaseline();
+        watcher
+    }
+
+    /// 记录当前文件状态并丢弃未处理的变化
+    pub fn rebaseline(&mut self) {
+        self.seen = self
+            .targets
+            .iter()
+            .flat_map(|t| t.files.iter())
+            .map(|f| (f.clone(), fingerprint(f)))
+            .collect();
+        self.pending = None;
+        self.waiting_for_lock = false;
+    }
+
+    /// 有任务开始运行：之后的变化单独记下，任务结束前不触发
+    pub fn begin_job(&mut self) {
+        self.during_job.get_or_insert_with(BTreeSet::new);
+    }
+
+    /// 所有任务都结束了；`wrote_bookmarks` 表示其中有任务可能写过书签文件
+    pub fn end_job(&mut self, wrote_bookmarks: bool, now: Instant) {
+        self.scan(now);
+        let Some(changed) = self.during_job.take() else {
+            return;
+        };
+        if wrote_bookmarks || changed.is_empty() {
+            return;
+        }
+        let pending = self.pending.get_or_insert_with(|| (now, BTreeSet::new()));
+        pending.0 = now;
+        pending.1.extend(changed);
+    }
+
+    /// 比较文件状态，记下变化的浏览器
+    fn scan(&mut self, now: Instant) {
+        for target in &self.targets {
+            for file in &target.files {
+                let current = fingerprint(file);
+                if self.seen.get(file) == Some(&current) {
+                    continue;
+                }
+                debug!("Bookmark file changed: {}", file.display());
+                self.seen.insert(file.clone(), current);
+                match self.during_job {
+                    Some(ref mut changed) => {
+                        changed.insert(target.browser.clone());
+                    }
+                    None => {
+                        let changed = self.pending.get_or_insert_with(|| (now, BTreeSet::new()));
+                        changed.0 = now;
+                        changed.1.insert(target.browser.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    /// 检查一次；变化停止超过防抖时间、没有任务在运行且数据库未锁定时返回变化的浏览器
+    pub fn poll(&mut self, now: Instant) -> Option<Vec<String>> {
+        self.scan(now);
+        if self.during_job.is_some() {
+            return None;
+        }
+
+        let (last_change, changed) = self.pending.as_mut()?;
+        if now.duration_since(*last_change) < self.debounce {
+            return None;
+        }
+
+        let locked: Vec<&str> = self
+            .targets
+            .iter()
+            .filter(|t| changed.contains(&t.browser))
+            .filter(|t| {
+                t.database
+                    .as_ref()
+                    .is_some_and(|db| db_safety::is_database_locked(db))
+            })
+            .map(|t| t.browser.as_str())
+            .collect();
+        if !locked.is_empty() {
+            if !self.waiting_for_lock {
+                info!(
+                    "🔒 Waiting for {} to release the bookmark database",
+                    locked.join(", ")
+                );
+                self.waiting_for_lock = true;
+            }
+            // 锁检查要打开数据库，不必每次轮询都做
+            *last_change = now;
+            return None;
+        }
+
+        self.waiting_for_lock = false;
+        self.pending
+            .take()
+            .map(|(_, changed)| changed.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sqlite_target_watches_wal() {
+        let target = WatchTarget::new("Waterfox", PathBuf::from("/p/places.sqlite"));
+        assert_eq!(
+            target.files,
+            vec![
+                PathBuf::from("/p/places.sqlite"),
+                PathBuf::from("/p/places.sqlite-wal")
+            ]
+        );
+        assert_eq!(target.database, Some(PathBuf::from("/p/places.sqlite")));
+
+        let target = WatchTarget::new("Chrome", PathBuf::from("/p/Bookmarks"));
+        assert_eq!(target.files.len(), 1);
+        assert!(target.database.is_none());
+    }
+
+    #[test]
+    fn test_debounce_and_rebaseline() {
+        let dir = TempDir::new().unwrap();
+        let chrome = dir.path().join("Bookmarks");
+        let safari = dir.path().join("Bookmarks.plist");
+        std::fs::write(&chrome, "{}").unwrap();
+        std::fs::write(&safari, "plist").unwrap();
+
+        let mut watcher = Watcher::new(
+            vec![
+                WatchTarget::new("Chrome", chrome.clone()),
+                WatchTarget::new("Safari", safari.clone()),
+            ],
+            Duration::from_secs(5),
+        );
+        let start = Instant::now();
+        assert_eq!(watcher.poll(start), None);
+
+        std::fs::write(&chrome, "{\"roots\":{}}").unwrap();
+        assert_eq!(watcher.poll(start + Duration::from_secs(1)), None);
+        // 又一次变化，防抖重新计时
+        std::fs::write(&safari, "plist v2").unwrap();
+        assert_eq!(watcher.poll(start + Duration::from_secs(4)), None);
+        assert_eq!(watcher.poll(start + Duration::from_secs(8)), None);
+        assert_eq!(
+            watcher.poll(start + Duration::from_secs(9)),
+            Some(vec!["Chrome".to_string(), "Safari".to_string()])
+        );
+        assert_eq!(watcher.poll(start + Duration::from_secs(20)), None);
+
+        // bsync 自己写入后重新记录，不触发
+        std::fs::write(&chrome, "{\"roots\":{\"bar\":{}}}").unwrap();
+        watcher.rebaseline();
+        assert_eq!(watcher.poll(start + Duration::from_secs(40)), None);
+    }
+
+    fn two_browsers(dir: &TempDir) -> (Watcher, PathBuf, PathBuf) {
+        let chrome = dir.path().join("Bookmarks");
+        let safari = dir.path().join("Bookmarks.plist");
+        std::fs::write(&chrome, "{}").unwrap();
+        std::fs::write(&safari, "plist").unwrap();
+        let watcher = Watcher::new(
+            vec![
+                WatchTarget::new("Chrome", chrome.clone()),
+                WatchTarget::new("Safari", safari.clone()),
+            ],
+            Duration::from_secs(5),
+        );
+        (watcher, chrome, safari)
+    }
+
+    #[test]
+    fn test_writing_job_absorbs_its_own_changes() {
+        let dir = TempDir::new().unwrap();
+        let (mut watcher, chrome, _) = two_browsers(&dir);
+        let start = Instant::now();
+
+        watcher.begin_job();
+        std::fs::write(&chrome, "{\"roots\":{}}").unwrap();
+        assert_eq!(watcher.poll(start + Duration::from_secs(1)), None);
+        assert_eq!(watcher.poll(start + Duration::from_secs(10)), None);
+        watcher.end_job(true, start + Duration::from_secs(11));
+        assert_eq!(watcher.poll(start + Duration::from_secs(30)), None);
+    }
+
+    #[test]
+    fn test_edits_during_read_only_job_are_kept() {
+        let dir = TempDir::new().unwrap();
+        let (mut watcher, chrome, _) = two_browsers(&dir);
+        let start = Instant::now();
+
+        watcher.begin_job();
+        std::fs::write(&chrome, "{\"roots\":{}}").unwrap();
+        assert_eq!(watcher.poll(start + Duration::from_secs(1)), None);
+        watcher.end_job(false, start + Duration::from_secs(2));
+        // 防抖从任务结束时开始
+        assert_eq!(watcher.poll(start + Duration::from_secs(6)), None);
+        assert_eq!(
+            watcher.poll(start + Duration::from_secs(7)),
+            Some(vec!["Chrome".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_edit_before_job_survives_writing_job() {
+        let dir = TempDir::new().unwrap();
+        let (mut watcher, chrome, safari) = two_browsers(&dir);
+        let start = Instant::now();
+
+        // 用户改了 Safari，还在防抖时定时任务开始写 Chrome
+        std::fs::write(&safari, "plist v2").unwrap();
+        assert_eq!(watcher.poll(start), None);
+        watcher.begin_job();
+        std::fs::write(&chrome, "{\"roots\":{}}").unwrap();
+        assert_eq!(watcher.poll(start + Duration::from_secs(6)), None);
+        watcher.end_job(true, start + Duration::from_secs(7));
+
+        assert_eq!(
+            watcher.poll(start + Duration::from_secs(8)),
+            Some(vec!["Safari".to_string()])
+        );
+    }
+}